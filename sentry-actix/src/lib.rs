@@ -56,6 +56,24 @@
 //! });
 //! ```
 //!
+//! # Capturing Request and Response Bodies
+//!
+//! The middleware can attach the request body of failed requests to the
+//! errors it captures.  This is disabled by default, and is enabled by
+//! setting the maximum number of bytes to capture.  The body is copied while
+//! the handler reads it, so at most that many bytes are ever buffered, and
+//! bodies that the handler does not read are not captured.  Likewise, the
+//! beginning of the error response is attached as the `response` context.
+//! As bodies may contain personal data, they are only captured if
+//! `send_default_pii` is enabled.
+//!
+//! ```
+//! let middleware = sentry_actix::Sentry::builder()
+//!     .capture_request_body(10 * 1024)
+//!     .capture_response_body(1024)
+//!     .finish();
+//! ```
+//!
 //! # Reusing the Hub
 //!
 //! This integration will automatically create a new per-request Hub from the main Hub, and update the
@@ -73,16 +91,17 @@
 
 use std::borrow::Cow;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::http::StatusCode;
-use actix_web::Error;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error, HttpMessage};
 use futures_util::future::{ok, Future, Ready};
-use futures_util::FutureExt;
+use futures_util::{FutureExt, StreamExt};
 
 use sentry_core::protocol::{self, ClientSdkPackage, Event, Request};
-use sentry_core::{Hub, SentryFutureExt};
+use sentry_core::{Hub, RouteFilter, SentryFutureExt};
 
 /// A helper construct that can be used to reconfigure and build the middleware.
 pub struct SentryBuilder {
@@ -123,6 +142,18 @@ impl SentryBuilder {
         self
     }
 
+    /// If configured, responses of requests during which an event was captured
+    /// get `X-Sentry-Event-Id` and `X-Sentry-Trace-Id` headers.
+    ///
+    /// This lets frontends and support tooling correlate user reports with
+    /// the events of the backend.  The trace id is only known if the request
+    /// has a transaction or span.
+    #[must_use]
+    pub fn emit_correlation_headers(mut self, val: bool) -> Self {
+        self.middleware.emit_correlation_headers = val;
+        self
+    }
+
     /// Enables or disables error reporting.
     ///
     /// The default is to report all errors.
@@ -131,6 +162,50 @@ impl SentryBuilder {
         self.middleware.capture_server_errors = val;
         self
     }
+
+    /// Attaches up to `max_bytes` of the request body to the errors captured
+    /// by the middleware.
+    ///
+    /// The body is copied as the handler reads it, and anything beyond
+    /// `max_bytes` is dropped.  The default of `0` does not capture bodies,
+    /// and neither does a client without `send_default_pii`.
+    #[must_use]
+    pub fn capture_request_body(mut self, max_bytes: usize) -> Self {
+        self.middleware.max_request_body = max_bytes;
+        self
+    }
+
+    /// Attaches up to `max_bytes` of the error response to the errors
+    /// captured by the middleware, as the `response` context.
+    ///
+    /// Only responses with a body that is known up front, rather than
+    /// streamed, are captured.  The default of `0` does not capture bodies,
+    /// and neither does a client without `send_default_pii`.
+    #[must_use]
+    pub fn capture_response_body(mut self, max_bytes: usize) -> Self {
+        self.middleware.max_response_body = max_bytes;
+        self
+    }
+
+    /// Sets the filter for the requests that the middleware instruments.
+    ///
+    /// No transactions are started for requests that the filter does not
+    /// allow, and their errors are not captured.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentry::RouteFilter;
+    ///
+    /// let middleware = sentry_actix::Sentry::builder()
+    ///     .route_filter(RouteFilter::new().deny("/healthz").deny("/metrics"))
+    ///     .finish();
+    /// ```
+    #[must_use]
+    pub fn route_filter(mut self, filter: RouteFilter) -> Self {
+        self.middleware.route_filter = filter;
+        self
+    }
 }
 
 /// Reports certain failures to Sentry.
@@ -138,8 +213,12 @@ impl SentryBuilder {
 pub struct Sentry {
     hub: Option<Arc<Hub>>,
     emit_header: bool,
+    emit_correlation_headers: bool,
     capture_server_errors: bool,
     start_transaction: bool,
+    max_request_body: usize,
+    max_response_body: usize,
+    route_filter: RouteFilter,
 }
 
 impl Sentry {
@@ -148,8 +227,12 @@ impl Sentry {
         Sentry {
             hub: None,
             emit_header: false,
+            emit_correlation_headers: false,
             capture_server_errors: true,
             start_transaction: false,
+            max_request_body: 0,
+            max_response_body: 0,
+            route_filter: RouteFilter::new(),
         }
     }
 
@@ -219,12 +302,13 @@ where
         self.service.poll_ready(cx)
     }
 
-    fn call(&self, req: ServiceRequest) -> Self::Future {
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
         let inner = self.inner.clone();
         let hub = Arc::new(Hub::new_from_top(
             inner.hub.clone().unwrap_or_else(Hub::main),
         ));
         let client = hub.client();
+        let allowed = inner.route_filter.is_allowed(req.path());
         let track_sessions = client.as_ref().map_or(false, |client| {
             let options = client.options();
             options.auto_session_tracking
@@ -239,19 +323,29 @@ where
 
         let (mut tx, sentry_req) = sentry_request_from_http(&req, with_pii);
 
-        let transaction = if inner.start_transaction {
-            let name = std::mem::take(&mut tx)
-                .unwrap_or_else(|| format!("{} {}", req.method(), req.uri()));
+        let transaction = if inner.start_transaction && allowed {
+            let (name, source) = match std::mem::take(&mut tx) {
+                Some((name, source)) => (name, source),
+                None => (
+                    format!(
+                        "{} {}",
+                        req.method(),
+                        sentry_core::sanitize_url_path(req.path())
+                    ),
+                    protocol::TransactionSource::Url,
+                ),
+            };
 
             let headers = req.headers().iter().flat_map(|(header, value)| {
                 value.to_str().ok().map(|value| (header.as_str(), value))
             });
 
-            let ctx = sentry_core::TransactionContext::continue_from_headers(
+            let mut ctx = sentry_core::TransactionContext::continue_from_headers(
                 &name,
                 "http.server",
                 headers,
             );
+            ctx.set_source(source);
             Some(hub.start_transaction(ctx))
         } else {
             None
@@ -262,12 +356,23 @@ where
             if let Some(transaction) = transaction.as_ref() {
                 scope.set_span(Some(transaction.clone().into()));
             } else {
-                scope.set_transaction(tx.as_deref());
+                scope.set_transaction(tx.as_ref().map(|(name, _)| name.as_str()));
             }
             scope.add_event_processor(move |event| Some(process_event(event, &sentry_req)));
             parent_span
         });
 
+        let capture_server_errors = inner.capture_server_errors && allowed;
+        let body = if capture_server_errors && with_pii && inner.max_request_body > 0 {
+            let body = Arc::new(Mutex::new(CapturedBody::default()));
+            let payload = tee_payload(req.take_payload(), body.clone(), inner.max_request_body);
+            req.set_payload(payload);
+            Some(body)
+        } else {
+            None
+        };
+        let max_response_body = if with_pii { inner.max_response_body } else { 0 };
+
         let fut = self.service.call(req).bind_hub(hub.clone());
 
         async move {
@@ -275,8 +380,8 @@ where
             let mut res: Self::Response = match fut.await {
                 Ok(res) => res,
                 Err(e) => {
-                    if inner.capture_server_errors {
-                        hub.capture_error(&e);
+                    if capture_server_errors {
+                        capture_error(&hub, &e, body.as_deref(), max_response_body);
                     }
 
                     if let Some(transaction) = transaction {
@@ -292,9 +397,9 @@ where
             };
 
             // Response errors
-            if inner.capture_server_errors && res.response().status().is_server_error() {
+            if capture_server_errors && res.response().status().is_server_error() {
                 if let Some(e) = res.response().error() {
-                    let event_id = hub.capture_error(e);
+                    let event_id = capture_error(&hub, e, body.as_deref(), max_response_body);
 
                     if inner.emit_header {
                         res.response_mut().headers_mut().insert(
@@ -305,9 +410,26 @@ where
                 }
             }
 
+            if inner.emit_correlation_headers {
+                if let Some(event_id) = hub.last_event_id() {
+                    let trace_id = hub.configure_scope(|scope| scope.get_span());
+                    let headers = res.response_mut().headers_mut();
+                    headers.insert(
+                        HeaderName::from_static("x-sentry-event-id"),
+                        HeaderValue::from_str(&event_id.simple().to_string()).unwrap(),
+                    );
+                    if let Some(span) = trace_id {
+                        headers.insert(
+                            HeaderName::from_static("x-sentry-trace-id"),
+                            HeaderValue::from_str(&span.trace_id().to_string()).unwrap(),
+                        );
+                    }
+                }
+            }
+
             if let Some(transaction) = transaction {
                 if transaction.get_status().is_none() {
-                    let status = map_status(res.status());
+                    let status = protocol::SpanStatus::from_http_status(res.status().as_u16());
                     transaction.set_status(status);
                 }
                 transaction.finish();
@@ -320,28 +442,88 @@ where
     }
 }
 
-fn map_status(status: StatusCode) -> protocol::SpanStatus {
-    match status {
-        StatusCode::UNAUTHORIZED => protocol::SpanStatus::Unauthenticated,
-        StatusCode::FORBIDDEN => protocol::SpanStatus::PermissionDenied,
-        StatusCode::NOT_FOUND => protocol::SpanStatus::NotFound,
-        StatusCode::TOO_MANY_REQUESTS => protocol::SpanStatus::ResourceExhausted,
-        status if status.is_client_error() => protocol::SpanStatus::InvalidArgument,
-        StatusCode::NOT_IMPLEMENTED => protocol::SpanStatus::Unimplemented,
-        StatusCode::SERVICE_UNAVAILABLE => protocol::SpanStatus::Unavailable,
-        status if status.is_server_error() => protocol::SpanStatus::InternalError,
-        StatusCode::CONFLICT => protocol::SpanStatus::AlreadyExists,
-        status if status.is_success() => protocol::SpanStatus::Ok,
-        _ => protocol::SpanStatus::UnknownError,
+/// The beginning of a request body, as far as it was read.
+#[derive(Default)]
+struct CapturedBody {
+    data: Vec<u8>,
+}
+
+/// Copies up to `max_bytes` of the payload into `body` while it is read.
+fn tee_payload(payload: Payload, body: Arc<Mutex<CapturedBody>>, max_bytes: usize) -> Payload {
+    let stream = payload.map(move |chunk| {
+        if let Ok(ref chunk) = chunk {
+            let mut body = body.lock().unwrap();
+            let len = chunk.len().min(max_bytes - body.data.len());
+            body.data.extend_from_slice(&chunk[..len]);
+        }
+        chunk
+    });
+    Payload::Stream {
+        payload: Box::pin(stream),
+    }
+}
+
+/// Captures an error, with the captured request body if there is one, and
+/// up to `max_response_body` bytes of its response.
+fn capture_error(
+    hub: &Hub,
+    error: &Error,
+    body: Option<&Mutex<CapturedBody>>,
+    max_response_body: usize,
+) -> sentry_core::types::Uuid {
+    let data = body
+        .map(|body| String::from_utf8_lossy(&body.lock().unwrap().data).into_owned())
+        .filter(|data| !data.is_empty());
+    let response = (max_response_body > 0)
+        .then(|| response_context(error, max_response_body))
+        .flatten();
+    if data.is_none() && response.is_none() {
+        return hub.capture_error(error);
     }
+    hub.with_scope(
+        |scope| {
+            if let Some(response) = response {
+                scope.set_context("response", response);
+            }
+            scope.add_event_processor(move |mut event| {
+                if let (Some(request), Some(data)) = (event.request.as_mut(), data.as_ref()) {
+                    request.data = Some(data.clone());
+                }
+                Some(event)
+            })
+        },
+        || hub.capture_error(error),
+    )
+}
+
+/// Returns the status code and the beginning of the body of the response
+/// for an error, if the body is known up front.
+fn response_context(error: &Error, max_bytes: usize) -> Option<protocol::Context> {
+    let response = error.error_response();
+    let status_code = response.status().as_u16();
+    let body = response.into_body().try_into_bytes().ok()?;
+    let len = body.len().min(max_bytes);
+
+    let mut context = protocol::Map::new();
+    context.insert("status_code".into(), status_code.into());
+    context.insert(
+        "data".into(),
+        String::from_utf8_lossy(&body[..len]).into_owned().into(),
+    );
+    Some(protocol::Context::Other(context))
 }
 
 /// Build a Sentry request struct from the HTTP request
-fn sentry_request_from_http(request: &ServiceRequest, with_pii: bool) -> (Option<String>, Request) {
+fn sentry_request_from_http(
+    request: &ServiceRequest,
+    with_pii: bool,
+) -> (Option<(String, protocol::TransactionSource)>, Request) {
     let transaction = if let Some(name) = request.match_name() {
-        Some(String::from(name))
+        Some((String::from(name), protocol::TransactionSource::View))
     } else {
-        request.match_pattern()
+        request
+            .match_pattern()
+            .map(|pattern| (pattern, protocol::TransactionSource::Route))
     };
 
     let mut sentry_req = Request {
@@ -499,6 +681,176 @@ mod tests {
         }
     }
 
+    /// Ensures the captured request and response bodies are attached to errors.
+    #[actix_web::test]
+    async fn test_capture_request_body() {
+        for send_default_pii in [true, false] {
+            let options = sentry::ClientOptions {
+                send_default_pii,
+                ..Default::default()
+            };
+            let events = sentry::test::with_captured_events_options(
+                || {
+                    block_on(async {
+                        async fn failing(body: String) -> Result<String, Error> {
+                            assert_eq!(body, "hello world");
+                            Err(io::Error::new(io::ErrorKind::Other, "Test Error").into())
+                        }
+
+                        let middleware = Sentry::builder()
+                            .with_hub(Hub::current())
+                            .capture_request_body(5)
+                            .capture_response_body(4)
+                            .finish();
+                        let app = init_service(
+                            App::new()
+                                .wrap(middleware)
+                                .service(web::resource("/test").to(failing)),
+                        )
+                        .await;
+
+                        let req = TestRequest::post()
+                            .uri("/test")
+                            .set_payload("hello world")
+                            .to_request();
+                        let res = call_service(&app, req).await;
+                        assert!(res.status().is_server_error());
+                    })
+                },
+                options,
+            );
+
+            assert_eq!(events.len(), 1);
+            let request = events[0].request.as_ref().expect("Request should be set.");
+            let expected = if send_default_pii {
+                Some("hello")
+            } else {
+                None
+            };
+            assert_eq!(request.data.as_deref(), expected);
+
+            let response = match events[0].contexts.get("response") {
+                Some(protocol::Context::Other(response)) => Some(response),
+                _ => None,
+            };
+            assert_eq!(response.is_some(), send_default_pii);
+            if let Some(response) = response {
+                assert_eq!(response["status_code"], 500);
+                assert_eq!(response["data"], "Test");
+            }
+        }
+    }
+
+    /// Ensures requests denied by the route filter are not instrumented.
+    #[actix_web::test]
+    async fn test_route_filter() {
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                block_on(async {
+                    async fn failing() -> Result<String, Error> {
+                        Err(io::Error::new(io::ErrorKind::Other, "Test Error").into())
+                    }
+
+                    let middleware = Sentry::builder()
+                        .with_hub(Hub::current())
+                        .start_transaction(true)
+                        .route_filter(RouteFilter::new().deny("/healthz"))
+                        .finish();
+                    let app = init_service(
+                        App::new()
+                            .wrap(middleware)
+                            .service(web::resource("/healthz").to(failing))
+                            .service(web::resource("/users").to(failing)),
+                    )
+                    .await;
+
+                    for uri in ["/healthz", "/users"] {
+                        let req = TestRequest::get().uri(uri).to_request();
+                        let res = call_service(&app, req).await;
+                        assert!(res.status().is_server_error());
+                    }
+                })
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+
+        // the error and the transaction of `/users`
+        assert_eq!(envelopes.len(), 2);
+        for envelope in envelopes {
+            match envelope.items().next().unwrap() {
+                sentry::protocol::EnvelopeItem::Event(event) => {
+                    let request = event.request.as_ref().unwrap();
+                    assert_eq!(request.url.as_ref().unwrap().path(), "/users");
+                }
+                sentry::protocol::EnvelopeItem::Transaction(transaction) => {
+                    assert_eq!(transaction.name.as_deref(), Some("/users"))
+                }
+                item => panic!("unexpected item {:?}", item),
+            }
+        }
+    }
+
+    /// Ensures the ids of captured events are added to the response headers.
+    #[actix_web::test]
+    async fn test_correlation_headers() {
+        let mut headers = None;
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                block_on(async {
+                    async fn reporting() -> HttpResponse {
+                        sentry::capture_message("Message", Level::Warning);
+                        HttpResponse::Ok().finish()
+                    }
+
+                    let middleware = Sentry::builder()
+                        .with_hub(Hub::current())
+                        .start_transaction(true)
+                        .emit_correlation_headers(true)
+                        .finish();
+                    let app = init_service(
+                        App::new()
+                            .wrap(middleware)
+                            .service(web::resource("/report").to(reporting))
+                            .service(web::resource("/quiet").to(HttpResponse::Ok)),
+                    )
+                    .await;
+
+                    let req = TestRequest::get().uri("/quiet").to_request();
+                    let res = call_service(&app, req).await;
+                    assert!(res.headers().get("x-sentry-event-id").is_none());
+
+                    let req = TestRequest::get().uri("/report").to_request();
+                    let res = call_service(&app, req).await;
+                    let header = |name| res.headers().get(name).unwrap().to_str().unwrap();
+                    headers = Some((
+                        header("x-sentry-event-id").to_owned(),
+                        header("x-sentry-trace-id").to_owned(),
+                    ));
+                })
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+
+        let (event_id, trace_id) = headers.unwrap();
+        let event = envelopes
+            .iter()
+            .find_map(|envelope| envelope.event())
+            .unwrap();
+        assert_eq!(event_id, event.event_id.simple().to_string());
+        match event.contexts.get("trace") {
+            Some(protocol::Context::Trace(trace)) => {
+                assert_eq!(trace_id, trace.trace_id.to_string())
+            }
+            context => panic!("expected trace context, got {:?}", context),
+        }
+    }
+
     /// Ensures client errors (4xx) are not captured.
     #[actix_web::test]
     async fn test_client_errors_discarded() {
@@ -558,6 +910,51 @@ mod tests {
         assert_eq!(request.method, Some("GET".into()));
     }
 
+    /// Ensures transactions are named after the route template.
+    #[actix_web::test]
+    async fn test_transaction_route_source() {
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                block_on(async {
+                    let user = HttpResponse::Ok;
+
+                    let middleware = Sentry::builder()
+                        .with_hub(Hub::current())
+                        .start_transaction(true)
+                        .finish();
+
+                    let app = init_service(
+                        App::new()
+                            .wrap(middleware)
+                            .service(web::resource("/users/{id}").to(user)),
+                    )
+                    .await;
+
+                    let req = TestRequest::get().uri("/users/123").to_request();
+                    let res = call_service(&app, req).await;
+                    assert!(res.status().is_success());
+                })
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(envelopes.len(), 1);
+        let transaction = match envelopes[0].items().next().unwrap() {
+            sentry::protocol::EnvelopeItem::Transaction(transaction) => transaction,
+            _ => panic!("expected a transaction"),
+        };
+        assert_eq!(transaction.name.as_deref(), Some("/users/{id}"));
+        assert_eq!(
+            transaction.transaction_info,
+            Some(protocol::TransactionInfo {
+                source: protocol::TransactionSource::Route
+            })
+        );
+    }
+
     #[actix_web::test]
     async fn test_track_session() {
         let envelopes = sentry::test::with_captured_envelopes_options(
@@ -0,0 +1,193 @@
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+use sentry_core::protocol::{Frame, Stacktrace};
+
+use crate::utils::function_starts_with;
+
+type FilterFn = Arc<dyn Fn(&Frame) -> bool + Send + Sync>;
+
+/// Functions of async runtime internals that drive tasks.
+const TOKIO_FRAMES: &[&str] = &[
+    "tokio::runtime::",
+    "tokio::task::",
+    "tokio::park::",
+    "tokio::loom::",
+    "tokio::coop::",
+];
+
+const RAYON_FRAMES: &[&str] = &["rayon_core::", "rayon::iter::plumbing::"];
+
+/// The poll machinery of futures and generated `async` state machines.
+const FUTURES_FRAMES: &[&str] = &[
+    "futures_core::",
+    "futures_util::",
+    "futures_executor::",
+    "<core::future::from_generator::GenFuture<T> as core::future::future::Future>::poll",
+    "<core::pin::Pin<P> as core::future::future::Future>::poll",
+    "core::future::",
+];
+
+/// The plumbing of panics and backtraces that surrounds user code.
+const PANIC_FRAMES: &[&str] = &[
+    "std::panicking::",
+    "std::panic::",
+    "core::panicking::",
+    "core::ops::function::FnOnce::call_once",
+    "std::sys_common::backtrace::",
+    "std::sys::backtrace::",
+    "std::rt::",
+    "__rust_begin_short_backtrace",
+    "__rust_try",
+];
+
+static FRAME_FILTERS: Lazy<RwLock<Vec<(&'static str, FilterFn)>>> =
+    Lazy::new(|| RwLock::new(builtin_filters()));
+
+fn builtin_filters() -> Vec<(&'static str, FilterFn)> {
+    let builtin = [
+        ("tokio", TOKIO_FRAMES),
+        ("rayon", RAYON_FRAMES),
+        ("futures", FUTURES_FRAMES),
+        ("panic", PANIC_FRAMES),
+    ];
+    builtin
+        .iter()
+        .map(|&(name, prefixes)| (name, prefix_filter(prefixes)))
+        .collect()
+}
+
+fn prefix_filter(prefixes: &'static [&'static str]) -> FilterFn {
+    Arc::new(move |frame: &Frame| match frame.function {
+        Some(ref func) => prefixes.iter().any(|m| function_starts_with(func, m)),
+        None => false,
+    })
+}
+
+/// Registers a filter that removes frames from stacktraces.
+///
+/// Filters are applied to event stacktraces by the
+/// [`ProcessStacktraceIntegration`](crate::ProcessStacktraceIntegration) when
+/// the `trim_backtraces` and `apply_frame_filters` options are enabled, and
+/// remove every frame for which they return `true`.  This keeps the scaffolding of runtimes and frameworks
+/// out of stacktraces, so they show the application logic instead.
+///
+/// Built-in filters named `"tokio"`, `"rayon"`, `"futures"` and `"panic"` are
+/// registered by default.  Registering a filter with an existing name replaces
+/// that filter.
+///
+/// # Examples
+///
+/// ```
+/// sentry_backtrace::register_frame_filter("my-framework", |frame| {
+///     frame
+///         .function
+///         .as_deref()
+///         .map_or(false, |function| function.starts_with("my_framework::dispatch::"))
+/// });
+/// ```
+pub fn register_frame_filter<F>(name: &'static str, filter: F)
+where
+    F: Fn(&Frame) -> bool + Send + Sync + 'static,
+{
+    let mut filters = FRAME_FILTERS.write().unwrap();
+    let filter: FilterFn = Arc::new(filter);
+    match filters.iter_mut().find(|(n, _)| *n == name) {
+        Some(existing) => existing.1 = filter,
+        None => filters.push((name, filter)),
+    }
+}
+
+/// Removes the frame filter registered with the given name.
+///
+/// This can also be used to disable one of the built-in filters.  Returns
+/// `true` if a filter was removed.
+pub fn unregister_frame_filter(name: &str) -> bool {
+    let mut filters = FRAME_FILTERS.write().unwrap();
+    let len = filters.len();
+    filters.retain(|(n, _)| *n != name);
+    filters.len() != len
+}
+
+/// Removes the frames matched by any registered frame filter.
+///
+/// Frames that were explicitly marked as in-app are kept, and so is the
+/// stacktrace as a whole if the filters would remove all of its frames.
+pub fn filter_frames(stacktrace: &mut Stacktrace) {
+    filter_frames_with(stacktrace, &FRAME_FILTERS.read().unwrap());
+}
+
+fn filter_frames_with(stacktrace: &mut Stacktrace, filters: &[(&'static str, FilterFn)]) {
+    let is_filtered = |frame: &Frame| {
+        frame.in_app != Some(true) && filters.iter().any(|(_, filter)| filter(frame))
+    };
+    if stacktrace.frames.iter().all(is_filtered) {
+        return;
+    }
+    stacktrace.frames.retain(|frame| !is_filtered(frame));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(function: &str) -> Frame {
+        Frame {
+            function: Some(function.into()),
+            ..Default::default()
+        }
+    }
+
+    fn functions(stacktrace: &Stacktrace) -> Vec<&str> {
+        stacktrace
+            .frames
+            .iter()
+            .filter_map(|frame| frame.function.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_frames() {
+        let filters = builtin_filters();
+        let mut stacktrace = Stacktrace {
+            frames: vec![
+                frame("std::rt::lang_start_internal"),
+                frame("tokio::runtime::task::harness::poll_future"),
+                frame("<core::pin::Pin<P> as core::future::future::Future>::poll"),
+                frame("app::handler::{{closure}}"),
+                frame("rayon_core::join::join_context"),
+                frame("app::compute"),
+            ],
+            ..Default::default()
+        };
+        filter_frames_with(&mut stacktrace, &filters);
+        assert_eq!(
+            functions(&stacktrace),
+            ["app::handler::{{closure}}", "app::compute"]
+        );
+
+        // filters never remove all frames
+        let mut stacktrace = Stacktrace {
+            frames: vec![frame("tokio::runtime::park")],
+            ..Default::default()
+        };
+        filter_frames_with(&mut stacktrace, &filters);
+        assert_eq!(functions(&stacktrace), ["tokio::runtime::park"]);
+    }
+
+    #[test]
+    fn test_register_frame_filter() {
+        // only matches frames of this test, so other tests are not affected
+        register_frame_filter("test-register", |frame| {
+            frame.function.as_deref() == Some("test_register::dispatch")
+        });
+        let mut stacktrace = Stacktrace {
+            frames: vec![frame("test_register::dispatch"), frame("app::main")],
+            ..Default::default()
+        };
+        filter_frames(&mut stacktrace);
+        assert!(unregister_frame_filter("test-register"));
+        assert!(!unregister_frame_filter("test-register"));
+        assert_eq!(functions(&stacktrace), ["app::main"]);
+    }
+}
@@ -1,17 +1,24 @@
 use std::thread;
 
-use sentry_core::protocol::{Event, Thread};
-use sentry_core::{ClientOptions, Integration};
+use sentry_core::protocol::{Event, Stacktrace, Thread};
+use sentry_core::{ClientOptions, Hint, Integration};
 
 use crate::current_stacktrace;
-use crate::process::process_event_stacktrace;
+use crate::process::{process_event_stacktrace, resolve_stacktrace};
 
 /// Integration to process Event stacktraces.
 ///
-/// This integration will trim backtraces, depending on the `trim_backtraces`
-/// and `extra_border_frames` options.
+/// This integration will resolve the symbols of stacktraces captured in this
+/// process with [deferred resolution](Stacktrace::deferred_resolution),
+/// unless the event was [forwarded](Hint::forwarded) from
+/// another process, and trim backtraces, depending on the `trim_backtraces`
+/// and `extra_border_frames` options.  With the `apply_frame_filters` option,
+/// trimming also removes the frames of runtime scaffolding matched by the
+/// filters registered via [`register_frame_filter`](crate::register_frame_filter).
 /// It will then classify each frame according to the `in_app_include` and
-/// `in_app_exclude` options.
+/// `in_app_exclude` options, falling back to the source file path of the frame:
+/// sources of cargo dependencies and the standard library are not in-app, while
+/// the relative paths of workspace members are.
 #[derive(Debug, Default)]
 pub struct ProcessStacktraceIntegration;
 
@@ -28,22 +35,39 @@ impl Integration for ProcessStacktraceIntegration {
     }
 
     fn process_event(
+        &self,
+        event: Event<'static>,
+        options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        self.process_event_with_hint(event, &Hint::default(), options)
+    }
+
+    fn process_event_with_hint(
         &self,
         mut event: Event<'static>,
+        hint: &Hint,
         options: &ClientOptions,
     ) -> Option<Event<'static>> {
+        // the addresses of forwarded events belong to another process
+        let resolve = !hint.is_forwarded();
+        let process = |stacktrace: &mut Stacktrace| {
+            if resolve {
+                resolve_stacktrace(stacktrace);
+            }
+            process_event_stacktrace(stacktrace, options);
+        };
         for exc in &mut event.exception {
             if let Some(ref mut stacktrace) = exc.stacktrace {
-                process_event_stacktrace(stacktrace, options);
+                process(stacktrace);
             }
         }
         for th in &mut event.threads {
             if let Some(ref mut stacktrace) = th.stacktrace {
-                process_event_stacktrace(stacktrace, options);
+                process(stacktrace);
             }
         }
         if let Some(ref mut stacktrace) = event.stacktrace {
-            process_event_stacktrace(stacktrace, options);
+            process(stacktrace);
         }
         Some(event)
     }
@@ -52,7 +76,9 @@ impl Integration for ProcessStacktraceIntegration {
 /// Integration to attach stacktraces to Events.
 ///
 /// This integration will add an additional thread backtrace to captured
-/// messages, respecting the `attach_stacktrace` option.
+/// messages, respecting the `attach_stacktrace` option.  The backtrace is
+/// taken on the capturing thread, even if the event is processed on another
+/// one.
 #[derive(Debug, Default)]
 pub struct AttachStacktraceIntegration;
 
@@ -68,18 +94,13 @@ impl Integration for AttachStacktraceIntegration {
         "attach-stacktrace"
     }
 
-    fn process_event(
-        &self,
-        mut event: Event<'static>,
-        options: &ClientOptions,
-    ) -> Option<Event<'static>> {
-        if options.attach_stacktrace && !has_stacktrace(&event) {
+    fn prepare_event(&self, event: &mut Event<'static>, options: &ClientOptions) {
+        if options.attach_stacktrace && !has_stacktrace(event) {
             let thread = current_thread(true);
             if thread.stacktrace.is_some() {
                 event.threads.values.push(thread);
             }
         }
-        Some(event)
     }
 }
 
@@ -109,3 +130,45 @@ pub fn current_thread(with_stack: bool) -> Thread {
         ..Default::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarded_frames_are_not_resolved() {
+        let event = Event {
+            stacktrace: crate::current_unresolved_stacktrace(),
+            ..Default::default()
+        };
+        let options = ClientOptions::default();
+        let integration = ProcessStacktraceIntegration;
+
+        let local = integration.process_event(event.clone(), &options).unwrap();
+        let frames = local.stacktrace.unwrap().frames;
+        assert!(frames.iter().any(|frame| frame.function.is_some()));
+
+        let hint = Hint::new().forwarded();
+        let forwarded = integration
+            .process_event_with_hint(event, &hint, &options)
+            .unwrap();
+        let frames = forwarded.stacktrace.unwrap().frames;
+        assert!(frames.iter().all(|frame| frame.function.is_none()));
+    }
+
+    #[test]
+    fn test_only_deferred_frames_are_resolved() {
+        // such as the frames of a minidump or of a hand-built event
+        let mut stacktrace = crate::current_unresolved_stacktrace().unwrap();
+        stacktrace.deferred_resolution = false;
+        let event = Event {
+            stacktrace: Some(stacktrace),
+            ..Default::default()
+        };
+        let processed = ProcessStacktraceIntegration
+            .process_event(event, &ClientOptions::default())
+            .unwrap();
+        let frames = processed.stacktrace.unwrap().frames;
+        assert!(frames.iter().all(|frame| frame.function.is_none()));
+    }
+}
@@ -7,17 +7,22 @@
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
 #![warn(missing_docs)]
 
+mod filter;
 mod integration;
 mod parse;
 mod process;
 mod trim;
 mod utils;
 
+pub use crate::filter::{filter_frames, register_frame_filter, unregister_frame_filter};
 pub use crate::integration::{
     current_thread, AttachStacktraceIntegration, ProcessStacktraceIntegration,
 };
 pub use crate::parse::parse_stacktrace;
-pub use crate::process::{backtrace_to_stacktrace, process_event_stacktrace};
+pub use crate::process::{
+    backtrace_to_stacktrace, process_event_stacktrace, resolve_stacktrace,
+    unresolved_backtrace_to_stacktrace,
+};
 pub use crate::trim::trim_stacktrace;
 pub use sentry_core::protocol::{Frame, Stacktrace};
 
@@ -25,3 +30,15 @@ pub use sentry_core::protocol::{Frame, Stacktrace};
 pub fn current_stacktrace() -> Option<Stacktrace> {
     backtrace_to_stacktrace(&backtrace::Backtrace::new())
 }
+
+/// Returns the current backtrace as sentry stacktrace, without resolving symbols.
+///
+/// Resolving symbols is by far the most expensive part of capturing a
+/// backtrace.  The frames of the returned stacktrace only carry their
+/// instruction address, and are resolved by the [`ProcessStacktraceIntegration`]
+/// once the event is processed, which happens on a background thread with the
+/// `background_processing` option.  They can also be resolved manually via
+/// [`resolve_stacktrace`].
+pub fn current_unresolved_stacktrace() -> Option<Stacktrace> {
+    unresolved_backtrace_to_stacktrace(&backtrace::Backtrace::new_unresolved())
+}
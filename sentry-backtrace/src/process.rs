@@ -1,18 +1,22 @@
 use std::borrow::Cow;
+use std::path::Path;
 
-use backtrace::Backtrace;
+use backtrace::{Backtrace, SymbolName};
+use sentry_core::protocol::Addr;
 use sentry_core::ClientOptions;
 
+use crate::filter::filter_frames;
 use crate::trim::{is_sys_function, trim_stacktrace};
 use crate::utils::{
-    demangle_symbol, filename, function_starts_with, parse_crate_name, strip_symbol,
+    demangle_symbol, filename, function_starts_with, is_in_app_path, parse_crate_name, strip_symbol,
 };
 use crate::{Frame, Stacktrace};
 
 /// Processes a `Stacktrace`.
 ///
-/// Trims a `Stacktrace` and marks frames as in-app based on the provided
-/// `ClientOptions`.
+/// Trims a `Stacktrace`, removes frames matched by the registered frame
+/// filters if `apply_frame_filters` is set, and marks frames as in-app based
+/// on the provided `ClientOptions`.
 pub fn process_event_stacktrace(stacktrace: &mut Stacktrace, options: &ClientOptions) {
     // automatically trim backtraces
     if options.trim_backtraces {
@@ -22,7 +26,10 @@ pub fn process_event_stacktrace(stacktrace: &mut Stacktrace, options: &ClientOpt
             } else {
                 false
             }
-        })
+        });
+        if options.apply_frame_filters {
+            filter_frames(stacktrace);
+        }
     }
 
     // automatically prime in_app and set package
@@ -72,6 +79,14 @@ pub fn process_event_stacktrace(stacktrace: &mut Stacktrace, options: &ClientOpt
             continue;
         }
 
+        // file paths are more reliable than function names, which for generic
+        // code are often prefixed with the crate that defines the type
+        if let Some(in_app) = frame.abs_path.as_deref().and_then(is_in_app_path) {
+            frame.in_app = Some(in_app);
+            any_in_app |= in_app;
+            continue;
+        }
+
         if is_sys_function(func_name) {
             frame.in_app = Some(false);
         }
@@ -98,27 +113,7 @@ pub fn backtrace_to_stacktrace(bt: &Backtrace) -> Option<Stacktrace> {
             symbols
                 .iter()
                 .map(move |sym| {
-                    let abs_path = sym.filename().map(|m| m.to_string_lossy().to_string());
-                    let filename = abs_path.as_ref().map(|p| filename(p).to_string());
-                    let real_symbol = sym
-                        .name()
-                        .map_or(Cow::Borrowed("<unknown>"), |n| Cow::Owned(n.to_string()));
-                    let symbol = strip_symbol(&real_symbol);
-                    let function = demangle_symbol(&symbol);
-                    Frame {
-                        symbol: if symbol != function {
-                            Some(symbol.into())
-                        } else {
-                            None
-                        },
-                        function: Some(function),
-                        instruction_addr: Some(frame.ip().into()),
-                        abs_path,
-                        filename,
-                        lineno: sym.lineno().map(u64::from),
-                        colno: None,
-                        ..Default::default()
-                    }
+                    symbol_to_frame(frame.ip().into(), sym.name(), sym.filename(), sym.lineno())
 
                     // If there were no symbols at all, make sure to add at least one frame, as we
                     // may be able to symbolicate it on the server.
@@ -126,7 +121,7 @@ pub fn backtrace_to_stacktrace(bt: &Backtrace) -> Option<Stacktrace> {
                 .chain(if symbols.is_empty() {
                     Some(Frame {
                         instruction_addr: Some(frame.ip().into()),
-                        function: Some("<unknown>".into()),
+                        function: Some(UNKNOWN_FUNCTION.into()),
                         ..Default::default()
                     })
                 } else {
@@ -136,3 +131,96 @@ pub fn backtrace_to_stacktrace(bt: &Backtrace) -> Option<Stacktrace> {
         .collect();
     Stacktrace::from_frames_reversed(frames)
 }
+
+/// Resolves the symbols of a stacktrace whose resolution was deferred.
+///
+/// This is the counterpart to capturing a stacktrace without resolving it,
+/// which is much cheaper on the capturing thread.  Only stacktraces marked
+/// with [`deferred_resolution`](Stacktrace::deferred_resolution) are resolved,
+/// all others are left as they are.  Frames that cannot be resolved are kept,
+/// so they can be symbolicated on the server.
+pub fn resolve_stacktrace(stacktrace: &mut Stacktrace) {
+    if !stacktrace.deferred_resolution {
+        return;
+    }
+    stacktrace.deferred_resolution = false;
+
+    let frames = std::mem::take(&mut stacktrace.frames);
+    for frame in frames {
+        let addr = match frame.instruction_addr {
+            Some(addr) => addr,
+            None => {
+                stacktrace.frames.push(frame);
+                continue;
+            }
+        };
+
+        // Frames are ordered from the outermost to the innermost, but inlined
+        // symbols are resolved from the innermost to the outermost.
+        let mut symbols = vec![];
+        // The return address points to the instruction after the call.
+        let ip = (addr.0 as usize).saturating_sub(1) as *mut std::ffi::c_void;
+        backtrace::resolve(ip, |sym| {
+            symbols.push(symbol_to_frame(
+                addr,
+                sym.name(),
+                sym.filename(),
+                sym.lineno(),
+            ));
+        });
+        if symbols.is_empty() {
+            stacktrace.frames.push(frame);
+        } else {
+            stacktrace.frames.extend(symbols.into_iter().rev());
+        }
+    }
+}
+
+const UNKNOWN_FUNCTION: &str = "<unknown>";
+
+/// Converts an unresolved `backtrace::Backtrace` into a `Stacktrace`.
+///
+/// The frames only carry their instruction address, and the stacktrace is
+/// marked for [`resolve_stacktrace`], which resolves them later on.
+pub fn unresolved_backtrace_to_stacktrace(bt: &Backtrace) -> Option<Stacktrace> {
+    let frames = bt
+        .frames()
+        .iter()
+        .map(|frame| Frame {
+            instruction_addr: Some(frame.ip().into()),
+            ..Default::default()
+        })
+        .collect();
+    let mut stacktrace = Stacktrace::from_frames_reversed(frames)?;
+    stacktrace.deferred_resolution = true;
+    Some(stacktrace)
+}
+
+fn symbol_to_frame(
+    addr: Addr,
+    name: Option<SymbolName>,
+    path: Option<&Path>,
+    lineno: Option<u32>,
+) -> Frame {
+    let abs_path = path.map(|m| m.to_string_lossy().to_string());
+    let filename = abs_path.as_ref().map(|p| filename(p).to_string());
+    let real_symbol = name.map_or(Cow::Borrowed(UNKNOWN_FUNCTION), |n| {
+        Cow::Owned(n.to_string())
+    });
+    let symbol = strip_symbol(&real_symbol);
+    let function = demangle_symbol(&symbol);
+    Frame {
+        symbol: if symbol != function {
+            Some(symbol.into())
+        } else {
+            None
+        },
+        function: Some(function),
+        instruction_addr: Some(addr),
+        abs_path,
+        filename,
+        lineno: lineno.map(u64::from),
+        colno: None,
+        ..Default::default()
+    }
+}
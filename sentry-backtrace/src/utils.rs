@@ -124,10 +124,61 @@ pub fn function_starts_with(mut func_name: &str, mut pattern: &str) -> bool {
         .all(|(f, p)| f == p || f == '.' && p == ':')
 }
 
+/// Classifies a frame as in-app based on its source file path.
+///
+/// Sources of dependencies downloaded by cargo and of the standard library
+/// are never in-app.  Cargo passes the sources of workspace members to rustc
+/// as relative paths, which is why those are considered in-app.  Returns
+/// `None` for all other paths.
+pub fn is_in_app_path(path: &str) -> Option<bool> {
+    let path = path.replace('\\', "/");
+    let is_dependency = path.contains("/.cargo/registry/")
+        || path.contains("/.cargo/git/")
+        || path.contains("/lib/rustlib/src/rust/")
+        || path.starts_with("/rustc/");
+    if is_dependency {
+        Some(false)
+    } else if is_relative_path(&path) {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+fn is_relative_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let is_windows_absolute = bytes.len() >= 3 && bytes[1] == b':' && bytes[2] == b'/';
+    !(path.is_empty() || path.starts_with('/') || path.starts_with('<') || is_windows_absolute)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_is_in_app_path() {
+        assert_eq!(
+            is_in_app_path(
+                "/home/user/.cargo/registry/src/github.com-1ecc6299db9ec823/tokio-1.24.1/src/runtime/mod.rs"
+            ),
+            Some(false)
+        );
+        assert_eq!(
+            is_in_app_path(r"C:\Users\user\.cargo\git\checkouts\foo-1234\src\lib.rs"),
+            Some(false)
+        );
+        assert_eq!(
+            is_in_app_path(
+                "/rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/panicking.rs"
+            ),
+            Some(false)
+        );
+        assert_eq!(is_in_app_path("src/main.rs"), Some(true));
+        assert_eq!(is_in_app_path(r"server\src\lib.rs"), Some(true));
+        assert_eq!(is_in_app_path("/home/user/project/src/main.rs"), None);
+        assert_eq!(is_in_app_path(r"C:\project\src\main.rs"), None);
+    }
+
     #[test]
     fn test_function_starts_with() {
         assert!(function_starts_with(
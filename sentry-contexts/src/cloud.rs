@@ -0,0 +1,365 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use sentry_core::protocol::{Context, Event, Map, Value};
+use sentry_core::{ClientOptions, Integration};
+
+/// The link-local address of the instance metadata service of AWS, GCP and
+/// Azure.
+const METADATA_ADDRESS: ([u8; 4], u16) = ([169, 254, 169, 254], 80);
+
+/// Adds a `cloud_resource` Context with the cloud instance to Sentry Events.
+///
+/// This integration is opt-in.  When the client is initialized, it queries
+/// the instance metadata service of AWS EC2, Google Compute Engine or Azure
+/// once, and adds the provider, region, availability zone, account, and
+/// instance id and type to every event, with the keys of the
+/// [Cloud Resource Context] (`cloud.provider`, `cloud.region`, `host.id`, …).
+///
+/// Every request to the metadata service uses the
+/// [`timeout`](Self::timeout), so outside of a cloud instance, `sentry::init`
+/// is delayed by at most that duration.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// let integration = sentry_contexts::CloudIntegration::new().timeout(Duration::from_millis(100));
+/// let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(integration));
+/// ```
+///
+/// [Cloud Resource Context]: https://develop.sentry.dev/sdk/event-payloads/contexts/#cloud-resource-context
+#[derive(Debug)]
+pub struct CloudIntegration {
+    address: SocketAddr,
+    timeout: Duration,
+    context: Mutex<Option<Map<String, Value>>>,
+}
+
+impl Default for CloudIntegration {
+    fn default() -> Self {
+        Self {
+            address: METADATA_ADDRESS.into(),
+            timeout: Duration::from_millis(300),
+            context: Mutex::new(None),
+        }
+    }
+}
+
+impl CloudIntegration {
+    /// Creates a new Cloud Integration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the timeout of the requests to the metadata service.
+    /// (defaults to 300ms)
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn query(&self) -> Map<String, Value> {
+        // outside of a cloud instance, the connection usually times out, so
+        // fail early instead of once per provider
+        if TcpStream::connect_timeout(&self.address, self.timeout).is_err() {
+            return Map::new();
+        }
+        self.query_aws()
+            .or_else(|| self.query_gcp())
+            .or_else(|| self.query_azure())
+            .unwrap_or_default()
+    }
+
+    fn query_aws(&self) -> Option<Map<String, Value>> {
+        let token = self.request(
+            "PUT",
+            "/latest/api/token",
+            &[("X-aws-ec2-metadata-token-ttl-seconds", "60")],
+        )?;
+        let document = self.request(
+            "GET",
+            "/latest/dynamic/instance-identity/document",
+            &[("X-aws-ec2-metadata-token", token.trim())],
+        )?;
+        parse_aws(&document)
+    }
+
+    fn query_gcp(&self) -> Option<Map<String, Value>> {
+        let instance = self.request(
+            "GET",
+            "/computeMetadata/v1/instance/?recursive=true",
+            &[("Metadata-Flavor", "Google")],
+        )?;
+        parse_gcp(&instance)
+    }
+
+    fn query_azure(&self) -> Option<Map<String, Value>> {
+        let compute = self.request(
+            "GET",
+            "/metadata/instance/compute?api-version=2021-02-01",
+            &[("Metadata", "true")],
+        )?;
+        parse_azure(&compute)
+    }
+
+    /// Sends a request to the metadata service, and returns the body of a
+    /// successful response.
+    fn request(&self, method: &str, path: &str, headers: &[(&str, &str)]) -> Option<String> {
+        let mut stream = TcpStream::connect_timeout(&self.address, self.timeout).ok()?;
+        stream.set_read_timeout(Some(self.timeout)).ok()?;
+        stream.set_write_timeout(Some(self.timeout)).ok()?;
+
+        // HTTP/1.0 responses are never chunked, and end with the connection
+        let mut request = format!(
+            "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Length: 0\r\n",
+            method,
+            path,
+            self.address.ip()
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).ok()?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        let (head, body) = response.split_once("\r\n\r\n")?;
+        let status = head.split_whitespace().nth(1)?;
+        (status == "200").then(|| body.to_owned())
+    }
+}
+
+impl Integration for CloudIntegration {
+    fn name(&self) -> &'static str {
+        "cloud"
+    }
+
+    fn setup(&self, _options: &mut ClientOptions) {
+        let mut context = self.context.lock().unwrap();
+        if context.is_none() {
+            *context = Some(self.query());
+        }
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _cfg: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        if let Some(context) = &*self.context.lock().unwrap() {
+            if !context.is_empty() {
+                event
+                    .contexts
+                    .entry("cloud_resource".into())
+                    .or_insert_with(|| Context::Other(context.clone()));
+            }
+        }
+        Some(event)
+    }
+}
+
+/// Builds the context from the given provider and platform, and the string
+/// fields of the metadata.
+fn cloud_context(
+    provider: &str,
+    platform: &str,
+    metadata: &Value,
+    fields: &[(&str, &str)],
+) -> Map<String, Value> {
+    let mut context = Map::new();
+    context.insert("cloud.provider".into(), provider.into());
+    context.insert("cloud.platform".into(), platform.into());
+    for (field, key) in fields {
+        match metadata.get(field) {
+            Some(Value::String(value)) if !value.is_empty() => {
+                context.insert((*key).into(), value.as_str().into());
+            }
+            // GCP has numeric instance ids
+            Some(Value::Number(value)) => {
+                context.insert((*key).into(), value.to_string().into());
+            }
+            _ => {}
+        }
+    }
+    context
+}
+
+/// Parses the AWS instance identity document.
+fn parse_aws(document: &str) -> Option<Map<String, Value>> {
+    let document: Value = serde_json::from_str(document).ok()?;
+    Some(cloud_context(
+        "aws",
+        "aws_ec2",
+        &document,
+        &[
+            ("region", "cloud.region"),
+            ("availabilityZone", "cloud.availability_zone"),
+            ("accountId", "cloud.account.id"),
+            ("instanceId", "host.id"),
+            ("instanceType", "host.type"),
+        ],
+    ))
+}
+
+/// Parses the GCP instance metadata, where the zone and machine type are
+/// given as resource paths like `projects/123/zones/us-central1-a`.
+fn parse_gcp(instance: &str) -> Option<Map<String, Value>> {
+    let mut instance: Value = serde_json::from_str(instance).ok()?;
+    let object = instance.as_object_mut()?;
+    for field in ["zone", "machineType"] {
+        if let Some(Value::String(value)) = object.get_mut(field) {
+            if let Some((_, name)) = value.rsplit_once('/') {
+                *value = name.to_owned();
+            }
+        }
+    }
+    let region = object
+        .get("zone")
+        .and_then(Value::as_str)
+        .and_then(|zone| zone.rsplit_once('-'))
+        .map(|(region, _)| region.to_owned());
+    let mut context = cloud_context(
+        "gcp",
+        "gcp_compute_engine",
+        &instance,
+        &[
+            ("zone", "cloud.availability_zone"),
+            ("id", "host.id"),
+            ("machineType", "host.type"),
+        ],
+    );
+    if let Some(region) = region {
+        context.insert("cloud.region".into(), region.into());
+    }
+    Some(context)
+}
+
+/// Parses the Azure compute metadata.
+fn parse_azure(compute: &str) -> Option<Map<String, Value>> {
+    let compute: Value = serde_json::from_str(compute).ok()?;
+    Some(cloud_context(
+        "azure",
+        "azure_vm",
+        &compute,
+        &[
+            ("location", "cloud.region"),
+            ("zone", "cloud.availability_zone"),
+            ("subscriptionId", "cloud.account.id"),
+            ("vmId", "host.id"),
+            ("vmSize", "host.type"),
+        ],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_gcp() {
+        let context = parse_gcp(
+            r#"{
+                "id": 4520031799277581759,
+                "zone": "projects/839437152/zones/europe-west1-b",
+                "machineType": "projects/839437152/machineTypes/e2-medium"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(context["cloud.provider"], "gcp");
+        assert_eq!(context["cloud.region"], "europe-west1");
+        assert_eq!(context["cloud.availability_zone"], "europe-west1-b");
+        assert_eq!(context["host.id"], "4520031799277581759");
+        assert_eq!(context["host.type"], "e2-medium");
+    }
+
+    #[test]
+    fn test_parse_azure() {
+        let context = parse_azure(
+            r#"{
+                "location": "westeurope",
+                "zone": "",
+                "subscriptionId": "8d10da13-8125-4ba9-a717-bf7490507b3d",
+                "vmId": "02aab8a4-74ef-476e-8182-f6d2ba4166a6",
+                "vmSize": "Standard_D2s_v3"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(context["cloud.provider"], "azure");
+        assert_eq!(context["cloud.region"], "westeurope");
+        assert!(!context.contains_key("cloud.availability_zone"));
+        assert_eq!(context["host.type"], "Standard_D2s_v3");
+    }
+
+    #[test]
+    fn test_query_aws() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut lines = std::io::BufReader::new(&stream).lines();
+                // the connection that probes the service sends no request
+                let request_line = match lines.next() {
+                    Some(Ok(line)) => line,
+                    _ => continue,
+                };
+                let headers: Vec<_> = lines
+                    .map(Result::unwrap)
+                    .take_while(|line| !line.is_empty())
+                    .collect();
+                let body = match request_line.as_str() {
+                    "PUT /latest/api/token HTTP/1.0" => "token",
+                    "GET /latest/dynamic/instance-identity/document HTTP/1.0"
+                        if headers
+                            .iter()
+                            .any(|h| h == "X-aws-ec2-metadata-token: token") =>
+                    {
+                        r#"{"region": "eu-central-1", "availabilityZone": "eu-central-1a",
+                            "instanceId": "i-0b22a22eec53b9321", "instanceType": "t3.micro",
+                            "accountId": "123456789012"}"#
+                    }
+                    _ => {
+                        let _ = stream.write_all(b"HTTP/1.0 404 Not Found\r\n\r\n");
+                        continue;
+                    }
+                };
+                let _ = write!(stream, "HTTP/1.0 200 OK\r\n\r\n{}", body);
+            }
+        });
+
+        let integration = CloudIntegration {
+            address,
+            ..CloudIntegration::new()
+        };
+        let context = integration.query();
+        assert_eq!(context["cloud.provider"], "aws");
+        assert_eq!(context["cloud.platform"], "aws_ec2");
+        assert_eq!(context["cloud.region"], "eu-central-1");
+        assert_eq!(context["cloud.availability_zone"], "eu-central-1a");
+        assert_eq!(context["cloud.account.id"], "123456789012");
+        assert_eq!(context["host.id"], "i-0b22a22eec53b9321");
+        assert_eq!(context["host.type"], "t3.micro");
+    }
+
+    #[test]
+    fn test_query_unreachable() {
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let integration = CloudIntegration {
+            address,
+            ..CloudIntegration::new()
+        };
+        assert!(integration.query().is_empty());
+    }
+}
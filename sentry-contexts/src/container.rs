@@ -0,0 +1,285 @@
+use std::fs;
+
+use sentry_core::protocol::{Context, Event, Map, Value};
+use sentry_core::{ClientOptions, Integration};
+
+/// The environment variables of the Kubernetes downward API that are read,
+/// and the keys of the `k8s` context they are added as.
+const K8S_ENV_VARS: &[(&str, &str)] = &[
+    ("POD_NAME", "pod_name"),
+    ("POD_NAMESPACE", "namespace"),
+    ("POD_UID", "pod_uid"),
+    ("POD_IP", "pod_ip"),
+    ("NODE_NAME", "node_name"),
+];
+
+/// Adds `container` and `k8s` Contexts to Sentry Events.
+///
+/// This integration is opt-in, and detects at startup whether the process
+/// runs in a container, and in a Kubernetes pod:
+///
+/// - The `container` context has the container `id` and `runtime` from the
+///   cgroup of the process, and the `memory_limit` (in bytes) and
+///   `cpu_limit` (in CPUs) of the cgroup.  The `name` and `image` of the
+///   container are taken from the `CONTAINER_NAME` and `CONTAINER_IMAGE`
+///   environment variables.
+/// - The `k8s` context is added if `KUBERNETES_SERVICE_HOST` is set.  It has
+///   the `pod_name`, `namespace`, `pod_uid`, `pod_ip` and `node_name` from
+///   the `POD_NAME`, `POD_NAMESPACE`, `POD_UID`, `POD_IP` and `NODE_NAME`
+///   environment variables, which can be exposed with the [downward API].
+///   Without them, the pod name defaults to the hostname and the namespace
+///   to the one of the service account.
+///
+/// # Examples
+///
+/// ```rust
+/// let integration = sentry_contexts::ContainerIntegration::new();
+/// let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(integration));
+/// ```
+///
+/// [downward API]: https://kubernetes.io/docs/concepts/workloads/pods/downward-api/
+#[derive(Debug)]
+pub struct ContainerIntegration {
+    container: Map<String, Value>,
+    k8s: Map<String, Value>,
+}
+
+impl Default for ContainerIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContainerIntegration {
+    /// Creates a new Container Integration, detecting the environment of the
+    /// process.
+    pub fn new() -> Self {
+        Self::detect(
+            |path| fs::read_to_string(path).ok(),
+            |name| std::env::var(name).ok(),
+        )
+    }
+
+    fn detect<R, E>(read: R, env: E) -> Self
+    where
+        R: Fn(&str) -> Option<String>,
+        E: Fn(&str) -> Option<String>,
+    {
+        let mut container = Map::new();
+        let mut k8s = Map::new();
+
+        let cgroup = read("/proc/self/cgroup").unwrap_or_default();
+        if let Some((runtime, id)) = container_from_cgroup(&cgroup) {
+            container.insert("runtime".into(), runtime.into());
+            container.insert("id".into(), id.into());
+        }
+        if let Some(name) = env("CONTAINER_NAME") {
+            container.insert("name".into(), name.into());
+        }
+        if let Some(image) = env("CONTAINER_IMAGE") {
+            container.insert("image".into(), image.into());
+        }
+        if !container.is_empty() {
+            if let Some(limit) = memory_limit(&read) {
+                container.insert("memory_limit".into(), limit.into());
+            }
+            if let Some(limit) = cpu_limit(&read) {
+                container.insert("cpu_limit".into(), limit.into());
+            }
+        }
+
+        if env("KUBERNETES_SERVICE_HOST").is_some() {
+            for (var, key) in K8S_ENV_VARS {
+                if let Some(value) = env(var) {
+                    k8s.insert((*key).into(), value.into());
+                }
+            }
+            if !k8s.contains_key("pod_name") {
+                if let Some(hostname) = env("HOSTNAME") {
+                    k8s.insert("pod_name".into(), hostname.into());
+                }
+            }
+            if !k8s.contains_key("namespace") {
+                let namespace = read("/var/run/secrets/kubernetes.io/serviceaccount/namespace");
+                if let Some(namespace) = namespace {
+                    k8s.insert("namespace".into(), namespace.trim().into());
+                }
+            }
+        }
+
+        ContainerIntegration { container, k8s }
+    }
+}
+
+impl Integration for ContainerIntegration {
+    fn name(&self) -> &'static str {
+        "container"
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _cfg: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        for (key, context) in [("container", &self.container), ("k8s", &self.k8s)] {
+            if !context.is_empty() {
+                event
+                    .contexts
+                    .entry(key.to_string())
+                    .or_insert_with(|| Context::Other(context.clone()));
+            }
+        }
+        Some(event)
+    }
+}
+
+/// Returns the container runtime and id from the contents of
+/// `/proc/self/cgroup`.
+fn container_from_cgroup(cgroup: &str) -> Option<(&'static str, String)> {
+    cgroup.lines().find_map(|line| {
+        let path = line.splitn(3, ':').nth(2)?;
+        let runtime = if path.contains("docker") {
+            "docker"
+        } else if path.contains("containerd") {
+            "containerd"
+        } else if path.contains("crio") {
+            "cri-o"
+        } else if path.contains("libpod") {
+            "podman"
+        } else if path.contains("kubepods") {
+            "kubernetes"
+        } else {
+            return None;
+        };
+        // the id is the last 64 hex characters, as in `/docker/<id>` or
+        // `cri-containerd-<id>.scope`
+        let id = path.rsplit('/').find_map(|component| {
+            let component = component.strip_suffix(".scope").unwrap_or(component);
+            let id = component.get(component.len().checked_sub(64)?..)?;
+            id.bytes()
+                .all(|b| b.is_ascii_hexdigit())
+                .then(|| id.to_owned())
+        })?;
+        Some((runtime, id))
+    })
+}
+
+/// Returns the memory limit of the cgroup in bytes.
+fn memory_limit<R: Fn(&str) -> Option<String>>(read: &R) -> Option<u64> {
+    let limit = read("/sys/fs/cgroup/memory.max")
+        .or_else(|| read("/sys/fs/cgroup/memory/memory.limit_in_bytes"))?;
+    let limit: u64 = limit.trim().parse().ok()?;
+    // cgroup v1 reports a huge number instead of no limit
+    if limit >= i64::MAX as u64 / 2 {
+        return None;
+    }
+    Some(limit)
+}
+
+/// Returns the CPU limit of the cgroup as a number of CPUs.
+fn cpu_limit<R: Fn(&str) -> Option<String>>(read: &R) -> Option<f64> {
+    let (quota, period) = match read("/sys/fs/cgroup/cpu.max") {
+        Some(max) => {
+            let mut parts = max.split_whitespace();
+            (parts.next()?.parse().ok()?, parts.next()?.parse().ok()?)
+        }
+        None => (
+            read("/sys/fs/cgroup/cpu/cpu.cfs_quota_us")?
+                .trim()
+                .parse()
+                .ok()?,
+            read("/sys/fs/cgroup/cpu/cpu.cfs_period_us")?
+                .trim()
+                .parse()
+                .ok()?,
+        ),
+    };
+    let (quota, period): (f64, f64) = (quota, period);
+    if quota <= 0.0 || period <= 0.0 {
+        return None;
+    }
+    Some(quota / period)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const ID: &str = "4f3c2d1e0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d";
+
+    fn detect(files: &[(&str, &str)], env: &[(&str, &str)]) -> ContainerIntegration {
+        let files: HashMap<_, _> = files.iter().copied().collect();
+        let env: HashMap<_, _> = env.iter().copied().collect();
+        ContainerIntegration::detect(
+            |path| files.get(path).map(|s| s.to_string()),
+            |name| env.get(name).map(|s| s.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_container_from_cgroup() {
+        assert_eq!(
+            container_from_cgroup(&format!("0::/docker/{}\n", ID)),
+            Some(("docker", ID.to_owned()))
+        );
+        assert_eq!(
+            container_from_cgroup(&format!(
+                "0::/kubepods.slice/kubepods-pod1.slice/cri-containerd-{}.scope",
+                ID
+            )),
+            Some(("containerd", ID.to_owned()))
+        );
+        assert_eq!(
+            container_from_cgroup("0::/system.slice/nginx.service"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detect_kubernetes() {
+        let integration = detect(
+            &[
+                ("/proc/self/cgroup", &format!("0::/docker/{}", ID)),
+                ("/sys/fs/cgroup/memory.max", "536870912\n"),
+                ("/sys/fs/cgroup/cpu.max", "150000 100000\n"),
+                (
+                    "/var/run/secrets/kubernetes.io/serviceaccount/namespace",
+                    "payments",
+                ),
+            ],
+            &[
+                ("KUBERNETES_SERVICE_HOST", "10.0.0.1"),
+                ("HOSTNAME", "api-7d9f8-x2x4z"),
+                ("NODE_NAME", "node-3"),
+                ("CONTAINER_IMAGE", "registry.example.com/api:1.2.3"),
+            ],
+        );
+
+        assert_eq!(integration.container["id"], ID);
+        assert_eq!(integration.container["runtime"], "docker");
+        assert_eq!(
+            integration.container["image"],
+            "registry.example.com/api:1.2.3"
+        );
+        assert_eq!(integration.container["memory_limit"], 536870912);
+        assert_eq!(integration.container["cpu_limit"], 1.5);
+        assert_eq!(integration.k8s["pod_name"], "api-7d9f8-x2x4z");
+        assert_eq!(integration.k8s["namespace"], "payments");
+        assert_eq!(integration.k8s["node_name"], "node-3");
+    }
+
+    #[test]
+    fn test_detect_no_container() {
+        let integration = detect(
+            &[
+                ("/proc/self/cgroup", "0::/user.slice/user-1000.slice"),
+                ("/sys/fs/cgroup/memory.max", "max\n"),
+            ],
+            &[("HOSTNAME", "laptop")],
+        );
+        assert!(integration.container.is_empty());
+        assert!(integration.k8s.is_empty());
+    }
+}
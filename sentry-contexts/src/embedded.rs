@@ -0,0 +1,176 @@
+use std::fmt::Write;
+use std::sync::RwLock;
+
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sentry_core::protocol::{Context, DeviceContext, Map};
+use sentry_core::ClientOptions;
+use sha2::Sha256;
+
+static EMBEDDED_DEVICE: Lazy<RwLock<Option<EmbeddedDevice>>> = Lazy::new(Default::default);
+
+/// The hardware and firmware of an embedded device or IoT gateway.
+///
+/// The device is registered with [`set_embedded_device`], and added to the
+/// `device` context of all events.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EmbeddedDevice {
+    /// The model of the device, added as `model`.
+    pub model: Option<String>,
+    /// The hardware revision of the device, added as `model_id`.
+    pub hardware_revision: Option<String>,
+    /// The version of the firmware, added as `firmware_version`.
+    pub firmware_version: Option<String>,
+    /// The serial number or other unique id of the device, added as
+    /// `device_unique_identifier`.
+    ///
+    /// Unless [`send_default_pii`](sentry_core::ClientOptions::send_default_pii)
+    /// is enabled, only the hex encoded HMAC-SHA256 of the serial is sent,
+    /// keyed with the public key of the DSN.  It still identifies the device
+    /// across the events sent with that key, but differs between keys.
+    ///
+    /// The hash is pseudonymous, not anonymous: serials are often short or
+    /// sequential, so anyone who knows the DSN, which is usually embedded in
+    /// the firmware, can recover them by hashing candidate serials.
+    pub serial: Option<String>,
+}
+
+/// Registers the embedded device the application runs on.
+///
+/// The [`ContextIntegration`](crate::ContextIntegration) adds it to the
+/// `device` context of all events, so fleets can be sliced by hardware
+/// revision and firmware version.  Fields of the `device` context that are
+/// already set, for instance on the scope, are kept.
+///
+/// # Examples
+///
+/// ```
+/// use sentry_contexts::EmbeddedDevice;
+///
+/// sentry_contexts::set_embedded_device(EmbeddedDevice {
+///     model: Some("Gateway 200".into()),
+///     hardware_revision: Some("rev-c".into()),
+///     firmware_version: Some("4.2.1".into()),
+///     serial: Some("GW200-00042".into()),
+/// });
+/// ```
+pub fn set_embedded_device(device: EmbeddedDevice) {
+    *EMBEDDED_DEVICE.write().unwrap() = Some(device);
+}
+
+/// Adds the registered embedded device to the `device` context.
+pub(crate) fn apply_embedded_device(contexts: &mut Map<String, Context>, options: &ClientOptions) {
+    let guard = EMBEDDED_DEVICE.read().unwrap();
+    let device = match &*guard {
+        Some(device) => device,
+        None => return,
+    };
+    let context = match contexts
+        .entry("device".into())
+        .or_insert_with(|| DeviceContext::default().into())
+    {
+        Context::Device(context) => context,
+        _ => return,
+    };
+    if context.model.is_none() {
+        context.model = device.model.clone();
+    }
+    if context.model_id.is_none() {
+        context.model_id = device.hardware_revision.clone();
+    }
+    if let Some(firmware_version) = &device.firmware_version {
+        context
+            .other
+            .entry("firmware_version".into())
+            .or_insert_with(|| firmware_version.as_str().into());
+    }
+    if let Some(serial) = &device.serial {
+        context
+            .other
+            .entry("device_unique_identifier".into())
+            .or_insert_with(|| {
+                if options.send_default_pii {
+                    serial.as_str().into()
+                } else {
+                    let key = options.dsn.as_ref().map_or("", |dsn| dsn.public_key());
+                    hash_serial(key, serial).into()
+                }
+            });
+    }
+}
+
+/// Computes the hex encoded HMAC-SHA256 of `serial`, keyed with `key`.
+fn hash_serial(key: &str, serial: &str) -> String {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).unwrap();
+    mac.update(serial.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry_core::protocol::Event;
+    use sentry_core::{ClientOptions, Integration};
+
+    use super::*;
+    use crate::ContextIntegration;
+
+    #[test]
+    fn test_embedded_device() {
+        set_embedded_device(EmbeddedDevice {
+            model: Some("Gateway 200".into()),
+            hardware_revision: Some("rev-c".into()),
+            firmware_version: Some("4.2.1".into()),
+            serial: Some("GW200-00042".into()),
+        });
+        let integration = ContextIntegration::new().add_device(false);
+
+        let options = ClientOptions {
+            dsn: Some("https://public@example.com/1".parse().unwrap()),
+            ..Default::default()
+        };
+        let event = integration.process_event(Event::new(), &options).unwrap();
+        let device = match &event.contexts["device"] {
+            Context::Device(device) => device,
+            context => panic!("unexpected device context: {:?}", context),
+        };
+        assert_eq!(device.model.as_deref(), Some("Gateway 200"));
+        assert_eq!(device.model_id.as_deref(), Some("rev-c"));
+        assert_eq!(device.other["firmware_version"], "4.2.1");
+        assert_eq!(
+            device.other["device_unique_identifier"],
+            hash_serial("public", "GW200-00042")
+        );
+        assert_ne!(
+            hash_serial("public", "GW200-00042"),
+            hash_serial("other", "GW200-00042")
+        );
+
+        let options = ClientOptions {
+            send_default_pii: true,
+            ..Default::default()
+        };
+        let event = integration.process_event(Event::new(), &options).unwrap();
+        match &event.contexts["device"] {
+            Context::Device(device) => {
+                assert_eq!(device.other["device_unique_identifier"], "GW200-00042")
+            }
+            context => panic!("unexpected device context: {:?}", context),
+        }
+    }
+
+    #[test]
+    fn test_hash_serial() {
+        // test case 2 of RFC 4231
+        assert_eq!(
+            hash_serial("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
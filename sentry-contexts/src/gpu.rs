@@ -0,0 +1,221 @@
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use sentry_core::protocol::{Context, GpuContext};
+
+static GPU_CONTEXT: Lazy<RwLock<Option<GpuContext>>> = Lazy::new(Default::default);
+
+/// Registers the GPU the application renders with.
+///
+/// The [`ContextIntegration`](crate::ContextIntegration) adds it as `gpu`
+/// context to all events, from all threads, so this only needs to be called
+/// once the graphics adapter has been selected.  Calling it again replaces
+/// the GPU, for instance when the application switches adapters.
+///
+/// The [`vulkan_gpu_context`] and [`wgpu_gpu_context`] functions create the
+/// context from the adapter information of Vulkan and `wgpu`.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::protocol::GpuContext;
+///
+/// sentry_contexts::set_gpu_context(GpuContext {
+///     name: "NVIDIA GeForce RTX 3080".into(),
+///     vendor_name: Some("NVIDIA".into()),
+///     driver_version: Some("535.54.3.0".into()),
+///     api_type: Some("Vulkan".into()),
+///     ..Default::default()
+/// });
+/// ```
+pub fn set_gpu_context(gpu: GpuContext) {
+    *GPU_CONTEXT.write().unwrap() = Some(gpu);
+}
+
+/// Returns the registered GPU as context.
+pub(crate) fn gpu_context() -> Option<Context> {
+    GPU_CONTEXT.read().unwrap().clone().map(Context::from)
+}
+
+/// Creates a GPU context from the `VkPhysicalDeviceProperties` of a Vulkan
+/// physical device.
+///
+/// The driver version is decoded with the vendor specific encodings of
+/// NVIDIA and Intel, and the Vulkan encoding for all other vendors.
+///
+/// # Examples
+///
+/// With the `ash` crate:
+///
+/// ```ignore
+/// let props = unsafe { instance.get_physical_device_properties(physical_device) };
+/// let name = unsafe { std::ffi::CStr::from_ptr(props.device_name.as_ptr()) };
+/// sentry_contexts::set_gpu_context(sentry_contexts::vulkan_gpu_context(
+///     &name.to_string_lossy(),
+///     props.vendor_id,
+///     props.device_id,
+///     props.driver_version,
+///     props.api_version,
+/// ));
+/// ```
+pub fn vulkan_gpu_context(
+    device_name: &str,
+    vendor_id: u32,
+    device_id: u32,
+    driver_version: u32,
+    api_version: u32,
+) -> GpuContext {
+    GpuContext {
+        name: device_name.into(),
+        version: Some(vulkan_version(api_version)),
+        driver_version: Some(vulkan_driver_version(vendor_id, driver_version)),
+        id: Some(format!("{:#06x}", device_id)),
+        vendor_id: Some(format!("{:#06x}", vendor_id)),
+        vendor_name: vendor_name(vendor_id).map(Into::into),
+        api_type: Some("Vulkan".into()),
+        ..Default::default()
+    }
+}
+
+/// Creates a GPU context from the `AdapterInfo` of a `wgpu` adapter.
+///
+/// The `backend` is the `Debug` representation of the backend of the
+/// adapter, like `Vulkan`, `Metal` or `Dx12`.
+///
+/// # Examples
+///
+/// ```ignore
+/// let info = adapter.get_info();
+/// sentry_contexts::set_gpu_context(sentry_contexts::wgpu_gpu_context(
+///     &info.name,
+///     info.vendor,
+///     info.device,
+///     &info.driver_info,
+///     &format!("{:?}", info.backend),
+/// ));
+/// ```
+pub fn wgpu_gpu_context(
+    name: &str,
+    vendor: u32,
+    device: u32,
+    driver_info: &str,
+    backend: &str,
+) -> GpuContext {
+    let api_type = match backend {
+        "Dx11" => "Direct3D 11",
+        "Dx12" => "Direct3D 12",
+        "Gl" => "OpenGL",
+        "BrowserWebGpu" => "WebGPU",
+        backend => backend,
+    };
+    // software and browser adapters have no PCI ids
+    let pci_id = |id: u32| (id != 0).then(|| format!("{:#06x}", id));
+    GpuContext {
+        name: name.into(),
+        driver_version: (!driver_info.is_empty()).then(|| driver_info.into()),
+        id: pci_id(device),
+        vendor_id: pci_id(vendor),
+        vendor_name: vendor_name(vendor).map(Into::into),
+        api_type: Some(api_type.into()),
+        ..Default::default()
+    }
+}
+
+/// Returns the name of the vendor with the given PCI vendor id.
+fn vendor_name(vendor_id: u32) -> Option<&'static str> {
+    Some(match vendor_id {
+        0x1002 => "AMD",
+        0x1010 => "Imagination Technologies",
+        0x106b => "Apple",
+        0x10de => "NVIDIA",
+        0x13b5 => "ARM",
+        0x5143 => "Qualcomm",
+        0x8086 => "Intel",
+        _ => return None,
+    })
+}
+
+/// Formats a Vulkan version number as `major.minor.patch`.
+fn vulkan_version(version: u32) -> String {
+    format!(
+        "{}.{}.{}",
+        (version >> 22) & 0x7f,
+        (version >> 12) & 0x3ff,
+        version & 0xfff
+    )
+}
+
+fn vulkan_driver_version(vendor_id: u32, version: u32) -> String {
+    match vendor_id {
+        0x10de => format!(
+            "{}.{}.{}.{}",
+            version >> 22,
+            (version >> 14) & 0xff,
+            (version >> 6) & 0xff,
+            version & 0x3f
+        ),
+        0x8086 if cfg!(windows) => format!("{}.{}", version >> 14, version & 0x3fff),
+        _ => vulkan_version(version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry_core::protocol::Event;
+    use sentry_core::{ClientOptions, Integration};
+
+    use super::*;
+    use crate::ContextIntegration;
+
+    #[test]
+    fn test_vulkan_gpu_context() {
+        let gpu = vulkan_gpu_context(
+            "NVIDIA GeForce RTX 3080",
+            0x10de,
+            0x2206,
+            (535 << 22) | (54 << 14) | (3 << 6),
+            (1 << 22) | (3 << 12) | 242,
+        );
+        assert_eq!(gpu.name, "NVIDIA GeForce RTX 3080");
+        assert_eq!(gpu.version.as_deref(), Some("1.3.242"));
+        assert_eq!(gpu.driver_version.as_deref(), Some("535.54.3.0"));
+        assert_eq!(gpu.id.as_deref(), Some("0x2206"));
+        assert_eq!(gpu.vendor_id.as_deref(), Some("0x10de"));
+        assert_eq!(gpu.vendor_name.as_deref(), Some("NVIDIA"));
+
+        let gpu = vulkan_gpu_context("AMD Radeon RX 6800", 0x1002, 0x73bf, 8388854, 4206830);
+        assert_eq!(gpu.driver_version.as_deref(), Some("2.0.246"));
+        assert_eq!(gpu.vendor_name.as_deref(), Some("AMD"));
+    }
+
+    #[test]
+    fn test_wgpu_gpu_context() {
+        let gpu = wgpu_gpu_context("Apple M1", 0, 0, "", "Metal");
+        assert_eq!(gpu.api_type.as_deref(), Some("Metal"));
+        assert_eq!(gpu.vendor_id, None);
+        assert_eq!(gpu.driver_version, None);
+
+        let gpu = wgpu_gpu_context(
+            "Intel(R) UHD Graphics 630",
+            0x8086,
+            0x3e92,
+            "31.0.101",
+            "Dx12",
+        );
+        assert_eq!(gpu.api_type.as_deref(), Some("Direct3D 12"));
+        assert_eq!(gpu.vendor_name.as_deref(), Some("Intel"));
+        assert_eq!(gpu.driver_version.as_deref(), Some("31.0.101"));
+    }
+
+    #[test]
+    fn test_gpu_context_integration() {
+        set_gpu_context(wgpu_gpu_context("llvmpipe", 0, 0, "", "Gl"));
+        let event = ContextIntegration::new()
+            .process_event(Event::new(), &ClientOptions::default())
+            .unwrap();
+        match event.contexts.get("gpu") {
+            Some(Context::Gpu(gpu)) => assert_eq!(gpu.name, "llvmpipe"),
+            context => panic!("unexpected gpu context: {:?}", context),
+        }
+    }
+}
@@ -4,13 +4,18 @@ use sentry_core::protocol::map::Entry;
 use sentry_core::protocol::Event;
 use sentry_core::{ClientOptions, Integration};
 
+use crate::embedded::apply_embedded_device;
+use crate::gpu::gpu_context;
 use crate::utils::{device_context, os_context, rust_context, server_name};
 
 /// Adds Contexts to Sentry Events.
 ///
 /// This integration is enabled by default in `sentry` and adds `device`, `os`
 /// and `rust` contexts to Events, and also sets a `server_name` if it is not
-/// already defined.
+/// already defined.  The GPU registered with
+/// [`set_gpu_context`](crate::set_gpu_context) is added as `gpu` context, and
+/// the device registered with [`set_embedded_device`](crate::set_embedded_device)
+/// to the `device` context.
 ///
 /// See the [Contexts Interface] documentation for more info.
 ///
@@ -80,7 +85,7 @@ impl Integration for ContextIntegration {
     fn process_event(
         &self,
         mut event: Event<'static>,
-        _cfg: &ClientOptions,
+        cfg: &ClientOptions,
     ) -> Option<Event<'static>> {
         if self.add_os {
             if let Entry::Vacant(entry) = event.contexts.entry("os".to_string()) {
@@ -101,6 +106,12 @@ impl Integration for ContextIntegration {
                 .entry("device".to_string())
                 .or_insert_with(device_context);
         }
+        apply_embedded_device(&mut event.contexts, cfg);
+        if let Entry::Vacant(entry) = event.contexts.entry("gpu".to_string()) {
+            if let Some(gpu) = gpu_context() {
+                entry.insert(gpu);
+            }
+        }
 
         Some(event)
     }
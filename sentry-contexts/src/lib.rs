@@ -19,9 +19,19 @@
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
 #![warn(missing_docs)]
 
+mod cloud;
+mod container;
+mod embedded;
+mod gpu;
 mod integration;
+mod power;
 /// Contains functions to retrieve various contexts that can be useful
 /// to attach to events
 pub mod utils;
 
+pub use cloud::CloudIntegration;
+pub use container::ContainerIntegration;
+pub use embedded::{set_embedded_device, EmbeddedDevice};
+pub use gpu::{set_gpu_context, vulkan_gpu_context, wgpu_gpu_context};
 pub use integration::ContextIntegration;
+pub use power::PowerIntegration;
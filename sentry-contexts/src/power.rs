@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sentry_core::protocol::{Context, DeviceContext, Event, Map, Value};
+use sentry_core::{ClientOptions, Integration};
+
+/// Adds the battery and thermal state to the `device` Context of Sentry
+/// Events.
+///
+/// This integration is opt-in, and reads the state when an event is
+/// captured:
+///
+/// - `battery_level` is the charge of the system battery in percent, and
+///   `charging` and `battery_status` (`Charging`, `Discharging`, `Full`,
+///   `Not charging`) tell whether it is being charged.
+/// - `temperature` is the highest temperature of all thermal zones in
+///   degrees Celsius, and `thermal_throttling` tells whether the processor is
+///   being throttled.
+///
+/// The state is read from sysfs, so it is only available on Linux.
+///
+/// # Examples
+///
+/// ```rust
+/// let integration = sentry_contexts::PowerIntegration::new();
+/// let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(integration));
+/// ```
+#[derive(Debug)]
+pub struct PowerIntegration {
+    sysfs: PathBuf,
+}
+
+impl Default for PowerIntegration {
+    fn default() -> Self {
+        Self {
+            sysfs: PathBuf::from("/sys"),
+        }
+    }
+}
+
+impl PowerIntegration {
+    /// Creates a new Power Integration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read_state(&self) -> (Option<f32>, Map<String, Value>) {
+        let mut battery_level = None;
+        let mut state = Map::new();
+
+        let supplies = self.sysfs.join("class/power_supply");
+        for supply in entries(&supplies, "") {
+            // peripherals like mice report their batteries with the `Device`
+            // scope
+            if read(&supply, "type").as_deref() != Some("Battery")
+                || read(&supply, "scope").as_deref() == Some("Device")
+            {
+                continue;
+            }
+            battery_level = read(&supply, "capacity").and_then(|c| c.parse().ok());
+            if let Some(status) = read(&supply, "status") {
+                state.insert("charging".into(), (status == "Charging").into());
+                state.insert("battery_status".into(), status.into());
+            }
+            break;
+        }
+
+        let thermal = self.sysfs.join("class/thermal");
+        let temperature = entries(&thermal, "thermal_zone")
+            .filter_map(|zone| read(&zone, "temp")?.parse::<i64>().ok())
+            .max();
+        if let Some(temperature) = temperature {
+            // sysfs reports millidegrees
+            state.insert("temperature".into(), (temperature as f64 / 1000.0).into());
+        }
+        let throttling = entries(&thermal, "cooling_device")
+            .filter(|device| read(device, "type").as_deref() == Some("Processor"))
+            .map(|device| read(&device, "cur_state").map_or(false, |state| state != "0"))
+            .reduce(|a, b| a || b);
+        if let Some(throttling) = throttling {
+            state.insert("thermal_throttling".into(), throttling.into());
+        }
+
+        (battery_level, state)
+    }
+}
+
+impl Integration for PowerIntegration {
+    fn name(&self) -> &'static str {
+        "power"
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _cfg: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        let (battery_level, state) = self.read_state();
+        if battery_level.is_none() && state.is_empty() {
+            return Some(event);
+        }
+        let context = event
+            .contexts
+            .entry("device".into())
+            .or_insert_with(|| DeviceContext::default().into());
+        if let Context::Device(device) = context {
+            if device.battery_level.is_none() {
+                device.battery_level = battery_level;
+            }
+            for (key, value) in state {
+                device.other.entry(key).or_insert(value);
+            }
+        }
+        Some(event)
+    }
+}
+
+/// Returns the sorted paths in `dir` whose names start with `prefix`.
+fn entries(dir: &Path, prefix: &str) -> impl Iterator<Item = PathBuf> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    paths.into_iter()
+}
+
+fn read(dir: &Path, attribute: &str) -> Option<String> {
+    let value = fs::read_to_string(dir.join(attribute)).ok()?;
+    Some(value.trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, value: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, value).unwrap();
+    }
+
+    #[test]
+    fn test_power_state() {
+        let sysfs = std::env::temp_dir().join(format!("sentry-power-{}", std::process::id()));
+        write(&sysfs, "class/power_supply/AC/type", "Mains\n");
+        write(&sysfs, "class/power_supply/AC/online", "1\n");
+        write(&sysfs, "class/power_supply/BAT0/type", "Battery\n");
+        write(&sysfs, "class/power_supply/BAT0/capacity", "87\n");
+        write(&sysfs, "class/power_supply/BAT0/status", "Charging\n");
+        write(&sysfs, "class/thermal/thermal_zone0/temp", "48000\n");
+        write(&sysfs, "class/thermal/thermal_zone1/temp", "91500\n");
+        write(&sysfs, "class/thermal/cooling_device0/type", "Fan\n");
+        write(&sysfs, "class/thermal/cooling_device0/cur_state", "1\n");
+        write(&sysfs, "class/thermal/cooling_device1/type", "Processor\n");
+        write(&sysfs, "class/thermal/cooling_device1/cur_state", "0\n");
+        write(&sysfs, "class/thermal/cooling_device2/type", "Processor\n");
+        write(&sysfs, "class/thermal/cooling_device2/cur_state", "3\n");
+
+        let integration = PowerIntegration {
+            sysfs: sysfs.clone(),
+        };
+        let event = integration
+            .process_event(Event::new(), &ClientOptions::default())
+            .unwrap();
+        fs::remove_dir_all(&sysfs).unwrap();
+
+        let device = match &event.contexts["device"] {
+            Context::Device(device) => device,
+            context => panic!("unexpected device context: {:?}", context),
+        };
+        assert_eq!(device.battery_level, Some(87.0));
+        assert_eq!(device.other["charging"], true);
+        assert_eq!(device.other["battery_status"], "Charging");
+        assert_eq!(device.other["temperature"], 91.5);
+        assert_eq!(device.other["thermal_throttling"], true);
+    }
+
+    #[test]
+    fn test_no_power_state() {
+        let integration = PowerIntegration {
+            sysfs: PathBuf::from("/nonexistent"),
+        };
+        let event = integration
+            .process_event(Event::new(), &ClientOptions::default())
+            .unwrap();
+        assert!(!event.contexts.contains_key("device"));
+    }
+}
@@ -0,0 +1,66 @@
+//! Sentry Disabled Client Benchmarks
+//!
+//! Run the benchmarks with:
+//!
+//! ```text
+//! $ cargo bench -p sentry-core --bench disabled_client_benchmark
+//! ```
+//!
+//! Libraries instrument hot paths unconditionally, so every top-level API call
+//! must stay well below 100ns while no *enabled* client is bound. We test:
+//! * No client is bound at all
+//! * A client without a DSN is bound, which is what `sentry::init` does when no
+//!   DSN is configured
+//!
+//! Neither case may assemble events or breadcrumbs, clone the scope or
+//! allocate. As soon as an enabled client was bound anywhere in the process,
+//! these numbers no longer apply, so this lives in its own benchmark binary.
+
+#[cfg(feature = "client")]
+use std::sync::Arc;
+
+use criterion::measurement::WallTime;
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, Criterion};
+use sentry::protocol::Breadcrumb;
+#[cfg(not(feature = "client"))]
+use sentry_core as sentry;
+
+fn bench_api(group: &mut BenchmarkGroup<'_, WallTime>) {
+    group.bench_function("capture_message", |b| {
+        b.iter(|| sentry::capture_message("hot path", sentry::Level::Info))
+    });
+    group.bench_function("add_breadcrumb", |b| {
+        b.iter(|| {
+            sentry::add_breadcrumb(|| Breadcrumb {
+                message: Some("hot path".into()),
+                ..Default::default()
+            })
+        })
+    });
+    group.bench_function("configure_scope", |b| {
+        b.iter(|| sentry::configure_scope(|scope| scope.set_tag("hot", "path")))
+    });
+    group.bench_function("with_scope", |b| {
+        b.iter(|| sentry::with_scope(|scope| scope.set_tag("hot", "path"), || 42))
+    });
+}
+
+fn disabled_client_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("no-client");
+    bench_api(&mut group);
+    group.finish();
+
+    #[cfg(feature = "client")]
+    {
+        let client = Arc::new(sentry::Client::from(sentry::ClientOptions::default()));
+        let hub = Arc::new(sentry::Hub::new(Some(client), Arc::new(Default::default())));
+        sentry::Hub::run(hub, || {
+            let mut group = c.benchmark_group("disabled-client");
+            bench_api(&mut group);
+            group.finish();
+        });
+    }
+}
+
+criterion_group!(benches, disabled_client_benchmark);
+criterion_main!(benches);
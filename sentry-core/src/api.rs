@@ -1,8 +1,62 @@
 use sentry_types::protocol::v7::SessionStatus;
 
-use crate::protocol::{Event, Level};
+use crate::protocol::{Event, Level, Value};
 use crate::types::Uuid;
-use crate::{Hub, Integration, IntoBreadcrumbs, Scope};
+use crate::{
+    Backpressure, FeedbackError, Hint, Hub, Integration, IntoBreadcrumbs, Scope, UserFeedback,
+};
+
+/// Returns whether a client is bound to the current hub and enabled.
+///
+/// All top-level functions of this crate are cheap no-ops while this returns
+/// `false`: events and breadcrumbs are not even assembled, and closures passed
+/// to [`add_breadcrumb`] or [`configure_scope`] are never invoked. Libraries
+/// can thus instrument their code unconditionally, without imposing any
+/// overhead on users that do not use Sentry. This function is useful to skip
+/// more expensive work, such as gathering data for a custom event.
+///
+/// # Examples
+///
+/// ```
+/// assert!(!sentry::is_enabled());
+///
+/// sentry::add_breadcrumb(|| -> sentry::Breadcrumb {
+///     unreachable!("never invoked without a client");
+/// });
+///
+/// # sentry::test::with_captured_events(|| {
+/// assert!(sentry::is_enabled());
+/// # });
+/// ```
+pub fn is_enabled() -> bool {
+    Hub::with_active(|_| true)
+}
+
+/// Returns the backpressure state of the active client's transport.
+///
+/// During incidents the transport queue may fill up or the server may rate
+/// limit the SDK. Applications can check this to reduce their own capture
+/// volume in the meantime, for example by skipping breadcrumbs that are
+/// recorded per processed item. Without an active client, this reports no
+/// pressure.
+///
+/// # Examples
+///
+/// ```
+/// # fn process(item: u32) {}
+/// for item in 0..100 {
+///     if !sentry::backpressure().is_under_pressure() {
+///         sentry::add_breadcrumb(|| sentry::Breadcrumb {
+///             message: Some(format!("processing item {}", item)),
+///             ..Default::default()
+///         });
+///     }
+///     process(item);
+/// }
+/// ```
+pub fn backpressure() -> Backpressure {
+    Hub::with_active(|hub| hub.backpressure())
+}
 
 /// Captures an event on the currently active client if any.
 ///
@@ -41,6 +95,15 @@ pub fn capture_event(event: Event<'static>) -> Uuid {
     Hub::with_active(|hub| hub.capture_event(event))
 }
 
+/// Captures an event along with its [`Hint`].
+///
+/// The hint is passed to the event processors of the scope and to
+/// [`ClientOptions::before_send_with_hint`](crate::ClientOptions::before_send_with_hint),
+/// but is not sent to Sentry.  See [`Hint`] for an example.
+pub fn capture_event_with_hint(event: Event<'static>, hint: Hint) -> Uuid {
+    Hub::with_active(|hub| hub.capture_event_with_hint(event, hint))
+}
+
 /// Captures an arbitrary message.
 ///
 /// This creates an event from the given message and sends it via
@@ -62,6 +125,33 @@ pub fn capture_message(msg: &str, level: Level) -> Uuid {
     Hub::with_active(|hub| hub.capture_message(msg, level))
 }
 
+/// Captures a parameterized message.
+///
+/// The `template` is sent along with the `params`, so Sentry groups events by
+/// the template instead of creating one issue per distinct parameter value.
+/// See [`LogEntry::with_params`] for the supported placeholders.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::protocol::Level;
+///
+/// # let events = sentry::test::with_captured_events(|| {
+/// let path = "/etc/app.toml";
+/// sentry::capture_message_with_params("failed to read {path}", vec![path.into()], Level::Error);
+/// # });
+/// # let captured_event = events.into_iter().next().unwrap();
+///
+/// let logentry = captured_event.logentry.unwrap();
+/// assert_eq!(logentry.message, "failed to read {path}");
+/// assert_eq!(logentry.formatted.as_deref(), Some("failed to read /etc/app.toml"));
+/// ```
+///
+/// [`LogEntry::with_params`]: crate::protocol::LogEntry::with_params
+pub fn capture_message_with_params(template: &str, params: Vec<Value>, level: Level) -> Uuid {
+    Hub::with_active(|hub| hub.capture_message_with_params(template, params, level))
+}
+
 /// Records a breadcrumb by calling a function.
 ///
 /// The total number of breadcrumbs that can be recorded are limited by the
@@ -110,6 +200,28 @@ pub fn add_breadcrumb<B: IntoBreadcrumbs>(breadcrumb: B) {
     Hub::with_active(|hub| hub.add_breadcrumb(breadcrumb))
 }
 
+/// Records the result of evaluating a feature flag on the current scope.
+///
+/// The most recently evaluated flags are sent in the `flags` context of
+/// events, so issues can be correlated with flag rollouts and experiments.
+/// Flag SDKs usually call this from a hook, so that all evaluations are
+/// recorded.
+///
+/// # Examples
+///
+/// ```
+/// # let events = sentry::test::with_captured_events(|| {
+/// sentry::record_flag_evaluation("new-checkout", true);
+///
+/// sentry::capture_message("checkout failed", sentry::Level::Error);
+/// # });
+/// # let flags = &events[0].contexts["flags"];
+/// # assert!(matches!(flags, sentry::protocol::Context::Other(flags) if flags["values"][0]["flag"] == "new-checkout"));
+/// ```
+pub fn record_flag_evaluation(name: &str, result: bool) {
+    Hub::with_active(|hub| hub.configure_scope(|scope| scope.record_flag_evaluation(name, result)))
+}
+
 /// Invokes a function that can modify the current scope.
 ///
 /// The function is passed a mutable reference to the [`Scope`] so that modifications
@@ -187,6 +299,9 @@ where
 {
     #[cfg(feature = "client")]
     {
+        if !Hub::any_enabled_client_bound() {
+            return callback();
+        }
         Hub::with(|hub| {
             if hub.is_active_and_usage_safe() {
                 hub.with_scope(scope_config, callback)
@@ -202,6 +317,45 @@ where
     }
 }
 
+/// Runs the callback in a scope that names the transaction of its events.
+///
+/// This names the logical operation, such as a batch job or a background
+/// task, on all events that are captured within the callback, without
+/// starting a performance monitoring transaction.  Unlike
+/// [`Scope::set_transaction`], this does not rename a transaction that is
+/// currently running, and the previous name is restored once the callback
+/// returns.
+///
+/// # Examples
+///
+/// ```
+/// # let events = sentry::test::with_captured_events(|| {
+/// sentry::transaction_scope("worker.process_batch", || {
+///     sentry::capture_message("batch is empty", sentry::Level::Warning);
+/// });
+/// # });
+/// # let captured_event = events.into_iter().next().unwrap();
+///
+/// assert_eq!(
+///     captured_event.transaction.as_deref(),
+///     Some("worker.process_batch")
+/// );
+/// ```
+pub fn transaction_scope<F, R>(name: &str, callback: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    #[cfg(feature = "client")]
+    {
+        with_scope(|scope| scope.transaction = Some(name.into()), callback)
+    }
+    #[cfg(not(feature = "client"))]
+    {
+        let _name = name;
+        callback()
+    }
+}
+
 /// Looks up an integration on the current Hub.
 ///
 /// Calls the given function with the requested integration instance when it
@@ -264,6 +418,33 @@ pub fn last_event_id() -> Option<Uuid> {
     }}
 }
 
+/// Captures feedback from a user about an event.
+///
+/// The feedback is validated first, and attached to the last captured event
+/// unless an event ID was set explicitly. See [`UserFeedback`] for more
+/// information.
+///
+/// # Examples
+///
+/// ```
+/// # sentry::test::with_captured_envelopes(|| {
+/// let event_id = sentry::capture_message("Something went wrong", sentry::Level::Error);
+///
+/// let feedback = sentry::UserFeedback::new("It crashed when I clicked save.").event_id(event_id);
+/// sentry::capture_user_feedback(feedback).unwrap();
+/// # });
+/// ```
+pub fn capture_user_feedback(feedback: UserFeedback) -> Result<(), FeedbackError> {
+    #[cfg(feature = "client")]
+    {
+        Hub::with(|hub| hub.capture_user_feedback(feedback))
+    }
+    #[cfg(not(feature = "client"))]
+    {
+        feedback.validate()
+    }
+}
+
 /// Start a new session for Release Health.
 ///
 /// This is still **experimental** for the moment and is not recommended to be
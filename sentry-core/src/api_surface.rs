@@ -0,0 +1,111 @@
+//! Compile-time checks for the minimal API surface.
+//!
+//! Libraries are expected to depend on `sentry-core` without the `client`
+//! feature, while applications enable it.  Both modes must expose the exact
+//! same instrumentation API, otherwise enabling the client somewhere in the
+//! dependency graph would break libraries that compiled fine on their own.
+//!
+//! The functions in this module are never called.  They coerce every part of
+//! the minimal API to a concrete function pointer type, so a signature that
+//! drifts apart between `scope/real.rs` and `scope/noop.rs` (or between the
+//! client and non-client branches of the hub) fails to compile in one of the
+//! two modes.  CI checks both via `make check-no-default-features`.
+#![allow(dead_code, clippy::type_complexity)]
+
+use std::io;
+use std::sync::Arc;
+
+use crate::protocol::{
+    Attachment, Breadcrumb, Context, Event, Level, RawEnvelopeItem, SessionStatus, Stacktrace,
+    User, Value,
+};
+use crate::types::Uuid;
+use crate::{
+    Backpressure, FeedbackError, Hint, Hub, Metric, Scope, ScopeGuard, SpanGuard, Transaction,
+    TransactionContext, TransactionOrSpan, UserFeedback,
+};
+
+struct NoopObserver;
+
+impl crate::ScopeObserver for NoopObserver {}
+
+fn global_api() {
+    let _: fn() -> bool = crate::is_enabled;
+    let _: fn() -> Backpressure = crate::backpressure;
+    let _: fn(Event<'static>) -> Uuid = crate::capture_event;
+    let _: fn(Event<'static>, Hint) -> Uuid = crate::capture_event_with_hint;
+    let _: fn(&str, Level) -> Uuid = crate::capture_message;
+    let _: fn(&str, Vec<Value>, Level) -> Uuid = crate::capture_message_with_params;
+    let _: fn(&io::Error) -> Uuid = crate::capture_error::<io::Error>;
+    let _: fn(&str, &'static [io::Error]) -> Uuid =
+        crate::capture_error_group::<&'static [io::Error], io::Error>;
+    let _: fn(&str, &str, Option<Stacktrace>) -> Uuid = crate::capture_exception;
+    let _: fn(UserFeedback) -> Result<(), FeedbackError> = crate::capture_user_feedback;
+    let _: fn(Breadcrumb) = crate::add_breadcrumb::<Breadcrumb>;
+    let _: fn(&str, bool) = crate::record_flag_evaluation;
+    let _: fn(fn(&mut Scope)) = crate::configure_scope::<fn(&mut Scope), ()>;
+    let _: fn(fn(&mut Scope), fn()) = crate::with_scope::<fn(&mut Scope), fn(), ()>;
+    let _: fn(&str, fn()) = crate::transaction_scope::<fn(), ()>;
+    let _: fn() -> Option<Uuid> = crate::last_event_id;
+    let _: fn() = crate::start_session;
+    let _: fn() = crate::end_session;
+    let _: fn(SessionStatus) = crate::end_session_with_status;
+    let _: fn(TransactionContext) -> Transaction = crate::start_transaction;
+    let _: fn(&str, &str) -> SpanGuard = crate::start_span;
+    let _: fn() -> Vec<(&'static str, String)> = crate::current_trace_headers;
+    let _: fn(Result<(), io::Error>, &str) = crate::unwrap_or_capture::<(), io::Error>;
+}
+
+fn hub_api() {
+    let _: fn(fn(&Arc<Hub>)) = Hub::with_active::<fn(&Arc<Hub>), ()>;
+    let _: fn(&Hub) -> Option<Uuid> = Hub::last_event_id;
+    let _: fn(&Hub) -> Backpressure = Hub::backpressure;
+    let _: fn(&Hub, Event<'static>) -> Uuid = Hub::capture_event;
+    let _: fn(&Hub, Event<'static>, Hint) -> Uuid = Hub::capture_event_with_hint;
+    let _: fn(&Hub, Metric) = Hub::capture_metric;
+    let _: fn(&Hub, &str, Level) -> Uuid = Hub::capture_message;
+    let _: fn(&Hub, &str, Vec<Value>, Level) -> Uuid = Hub::capture_message_with_params;
+    let _: fn(&Hub, &io::Error) -> Uuid = Hub::capture_error::<io::Error>;
+    let _: fn(&Hub, &str, &'static [io::Error]) -> Uuid =
+        Hub::capture_error_group::<&'static [io::Error], io::Error>;
+    let _: fn(&Hub, &str, &str, Option<Stacktrace>) -> Uuid = Hub::capture_exception;
+    let _: fn(&Hub, UserFeedback) -> Result<(), FeedbackError> = Hub::capture_user_feedback;
+    let _: fn(&Hub, Breadcrumb) = Hub::add_breadcrumb::<Breadcrumb>;
+    let _: fn(&Hub) -> ScopeGuard = Hub::push_scope;
+    let _: fn(&mut ScopeGuard, fn()) = ScopeGuard::on_pop::<fn()>;
+    let _: fn(&Hub, fn(&mut Scope)) = Hub::configure_scope::<fn(&mut Scope), ()>;
+    let _: fn(&Hub, fn(&mut Scope), fn()) = Hub::with_scope::<fn(&mut Scope), fn(), ()>;
+    let _: fn(&Hub) = Hub::start_session;
+    let _: fn(&Hub) = Hub::end_session;
+    let _: fn(&Hub, SessionStatus) = Hub::end_session_with_status;
+    let _: fn(&Hub, TransactionContext) -> Transaction = Hub::start_transaction;
+}
+
+fn scope_api() {
+    let _: fn(&mut Scope) = Scope::clear;
+    let _: fn(&mut Scope) = Scope::clear_breadcrumbs;
+    let _: fn(&mut Scope, Option<Level>) = Scope::set_level;
+    let _: fn(&mut Scope, Option<&[&str]>) = Scope::set_fingerprint;
+    let _: fn(&mut Scope, Option<&str>) = Scope::set_transaction;
+    let _: fn(&Scope) -> Option<&str> = Scope::transaction;
+    let _: fn(&mut Scope, Option<User>) = Scope::set_user;
+    let _: fn(&mut Scope, &str, String) = Scope::set_tag::<String>;
+    let _: fn(&mut Scope, &str) = Scope::remove_tag;
+    let _: fn(&mut Scope, &str, Context) = Scope::set_context::<Context>;
+    let _: fn(&mut Scope, &str) = Scope::remove_context;
+    let _: fn(&mut Scope, &str, bool) = Scope::record_flag_evaluation;
+    let _: fn(&mut Scope, &str, Value) = Scope::set_extra;
+    let _: fn(&mut Scope, &str) = Scope::remove_extra;
+    let _: fn(&mut Scope, fn(Event<'static>) -> Option<Event<'static>>) =
+        Scope::add_event_processor::<fn(Event<'static>) -> Option<Event<'static>>>;
+    let _: fn(&mut Scope, fn(Event<'static>, &Hint) -> Option<Event<'static>>) =
+        Scope::add_event_processor_with_hint::<fn(Event<'static>, &Hint) -> Option<Event<'static>>>;
+    let _: fn(&mut Scope, NoopObserver) = Scope::add_observer::<NoopObserver>;
+    let _: fn(&mut Scope, Attachment) = Scope::add_attachment;
+    let _: fn(&mut Scope) = Scope::clear_attachments;
+    let _: fn(&mut Scope, RawEnvelopeItem) = Scope::add_envelope_item;
+    let _: fn(&mut Scope) = Scope::clear_envelope_items;
+    let _: fn(&Scope, Event<'static>) -> Option<Event<'static>> = Scope::apply_to_event;
+    let _: fn(&mut Scope, Option<TransactionOrSpan>) = Scope::set_span;
+    let _: fn(&Scope) -> Option<TransactionOrSpan> = Scope::get_span;
+}
@@ -3,17 +3,29 @@ use std::borrow::Cow;
 use std::fmt;
 use std::panic::RefUnwindSafe;
 use std::sync::Arc;
-use std::sync::RwLock;
-use std::time::Duration;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 
 use rand::random;
 use sentry_types::protocol::v7::SessionUpdate;
 
 use crate::constants::SDK_INFO;
-use crate::protocol::{ClientSdkInfo, Event};
-use crate::session::SessionFlusher;
+use crate::deferred::{DeferredEvent, PendingEvents};
+use crate::hub::LastEventId;
+use crate::ignore::{is_error_ignored, is_transaction_ignored};
+use crate::metrics::MetricsAggregator;
+use crate::processing::EventWorker;
+use crate::protocol::value::{Map, Value};
+use crate::protocol::{Attachment, ClientSdkInfo, Event, Frame, RawEnvelopeItem};
+use crate::remote_config::valid_rate;
+use crate::scope::BreadcrumbRing;
+use crate::session::{Session, SessionFlusher};
+use crate::spotlight::SpotlightTransport;
 use crate::types::{Dsn, Uuid};
-use crate::{ClientOptions, Envelope, Hub, Integration, Scope, SessionMode, Transport};
+use crate::{
+    Backpressure, ClientOptions, Envelope, Hint, Hub, Integration, Metric, PendingEvent,
+    RemoteConfig, RemoteConfigIntegration, Scope, SessionMode, Spool, Transport,
+};
 
 impl<T: Into<ClientOptions>> From<T> for Client {
     fn from(o: T) -> Client {
@@ -44,8 +56,12 @@ pub struct Client {
     options: ClientOptions,
     transport: TransportArc,
     session_flusher: RwLock<Option<SessionFlusher>>,
+    metrics_aggregator: RwLock<Option<MetricsAggregator>>,
     integrations: Vec<(TypeId, Arc<dyn Integration>)>,
-    pub(crate) sdk_info: ClientSdkInfo,
+    pub(crate) sdk_info: Arc<ClientSdkInfo>,
+    event_worker: Option<Arc<EventWorker>>,
+    pub(crate) pending_events: PendingEvents,
+    created_at: Instant,
 }
 
 impl fmt::Debug for Client {
@@ -64,12 +80,20 @@ impl Clone for Client {
             transport.clone(),
             self.options.session_mode,
         )));
+        let metrics_aggregator = RwLock::new(
+            (self.options.enable_metrics || self.options.unsampled_transaction_metrics)
+                .then(|| MetricsAggregator::new(transport.clone())),
+        );
         Client {
             options: self.options.clone(),
             transport,
             session_flusher,
+            metrics_aggregator,
             integrations: self.integrations.clone(),
             sdk_info: self.sdk_info.clone(),
+            event_worker: self.event_worker.clone(),
+            pending_events: PendingEvents::default(),
+            created_at: self.created_at,
         }
     }
 }
@@ -108,11 +132,22 @@ impl Client {
         // See https://github.com/getsentry/sentry-rust/issues/237
         Hub::with(|_| {});
 
+        // the DSN might not have been set through one of the `From` impls
+        options.apply_dsn_query();
+
         let create_transport = || {
             options.dsn.as_ref()?;
             let factory = options.transport.as_ref()?;
             Some(factory.create_transport(&options))
         };
+        let create_transport = || {
+            let transport = create_transport();
+            if !options.spotlight {
+                return transport;
+            }
+            let spotlight = SpotlightTransport::new(&options.spotlight_url, transport);
+            Some(Arc::new(spotlight) as Arc<dyn Transport>)
+        };
 
         let transport = Arc::new(RwLock::new(create_transport()));
 
@@ -135,12 +170,25 @@ impl Client {
             transport.clone(),
             options.session_mode,
         )));
+        let metrics_aggregator = RwLock::new(
+            (options.enable_metrics || options.unsampled_transaction_metrics)
+                .then(|| MetricsAggregator::new(transport.clone())),
+        );
+        let event_worker = if options.background_processing && transport.read().unwrap().is_some() {
+            Some(Arc::new(EventWorker::new()))
+        } else {
+            None
+        };
         Client {
             options,
             transport,
             session_flusher,
+            metrics_aggregator,
             integrations,
-            sdk_info,
+            sdk_info: Arc::new(sdk_info),
+            event_worker,
+            pending_events: PendingEvents::default(),
+            created_at: Instant::now(),
         }
     }
 
@@ -153,9 +201,14 @@ impl Client {
         integration.as_ref().as_any().downcast_ref()
     }
 
-    fn prepare_event(
+    /// Assigns the event ID and SDK info, applies the scope, samples the
+    /// event by its level and prepares it with the integrations.
+    ///
+    /// This always runs on the capturing thread, as the scope is not `Send`.
+    fn apply_scope(
         &self,
         mut event: Event<'static>,
+        hint: &Hint,
         scope: Option<&Scope>,
     ) -> Option<Event<'static>> {
         // event_id and sdk_info are set before the processors run so that the
@@ -165,20 +218,72 @@ impl Client {
         }
 
         if event.sdk.is_none() {
-            // NOTE: we need to clone here because `Event` must be `'static`
-            event.sdk = Some(Cow::Owned(self.sdk_info.clone()));
+            event.sdk = Some(Cow::Owned(ClientSdkInfo::clone(&self.sdk_info)));
         }
 
-        if let Some(scope) = scope {
-            event = match scope.apply_to_event(event) {
-                Some(event) => event,
-                None => return None,
-            };
+        let mut event = match scope {
+            Some(scope) => scope.apply_to_event_with_hint(event, hint)?,
+            None => event,
+        };
+
+        if let Some(&rate) = self.options.sample_rates.get(&event.level) {
+            if !self.sample_should_send(rate) {
+                sentry_debug!("sample_rates dropped event {:?}", event.event_id);
+                // the session still counts the event, like with `sample_rate`
+                if let Some(scope) = scope {
+                    if let Some(session) = scope.session.lock().unwrap().as_mut() {
+                        session.update_from_event(&event);
+                    }
+                }
+                return None;
+            }
+        }
+
+        for (_, integration) in self.integrations.iter() {
+            integration.prepare_event(&mut event, &self.options);
+        }
+
+        Some(event)
+    }
+
+    /// Runs the `before_send` and `before_send_with_hint` callbacks.
+    fn before_send(&self, mut event: Event<'static>, hint: &Hint) -> Option<Event<'static>> {
+        if let Some(ref func) = self.options.before_send {
+            sentry_debug!("invoking before_send callback");
+            let id = event.event_id;
+            if let Some(processed_event) = func(event) {
+                event = processed_event;
+            } else {
+                sentry_debug!("before_send dropped event {:?}", id);
+                return None;
+            }
         }
+        if let Some(ref func) = self.options.before_send_with_hint {
+            sentry_debug!("invoking before_send_with_hint callback");
+            let id = event.event_id;
+            if let Some(processed_event) = func(event, hint) {
+                event = processed_event;
+            } else {
+                sentry_debug!("before_send_with_hint dropped event {:?}", id);
+                return None;
+            }
+        }
+        Some(event)
+    }
 
+    /// Runs the integrations, `before_send` and sampling on a scoped event.
+    ///
+    /// With [`ClientOptions::background_processing`] this runs on the worker
+    /// thread.
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        hint: &Hint,
+        session: Option<&Mutex<Option<Session>>>,
+    ) -> Option<Event<'static>> {
         for (_, integration) in self.integrations.iter() {
             let id = event.event_id;
-            event = match integration.process_event(event, &self.options) {
+            event = match integration.process_event_with_hint(event, hint, &self.options) {
                 Some(event) => event,
                 None => {
                     sentry_debug!("integration dropped event {:?}", id);
@@ -199,23 +304,35 @@ impl Client {
         if &event.platform == "other" {
             event.platform = "native".into();
         }
-
-        if let Some(ref func) = self.options.before_send {
-            sentry_debug!("invoking before_send callback");
-            let id = event.event_id;
-            if let Some(processed_event) = func(event) {
-                event = processed_event;
-            } else {
-                sentry_debug!("before_send dropped event {:?}", id);
-                return None;
+        if event.culprit.is_none()
+            && event.transaction.is_none()
+            && !self.options.strip_frame_symbols
+        {
+            event.culprit = infer_culprit(&event);
+        }
+        if let Some(ref func) = self.options.fingerprint_fn {
+            if let Some(fingerprint) = func(&event) {
+                event.fingerprint = Cow::Owned(fingerprint.into_iter().map(Cow::Owned).collect());
             }
         }
 
-        if let Some(scope) = scope {
-            scope.update_session_from_event(&event);
+        if self.is_error_ignored(&event) {
+            sentry_debug!("ignore_errors dropped event {:?}", event.event_id);
+            return None;
         }
 
-        if !self.sample_should_send(self.options.sample_rate) {
+        let mut event = self.before_send(event, hint)?;
+        if self.options.strip_frame_symbols {
+            strip_frame_symbols(&mut event);
+        }
+
+        if let Some(session) = session {
+            if let Some(session) = session.lock().unwrap().as_mut() {
+                session.update_from_event(&event);
+            }
+        }
+
+        if !self.sample_should_send(self.sample_rate()) {
             None
         } else {
             Some(event)
@@ -227,6 +344,14 @@ impl Client {
         &self.options
     }
 
+    /// Returns the current time according to the configured clock.
+    pub(crate) fn now(&self) -> SystemTime {
+        match self.options.clock.as_ref() {
+            Some(clock) => clock.now(),
+            None => SystemTime::now(),
+        }
+    }
+
     /// Returns the DSN that constructed this client.
     pub fn dsn(&self) -> Option<&Dsn> {
         self.options.dsn.as_ref()
@@ -234,7 +359,8 @@ impl Client {
 
     /// Quick check to see if the client is enabled.
     ///
-    /// The Client is enabled if it has a valid DSN and Transport configured.
+    /// The Client is enabled if it has a valid DSN and Transport configured,
+    /// or if [`ClientOptions::spotlight`] is enabled.
     ///
     /// # Examples
     ///
@@ -256,21 +382,351 @@ impl Client {
     /// assert!(client.is_enabled());
     /// ```
     pub fn is_enabled(&self) -> bool {
-        self.options.dsn.is_some() && self.transport.read().unwrap().is_some()
+        (self.options.dsn.is_some() || self.options.spotlight)
+            && self.transport.read().unwrap().is_some()
+    }
+
+    /// Returns the backpressure state of the transport.
+    ///
+    /// A client without a transport reports no pressure.
+    pub fn backpressure(&self) -> Backpressure {
+        match *self.transport.read().unwrap() {
+            Some(ref transport) => transport.backpressure(),
+            None => Backpressure::default(),
+        }
     }
 
     /// Captures an event and sends it to sentry.
+    ///
+    /// This always processes the event on the calling thread, even with
+    /// [`ClientOptions::background_processing`] enabled.
     pub fn capture_event(&self, event: Event<'static>, scope: Option<&Scope>) -> Uuid {
+        self.capture_event_with_hint(event, Hint::default(), scope)
+    }
+
+    /// Captures an event along with its [`Hint`] and sends it to sentry.
+    ///
+    /// The hint is passed to the event processors of the scope and to
+    /// [`ClientOptions::before_send_with_hint`], but is not sent itself.
+    pub fn capture_event_with_hint(
+        &self,
+        event: Event<'static>,
+        hint: Hint,
+        scope: Option<&Scope>,
+    ) -> Uuid {
+        if self.transport.read().unwrap().is_none() {
+            return Default::default();
+        }
+        let event_id = match self.apply_scope(event, &hint, scope) {
+            Some(event) => self.process_and_send_event(
+                event,
+                &hint,
+                scope.map(|scope| &*scope.session),
+                scope.map(|scope| &*scope.breadcrumbs),
+                scope.map_or(&[], |scope| &scope.attachments[..]),
+                scope.map_or(&[], |scope| &scope.envelope_items[..]),
+            ),
+            None => Default::default(),
+        };
+        self.flush_if_starting_up(event_id);
+        event_id
+    }
+
+    /// Captures an event, but defers its processing until it is enriched.
+    ///
+    /// The scope is applied right away, while the integrations, `before_send`
+    /// and sampling only run once the returned [`PendingEvent`] is sent, or
+    /// after [`ClientOptions::deferred_event_timeout`].  This allows to add
+    /// data to the event that has to be looked up asynchronously.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let events = sentry::test::with_captured_events(|| {
+    /// let event = sentry::protocol::Event {
+    ///     message: Some("deployment failed".into()),
+    ///     ..Default::default()
+    /// };
+    /// let pending = sentry::Hub::current().capture_event_deferred(event).unwrap();
+    ///
+    /// // … look up the deployment asynchronously
+    /// let deployment_id = "d-42";
+    /// pending.enrich(|event| {
+    ///     event.tags.insert("deployment".into(), deployment_id.into());
+    /// });
+    /// pending.send();
+    /// # });
+    /// # assert_eq!(events[0].tags["deployment"], "d-42");
+    /// ```
+    pub fn capture_event_deferred(
+        self: &Arc<Self>,
+        event: Event<'static>,
+        scope: Option<&Scope>,
+    ) -> PendingEvent {
+        let timeout = self.options.deferred_event_timeout;
+        self.defer_event(event, Hint::default(), scope, timeout, None)
+    }
+
+    /// Applies the scope to the event and defers the rest of its processing
+    /// for up to `timeout`.
+    ///
+    /// The id of the event is recorded in `last_event_id`, and forgotten
+    /// again if the event is dropped once it is processed.
+    pub(crate) fn defer_event(
+        self: &Arc<Self>,
+        event: Event<'static>,
+        hint: Hint,
+        scope: Option<&Scope>,
+        timeout: Duration,
+        last_event_id: Option<&LastEventId>,
+    ) -> PendingEvent {
+        if self.transport.read().unwrap().is_none() {
+            return PendingEvent::new(None, timeout);
+        }
+        let event = match self.apply_scope(event, &hint, scope) {
+            Some(event) => event,
+            None => return PendingEvent::new(None, timeout),
+        };
+        if let Some(last_event_id) = last_event_id {
+            last_event_id.set(event.event_id);
+        }
+        let deferred = DeferredEvent {
+            last_event_id: last_event_id.cloned(),
+            client: self.clone(),
+            event,
+            hint,
+            session: scope.map(|scope| scope.session.clone()).unwrap_or_default(),
+            breadcrumbs: scope
+                .map(|scope| scope.breadcrumbs.clone())
+                .unwrap_or_default(),
+            attachments: scope
+                .map(|scope| scope.attachments.clone())
+                .unwrap_or_default(),
+            envelope_items: scope
+                .map(|scope| scope.envelope_items.clone())
+                .unwrap_or_default(),
+        };
+        PendingEvent::new(Some(deferred), timeout)
+    }
+
+    /// Modifies an event that was captured, but is not sent yet.
+    ///
+    /// This finds the event by its id among the events that were captured
+    /// with [`capture_event_deferred`](Self::capture_event_deferred) or, if
+    /// [`ClientOptions::event_enrichment_window`] is set, with any of the
+    /// capture functions of the [`Hub`](crate::Hub).  That way, data that is
+    /// only known later on, such as the status code of the request during
+    /// which an error occurred, can still be added to the event.
+    ///
+    /// Returns `false` if there is no such event, for instance because it
+    /// was already sent, in which case the callback is not invoked.
+    pub fn enrich_event<F>(&self, event_id: Uuid, f: F) -> bool
+    where
+        F: FnOnce(&mut Event<'static>),
+    {
+        match self.pending_events.get(event_id) {
+            Some(pending) => pending.enrich(f),
+            None => false,
+        }
+    }
+
+    /// Captures an event given as raw JSON.
+    ///
+    /// This is an escape hatch for forwarding events that were produced by
+    /// other systems, such as SDKs for other languages, through a Rust
+    /// service.  The event is sent as-is, without applying the scope, the
+    /// integrations or the defaults of the client, so fields that are unknown
+    /// to the protocol types of this SDK are retained.
+    ///
+    /// The `before_send` callbacks are still invoked on a typed copy of the
+    /// event, and the changes they make are applied to the JSON.  If the event
+    /// cannot be represented as an [`Event`], the callbacks are skipped.  The
+    /// event is also subject to the [`ClientOptions::sample_rate`].
+    ///
+    /// Returns the id of the event, which is generated if the event has none,
+    /// or a nil id if the event was dropped or is not a JSON object.
+    pub fn capture_raw_event(&self, mut event: Value) -> Uuid {
+        let transport = self.transport.read().unwrap();
+        let transport = match *transport {
+            Some(ref transport) => transport,
+            None => return Default::default(),
+        };
+        let object = match event.as_object_mut() {
+            Some(object) => object,
+            None => {
+                sentry_debug!("dropping raw event that is not a JSON object");
+                return Default::default();
+            }
+        };
+        let existing_id = object
+            .get("event_id")
+            .and_then(Value::as_str)
+            .and_then(|id| id.parse().ok());
+        let event_id = existing_id.unwrap_or_else(|| {
+            let event_id = Uuid::new_v4();
+            object.insert("event_id".into(), event_id.simple().to_string().into());
+            event_id
+        });
+
+        if self.options.before_send.is_some() || self.options.before_send_with_hint.is_some() {
+            match serde_json::from_value::<Event<'static>>(Value::Object(object.clone())) {
+                Ok(typed) => {
+                    let original = serde_json::to_value(&typed).unwrap_or_default();
+                    let processed = match self.before_send(typed, &Hint::default()) {
+                        Some(processed) => serde_json::to_value(&processed).unwrap_or_default(),
+                        None => return Default::default(),
+                    };
+                    apply_changes(object, &original, &processed);
+                }
+                Err(err) => {
+                    sentry_debug!("skipping before_send for raw event {}: {}", event_id, err);
+                }
+            }
+        }
+
+        if !self.sample_should_send(self.sample_rate()) {
+            return Default::default();
+        }
+        match RawEnvelopeItem::from_json("event", &event) {
+            Ok(item) => {
+                let mut envelope = Envelope::new();
+                envelope.add_item(item);
+                transport.send_envelope(envelope);
+                event_id
+            }
+            Err(err) => {
+                sentry_debug!("failed to serialize raw event {}: {}", event_id, err);
+                Default::default()
+            }
+        }
+    }
+
+    /// Captures an event, offloading its processing to the background worker
+    /// if [`ClientOptions::background_processing`] is enabled.
+    ///
+    /// Only the scope is applied on the calling thread. The returned event ID
+    /// is assigned up-front, so it is not nil even if the event is dropped
+    /// later on.  It is recorded in `last_event_id` right away, and forgotten
+    /// again if the event is dropped once it is processed.
+    pub(crate) fn enqueue_event(
+        self: &Arc<Self>,
+        event: Event<'static>,
+        hint: Hint,
+        scope: &Scope,
+        last_event_id: &LastEventId,
+    ) -> Uuid {
+        // the worker and the timer of deferred events are not panicking, so
+        // the event has to be processed and spooled on this thread
+        let aborting = self.is_aborting();
+        if let Some(window) = self.options.event_enrichment_window.filter(|_| !aborting) {
+            let event_id = self
+                .defer_event(event, hint, Some(scope), window, Some(last_event_id))
+                .event_id();
+            self.flush_if_starting_up(event_id);
+            return event_id;
+        }
+        let worker = match self.event_worker {
+            Some(ref worker) if !aborting => worker,
+            _ => {
+                let event_id = self.capture_event_with_hint(event, hint, Some(scope));
+                last_event_id.set(event_id);
+                return event_id;
+            }
+        };
+        if self.transport.read().unwrap().is_none() {
+            return Default::default();
+        }
+        let event = match self.apply_scope(event, &hint, Some(scope)) {
+            Some(event) => event,
+            None => return Default::default(),
+        };
+
+        let event_id = event.event_id;
+        last_event_id.set(event_id);
+        let forget_event_id = last_event_id.clone();
+        let client = self.clone();
+        let session = scope.session.clone();
+        let breadcrumbs = scope.breadcrumbs.clone();
+        let attachments = scope.attachments.clone();
+        let envelope_items = scope.envelope_items.clone();
+        let enqueued = worker.enqueue(Box::new(move || {
+            let sent = client.process_and_send_event(
+                event,
+                &hint,
+                Some(&session),
+                Some(&breadcrumbs),
+                &attachments,
+                &envelope_items,
+            );
+            if sent.is_nil() {
+                forget_event_id.forget(event_id);
+            }
+        }));
+        if !enqueued {
+            sentry_debug!(
+                "event processing queue is full, dropping event {}",
+                event_id
+            );
+            last_event_id.forget(event_id);
+            return Default::default();
+        }
+        self.flush_if_starting_up(event_id);
+        event_id
+    }
+
+    /// Flushes synchronously if the event was captured within the
+    /// [`ClientOptions::startup_crash_window`].
+    fn flush_if_starting_up(&self, event_id: Uuid) {
+        let window = match self.options.startup_crash_window {
+            Some(window) if !event_id.is_nil() => window,
+            _ => return,
+        };
+        if self.created_at.elapsed() < window {
+            sentry_debug!("event {} captured during startup, flushing", event_id);
+            self.flush(Some(self.options.startup_crash_flush_timeout));
+        }
+    }
+
+    /// Copies the breadcrumbs of the scope into the event, processes it and
+    /// hands it to the transport.
+    ///
+    /// The breadcrumbs are only copied here, so that events which are
+    /// dropped by the scope or by sampling never clone them, and so that
+    /// copying them happens on the worker with background processing.
+    pub(crate) fn process_and_send_event(
+        &self,
+        mut event: Event<'static>,
+        hint: &Hint,
+        session: Option<&Mutex<Option<Session>>>,
+        breadcrumbs: Option<&BreadcrumbRing>,
+        attachments: &[Attachment],
+        envelope_items: &[RawEnvelopeItem],
+    ) -> Uuid {
         if let Some(ref transport) = *self.transport.read().unwrap() {
-            if let Some(event) = self.prepare_event(event, scope) {
+            if let Some(breadcrumbs) = breadcrumbs {
+                event.breadcrumbs.extend(breadcrumbs.iter().cloned());
+            }
+            if let Some(max_age) = self.options.max_breadcrumb_age {
+                let now = self.now();
+                event.breadcrumbs.values.retain(|breadcrumb| {
+                    now.duration_since(breadcrumb.timestamp)
+                        .map_or(true, |age| age <= max_age)
+                });
+            }
+            if let Some(event) = self.process_event(event, hint, session) {
                 let event_id = event.event_id;
+                #[cfg(debug_assertions)]
+                if self.options.debug {
+                    for problem in crate::validate::validate_event(&event) {
+                        sentry_debug!("event {} failed validation: {}", event_id, problem);
+                    }
+                }
                 let mut envelope: Envelope = event.into();
                 // For request-mode sessions, we aggregate them all instead of
                 // flushing them out early.
                 if self.options.session_mode == SessionMode::Application {
-                    let session_item = scope.and_then(|scope| {
-                        scope
-                            .session
+                    let session_item = session.and_then(|session| {
+                        session
                             .lock()
                             .unwrap()
                             .as_mut()
@@ -281,19 +737,56 @@ impl Client {
                     }
                 }
 
-                if let Some(scope) = scope {
-                    for attachment in scope.attachments.iter().cloned() {
-                        envelope.add_item(attachment);
+                for attachment in attachments.iter().cloned() {
+                    envelope.add_item(attachment);
+                }
+                for item in envelope_items.iter().cloned() {
+                    envelope.add_item(item);
+                }
+
+                if let Some(ref hook) = self.options.capture_hook {
+                    if let Some(event) = envelope.event() {
+                        sentry_debug!("invoking capture_hook callback");
+                        for attachment in hook(event) {
+                            envelope.add_item(attachment);
+                        }
                     }
                 }
 
-                transport.send_envelope(envelope);
+                if !self.write_to_panic_spool(&envelope) {
+                    transport.send_envelope(envelope);
+                }
                 return event_id;
             }
         }
         Default::default()
     }
 
+    /// Whether the process is about to abort because the current thread
+    /// panics, and events should go to the [`ClientOptions::panic_spool_dir`].
+    fn is_aborting(&self) -> bool {
+        self.options.panic_spool_dir.is_some() && cfg!(panic = "abort") && std::thread::panicking()
+    }
+
+    /// Writes the envelope to the [`ClientOptions::panic_spool_dir`] if the
+    /// process is about to abort because of a panic.
+    ///
+    /// Events captured while panicking are always processed on the panicking
+    /// thread, see [`enqueue_event`](Self::enqueue_event).
+    fn write_to_panic_spool(&self, envelope: &Envelope) -> bool {
+        let dir = match self.options.panic_spool_dir {
+            Some(ref dir) if self.is_aborting() => dir,
+            _ => return false,
+        };
+        match Spool::open(dir).and_then(|spool| spool.write(envelope)) {
+            Ok(()) => true,
+            Err(err) => {
+                sentry_debug!("failed to spool envelope: {}", err);
+                false
+            }
+        }
+    }
+
     /// Sends the specified [`Envelope`] to sentry.
     pub fn send_envelope(&self, envelope: Envelope) {
         if let Some(ref transport) = *self.transport.read().unwrap() {
@@ -307,13 +800,63 @@ impl Client {
         }
     }
 
+    /// Adds a metric to the aggregator, if metrics are enabled.
+    ///
+    /// The `release` and `environment` of the client and the transaction of
+    /// the `scope` are added as default tags, unless the metric has tags of
+    /// the same name.
+    pub(crate) fn capture_metric(&self, metric: Metric, scope: Option<&Scope>) {
+        let aggregator = self.metrics_aggregator.read().unwrap();
+        let aggregator = match *aggregator {
+            Some(ref aggregator) => aggregator,
+            None => return,
+        };
+
+        let Metric {
+            name,
+            unit,
+            value,
+            mut tags,
+            timestamp,
+        } = metric;
+        let mut add_default_tag = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                tags.entry(key.to_owned()).or_insert(value);
+            }
+        };
+        add_default_tag("release", self.options.release.as_deref().map(Into::into));
+        add_default_tag(
+            "environment",
+            self.options.environment.as_deref().map(Into::into),
+        );
+        if let Some(scope) = scope {
+            add_default_tag("transaction", scope.transaction_name());
+        }
+
+        let timestamp = timestamp.unwrap_or_else(|| self.now());
+        aggregator.add(&name, unit, value, tags, timestamp);
+    }
+
     /// Drains all pending events without shutting down.
     pub fn flush(&self, timeout: Option<Duration>) -> bool {
+        let timeout = timeout.unwrap_or(self.options.shutdown_timeout);
+        let started = Instant::now();
+        if !self.pending_events.send_all(timeout) {
+            return false;
+        }
+        if let Some(ref worker) = self.event_worker {
+            if !worker.flush(timeout.saturating_sub(started.elapsed())) {
+                return false;
+            }
+        }
         if let Some(ref flusher) = *self.session_flusher.read().unwrap() {
             flusher.flush();
         }
+        if let Some(ref aggregator) = *self.metrics_aggregator.read().unwrap() {
+            aggregator.flush();
+        }
         if let Some(ref transport) = *self.transport.read().unwrap() {
-            transport.flush(timeout.unwrap_or(self.options.shutdown_timeout))
+            transport.flush(timeout.saturating_sub(started.elapsed()))
         } else {
             true
         }
@@ -327,11 +870,19 @@ impl Client {
     /// If no timeout is provided the client will wait for as long a
     /// `shutdown_timeout` in the client options.
     pub fn close(&self, timeout: Option<Duration>) -> bool {
+        let timeout = timeout.unwrap_or(self.options.shutdown_timeout);
+        let started = Instant::now();
+        self.pending_events.send_all(timeout);
+        if let Some(ref worker) = self.event_worker {
+            worker.flush(timeout.saturating_sub(started.elapsed()));
+        }
+        let timeout = timeout.saturating_sub(started.elapsed());
         drop(self.session_flusher.write().unwrap().take());
+        drop(self.metrics_aggregator.write().unwrap().take());
         let transport_opt = self.transport.write().unwrap().take();
         if let Some(transport) = transport_opt {
             sentry_debug!("client close; request transport to shut down");
-            transport.shutdown(timeout.unwrap_or(self.options.shutdown_timeout))
+            transport.shutdown(timeout)
         } else {
             sentry_debug!("client close; no transport to shut down");
             true
@@ -347,8 +898,126 @@ impl Client {
             random::<f32>() <= rate
         }
     }
+
+    /// Returns the config of the [`RemoteConfigIntegration`] that was fetched
+    /// last, if any.
+    fn remote_config(&self) -> Option<Arc<RemoteConfig>> {
+        self.get_integration::<RemoteConfigIntegration>()?.config()
+    }
+
+    /// Returns the `sample_rate`, as overridden by the remote config.
+    fn sample_rate(&self) -> f32 {
+        let remote = self.remote_config().and_then(|c| valid_rate(c.sample_rate));
+        remote.unwrap_or(self.options.sample_rate)
+    }
+
+    /// Returns the `traces_sample_rate`, as overridden by the remote config.
+    pub(crate) fn traces_sample_rate(&self) -> f32 {
+        let remote = self
+            .remote_config()
+            .and_then(|c| valid_rate(c.traces_sample_rate));
+        remote.unwrap_or(self.options.traces_sample_rate)
+    }
+
+    /// Whether the event matches the `ignore_errors` of the options or the
+    /// remote config.
+    fn is_error_ignored(&self, event: &Event<'_>) -> bool {
+        is_error_ignored(event, &self.options.ignore_errors)
+            || self
+                .remote_config()
+                .map_or(false, |c| is_error_ignored(event, &c.ignore_errors))
+    }
+
+    /// Whether the transaction matches the `ignore_transactions` of the
+    /// options or the remote config.
+    pub(crate) fn is_transaction_ignored(&self, name: Option<&str>) -> bool {
+        is_transaction_ignored(name, &self.options.ignore_transactions)
+            || self.remote_config().map_or(false, |c| {
+                is_transaction_ignored(name, &c.ignore_transactions)
+            })
+    }
 }
 
 // Make this unwind safe. It's not out of the box because of the
 // `BeforeCallback`s inside `ClientOptions`, and the contained Integrations
 impl RefUnwindSafe for Client {}
+
+/// Applies the changes between the `original` and `processed` serialized
+/// events to the fields of a raw event.
+///
+/// Fields that the protocol types do not know are not part of either, so they
+/// are retained.
+fn apply_changes(raw: &mut Map<String, Value>, original: &Value, processed: &Value) {
+    let (original, processed) = match (original.as_object(), processed.as_object()) {
+        (Some(original), Some(processed)) => (original, processed),
+        _ => return,
+    };
+    for key in original.keys() {
+        if !processed.contains_key(key) {
+            raw.remove(key);
+        }
+    }
+    for (key, value) in processed {
+        if original.get(key) != Some(value) {
+            raw.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+/// Infers the culprit of an event from its topmost in-app frame.
+///
+/// The stacktrace of the last exception is the one closest to where the event
+/// was captured, and frames are ordered from the outermost to the innermost.
+fn infer_culprit(event: &Event<'_>) -> Option<String> {
+    let stacktrace = event
+        .exception
+        .values
+        .iter()
+        .rev()
+        .find_map(|exception| exception.stacktrace.as_ref())
+        .or(event.stacktrace.as_ref())
+        .or_else(|| {
+            event
+                .threads
+                .values
+                .iter()
+                .find_map(|thread| thread.stacktrace.as_ref())
+        })?;
+    let frame = stacktrace
+        .frames
+        .iter()
+        .rev()
+        .find(|frame| frame.in_app == Some(true))?;
+    let function = frame.function.as_deref()?;
+    Some(match frame.module.as_deref() {
+        Some(module) => format!("{} in {}", module, function),
+        None => function.to_owned(),
+    })
+}
+
+/// Removes everything but the addresses from the frames of the event.
+fn strip_frame_symbols(event: &mut Event<'_>) {
+    let stacktraces = event
+        .exception
+        .values
+        .iter_mut()
+        .flat_map(|exception| {
+            (exception.stacktrace.as_mut().into_iter()).chain(exception.raw_stacktrace.as_mut())
+        })
+        .chain(event.stacktrace.as_mut())
+        .chain(event.threads.values.iter_mut().flat_map(|thread| {
+            (thread.stacktrace.as_mut().into_iter()).chain(thread.raw_stacktrace.as_mut())
+        }));
+    for stacktrace in stacktraces {
+        for frame in &mut stacktrace.frames {
+            *frame = Frame {
+                in_app: frame.in_app,
+                image_addr: frame.image_addr,
+                instruction_addr: frame.instruction_addr,
+                symbol_addr: frame.symbol_addr,
+                addr_mode: frame.addr_mode.take(),
+                ..Default::default()
+            };
+        }
+    }
+}
@@ -1,17 +1,49 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::constants::USER_AGENT;
-use crate::performance::TracesSampler;
-use crate::protocol::{Breadcrumb, Event};
+use crate::constants::{SPOTLIGHT_URL, USER_AGENT};
+use crate::performance::{SpanCompression, TracesSampler};
+use crate::protocol::{Attachment, Breadcrumb, Event, Level};
 use crate::types::Dsn;
-use crate::{Integration, IntoDsn, TransportFactory};
+use crate::{
+    Backpressure, Clock, EventRejection, FlushProgress, Hint, Integration, IntoDsn,
+    TransportFactory,
+};
 
 /// Type alias for before event/breadcrumb handlers.
 pub type BeforeCallback<T> = Arc<dyn Fn(T) -> Option<T> + Send + Sync>;
 
+/// Type alias for the before send handler that also receives the [`Hint`] of
+/// the event.
+pub type BeforeSendWithHint =
+    Arc<dyn Fn(Event<'static>, &Hint) -> Option<Event<'static>> + Send + Sync>;
+
+/// Type alias for the capture hook, which can produce additional attachments
+/// for a single event.
+pub type CaptureHook = Arc<dyn Fn(&Event<'static>) -> Vec<Attachment> + Send + Sync>;
+
+/// Type alias for the function that computes the fingerprint of an event.
+pub type FingerprintFn = Arc<dyn Fn(&Event<'static>) -> Option<Vec<String>> + Send + Sync>;
+
+/// Type alias for the hook that is run on each transport thread when it starts.
+pub type ThreadStartHook = Arc<dyn Fn() + Send + Sync>;
+
+/// Type alias for the callback that is invoked when the server rejects an
+/// envelope.
+pub type EventRejectedCallback = Arc<dyn Fn(&EventRejection) + Send + Sync>;
+
+/// Type alias for the callback that reports the progress of flushing the
+/// transport.
+pub type FlushProgressCallback = Arc<dyn Fn(FlushProgress) + Send + Sync>;
+
+/// Type alias for the callback that reports changes of the backpressure of
+/// the transport.
+pub type BackpressureCallback = Arc<dyn Fn(Backpressure) + Send + Sync>;
+
 /// The Session Mode of the SDK.
 ///
 /// Depending on the use-case, the SDK can be set to two different session modes:
@@ -40,6 +72,126 @@ pub enum SessionMode {
     Request,
 }
 
+/// Which fields of the scope are inherited by a derived hub.
+///
+/// A hub that is derived from another one with [`Hub::new_from_top`], such as
+/// the hub of a new thread or of a future bound with
+/// [`SentryFutureExt::bind_hub`], starts out with a copy of the top scope of
+/// the other hub.  The fields that are disabled here start out empty instead.
+///
+/// By default, all fields are inherited.
+///
+/// # Examples
+///
+/// Record fresh breadcrumbs for every request, while keeping the user and
+/// tags of the application:
+///
+/// ```
+/// let _options = sentry::ClientOptions {
+///     scope_inheritance: sentry::ScopeInheritance {
+///         breadcrumbs: false,
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// ```
+///
+/// [`Hub::new_from_top`]: crate::Hub::new_from_top
+/// [`SentryFutureExt::bind_hub`]: crate::SentryFutureExt::bind_hub
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ScopeInheritance {
+    /// Inherit the breadcrumbs.
+    pub breadcrumbs: bool,
+    /// Inherit the tags.
+    pub tags: bool,
+    /// Inherit the user.
+    pub user: bool,
+    /// Inherit the contexts.
+    pub contexts: bool,
+    /// Inherit the extra data.
+    pub extra: bool,
+}
+
+impl Default for ScopeInheritance {
+    fn default() -> Self {
+        ScopeInheritance {
+            breadcrumbs: true,
+            tags: true,
+            user: true,
+            contexts: true,
+            extra: true,
+        }
+    }
+}
+
+/// How the built-in transports retry envelopes that could not be sent.
+///
+/// Sending is retried after network errors and server errors (`5xx`), but
+/// not after the server rejected or rate limited an envelope.  The delay
+/// before a retry starts at `initial_backoff` and doubles with every further
+/// retry, up to `max_backoff`.
+///
+/// A worker of the transport waits for the retry before it sends the next
+/// envelope, also in the async transports, whose workers each drive a
+/// runtime of their own.  Other envelopes are sent in the meantime only with
+/// more than one of the [`transport_threads`](ClientOptions::transport_threads).
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let _options = sentry::ClientOptions {
+///     retry_policy: Some(sentry::RetryPolicy {
+///         max_attempts: 5,
+///         max_backoff: Duration::from_secs(10),
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts to send an envelope, including the
+    /// first one.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+    /// The fraction by which delays are randomly shortened, so that clients
+    /// which failed at the same time do not retry in lockstep.
+    pub jitter: f32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.2,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl RetryPolicy {
+    /// Returns the delay before the given retry, starting at `1` for the
+    /// retry after the first attempt.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let delay = self
+            .initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff));
+        let jitter = self.jitter.clamp(0.0, 1.0) * rand::random::<f32>();
+        delay.mul_f32(1.0 - jitter)
+    }
+}
+
 /// Configuration settings for the client.
 ///
 /// These options are explained in more detail in the general
@@ -57,12 +209,44 @@ pub enum SessionMode {
 pub struct ClientOptions {
     // Common options
     /// The DSN to use.  If not set the client is effectively disabled.
+    ///
+    /// Some options can also be set in the query string of the DSN, for
+    /// environments where only the DSN can be configured.  They are applied
+    /// when the options are created from a DSN, and again when a client is
+    /// created, so they also apply to a DSN from the `SENTRY_DSN` environment
+    /// variable.  They take precedence over the fields of the options:
+    ///
+    /// * `timeout`: the [`transport_timeout`](Self::transport_timeout) in seconds.
+    /// * `verify_ssl`: `0` or `false` sets [`accept_invalid_certs`](Self::accept_invalid_certs),
+    ///   but only if [`accept_invalid_certs_from_dsn`](Self::accept_invalid_certs_from_dsn)
+    ///   is enabled.
+    /// * `proxy`: both the [`http_proxy`](Self::http_proxy) and the
+    ///   [`https_proxy`](Self::https_proxy).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let options: sentry::ClientOptions = (
+    ///     "https://key@sentry.io/42?timeout=5&verify_ssl=0",
+    ///     sentry::ClientOptions {
+    ///         accept_invalid_certs_from_dsn: true,
+    ///         ..Default::default()
+    ///     },
+    /// )
+    ///     .into();
+    /// assert_eq!(options.transport_timeout.unwrap().as_secs(), 5);
+    /// assert!(options.accept_invalid_certs);
+    /// ```
     pub dsn: Option<Dsn>,
     /// Enables debug mode.
     ///
     /// In debug mode debug information is printed to stderr to help you understand what
     /// sentry is doing.  When the `log` feature is enabled, Sentry will instead
     /// log to the `sentry` logger independently of this flag with the `Debug` level.
+    ///
+    /// In debug builds, captured events are additionally validated against known
+    /// schema constraints of the Sentry server, such as tag lengths, levels and
+    /// timestamps, and every violation is reported before the event is sent.
     pub debug: bool,
     /// The release to be sent with events.
     pub release: Option<Cow<'static, str>>,
@@ -72,7 +256,41 @@ pub struct ClientOptions {
     /// `debug_assertions` cfg-attribute.
     pub environment: Option<Cow<'static, str>>,
     /// The sample rate for event submission. (0.0 - 1.0, defaults to 1.0)
+    ///
+    /// Every captured event is sent with this probability, and discarded
+    /// otherwise.  The event is sampled after `before_send`, so the callback
+    /// sees all events.  A [`RemoteConfig`](crate::RemoteConfig) can override
+    /// this rate at runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let _options = sentry::ClientOptions {
+    ///     // send a quarter of all events
+    ///     sample_rate: 0.25,
+    ///     ..Default::default()
+    /// };
+    /// ```
     pub sample_rate: f32,
+    /// The sample rates for events of specific levels. (0.0 - 1.0, defaults to none)
+    ///
+    /// This allows to heavily sample low-severity events, while keeping all
+    /// errors, for instance.  Events are sampled by the rate of their level
+    /// right when they are captured, before they are queued for processing,
+    /// and in addition to the [`sample_rate`](Self::sample_rate).  Events of
+    /// levels without a rate are not sampled by level.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentry::Level;
+    ///
+    /// let _options = sentry::ClientOptions {
+    ///     sample_rates: [(Level::Info, 0.01), (Level::Warning, 0.1)].into(),
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub sample_rates: BTreeMap<Level, f32>,
     /// The sample rate for tracing transactions. (0.0 - 1.0, defaults to 0.0)
     pub traces_sample_rate: f32,
     /// If given, called with a SamplingContext for each transaction to determine the sampling rate.
@@ -87,8 +305,39 @@ pub struct ClientOptions {
     /// This represents the probability that a sampled transaction
     /// will send a profile to Sentry
     pub profiles_sample_rate: f32,
+    /// Merges runs of identical short spans before sending transactions. (defaults to `None`)
+    ///
+    /// See [`SpanCompression`] for how spans are merged.
+    pub span_compression: Option<SpanCompression>,
+    /// Records the duration of unsampled transactions as metrics. (defaults to `false`)
+    ///
+    /// Transactions that are not sampled are not sent to Sentry.  With this
+    /// option, their durations are still aggregated into a
+    /// `transaction.duration` distribution per transaction name, which retains
+    /// visibility into throughput and latency percentiles at a fraction of the
+    /// volume of full transaction events.
+    pub unsampled_transaction_metrics: bool,
+    /// Enables recording of custom [`Metric`](crate::Metric)s. (defaults to `false`)
+    pub enable_metrics: bool,
     /// Maximum number of breadcrumbs. (defaults to 100)
     pub max_breadcrumbs: usize,
+    /// Maximum age of the breadcrumbs that are attached to an event.
+    /// (defaults to unlimited)
+    ///
+    /// Breadcrumbs that are older than this when an event is captured are
+    /// not attached to it, so events of long-running processes don't carry
+    /// breadcrumbs from hours ago.
+    pub max_breadcrumb_age: Option<Duration>,
+    /// Records breadcrumbs into per-thread buffers. (defaults to `false`)
+    ///
+    /// By default, every breadcrumb locks the hub it is added to.  With this
+    /// option, breadcrumbs are buffered per thread instead and merged by
+    /// timestamp into the scope when an event is captured, which avoids
+    /// contention on hubs that are shared by many threads.  Scope observers
+    /// are notified of the breadcrumbs when they are merged.
+    pub thread_local_breadcrumbs: bool,
+    /// Which fields of the scope are inherited by derived hubs. (defaults to all)
+    pub scope_inheritance: ScopeInheritance,
     /// Attaches stacktraces to messages.
     pub attach_stacktrace: bool,
     /// If turned on some default PII informat is attached.
@@ -99,6 +348,20 @@ pub struct ClientOptions {
     pub in_app_include: Vec<&'static str>,
     /// Module prefixes that are never "in_app".
     pub in_app_exclude: Vec<&'static str>,
+    /// Patterns of errors that are never sent.
+    ///
+    /// The patterns are matched against the type and value of each exception,
+    /// also in the form of `"Type: value"`, and against the message of an
+    /// event.  A pattern matches exactly, unless it contains the wildcards `*`
+    /// for any sequence of characters, or `?` for a single character, so
+    /// `"*connection reset by peer*"` ignores all errors mentioning a reset
+    /// connection.
+    pub ignore_errors: Vec<Cow<'static, str>>,
+    /// Patterns of transaction names that are never sent, such as
+    /// `"GET /health"`.
+    ///
+    /// The patterns support the same wildcards as `ignore_errors`.
+    pub ignore_transactions: Vec<Cow<'static, str>>,
     // Integration options
     /// A list of integrations to enable.
     ///
@@ -112,9 +375,183 @@ pub struct ClientOptions {
     pub default_integrations: bool,
     // Hooks
     /// Callback that is executed before event sending.
+    ///
+    /// It runs after the event processors and integrations, right before the
+    /// event is sampled and handed to the transport.  The callback can modify
+    /// the event, or return `None` to discard it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// let options = sentry::ClientOptions {
+    ///     before_send: Some(Arc::new(|mut event| {
+    ///         if event.logger.as_deref() == Some("healthcheck") {
+    ///             return None;
+    ///         }
+    ///         event.server_name = None;
+    ///         Some(event)
+    ///     })),
+    ///     ..Default::default()
+    /// };
+    /// # let events = sentry::test::with_captured_events_options(
+    /// #     || {
+    /// #         sentry::capture_event(sentry::protocol::Event {
+    /// #             logger: Some("healthcheck".into()),
+    /// #             ..Default::default()
+    /// #         });
+    /// #         sentry::capture_message("kept", sentry::Level::Info);
+    /// #     },
+    /// #     options,
+    /// # );
+    /// # assert_eq!(events.len(), 1);
+    /// # assert_eq!(events[0].server_name, None);
+    /// ```
     pub before_send: Option<BeforeCallback<Event<'static>>>,
+    /// Callback that is executed before event sending, along with the
+    /// [`Hint`] of the event.
+    ///
+    /// This runs after `before_send`, and can inspect the original error, the
+    /// panic or the integration an event was created from.
+    pub before_send_with_hint: Option<BeforeSendWithHint>,
     /// Callback that is executed for each Breadcrumb being added.
+    ///
+    /// It runs before the breadcrumb is recorded on the scope.  The callback
+    /// can scrub the breadcrumb, or return `None` to drop it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// let options = sentry::ClientOptions {
+    ///     before_breadcrumb: Some(Arc::new(|mut breadcrumb| {
+    ///         if breadcrumb.category.as_deref() == Some("healthcheck") {
+    ///             return None;
+    ///         }
+    ///         // redact query strings, which can contain tokens
+    ///         if let Some(sentry::protocol::Value::String(url)) = breadcrumb.data.get_mut("url") {
+    ///             url.truncate(url.find('?').unwrap_or(url.len()));
+    ///         }
+    ///         Some(breadcrumb)
+    ///     })),
+    ///     ..Default::default()
+    /// };
+    /// # let events = sentry::test::with_captured_events_options(
+    /// #     || {
+    /// #         sentry::add_breadcrumb(sentry::Breadcrumb {
+    /// #             category: Some("healthcheck".into()),
+    /// #             ..Default::default()
+    /// #         });
+    /// #         let mut breadcrumb = sentry::Breadcrumb::default();
+    /// #         breadcrumb.data.insert("url".into(), "/login?token=secret".into());
+    /// #         sentry::add_breadcrumb(breadcrumb);
+    /// #         sentry::capture_message("with breadcrumbs", sentry::Level::Info);
+    /// #     },
+    /// #     options,
+    /// # );
+    /// # assert_eq!(events[0].breadcrumbs.len(), 1);
+    /// # assert_eq!(events[0].breadcrumbs[0].data["url"], "/login");
+    /// ```
     pub before_breadcrumb: Option<BeforeCallback<Breadcrumb>>,
+    /// Callback that is executed when a captured event is sent.
+    ///
+    /// The returned attachments, such as a rendered frame or a dump of the
+    /// application state, are sent along with that specific event only.  The
+    /// hook runs after `before_send`, and is not invoked for dropped events.
+    ///
+    /// By default, the hook runs on the capturing thread.  With
+    /// [`background_processing`](Self::background_processing) it runs on the
+    /// worker thread instead, and with
+    /// [`event_enrichment_window`](Self::event_enrichment_window) on the timer
+    /// thread once the window elapsed, or on the thread that flushes the event
+    /// earlier.  In these cases, neither the scope nor the thread-locals of the
+    /// capturing thread are available, so state that is only accessible from
+    /// there, such as a GPU context, has to be shared with the hook explicitly.
+    pub capture_hook: Option<CaptureHook>,
+    /// Computes the fingerprint of an event from its content.
+    ///
+    /// The function runs after the integrations have processed the event and
+    /// before `before_send`.  A returned fingerprint replaces the one set on
+    /// the event or the scope, while `None` keeps it unchanged.  This allows
+    /// grouping events by data that is only known at capture time, such as an
+    /// error code that is part of the message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// let options = sentry::ClientOptions {
+    ///     fingerprint_fn: Some(Arc::new(|event| {
+    ///         let message = event.message.as_deref()?;
+    ///         let code = message.strip_prefix("error ")?.split(':').next()?;
+    ///         Some(vec!["error-code".into(), code.into()])
+    ///     })),
+    ///     ..Default::default()
+    /// };
+    /// # let events = sentry::test::with_captured_events_options(
+    /// #     || {
+    /// #         sentry::capture_message("error E42: disk full", sentry::Level::Error);
+    /// #     },
+    /// #     options,
+    /// # );
+    /// # assert_eq!(events[0].fingerprint[..], ["error-code", "E42"]);
+    /// ```
+    pub fingerprint_fn: Option<FingerprintFn>,
+    /// Processes captured events on a background thread. (defaults to `false`)
+    ///
+    /// Only the scope is applied on the capturing thread. The heavier work, such
+    /// as the `process_event` hooks of integrations (collecting debug images,
+    /// trimming backtraces, adding contexts), `before_send`, the `capture_hook`
+    /// and sampling run on a dedicated worker thread, while serialization
+    /// happens on the transport thread as usual.  This keeps capture latency
+    /// low, for instance in request handlers.  Data that depends on the
+    /// capturing thread, such as the stacktrace attached with
+    /// [`attach_stacktrace`](Self::attach_stacktrace), is still collected on
+    /// the capturing thread, see
+    /// [`Integration::prepare_event`](crate::Integration::prepare_event).
+    ///
+    /// At most 30 events are queued for processing, further events are
+    /// dropped until the worker caught up.
+    ///
+    /// The event ID returned by the capture functions is assigned up-front, so
+    /// it is not nil even if the event is dropped later on.  Events captured
+    /// via [`Client::capture_event`](crate::Client::capture_event) directly are
+    /// always processed on the calling thread.
+    pub background_processing: bool,
+    /// The time after which an event captured with
+    /// [`Client::capture_event_deferred`](crate::Client::capture_event_deferred)
+    /// is sent, even if it was not sent explicitly. (defaults to 2 seconds)
+    pub deferred_event_timeout: Duration,
+    /// Holds back events captured via the [`Hub`](crate::Hub) for the given
+    /// duration, before they are processed and sent.
+    ///
+    /// While an event is held back, it can be modified with
+    /// [`Client::enrich_event`](crate::Client::enrich_event), given its id.
+    /// Flushing or closing the client sends the held events right away.
+    /// (defaults to `None`)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # let options = sentry::ClientOptions {
+    /// #     event_enrichment_window: Some(Duration::from_secs(10)),
+    /// #     ..Default::default()
+    /// # };
+    /// # let events = sentry::test::with_captured_events_options(|| {
+    /// let event_id = sentry::capture_message("request failed", sentry::Level::Error);
+    ///
+    /// // … once the response of the request is known
+    /// sentry::Hub::current().enrich_event(event_id, |event| {
+    ///     event.tags.insert("status_code".into(), "503".into());
+    /// });
+    /// # }, options);
+    /// # assert_eq!(events[0].tags["status_code"], "503");
+    /// ```
+    pub event_enrichment_window: Option<Duration>,
     // Transport options
     /// The transport to use.
     ///
@@ -131,8 +568,130 @@ pub struct ClientOptions {
     /// This will default to the `HTTPS_PROXY` environment variable
     /// or `http_proxy` if that one exists.
     pub https_proxy: Option<Cow<'static, str>>,
+    /// The timeout of a single request of the built-in HTTP transports.
+    /// (defaults to the timeout of the HTTP client)
+    ///
+    /// This also applies to transports created with a custom client or agent,
+    /// except for the `surf` transport, which uses its client as it is.
+    pub transport_timeout: Option<Duration>,
+    /// Gzip-compresses the request bodies of the built-in HTTP transports.
+    /// (defaults to `true` if the `compression` feature is enabled)
+    ///
+    /// Events with long backtraces and many breadcrumbs can be large, and
+    /// usually compress well.  Compressed bodies are sent with a
+    /// `Content-Encoding: gzip` header, so the server behind the DSN needs to
+    /// support it, as Sentry and Relay do.  Set this to `false` for servers
+    /// that do not.  This only has an effect if the `compression` feature of
+    /// the `sentry` crate is enabled.
+    pub compress_payloads: bool,
     /// The timeout on client drop for draining events on shutdown.
     pub shutdown_timeout: Duration,
+    /// Flushes events captured shortly after the client was created. (defaults to `None`)
+    ///
+    /// Crashes during startup otherwise race the transport worker, and can be
+    /// lost before the guard of `sentry::init` gets to drain the queue.  Every
+    /// event captured within this duration after creating the client is
+    /// flushed synchronously, waiting at most `startup_crash_flush_timeout`.
+    pub startup_crash_window: Option<Duration>,
+    /// The timeout for flushing events captured during startup. (defaults to 2 seconds)
+    ///
+    /// See [`startup_crash_window`](Self::startup_crash_window).
+    pub startup_crash_flush_timeout: Duration,
+    /// A directory for events that are captured while the process aborts on
+    /// a panic.
+    ///
+    /// In builds with `panic = "abort"`, the process aborts as soon as the
+    /// panic hook returns, so events that are still queued in the transport
+    /// might never be sent.  If this is set, events captured on a panicking
+    /// thread are instead written synchronously to a [`Spool`](crate::Spool)
+    /// in this directory, and `sentry::init` sends them in the background the
+    /// next time the application starts.  This has no effect in builds that
+    /// unwind on panic.
+    ///
+    /// Nothing is sent before the process aborts, and no other process is
+    /// started to send the spooled events.  They are only sent once a client
+    /// with this option is initialized again, so they are delayed until the
+    /// application restarts, and never sent if it does not.  Applications that
+    /// are not restarted by a supervisor can send them from a separate
+    /// process, for instance a small tool that calls `sentry::init` with the
+    /// same directory, or drains the [`Spool`](crate::Spool) itself.
+    pub panic_spool_dir: Option<PathBuf>,
+    /// Retries sending envelopes with the built-in transports after transient
+    /// failures. (defaults to `None`, which sends every envelope only once)
+    pub retry_policy: Option<RetryPolicy>,
+    /// A directory for envelopes that the built-in transports could not send.
+    /// (defaults to `None`)
+    ///
+    /// If this is set, envelopes that could not be sent, even after the
+    /// retries of the [`retry_policy`](Self::retry_policy), are written to a
+    /// [`Spool`](crate::Spool) in this directory instead of being dropped.
+    /// They are sent again once the next envelope was sent successfully, or
+    /// when the next client with this option is created, for instance on the
+    /// next start of the application.
+    ///
+    /// To also spool envelopes up-front while the network is known to be
+    /// down, use a `ConnectivityTransportFactory` as the transport.
+    pub offline_spool_dir: Option<PathBuf>,
+    /// The number of worker threads of the built-in transports. (defaults to 1)
+    ///
+    /// Multiple workers send envelopes concurrently, and share the rate limits
+    /// reported by the server.
+    pub transport_threads: usize,
+    /// The name of the transport worker threads. (defaults to `"sentry-transport"`)
+    ///
+    /// With more than one worker, the index of the worker is appended, as in
+    /// `sentry-transport-0`.
+    pub transport_thread_name: Cow<'static, str>,
+    /// Callback that is executed on each transport worker thread when it starts.
+    ///
+    /// The standard library has no portable way to change the priority or CPU
+    /// affinity of a thread.  Latency-sensitive applications can use this hook
+    /// to do so with a crate of their choice, and keep SDK work away from their
+    /// real-time threads.
+    pub on_transport_thread_start: Option<ThreadStartHook>,
+    /// Callback that is executed when the server rejects an envelope.
+    ///
+    /// Rejected envelopes, for instance because they are too large or
+    /// malformed, are dropped by the server.  This callback is invoked by the
+    /// HTTP transports with the reason, which helps to detect systematic
+    /// payload problems.  It runs on the transport thread, so it should
+    /// return quickly and must not capture events itself.
+    pub on_event_rejected: Option<EventRejectedCallback>,
+    /// Callback that reports the progress of flushing the transport.
+    ///
+    /// While [`Client::flush`](crate::Client::flush) or
+    /// [`Client::close`](crate::Client::close) wait for the built-in
+    /// transports, this is invoked with the number and size of the envelopes
+    /// that are still queued, once when the wait starts and whenever an
+    /// envelope was sent or discarded.  This allows applications to show the
+    /// progress of a slow shutdown.  It runs on the flushing thread.
+    pub on_flush_progress: Option<FlushProgressCallback>,
+    /// Callback that reports changes of the backpressure of the transport.
+    ///
+    /// The built-in transports invoke this with the new
+    /// [`Backpressure`] whenever their queue becomes nearly full or has room
+    /// again, and whenever the rate limits of the server change, so
+    /// applications can reduce their capture volume without polling
+    /// [`backpressure`](crate::backpressure).  Rate limits expire at the
+    /// reported point in time without another invocation.  It runs on the
+    /// thread that sends or captures the envelope, so it should return
+    /// quickly and must not capture events itself.
+    pub on_backpressure_change: Option<BackpressureCallback>,
+    /// The clock used for the timestamps and durations of transactions, spans
+    /// and sessions. (defaults to the system clock)
+    pub clock: Option<Arc<dyn Clock>>,
+    /// Forward a copy of every envelope to a local Spotlight sidecar.
+    ///
+    /// [Spotlight](https://spotlightjs.com) shows the events of a development
+    /// build in a local UI.  With this enabled, envelopes are also sent to the
+    /// sidecar at [`spotlight_url`](Self::spotlight_url), even when no DSN is
+    /// configured.  This is meant for development only.
+    pub spotlight: bool,
+    /// The URL of the Spotlight sidecar.
+    /// (defaults to `"http://localhost:8969/stream"`)
+    ///
+    /// Only plain `http` URLs are supported.
+    pub spotlight_url: Cow<'static, str>,
     // Other options not documented in Unified API
     /// Disable SSL verification.
     ///
@@ -140,6 +699,13 @@ pub struct ClientOptions {
     ///
     /// This introduces significant vulnerabilities, and should only be used as a last resort.
     pub accept_invalid_certs: bool,
+    /// Allows `verify_ssl=0` in the query string of the [`dsn`](Self::dsn) to
+    /// disable SSL verification. (defaults to `false`)
+    ///
+    /// Otherwise, the DSN alone can not disable SSL verification, as it is
+    /// often configured by other parties than the application, for instance
+    /// through the `SENTRY_DSN` environment variable.
+    pub accept_invalid_certs_from_dsn: bool,
     /// Enable Release Health Session tracking.
     ///
     /// When automatic session tracking is enabled, a new "user-mode" session
@@ -153,6 +719,22 @@ pub struct ClientOptions {
     pub extra_border_frames: Vec<&'static str>,
     /// Automatically trim backtraces of junk before sending. (defaults to true)
     pub trim_backtraces: bool,
+    /// Removes the frames of runtime scaffolding from trimmed stacktraces.
+    /// (defaults to `false`)
+    ///
+    /// With `trim_backtraces`, this removes the frames matched by the frame
+    /// filters of `sentry-backtrace`, such as tokio, rayon and futures
+    /// internals and the plumbing of panics.  Removing frames changes the
+    /// grouping of issues, so this is opt-in.
+    pub apply_frame_filters: bool,
+    /// Strips symbol information from all frames before sending. (defaults to false)
+    ///
+    /// This removes the function names, modules, file names, line numbers and
+    /// source context of frames, so that only their addresses leave the
+    /// machine.  Sentry can still symbolicate the frames with privately
+    /// uploaded debug files, based on the debug ids of the loaded images
+    /// that the `debug-images` integration adds to events.
+    pub strip_frame_symbols: bool,
     /// The user agent that should be reported.
     pub user_agent: Cow<'static, str>,
 }
@@ -188,10 +770,49 @@ impl fmt::Debug for ClientOptions {
         struct BeforeSend;
         let before_send = self.before_send.as_ref().map(|_| BeforeSend);
         #[derive(Debug)]
+        struct BeforeSendWithHint;
+        let before_send_with_hint = self
+            .before_send_with_hint
+            .as_ref()
+            .map(|_| BeforeSendWithHint);
+        #[derive(Debug)]
         struct BeforeBreadcrumb;
         let before_breadcrumb = self.before_breadcrumb.as_ref().map(|_| BeforeBreadcrumb);
         #[derive(Debug)]
+        struct CaptureHook;
+        let capture_hook = self.capture_hook.as_ref().map(|_| CaptureHook);
+        #[derive(Debug)]
+        struct FingerprintFn;
+        let fingerprint_fn = self.fingerprint_fn.as_ref().map(|_| FingerprintFn);
+        #[derive(Debug)]
         struct TransportFactory;
+        #[derive(Debug)]
+        struct ThreadStartHook;
+        let on_transport_thread_start = self
+            .on_transport_thread_start
+            .as_ref()
+            .map(|_| ThreadStartHook);
+        #[derive(Debug)]
+        struct EventRejectedCallback;
+        let on_event_rejected = self
+            .on_event_rejected
+            .as_ref()
+            .map(|_| EventRejectedCallback);
+        #[derive(Debug)]
+        struct FlushProgressCallback;
+        let on_flush_progress = self
+            .on_flush_progress
+            .as_ref()
+            .map(|_| FlushProgressCallback);
+        #[derive(Debug)]
+        struct BackpressureCallback;
+        let on_backpressure_change = self
+            .on_backpressure_change
+            .as_ref()
+            .map(|_| BackpressureCallback);
+        #[derive(Debug)]
+        struct Clock;
+        let clock = self.clock.as_ref().map(|_| Clock);
 
         let integrations: Vec<_> = self.integrations.iter().map(|i| i.name()).collect();
 
@@ -201,6 +822,7 @@ impl fmt::Debug for ClientOptions {
             .field("release", &self.release)
             .field("environment", &self.environment)
             .field("sample_rate", &self.sample_rate)
+            .field("sample_rates", &self.sample_rates)
             .field("traces_sample_rate", &self.traces_sample_rate)
             .field(
                 "traces_sampler",
@@ -211,25 +833,67 @@ impl fmt::Debug for ClientOptions {
             )
             .field("enable_profiling", &self.enable_profiling)
             .field("profiles_sample_rate", &self.profiles_sample_rate)
+            .field("span_compression", &self.span_compression)
+            .field(
+                "unsampled_transaction_metrics",
+                &self.unsampled_transaction_metrics,
+            )
+            .field("enable_metrics", &self.enable_metrics)
             .field("max_breadcrumbs", &self.max_breadcrumbs)
+            .field("max_breadcrumb_age", &self.max_breadcrumb_age)
+            .field("thread_local_breadcrumbs", &self.thread_local_breadcrumbs)
+            .field("scope_inheritance", &self.scope_inheritance)
             .field("attach_stacktrace", &self.attach_stacktrace)
             .field("send_default_pii", &self.send_default_pii)
             .field("server_name", &self.server_name)
             .field("in_app_include", &self.in_app_include)
             .field("in_app_exclude", &self.in_app_exclude)
+            .field("ignore_errors", &self.ignore_errors)
+            .field("ignore_transactions", &self.ignore_transactions)
             .field("integrations", &integrations)
             .field("default_integrations", &self.default_integrations)
             .field("before_send", &before_send)
+            .field("before_send_with_hint", &before_send_with_hint)
             .field("before_breadcrumb", &before_breadcrumb)
+            .field("capture_hook", &capture_hook)
+            .field("fingerprint_fn", &fingerprint_fn)
+            .field("background_processing", &self.background_processing)
+            .field("deferred_event_timeout", &self.deferred_event_timeout)
+            .field("event_enrichment_window", &self.event_enrichment_window)
             .field("transport", &TransportFactory)
             .field("http_proxy", &self.http_proxy)
             .field("https_proxy", &self.https_proxy)
+            .field("transport_timeout", &self.transport_timeout)
+            .field("compress_payloads", &self.compress_payloads)
             .field("shutdown_timeout", &self.shutdown_timeout)
+            .field("startup_crash_window", &self.startup_crash_window)
+            .field(
+                "startup_crash_flush_timeout",
+                &self.startup_crash_flush_timeout,
+            )
+            .field("panic_spool_dir", &self.panic_spool_dir)
+            .field("retry_policy", &self.retry_policy)
+            .field("offline_spool_dir", &self.offline_spool_dir)
+            .field("transport_threads", &self.transport_threads)
+            .field("transport_thread_name", &self.transport_thread_name)
+            .field("on_transport_thread_start", &on_transport_thread_start)
+            .field("on_event_rejected", &on_event_rejected)
+            .field("on_flush_progress", &on_flush_progress)
+            .field("on_backpressure_change", &on_backpressure_change)
+            .field("clock", &clock)
+            .field("spotlight", &self.spotlight)
+            .field("spotlight_url", &self.spotlight_url)
             .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field(
+                "accept_invalid_certs_from_dsn",
+                &self.accept_invalid_certs_from_dsn,
+            )
             .field("auto_session_tracking", &self.auto_session_tracking)
             .field("session_mode", &self.session_mode)
             .field("extra_border_frames", &self.extra_border_frames)
             .field("trim_backtraces", &self.trim_backtraces)
+            .field("apply_frame_filters", &self.apply_frame_filters)
+            .field("strip_frame_symbols", &self.strip_frame_symbols)
             .field("user_agent", &self.user_agent)
             .finish()
     }
@@ -243,46 +907,117 @@ impl Default for ClientOptions {
             release: None,
             environment: None,
             sample_rate: 1.0,
+            sample_rates: BTreeMap::new(),
             traces_sample_rate: 0.0,
             traces_sampler: None,
             enable_profiling: false,
             profiles_sample_rate: 0.0,
+            span_compression: None,
+            unsampled_transaction_metrics: false,
+            enable_metrics: false,
             max_breadcrumbs: 100,
+            max_breadcrumb_age: None,
+            thread_local_breadcrumbs: false,
+            scope_inheritance: Default::default(),
             attach_stacktrace: false,
             send_default_pii: false,
             server_name: None,
             in_app_include: vec![],
             in_app_exclude: vec![],
+            ignore_errors: vec![],
+            ignore_transactions: vec![],
             integrations: vec![],
             default_integrations: true,
             before_send: None,
+            before_send_with_hint: None,
             before_breadcrumb: None,
+            capture_hook: None,
+            fingerprint_fn: None,
+            background_processing: false,
+            deferred_event_timeout: Duration::from_secs(2),
+            event_enrichment_window: None,
             transport: None,
             http_proxy: None,
             https_proxy: None,
+            transport_timeout: None,
+            compress_payloads: cfg!(feature = "compression"),
             shutdown_timeout: Duration::from_secs(2),
+            startup_crash_window: None,
+            startup_crash_flush_timeout: Duration::from_secs(2),
+            panic_spool_dir: None,
+            retry_policy: None,
+            offline_spool_dir: None,
+            transport_threads: 1,
+            transport_thread_name: Cow::Borrowed("sentry-transport"),
+            on_transport_thread_start: None,
+            on_event_rejected: None,
+            on_flush_progress: None,
+            on_backpressure_change: None,
+            clock: None,
+            spotlight: false,
+            spotlight_url: Cow::Borrowed(SPOTLIGHT_URL),
             accept_invalid_certs: false,
+            accept_invalid_certs_from_dsn: false,
             auto_session_tracking: false,
             session_mode: SessionMode::Application,
             extra_border_frames: vec![],
             trim_backtraces: true,
+            apply_frame_filters: false,
+            strip_frame_symbols: false,
             user_agent: Cow::Borrowed(USER_AGENT),
         }
     }
 }
 
+impl ClientOptions {
+    /// Applies the options in the query string of the DSN.
+    pub(crate) fn apply_dsn_query(&mut self) {
+        let dsn = match self.dsn {
+            Some(ref dsn) => dsn.clone(),
+            None => return,
+        };
+        for (key, value) in dsn.query() {
+            match key.as_str() {
+                "timeout" => {
+                    if let Ok(secs) = value.parse::<f64>() {
+                        if secs.is_finite() && secs >= 0.0 {
+                            self.transport_timeout = Some(Duration::from_secs_f64(secs));
+                        }
+                    }
+                }
+                "verify_ssl" => match value.as_str() {
+                    // the DSN alone must not disable SSL verification
+                    "0" | "false" if self.accept_invalid_certs_from_dsn => {
+                        self.accept_invalid_certs = true
+                    }
+                    "1" | "true" => self.accept_invalid_certs = false,
+                    _ => {}
+                },
+                "proxy" => {
+                    self.http_proxy = Some(value.clone().into());
+                    self.https_proxy = Some(value.clone().into());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
 impl<T: IntoDsn> From<(T, ClientOptions)> for ClientOptions {
     fn from((into_dsn, mut opts): (T, ClientOptions)) -> ClientOptions {
         opts.dsn = into_dsn.into_dsn().expect("invalid value for DSN");
+        opts.apply_dsn_query();
         opts
     }
 }
 
 impl<T: IntoDsn> From<T> for ClientOptions {
     fn from(into_dsn: T) -> ClientOptions {
-        ClientOptions {
+        let mut opts = ClientOptions {
             dsn: into_dsn.into_dsn().expect("invalid value for DSN"),
             ..ClientOptions::default()
-        }
+        };
+        opts.apply_dsn_query();
+        opts
     }
 }
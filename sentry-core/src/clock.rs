@@ -0,0 +1,38 @@
+use std::time::SystemTime;
+
+/// A source of the current time.
+///
+/// The SDK uses the system clock by default.  Simulations, games that keep
+/// their own time, and deterministic tests can provide a custom clock via
+/// [`ClientOptions::clock`] to control the timestamps and durations of
+/// transactions, spans and sessions.
+///
+/// Any `Fn() -> SystemTime` closure can be used as a clock.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::{Duration, SystemTime};
+///
+/// let epoch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+/// let options = sentry::ClientOptions {
+///     clock: Some(Arc::new(move || epoch)),
+///     ..Default::default()
+/// };
+/// ```
+///
+/// [`ClientOptions::clock`]: crate::ClientOptions::clock
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> SystemTime + Send + Sync,
+{
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
@@ -8,6 +8,8 @@ use crate::protocol::{ClientSdkInfo, ClientSdkPackage};
 /// The version of the library
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub(crate) const USER_AGENT: &str = concat!("sentry.rust/", env!("CARGO_PKG_VERSION"));
+/// The default URL of the Spotlight sidecar
+pub(crate) const SPOTLIGHT_URL: &str = "http://localhost:8969/stream";
 
 pub(crate) static SDK_INFO: Lazy<ClientSdkInfo> = Lazy::new(|| ClientSdkInfo {
     name: "sentry.rust".into(),
@@ -0,0 +1,377 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, PoisonError, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+
+use crate::hub::LastEventId;
+use crate::protocol::{Attachment, Event, RawEnvelopeItem};
+use crate::scope::BreadcrumbRing;
+use crate::session::Session;
+use crate::types::Uuid;
+use crate::{Client, Hint, Hub};
+
+/// A scoped event that is waiting to be processed and sent.
+pub(crate) struct DeferredEvent {
+    pub client: Arc<Client>,
+    pub event: Event<'static>,
+    pub hint: Hint,
+    pub session: Arc<Mutex<Option<Session>>>,
+    pub breadcrumbs: Arc<BreadcrumbRing>,
+    pub attachments: Arc<Vec<Attachment>>,
+    pub envelope_items: Arc<Vec<RawEnvelopeItem>>,
+    pub last_event_id: Option<LastEventId>,
+}
+
+impl DeferredEvent {
+    fn send(self) {
+        let event_id = self.event.event_id;
+        let sent = self.client.process_and_send_event(
+            self.event,
+            &self.hint,
+            Some(&self.session),
+            Some(&self.breadcrumbs),
+            &self.attachments,
+            &self.envelope_items,
+        );
+        if let (true, Some(last_event_id)) = (sent.is_nil(), self.last_event_id) {
+            last_event_id.forget(event_id);
+        }
+    }
+}
+
+struct Inner {
+    event_id: Uuid,
+    event: Mutex<Option<DeferredEvent>>,
+    /// Whether the event was handed to the transport or dropped.
+    done: Mutex<bool>,
+    done_changed: Condvar,
+}
+
+impl Inner {
+    /// Sends the event, unless it was already sent.
+    ///
+    /// The event is processed without holding the lock, so it can not be
+    /// enriched anymore while its integrations and `before_send` run.
+    fn send(&self) -> bool {
+        let event = self
+            .event
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        let event = match event {
+            Some(event) => event,
+            None => return false,
+        };
+        let _done = MarkDone {
+            inner: self,
+            client: event.client.clone(),
+        };
+        event.send();
+        true
+    }
+
+    fn is_done(&self) -> bool {
+        *self.done.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits until the event was sent by whoever took it.
+    fn wait_done(&self, timeout: Duration) -> bool {
+        let done = self.done.lock().unwrap_or_else(PoisonError::into_inner);
+        let (done, _) = self
+            .done_changed
+            .wait_timeout_while(done, timeout, |done| !*done)
+            .unwrap_or_else(PoisonError::into_inner);
+        *done
+    }
+}
+
+/// Marks an event as done once it was sent, even if sending it panicked.
+struct MarkDone<'a> {
+    inner: &'a Inner,
+    client: Arc<Client>,
+}
+
+impl Drop for MarkDone<'_> {
+    fn drop(&mut self) {
+        self.client.pending_events.remove(self.inner.event_id);
+        *self
+            .inner
+            .done
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = true;
+        self.inner.done_changed.notify_all();
+    }
+}
+
+/// Sends deferred events once they time out.
+///
+/// A single thread is shared by all clients, and started along with the
+/// first deferred event.  It is `None` if the thread could not be spawned.
+static TIMER: Lazy<Option<Arc<Timer>>> = Lazy::new(|| {
+    let timer = Arc::new(Timer::default());
+    let thread_timer = timer.clone();
+    let spawned = thread::Builder::new()
+        .name("sentry-deferred-events".into())
+        .spawn(move || {
+            // without a client, panics while sending are not captured as
+            // events, which might be deferred here again
+            let hub = Arc::new(Hub::new(None, Default::default()));
+            Hub::run(hub, || thread_timer.run())
+        });
+    match spawned {
+        Ok(_) => Some(timer),
+        Err(err) => {
+            sentry_debug!("failed to spawn the timer of deferred events: {}", err);
+            None
+        }
+    }
+});
+
+#[derive(Default)]
+struct Timer {
+    deadlines: Mutex<BinaryHeap<Deadline>>,
+    deadlines_changed: Condvar,
+}
+
+/// The time at which a deferred event is sent at the latest.
+///
+/// This is ordered by reverse time, so the [`BinaryHeap`] of the [`Timer`]
+/// returns the earliest deadline first.
+struct Deadline {
+    at: Instant,
+    event: Weak<Inner>,
+}
+
+impl PartialEq for Deadline {
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at
+    }
+}
+
+impl Eq for Deadline {}
+
+impl PartialOrd for Deadline {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Deadline {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.at.cmp(&self.at)
+    }
+}
+
+impl Timer {
+    fn schedule(&self, at: Instant, event: Weak<Inner>) {
+        self.deadlines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Deadline { at, event });
+        self.deadlines_changed.notify_one();
+    }
+
+    fn run(&self) {
+        let mut deadlines = self
+            .deadlines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        loop {
+            let now = Instant::now();
+            match deadlines.peek() {
+                Some(next) if next.at <= now => {}
+                Some(next) => {
+                    let wait = next.at - now;
+                    deadlines = self
+                        .deadlines_changed
+                        .wait_timeout(deadlines, wait)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0;
+                    continue;
+                }
+                None => {
+                    deadlines = self
+                        .deadlines_changed
+                        .wait(deadlines)
+                        .unwrap_or_else(PoisonError::into_inner);
+                    continue;
+                }
+            }
+
+            let event = deadlines
+                .pop()
+                .and_then(|deadline| deadline.event.upgrade());
+            drop(deadlines);
+            if let Some(event) = event {
+                // a panicking `before_send` must not stop the timer
+                let sent = panic::catch_unwind(AssertUnwindSafe(|| event.send()));
+                if let Ok(true) = sent {
+                    sentry_debug!("sent deferred event {} after it timed out", event.event_id);
+                }
+            }
+            deadlines = self
+                .deadlines
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+/// An event that was captured, but is not sent yet.
+///
+/// This is returned by [`Client::capture_event_deferred`].  The scope has
+/// already been applied to the event, which can still be enriched with data
+/// that is only available asynchronously, such as the current deployment or
+/// the profile of the user, before it is processed and sent with
+/// [`send`](Self::send).
+///
+/// If the event is not sent within [`ClientOptions::deferred_event_timeout`],
+/// it is sent as it is.  Handles can be cloned to enrich the event from
+/// several tasks.
+///
+/// [`ClientOptions::deferred_event_timeout`]: crate::ClientOptions::deferred_event_timeout
+#[derive(Clone)]
+pub struct PendingEvent {
+    inner: Arc<Inner>,
+}
+
+impl PendingEvent {
+    pub(crate) fn new(event: Option<DeferredEvent>, timeout: Duration) -> PendingEvent {
+        let client = event.as_ref().map(|e| e.client.clone());
+        let inner = Arc::new(Inner {
+            event_id: event.as_ref().map_or_else(Uuid::nil, |e| e.event.event_id),
+            done: Mutex::new(event.is_none()),
+            event: Mutex::new(event),
+            done_changed: Condvar::new(),
+        });
+        let pending = PendingEvent { inner };
+
+        if let Some(client) = client {
+            // registered before the timer starts, so a timed out event is
+            // never left behind in the registry
+            client.pending_events.insert(pending.clone());
+            match *TIMER {
+                Some(ref timer) => {
+                    timer.schedule(Instant::now() + timeout, Arc::downgrade(&pending.inner))
+                }
+                // without a timer, the event can not be deferred
+                None => {
+                    pending.inner.send();
+                }
+            }
+        }
+
+        pending
+    }
+
+    /// Returns the id of the event.
+    ///
+    /// This is nil if the event was dropped while its scope was applied.
+    pub fn event_id(&self) -> Uuid {
+        self.inner.event_id
+    }
+
+    /// Modifies the event before it is sent.
+    ///
+    /// Returns `false` if the event was already sent, for instance because
+    /// it timed out, in which case the callback is not invoked.
+    pub fn enrich<F>(&self, f: F) -> bool
+    where
+        F: FnOnce(&mut Event<'static>),
+    {
+        let mut event = self
+            .inner
+            .event
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match *event {
+            Some(ref mut deferred) => {
+                f(&mut deferred.event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` if the event was sent or dropped.
+    pub fn is_sent(&self) -> bool {
+        self.inner.is_done()
+    }
+
+    /// Processes and sends the event on the current thread.
+    ///
+    /// This runs the integrations, `before_send` and sampling, just like
+    /// [`Client::capture_event`].  Returns `false` if the event was already
+    /// sent.
+    pub fn send(&self) -> bool {
+        self.inner.send()
+    }
+}
+
+impl fmt::Debug for PendingEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingEvent")
+            .field("event_id", &self.inner.event_id)
+            .field("sent", &self.is_sent())
+            .finish()
+    }
+}
+
+/// The events of a client that are not sent yet, by their id.
+#[derive(Default)]
+pub(crate) struct PendingEvents {
+    events: Mutex<HashMap<Uuid, PendingEvent>>,
+}
+
+impl PendingEvents {
+    fn insert(&self, event: PendingEvent) {
+        self.events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(event.event_id(), event);
+    }
+
+    fn remove(&self, event_id: Uuid) {
+        self.events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&event_id);
+    }
+
+    /// Returns the pending event with the given id.
+    pub fn get(&self, event_id: Uuid) -> Option<PendingEvent> {
+        self.events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&event_id)
+            .cloned()
+    }
+
+    /// Sends all pending events right away.
+    ///
+    /// Events that are being sent on another thread are waited for, for at
+    /// most `timeout`.  Returns `false` if that timed out.
+    pub fn send_all(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let events: Vec<_> = self
+            .events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain()
+            .map(|(_, event)| event)
+            .collect();
+        let mut done = true;
+        for event in events {
+            if !event.send() {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                done &= event.inner.wait_done(timeout);
+            }
+        }
+        done
+    }
+}
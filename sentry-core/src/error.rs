@@ -1,16 +1,54 @@
 use std::error::Error;
+use std::io;
 
-use crate::protocol::{Event, Exception, Level};
+use crate::protocol::{CError, Event, Exception, Level, Map, Mechanism, MechanismMeta, Stacktrace};
 use crate::types::Uuid;
 use crate::Hub;
 
+/// The maximum number of errors of a chain that are converted to exceptions.
+const MAX_ERROR_CHAIN_LENGTH: usize = 50;
+
+/// An error that can be viewed as a `dyn Error + 'static`.
+///
+/// This is implemented for all sized `'static` errors and for `dyn Error`
+/// trait objects, and allows the error capturing functions to inspect the
+/// concrete type of an error, such as the kind of an `io::Error`.
+pub trait AsDynError: Error {
+    /// Returns the error as a trait object.
+    fn as_error_object(&self) -> &(dyn Error + 'static);
+}
+
+impl<E: Error + 'static> AsDynError for E {
+    fn as_error_object(&self) -> &(dyn Error + 'static) {
+        self
+    }
+}
+
+impl AsDynError for dyn Error + 'static {
+    fn as_error_object(&self) -> &(dyn Error + 'static) {
+        self
+    }
+}
+
+impl AsDynError for dyn Error + Send + 'static {
+    fn as_error_object(&self) -> &(dyn Error + 'static) {
+        self
+    }
+}
+
+impl AsDynError for dyn Error + Send + Sync + 'static {
+    fn as_error_object(&self) -> &(dyn Error + 'static) {
+        self
+    }
+}
+
 impl Hub {
     /// Capture any `std::error::Error`.
     ///
     /// See the global [`capture_error`](fn.capture_error.html)
     /// for more documentation.
     #[allow(unused)]
-    pub fn capture_error<E: Error + ?Sized>(&self, error: &E) -> Uuid {
+    pub fn capture_error<E: AsDynError + ?Sized>(&self, error: &E) -> Uuid {
         with_client_impl! {{
             self.inner.with(|stack| {
                 let top = stack.top();
@@ -23,6 +61,48 @@ impl Hub {
             })
         }}
     }
+
+    /// Capture a group of `std::error::Error`s as a single event.
+    ///
+    /// See the global [`capture_error_group`](fn.capture_error_group.html)
+    /// for more documentation.
+    #[allow(unused)]
+    pub fn capture_error_group<'a, I, E>(&self, message: &str, errors: I) -> Uuid
+    where
+        I: IntoIterator<Item = &'a E>,
+        E: AsDynError + ?Sized + 'a,
+    {
+        with_client_impl! {{
+            self.inner.with(|stack| {
+                let top = stack.top();
+                if top.client.is_some() {
+                    let event = event_from_error_group(message, errors);
+                    self.capture_event(event)
+                } else {
+                    Uuid::nil()
+                }
+            })
+        }}
+    }
+
+    /// Capture an exception from its type name and message.
+    ///
+    /// See the global [`capture_exception`](fn.capture_exception.html)
+    /// for more documentation.
+    #[allow(unused)]
+    pub fn capture_exception(&self, ty: &str, value: &str, stacktrace: Option<Stacktrace>) -> Uuid {
+        with_client_impl! {{
+            self.inner.with(|stack| {
+                let top = stack.top();
+                if top.client.is_some() {
+                    let event = event_from_exception(ty, value, stacktrace);
+                    self.capture_event(event)
+                } else {
+                    Uuid::nil()
+                }
+            })
+        }}
+    }
 }
 
 /// Captures a `std::error::Error`.
@@ -47,14 +127,103 @@ impl Hub {
 ///
 /// [sentry event payloads]: https://develop.sentry.dev/sdk/event-payloads/exception/
 #[allow(unused_variables)]
-pub fn capture_error<E: Error + ?Sized>(error: &E) -> Uuid {
+pub fn capture_error<E: AsDynError + ?Sized>(error: &E) -> Uuid {
     Hub::with_active(|hub| hub.capture_error(error))
 }
 
+/// Captures a group of `std::error::Error`s as a single event.
+///
+/// This is useful for aggregate failures, such as the errors of joined
+/// futures or of a validation.  See [`event_from_error_group`] for how the
+/// errors are represented.
+///
+/// # Examples
+///
+/// ```
+/// let errors: Vec<_> = ["NaN", "", "-1"]
+///     .iter()
+///     .filter_map(|input| input.parse::<usize>().err())
+///     .collect();
+///
+/// # let events = sentry::test::with_captured_events(|| {
+/// sentry::capture_error_group("failed to parse inputs", &errors);
+/// # });
+/// # let captured_event = events.into_iter().next().unwrap();
+///
+/// assert_eq!(captured_event.exception.len(), 4);
+/// ```
+#[allow(unused_variables)]
+pub fn capture_error_group<'a, I, E>(message: &str, errors: I) -> Uuid
+where
+    I: IntoIterator<Item = &'a E>,
+    E: AsDynError + ?Sized + 'a,
+{
+    Hub::with_active(|hub| hub.capture_error_group(message, errors))
+}
+
+/// Captures an exception from its type name and message.
+///
+/// This is useful for errors that do not implement `std::error::Error`, such
+/// as error codes of a C library or errors that are decoded from the wire.
+/// See [`event_from_exception`] for how the event is created.
+///
+/// # Examples
+///
+/// ```
+/// # let events = sentry::test::with_captured_events(|| {
+/// sentry::capture_exception("EACCES", "permission denied", None);
+/// # });
+/// # let captured_event = events.into_iter().next().unwrap();
+///
+/// assert_eq!(&captured_event.exception[0].ty, "EACCES");
+/// ```
+#[allow(unused_variables)]
+pub fn capture_exception(ty: &str, value: &str, stacktrace: Option<Stacktrace>) -> Uuid {
+    Hub::with_active(|hub| hub.capture_exception(ty, value, stacktrace))
+}
+
+/// Create a sentry `Event` from an exception type name and message.
+///
+/// The event contains a single exception with the given type, message and
+/// optional stacktrace, for instance one that was captured with
+/// `sentry::current_stacktrace`.
+///
+/// # Examples
+///
+/// ```
+/// let event = sentry::event_from_exception("ProtocolError", "unexpected frame type 0x7", None);
+/// assert_eq!(event.level, sentry::protocol::Level::Error);
+/// assert_eq!(event.exception.len(), 1);
+/// assert_eq!(&event.exception[0].ty, "ProtocolError");
+/// assert_eq!(
+///     event.exception[0].value.as_deref(),
+///     Some("unexpected frame type 0x7")
+/// );
+/// ```
+pub fn event_from_exception(
+    ty: &str,
+    value: &str,
+    stacktrace: Option<Stacktrace>,
+) -> Event<'static> {
+    Event {
+        exception: vec![Exception {
+            ty: ty.to_owned(),
+            value: Some(value.to_owned()),
+            stacktrace,
+            ..Default::default()
+        }]
+        .into(),
+        level: Level::Error,
+        ..Default::default()
+    }
+}
+
 /// Create a sentry `Event` from a `std::error::Error`.
 ///
 /// A chain of errors will be resolved as well, and sorted oldest to newest, as
-/// described in the [sentry event payloads].
+/// described in the [sentry event payloads].  Chains with cycles or more than
+/// 50 errors are truncated, and the oldest exception is then a
+/// `TruncatedErrorChain` marker.
 ///
 /// # Examples
 ///
@@ -78,25 +247,125 @@ pub fn capture_error<E: Error + ?Sized>(error: &E) -> Uuid {
 /// assert_eq!(event.exception[1].value, Some("outer".into()));
 /// ```
 ///
+/// The error has to be `'static`, or a `dyn Error` trait object, see
+/// [`AsDynError`].
+///
 /// [sentry event payloads]: https://develop.sentry.dev/sdk/event-payloads/exception/
-pub fn event_from_error<E: Error + ?Sized>(err: &E) -> Event<'static> {
+pub fn event_from_error<E: AsDynError + ?Sized>(err: &E) -> Event<'static> {
+    let mut exceptions = exceptions_from_error_chain(err.as_error_object());
+    exceptions.reverse();
+    Event {
+        exception: exceptions.into(),
+        level: Level::Error,
+        ..Default::default()
+    }
+}
+
+/// Create a sentry `Event` from a group of `std::error::Error`s.
+///
+/// The event contains an `ErrorGroup` exception with the given `message`,
+/// which is marked as an exception group, followed by the chains of all the
+/// errors in the group.  The exceptions are linked to their parents via
+/// their mechanism, so Sentry renders them as an aggregate exception.
+///
+/// # Examples
+///
+/// ```
+/// let errors = vec![
+///     "NaN".parse::<usize>().unwrap_err(),
+///     "".parse::<usize>().unwrap_err(),
+/// ];
+///
+/// let event = sentry::event_from_error_group("failed to parse inputs", &errors);
+/// assert_eq!(event.exception.len(), 3);
+/// let group = event.exception.last().unwrap();
+/// assert_eq!(&group.ty, "ErrorGroup");
+/// assert_eq!(group.value.as_deref(), Some("failed to parse inputs"));
+/// assert_eq!(group.mechanism.as_ref().unwrap().is_exception_group, Some(true));
+/// ```
+pub fn event_from_error_group<'a, I, E>(message: &str, errors: I) -> Event<'static>
+where
+    I: IntoIterator<Item = &'a E>,
+    E: AsDynError + ?Sized + 'a,
+{
+    let mut exceptions = vec![Exception {
+        ty: String::from("ErrorGroup"),
+        value: Some(message.to_owned()),
+        mechanism: Some(Mechanism {
+            ty: String::from("error_group"),
+            is_exception_group: Some(true),
+            exception_id: Some(0),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }];
+
+    for (i, err) in errors.into_iter().enumerate() {
+        let mut parent_id = 0;
+        let mut source = format!("errors[{}]", i);
+        for mut exception in exceptions_from_error_chain(err.as_error_object()) {
+            let exception_id = exceptions.len() as u64;
+            let mechanism = exception.mechanism.take().unwrap_or_default();
+            exception.mechanism = Some(Mechanism {
+                ty: String::from("chained"),
+                source: Some(source),
+                exception_id: Some(exception_id),
+                parent_id: Some(parent_id),
+                ..mechanism
+            });
+            exceptions.push(exception);
+            parent_id = exception_id;
+            source = String::from("source");
+        }
+    }
+
+    exceptions.reverse();
+    Event {
+        exception: exceptions.into(),
+        level: Level::Error,
+        ..Default::default()
+    }
+}
+
+/// Converts an error and its sources to exceptions, sorted newest to oldest.
+fn exceptions_from_error_chain(err: &(dyn Error + 'static)) -> Vec<Exception> {
     let mut exceptions = vec![exception_from_error(err)];
 
+    // Some error wrappers produce sources that refer back to an earlier error
+    // in the chain, so the chain is cut at the first repeated error or once
+    // it gets too long, with a marker exception recording why.
+    let mut seen: Vec<&(dyn Error + 'static)> = Vec::new();
     let mut source = err.source();
     while let Some(err) = source {
+        if seen.iter().any(|seen| std::ptr::eq(*seen, err)) {
+            exceptions.push(truncated_chain_exception(
+                "the error chain contains a cycle",
+            ));
+            break;
+        }
+        if exceptions.len() >= MAX_ERROR_CHAIN_LENGTH {
+            exceptions.push(truncated_chain_exception(&format!(
+                "the error chain is longer than {} errors",
+                MAX_ERROR_CHAIN_LENGTH
+            )));
+            break;
+        }
+        seen.push(err);
         exceptions.push(exception_from_error(err));
         source = err.source();
     }
+    exceptions
+}
 
-    exceptions.reverse();
-    Event {
-        exception: exceptions.into(),
-        level: Level::Error,
+fn truncated_chain_exception(reason: &str) -> Exception {
+    Exception {
+        ty: String::from("TruncatedErrorChain"),
+        value: Some(format!("error chain truncated: {}", reason)),
         ..Default::default()
     }
 }
 
-fn exception_from_error<E: Error + ?Sized>(err: &E) -> Exception {
+fn exception_from_error(err: &(dyn Error + 'static)) -> Exception {
     let dbg = format!("{:?}", err);
     let value = err.to_string();
 
@@ -111,13 +380,162 @@ fn exception_from_error<E: Error + ?Sized>(err: &E) -> Exception {
     } else {
         parse_type_from_debug(&dbg).to_owned()
     };
+    let mechanism = err.downcast_ref::<io::Error>().map(io_error_mechanism);
     Exception {
         ty,
         value: Some(err.to_string()),
+        mechanism,
+        ..Default::default()
+    }
+}
+
+/// Creates the mechanism of an `io::Error`, recording its kind and OS error.
+///
+/// On unix, the OS error is recorded as errno along with the name of its
+/// constant.  Elsewhere, such as the error codes of Windows, it is recorded
+/// in the mechanism data.
+fn io_error_mechanism(err: &io::Error) -> Mechanism {
+    let mut data = Map::new();
+    data.insert("kind".into(), format!("{:?}", err.kind()).into());
+    let mut meta = MechanismMeta::default();
+    if let Some(code) = err.raw_os_error() {
+        if cfg!(unix) {
+            meta.errno = Some(CError {
+                number: code,
+                name: errno_name(code).map(String::from),
+            });
+        } else {
+            data.insert("os_error_code".into(), code.into());
+        }
+    }
+    Mechanism {
+        ty: String::from("generic"),
+        data,
+        meta,
         ..Default::default()
     }
 }
 
+/// Returns the name of the errno constant with the given number.
+///
+/// This only covers the common codes, of which the first 34 are the same on
+/// all unix platforms.
+fn errno_name(number: i32) -> Option<&'static str> {
+    let name = match number {
+        1 => "EPERM",
+        2 => "ENOENT",
+        3 => "ESRCH",
+        4 => "EINTR",
+        5 => "EIO",
+        6 => "ENXIO",
+        7 => "E2BIG",
+        8 => "ENOEXEC",
+        9 => "EBADF",
+        10 => "ECHILD",
+        12 => "ENOMEM",
+        13 => "EACCES",
+        14 => "EFAULT",
+        15 => "ENOTBLK",
+        16 => "EBUSY",
+        17 => "EEXIST",
+        18 => "EXDEV",
+        19 => "ENODEV",
+        20 => "ENOTDIR",
+        21 => "EISDIR",
+        22 => "EINVAL",
+        23 => "ENFILE",
+        24 => "EMFILE",
+        25 => "ENOTTY",
+        26 => "ETXTBSY",
+        27 => "EFBIG",
+        28 => "ENOSPC",
+        29 => "ESPIPE",
+        30 => "EROFS",
+        31 => "EMLINK",
+        32 => "EPIPE",
+        33 => "EDOM",
+        34 => "ERANGE",
+        _ => return platform_errno_name(number),
+    };
+    Some(name)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn platform_errno_name(number: i32) -> Option<&'static str> {
+    let name = match number {
+        11 => "EAGAIN",
+        35 => "EDEADLK",
+        36 => "ENAMETOOLONG",
+        38 => "ENOSYS",
+        39 => "ENOTEMPTY",
+        40 => "ELOOP",
+        95 => "EOPNOTSUPP",
+        98 => "EADDRINUSE",
+        99 => "EADDRNOTAVAIL",
+        100 => "ENETDOWN",
+        101 => "ENETUNREACH",
+        103 => "ECONNABORTED",
+        104 => "ECONNRESET",
+        107 => "ENOTCONN",
+        110 => "ETIMEDOUT",
+        111 => "ECONNREFUSED",
+        113 => "EHOSTUNREACH",
+        114 => "EALREADY",
+        115 => "EINPROGRESS",
+        122 => "EDQUOT",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+fn platform_errno_name(number: i32) -> Option<&'static str> {
+    let name = match number {
+        11 => "EDEADLK",
+        35 => "EAGAIN",
+        36 => "EINPROGRESS",
+        37 => "EALREADY",
+        48 => "EADDRINUSE",
+        49 => "EADDRNOTAVAIL",
+        50 => "ENETDOWN",
+        51 => "ENETUNREACH",
+        53 => "ECONNABORTED",
+        54 => "ECONNRESET",
+        57 => "ENOTCONN",
+        60 => "ETIMEDOUT",
+        61 => "ECONNREFUSED",
+        62 => "ELOOP",
+        63 => "ENAMETOOLONG",
+        65 => "EHOSTUNREACH",
+        66 => "ENOTEMPTY",
+        69 => "EDQUOT",
+        78 => "ENOSYS",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+fn platform_errno_name(_number: i32) -> Option<&'static str> {
+    None
+}
+
 /// Parse the types name from `Debug` output.
 ///
 /// # Examples
@@ -170,3 +588,158 @@ fn test_parse_anyhow_as_error() {
     assert_eq!(&exc.ty, "Error");
     assert_eq!(exc.value.as_deref(), Some("Ooops, something bad happened"));
 }
+
+#[test]
+fn test_error_chain_cycle() {
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct CyclicError;
+
+    impl fmt::Display for CyclicError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("cyclic")
+        }
+    }
+
+    impl Error for CyclicError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&CyclicError)
+        }
+    }
+
+    let event = event_from_error(&CyclicError);
+    assert_eq!(event.exception.len(), 3);
+    assert_eq!(&event.exception[0].ty, "TruncatedErrorChain");
+    assert_eq!(
+        event.exception[0].value.as_deref(),
+        Some("error chain truncated: the error chain contains a cycle")
+    );
+    assert_eq!(&event.exception[1].ty, "CyclicError");
+    assert_eq!(&event.exception[2].ty, "CyclicError");
+}
+
+#[test]
+fn test_error_chain_max_length() {
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct NestedError(Option<Box<NestedError>>);
+
+    impl fmt::Display for NestedError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("nested")
+        }
+    }
+
+    impl Error for NestedError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            self.0.as_deref().map(|err| err as _)
+        }
+    }
+
+    let err = (0..100).fold(NestedError(None), |err, _| NestedError(Some(Box::new(err))));
+    let event = event_from_error(&err);
+    assert_eq!(event.exception.len(), MAX_ERROR_CHAIN_LENGTH + 1);
+    assert_eq!(&event.exception[0].ty, "TruncatedErrorChain");
+    assert_eq!(&event.exception[1].ty, "NestedError");
+}
+
+#[test]
+fn test_error_group() {
+    #[derive(Debug, thiserror::Error)]
+    #[error("inner")]
+    struct InnerError;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("outer")]
+    struct OuterError(#[source] InnerError);
+
+    let errors = [OuterError(InnerError), OuterError(InnerError)];
+    let event = event_from_error_group("group", &errors);
+
+    let relations: Vec<_> = event
+        .exception
+        .iter()
+        .map(|exc| {
+            let mechanism = exc.mechanism.as_ref().unwrap();
+            (
+                exc.ty.as_str(),
+                mechanism.exception_id,
+                mechanism.parent_id,
+                mechanism.source.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        relations,
+        [
+            ("InnerError", Some(4), Some(3), Some("source")),
+            ("OuterError", Some(3), Some(0), Some("errors[1]")),
+            ("InnerError", Some(2), Some(1), Some("source")),
+            ("OuterError", Some(1), Some(0), Some("errors[0]")),
+            ("ErrorGroup", Some(0), None, None),
+        ]
+    );
+}
+
+#[test]
+fn test_io_error_mechanism() {
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct ConfigError(io::Error);
+
+    impl fmt::Display for ConfigError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("failed to read the configuration")
+        }
+    }
+
+    impl Error for ConfigError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    let event = event_from_error(&io::Error::from_raw_os_error(2));
+    let mechanism = event.exception[0].mechanism.as_ref().unwrap();
+    assert_eq!(mechanism.data["kind"], "NotFound");
+    if cfg!(unix) {
+        let errno = mechanism.meta.errno.as_ref().unwrap();
+        assert_eq!(errno.number, 2);
+        assert_eq!(errno.name.as_deref(), Some("ENOENT"));
+    }
+
+    let event = event_from_error(&ConfigError(io::Error::from_raw_os_error(13)));
+    assert!(event.exception[1].mechanism.is_none());
+    let mechanism = event.exception[0].mechanism.as_ref().unwrap();
+    assert_eq!(mechanism.data["kind"], "PermissionDenied");
+    if cfg!(unix) {
+        let errno = mechanism.meta.errno.as_ref().unwrap();
+        assert_eq!(errno.name.as_deref(), Some("EACCES"));
+    }
+
+    // trait objects are downcast as well
+    let err: Box<dyn Error + Send + Sync> = Box::new(io::Error::from_raw_os_error(2));
+    let event = event_from_error(&*err);
+    let mechanism = event.exception[0].mechanism.as_ref().unwrap();
+    assert_eq!(mechanism.data["kind"], "NotFound");
+
+    // errors without an OS error only record their kind
+    let err = io::Error::new(io::ErrorKind::Other, "custom");
+    let event = event_from_error(&ConfigError(err));
+    let mechanism = event.exception[0].mechanism.as_ref().unwrap();
+    assert_eq!(mechanism.data["kind"], "Other");
+    assert!(mechanism.meta.errno.is_none());
+
+    for err in [
+        io::Error::new(io::ErrorKind::InvalidData, "custom"),
+        io::Error::from(io::ErrorKind::InvalidData),
+    ] {
+        let event = event_from_error(&err);
+        let mechanism = event.exception[0].mechanism.as_ref().unwrap();
+        assert_eq!(mechanism.data["kind"], "InvalidData");
+        assert!(mechanism.meta.errno.is_none());
+    }
+}
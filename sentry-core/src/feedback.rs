@@ -0,0 +1,194 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::protocol::UserReport;
+use crate::types::Uuid;
+
+/// The maximum number of characters allowed in feedback comments.
+pub const MAX_FEEDBACK_COMMENTS_LENGTH: usize = 5000;
+
+/// An error returned when validating [`UserFeedback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FeedbackError {
+    /// No event ID was given, and no event was captured before.
+    MissingEventId,
+    /// The comments are empty.
+    EmptyComments,
+    /// The comments exceed [`MAX_FEEDBACK_COMMENTS_LENGTH`].
+    CommentsTooLong,
+    /// The email address is not valid.
+    InvalidEmail,
+}
+
+impl fmt::Display for FeedbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedbackError::MissingEventId => write!(f, "no event to attach the feedback to"),
+            FeedbackError::EmptyComments => write!(f, "feedback comments are empty"),
+            FeedbackError::CommentsTooLong => write!(
+                f,
+                "feedback comments exceed {} characters",
+                MAX_FEEDBACK_COMMENTS_LENGTH
+            ),
+            FeedbackError::InvalidEmail => write!(f, "invalid email address"),
+        }
+    }
+}
+
+impl Error for FeedbackError {}
+
+/// Feedback collected from a user, for example through a dialog of a desktop app.
+///
+/// The feedback is attached to an event, which defaults to the last event
+/// captured by the [`Hub`](crate::Hub) it is submitted to. It is validated and
+/// sent using [`capture_user_feedback`](crate::capture_user_feedback), through
+/// the same transport as events, so it is queued like them when the transport
+/// cannot reach Sentry.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::UserFeedback;
+///
+/// # sentry::test::with_captured_envelopes(|| {
+/// sentry::capture_message("Something went wrong", sentry::Level::Error);
+///
+/// let feedback = UserFeedback::new("It crashed when I clicked save.")
+///     .name("Jane Doe")
+///     .email("jane@example.com");
+/// sentry::capture_user_feedback(feedback).unwrap();
+/// # });
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserFeedback {
+    event_id: Option<Uuid>,
+    name: Option<String>,
+    email: Option<String>,
+    comments: String,
+}
+
+impl UserFeedback {
+    /// Creates new feedback with the given comments.
+    pub fn new<S: Into<String>>(comments: S) -> Self {
+        Self {
+            comments: comments.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the name of the user.
+    #[must_use]
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the email address of the user.
+    #[must_use]
+    pub fn email<S: Into<String>>(mut self, email: S) -> Self {
+        self.email = Some(email.into());
+        self
+    }
+
+    /// Sets the ID of the event this feedback is about.
+    #[must_use]
+    pub fn event_id(mut self, event_id: Uuid) -> Self {
+        self.event_id = Some(event_id);
+        self
+    }
+
+    /// Validates the comments and email address of the feedback.
+    pub fn validate(&self) -> Result<(), FeedbackError> {
+        if self.comments.trim().is_empty() {
+            return Err(FeedbackError::EmptyComments);
+        }
+        if self.comments.chars().count() > MAX_FEEDBACK_COMMENTS_LENGTH {
+            return Err(FeedbackError::CommentsTooLong);
+        }
+        match self.email {
+            Some(ref email) if !is_valid_email(email) => Err(FeedbackError::InvalidEmail),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates the feedback and converts it into a [`UserReport`].
+    ///
+    /// The `last_event_id` is used if no event ID was set explicitly.
+    pub(crate) fn into_report(
+        self,
+        last_event_id: Option<Uuid>,
+    ) -> Result<UserReport, FeedbackError> {
+        self.validate()?;
+        let event_id = self
+            .event_id
+            .or(last_event_id)
+            .filter(|event_id| !event_id.is_nil())
+            .ok_or(FeedbackError::MissingEventId)?;
+        Ok(UserReport {
+            event_id,
+            name: self.name.unwrap_or_default(),
+            email: self.email.unwrap_or_default(),
+            comments: self.comments,
+        })
+    }
+}
+
+fn is_valid_email(email: &str) -> bool {
+    let (local, domain) = match email.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.chars().any(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation() {
+        let event_id = Uuid::new_v4();
+        assert_eq!(
+            UserFeedback::new(" ").validate(),
+            Err(FeedbackError::EmptyComments)
+        );
+        assert_eq!(
+            UserFeedback::new("x".repeat(MAX_FEEDBACK_COMMENTS_LENGTH + 1)).validate(),
+            Err(FeedbackError::CommentsTooLong)
+        );
+        for email in [
+            "jane",
+            "@example.com",
+            "jane@example",
+            "jane@@example.com",
+            "j ane@example.com",
+        ] {
+            assert_eq!(
+                UserFeedback::new("broken").email(email).validate(),
+                Err(FeedbackError::InvalidEmail),
+                "{}",
+                email
+            );
+        }
+        assert_eq!(
+            UserFeedback::new("broken").into_report(None),
+            Err(FeedbackError::MissingEventId)
+        );
+
+        let report = UserFeedback::new("broken")
+            .name("Jane")
+            .email("jane@example.com")
+            .into_report(Some(event_id))
+            .unwrap();
+        assert_eq!(report.event_id, event_id);
+        assert_eq!(report.name, "Jane");
+        assert_eq!(report.email, "jane@example.com");
+        assert_eq!(report.comments, "broken");
+    }
+}
@@ -0,0 +1,227 @@
+use std::borrow::Cow;
+
+use crate::Level;
+
+/// The action that an integration should perform for a record.
+///
+/// This is shared by the bridge integrations, such as `sentry-log` and
+/// `sentry-tracing`, which decide per log record or tracing event whether it
+/// turns into a breadcrumb, an event, or is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFilter {
+    /// Ignore the record.
+    Ignore,
+    /// Create a [`Breadcrumb`](crate::Breadcrumb) from the record.
+    Breadcrumb,
+    /// Create a message [`Event`](crate::protocol::Event) from the record.
+    Event,
+    /// Create an exception [`Event`](crate::protocol::Event) from the record.
+    Exception,
+}
+
+/// The default action for a record of the given level.
+///
+/// By default, an exception event is captured for `error` and `fatal`, a
+/// breadcrumb for `warning` and `info`, and `debug` records are ignored.
+pub fn default_event_filter(level: Level) -> EventFilter {
+    match level {
+        Level::Fatal | Level::Error => EventFilter::Exception,
+        Level::Warning | Level::Info => EventFilter::Breadcrumb,
+        Level::Debug => EventFilter::Ignore,
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    target: Option<String>,
+    level: Option<Level>,
+    filter: EventFilter,
+}
+
+impl Rule {
+    fn matches(&self, level: Level, target: &str) -> bool {
+        self.level.map_or(true, |l| l == level)
+            && self.target.as_deref().map_or(true, |t| {
+                target == t
+                    || target
+                        .strip_prefix(t)
+                        .map_or(false, |rest| rest.starts_with("::"))
+            })
+    }
+}
+
+/// A configurable filter that decides the [`EventFilter`] for records.
+///
+/// The filter is made up of rules based on the level and target of records,
+/// where rules added later take precedence over earlier ones.  Records that
+/// match no rule are handled according to [`default_event_filter`].  A target
+/// rule applies to the given module and all its submodules.
+///
+/// The same filter can be used with all bridge integrations, so records are
+/// handled consistently whether they come from `log` or `tracing`.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::{EventFilter, Level, RecordFilter};
+///
+/// let filter = RecordFilter::new()
+///     .level(Level::Warning, EventFilter::Event)
+///     .target("hyper", EventFilter::Ignore)
+///     .target_level("hyper::client", Level::Error, EventFilter::Breadcrumb);
+///
+/// assert_eq!(filter.filter(Level::Warning, "app"), EventFilter::Event);
+/// assert_eq!(filter.filter(Level::Error, "hyper::proto"), EventFilter::Ignore);
+/// assert_eq!(
+///     filter.filter(Level::Error, "hyper::client::pool"),
+///     EventFilter::Breadcrumb
+/// );
+/// assert_eq!(filter.filter(Level::Debug, "app"), EventFilter::Ignore);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RecordFilter {
+    rules: Vec<Rule>,
+}
+
+impl RecordFilter {
+    /// Creates a filter that handles all records according to
+    /// [`default_event_filter`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the action for records of the given level.
+    #[must_use]
+    pub fn level(self, level: Level, filter: EventFilter) -> Self {
+        self.rule(None, Some(level), filter)
+    }
+
+    /// Sets the action for all records of the given target.
+    #[must_use]
+    pub fn target<T: Into<String>>(self, target: T, filter: EventFilter) -> Self {
+        self.rule(Some(target.into()), None, filter)
+    }
+
+    /// Sets the action for records of the given target and level.
+    #[must_use]
+    pub fn target_level<T: Into<String>>(
+        self,
+        target: T,
+        level: Level,
+        filter: EventFilter,
+    ) -> Self {
+        self.rule(Some(target.into()), Some(level), filter)
+    }
+
+    fn rule(mut self, target: Option<String>, level: Option<Level>, filter: EventFilter) -> Self {
+        self.rules.push(Rule {
+            target,
+            level,
+            filter,
+        });
+        self
+    }
+
+    /// Returns the action for a record of the given level and target.
+    pub fn filter(&self, level: Level, target: &str) -> EventFilter {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(level, target))
+            .map_or_else(|| default_event_filter(level), |rule| rule.filter)
+    }
+}
+
+/// Matches `value` against a pattern, where `*` matches any sequence of
+/// characters and `?` matches a single character.
+///
+/// Patterns without wildcards match exactly.
+pub(crate) fn glob_matches(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+    // the position of the last `*` in the pattern, and the position in the
+    // value that it currently matches up to
+    let mut backtrack = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    v = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A filter for the requests handled by the web framework integrations.
+///
+/// Requests are matched by their path against glob patterns, where `*`
+/// matches any sequence of characters and `?` matches a single character.
+/// A request is allowed if it matches no `deny` pattern and, if any `allow`
+/// patterns are given, at least one of them.  The integrations do not start
+/// transactions for requests that are not allowed, and do not capture their
+/// errors.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::RouteFilter;
+///
+/// let filter = RouteFilter::new().deny("/healthz").deny("/metrics*");
+///
+/// assert!(filter.is_allowed("/users/1"));
+/// assert!(!filter.is_allowed("/healthz"));
+/// assert!(!filter.is_allowed("/metrics/prometheus"));
+///
+/// let filter = RouteFilter::new().allow("/api/*").deny("/api/internal/*");
+///
+/// assert!(filter.is_allowed("/api/users"));
+/// assert!(!filter.is_allowed("/api/internal/cache"));
+/// assert!(!filter.is_allowed("/static/app.js"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RouteFilter {
+    allow: Vec<Cow<'static, str>>,
+    deny: Vec<Cow<'static, str>>,
+}
+
+impl RouteFilter {
+    /// Creates a filter that allows all requests.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only allows requests that match this or another `allow` pattern.
+    #[must_use]
+    pub fn allow<P: Into<Cow<'static, str>>>(mut self, pattern: P) -> Self {
+        self.allow.push(pattern.into());
+        self
+    }
+
+    /// Denies requests that match the pattern.
+    #[must_use]
+    pub fn deny<P: Into<Cow<'static, str>>>(mut self, pattern: P) -> Self {
+        self.deny.push(pattern.into());
+        self
+    }
+
+    /// Returns `true` if the request with the given path is allowed.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let matches = |pattern: &Cow<'static, str>| glob_matches(pattern, path);
+        (self.allow.is_empty() || self.allow.iter().any(matches)) && !self.deny.iter().any(matches)
+    }
+}
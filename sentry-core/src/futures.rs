@@ -69,7 +69,9 @@ impl<F> SentryFutureExt for F where F: Future {}
 #[cfg(all(test, feature = "test"))]
 mod tests {
     use crate::test::with_captured_events;
-    use crate::{capture_message, configure_scope, Hub, Level, SentryFutureExt};
+    use crate::{
+        add_breadcrumb, capture_message, configure_scope, Breadcrumb, Hub, Level, SentryFutureExt,
+    };
     use tokio::runtime::Runtime;
 
     #[test]
@@ -105,4 +107,35 @@ mod tests {
         assert_eq!(events[1].transaction, Some("transaction1".into()));
         assert_eq!(events[2].transaction, Some("transaction2".into()));
     }
+
+    #[test]
+    fn test_run_sync() {
+        let events = with_captured_events(|| {
+            let runtime = Runtime::new().unwrap();
+
+            runtime.block_on(async {
+                let task = async {
+                    configure_scope(|scope| scope.set_transaction(Some("transaction")));
+                    tokio::task::spawn_blocking(Hub::run_sync(|| {
+                        add_breadcrumb(Breadcrumb {
+                            message: Some("from blocking section".into()),
+                            ..Default::default()
+                        });
+                    }))
+                    .await
+                    .unwrap();
+                    capture_message("oh hai from the task", Level::Info);
+                }
+                .bind_hub(Hub::new_from_top(Hub::current()));
+                tokio::task::spawn(task).await.unwrap();
+            });
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].transaction, Some("transaction".into()));
+        assert_eq!(
+            events[0].breadcrumbs[0].message.as_deref(),
+            Some("from blocking section")
+        );
+    }
 }
@@ -0,0 +1,156 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Information about a panic that caused an event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PanicHint {
+    /// The panic message.
+    pub message: String,
+    /// The source file in which the panic occurred.
+    pub file: Option<String>,
+    /// The line in which the panic occurred.
+    pub line: Option<u32>,
+    /// The column in which the panic occurred.
+    pub column: Option<u32>,
+}
+
+/// Additional information about the origin of an event.
+///
+/// A hint is passed along with an event into
+/// [`before_send_with_hint`](crate::ClientOptions::before_send_with_hint) and
+/// the event processors added via
+/// [`Scope::add_event_processor_with_hint`](crate::Scope::add_event_processor_with_hint).
+/// It is never sent to Sentry, but allows filtering logic to inspect the
+/// original typed error, the panic or the integration an event was created
+/// from, rather than only the serialized event.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use sentry::{ClientOptions, Hint};
+///
+/// let options = ClientOptions {
+///     before_send_with_hint: Some(Arc::new(|event, hint| {
+///         let is_not_found = hint
+///             .error()
+///             .and_then(|err| err.downcast_ref::<std::io::Error>())
+///             .map_or(false, |err| err.kind() == std::io::ErrorKind::NotFound);
+///         (!is_not_found).then(|| event)
+///     })),
+///     ..Default::default()
+/// };
+///
+/// # let events = sentry::test::with_captured_events_options(|| {
+/// let err = std::io::Error::from(std::io::ErrorKind::NotFound);
+/// let event = sentry::event_from_error(&err);
+/// sentry::capture_event_with_hint(event, Hint::new().with_error(err));
+/// # }, options);
+/// # assert!(events.is_empty());
+/// ```
+#[derive(Default)]
+pub struct Hint {
+    error: Option<Arc<dyn Error + Send + Sync>>,
+    panic: Option<PanicHint>,
+    integration: Option<&'static str>,
+    forwarded: bool,
+    data: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Hint {
+    /// Creates an empty hint.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the original error of the event.
+    pub fn with_error<E>(mut self, error: E) -> Self
+    where
+        E: Error + Send + Sync + 'static,
+    {
+        self.error = Some(Arc::new(error));
+        self
+    }
+
+    /// Sets the original error of the event from a shared error.
+    pub fn with_shared_error(mut self, error: Arc<dyn Error + Send + Sync>) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Sets the panic that caused the event.
+    pub fn with_panic(mut self, panic: PanicHint) -> Self {
+        self.panic = Some(panic);
+        self
+    }
+
+    /// Sets the name of the integration that created the event.
+    pub fn with_integration(mut self, integration: &'static str) -> Self {
+        self.integration = Some(integration);
+        self
+    }
+
+    /// Marks the event as captured by another process, for instance because
+    /// it was forwarded by a relay like `sentry-ingest`.
+    ///
+    /// The instruction addresses of such an event do not belong to this
+    /// process, so integrations do not resolve them to symbols.
+    pub fn forwarded(mut self) -> Self {
+        self.forwarded = true;
+        self
+    }
+
+    /// Attaches custom data to the hint.
+    ///
+    /// The data is keyed by its type, so a hint holds at most one value of
+    /// each type.
+    pub fn with_data<T: Any + Send + Sync>(mut self, data: T) -> Self {
+        self.data.insert(TypeId::of::<T>(), Box::new(data));
+        self
+    }
+
+    /// Returns the original error of the event, if any.
+    ///
+    /// Use `downcast_ref` on the error to inspect its concrete type.
+    pub fn error(&self) -> Option<&(dyn Error + Send + Sync + 'static)> {
+        self.error.as_deref()
+    }
+
+    /// Returns the panic that caused the event, if any.
+    pub fn panic(&self) -> Option<&PanicHint> {
+        self.panic.as_ref()
+    }
+
+    /// Returns the name of the integration that created the event, if any.
+    pub fn integration(&self) -> Option<&'static str> {
+        self.integration
+    }
+
+    /// Whether the event was captured by another process.
+    ///
+    /// See [`forwarded`](Self::forwarded).
+    pub fn is_forwarded(&self) -> bool {
+        self.forwarded
+    }
+
+    /// Returns the custom data of type `T`, if any.
+    pub fn data<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.data.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+}
+
+impl fmt::Debug for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hint")
+            .field("error", &self.error)
+            .field("panic", &self.panic)
+            .field("integration", &self.integration)
+            .field("forwarded", &self.forwarded)
+            .field("data", &self.data.len())
+            .finish()
+    }
+}
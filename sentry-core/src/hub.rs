@@ -4,9 +4,12 @@
 
 use std::sync::{Arc, RwLock};
 
-use crate::protocol::{Event, Level, SessionStatus};
+use crate::protocol::{Event, Level, LogEntry, SessionStatus, Value};
 use crate::types::Uuid;
-use crate::{Integration, IntoBreadcrumbs, Scope, ScopeGuard};
+use crate::{
+    Backpressure, Envelope, FeedbackError, Hint, Integration, IntoBreadcrumbs, Metric, Scope,
+    ScopeGuard, UserFeedback,
+};
 
 /// The central object that can manages scopes and clients.
 ///
@@ -39,7 +42,36 @@ use crate::{Integration, IntoBreadcrumbs, Scope, ScopeGuard};
 pub struct Hub {
     #[cfg(feature = "client")]
     pub(crate) inner: crate::hub_impl::HubImpl,
-    pub(crate) last_event_id: RwLock<Option<Uuid>>,
+    pub(crate) last_event_id: LastEventId,
+}
+
+/// The id of the event that was captured last on a [`Hub`].
+///
+/// This is shared with the events that are still processed in the
+/// background, so that their id can be forgotten if they are dropped.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct LastEventId(Arc<RwLock<Option<Uuid>>>);
+
+impl LastEventId {
+    pub fn get(&self) -> Option<Uuid> {
+        *self.0.read().unwrap()
+    }
+
+    /// Records the id of a captured event, unless the event was dropped.
+    pub fn set(&self, event_id: Uuid) {
+        if !event_id.is_nil() {
+            *self.0.write().unwrap() = Some(event_id);
+        }
+    }
+
+    /// Forgets the id of an event that was dropped after it was captured,
+    /// unless another event was captured since.
+    pub fn forget(&self, event_id: Uuid) {
+        let mut last_event_id = self.0.write().unwrap();
+        if *last_event_id == Some(event_id) {
+            *last_event_id = None;
+        }
+    }
 }
 
 impl Hub {
@@ -48,12 +80,18 @@ impl Hub {
     /// This is useful for integrations that want to do efficiently nothing if there is no
     /// client bound.  Additionally this internally ensures that the client can be safely
     /// synchronized.  This prevents accidental recursive calls into the client.
+    ///
+    /// As long as no enabled client was ever bound to any hub, this does not even look
+    /// up the thread-local hub.
     pub fn with_active<F, R>(f: F) -> R
     where
         F: FnOnce(&Arc<Hub>) -> R,
         R: Default,
     {
         with_client_impl! {{
+            if !Hub::any_enabled_client_bound() {
+                return Default::default();
+            }
             Hub::with(|hub| {
                 if hub.is_active_and_usage_safe() {
                     f(hub)
@@ -88,8 +126,23 @@ impl Hub {
     }
 
     /// Returns the last event id.
+    ///
+    /// Events that are dropped, for instance by `before_send` or because of
+    /// sampling, are not recorded.
     pub fn last_event_id(&self) -> Option<Uuid> {
-        *self.last_event_id.read().unwrap()
+        self.last_event_id.get()
+    }
+
+    /// Returns the backpressure state of the current client's transport.
+    ///
+    /// See the global [`backpressure`](fn.backpressure.html)
+    /// for more documentation.
+    pub fn backpressure(&self) -> Backpressure {
+        with_client_impl! {{
+            self.client()
+                .map(|client| client.backpressure())
+                .unwrap_or_default()
+        }}
     }
 
     /// Sends the event to the current client with the current scope.
@@ -99,13 +152,23 @@ impl Hub {
     /// See the global [`capture_event`](fn.capture_event.html)
     /// for more documentation.
     pub fn capture_event(&self, event: Event<'static>) -> Uuid {
+        self.capture_event_with_hint(event, Hint::default())
+    }
+
+    /// Sends the event along with its [`Hint`] to the current client with the
+    /// current scope.
+    ///
+    /// In case no client is bound this does nothing instead.
+    ///
+    /// See the global [`capture_event_with_hint`](fn.capture_event_with_hint.html)
+    /// for more documentation.
+    pub fn capture_event_with_hint(&self, event: Event<'static>, hint: Hint) -> Uuid {
         with_client_impl! {{
+            self.inner.merge_recorded_breadcrumbs();
             self.inner.with(|stack| {
                 let top = stack.top();
                 if let Some(ref client) = top.client {
-                    let event_id = client.capture_event(event, Some(&top.scope));
-                    *self.last_event_id.write().unwrap() = Some(event_id);
-                    event_id
+                    client.enqueue_event(event, hint, &top.scope, &self.last_event_id)
                 } else {
                     Default::default()
                 }
@@ -113,12 +176,50 @@ impl Hub {
         }}
     }
 
+    /// Sends the metric to the current client, tagged with the data of the
+    /// current scope.
+    ///
+    /// See [`Metric`] for more documentation.
+    pub fn capture_metric(&self, metric: Metric) {
+        with_client_impl! {{
+            self.inner.with(|stack| {
+                let top = stack.top();
+                if let Some(ref client) = top.client {
+                    client.capture_metric(metric, Some(&top.scope));
+                }
+            })
+        }}
+    }
+
+    /// Validates and sends user feedback to the current client.
+    ///
+    /// In case no client is bound the feedback is only validated.
+    ///
+    /// See the global [`capture_user_feedback`](fn.capture_user_feedback.html)
+    /// for more documentation.
+    pub fn capture_user_feedback(&self, feedback: UserFeedback) -> Result<(), FeedbackError> {
+        let report = feedback.into_report(self.last_event_id())?;
+        with_client_impl! {{
+            self.inner.with(|stack| {
+                if let Some(ref client) = stack.top().client {
+                    let mut envelope = Envelope::new();
+                    envelope.add_item(report);
+                    client.send_envelope(envelope);
+                }
+            })
+        }}
+        Ok(())
+    }
+
     /// Captures an arbitrary message.
     ///
     /// See the global [`capture_message`](fn.capture_message.html)
     /// for more documentation.
     pub fn capture_message(&self, msg: &str, level: Level) -> Uuid {
         with_client_impl! {{
+            if !self.is_active_and_usage_safe() {
+                return Default::default();
+            }
             let event = Event {
                 message: Some(msg.to_string()),
                 level,
@@ -128,6 +229,29 @@ impl Hub {
         }}
     }
 
+    /// Captures a parameterized message.
+    ///
+    /// See the global [`capture_message_with_params`](fn.capture_message_with_params.html)
+    /// for more documentation.
+    pub fn capture_message_with_params(
+        &self,
+        template: &str,
+        params: Vec<Value>,
+        level: Level,
+    ) -> Uuid {
+        with_client_impl! {{
+            if !self.is_active_and_usage_safe() {
+                return Default::default();
+            }
+            let event = Event {
+                logentry: Some(LogEntry::with_params(template, params)),
+                level,
+                ..Default::default()
+            };
+            self.capture_event(event)
+        }}
+    }
+
     /// Start a new session for Release Health.
     ///
     /// See the global [`start_session`](fn.start_session.html)
@@ -174,9 +298,14 @@ impl Hub {
     /// This returns a guard that when dropped will pop the scope again.
     pub fn push_scope(&self) -> ScopeGuard {
         with_client_impl! {{
+            self.inner.merge_recorded_breadcrumbs();
             self.inner.with_mut(|stack| {
                 stack.push();
-                ScopeGuard(Some((self.inner.stack.clone(), stack.depth())))
+                ScopeGuard::new(
+                    self.inner.stack.clone(),
+                    stack.depth(),
+                    self.inner.recorders.clone(),
+                )
             })
         }}
     }
@@ -213,6 +342,7 @@ impl Hub {
         F: FnOnce(&mut Scope) -> R,
     {
         with_client_impl! {{
+            self.inner.merge_recorded_breadcrumbs();
             let mut new_scope = self.with_current_scope(|scope| scope.clone());
             let rv = f(&mut new_scope);
             self.with_current_scope_mut(|ptr| *ptr = new_scope);
@@ -226,9 +356,30 @@ impl Hub {
     /// for more documentation.
     pub fn add_breadcrumb<B: IntoBreadcrumbs>(&self, breadcrumb: B) {
         with_client_impl! {{
+            let recording_client = self.inner.with(|stack| {
+                stack.top().client.clone().filter(|client| {
+                    client.is_enabled() && client.options().thread_local_breadcrumbs
+                })
+            });
+            if let Some(client) = recording_client {
+                let options = client.options();
+                for breadcrumb in breadcrumb.into_breadcrumbs() {
+                    let breadcrumb_opt = match options.before_breadcrumb {
+                        Some(ref callback) => callback(breadcrumb),
+                        None => Some(breadcrumb)
+                    };
+                    if let Some(breadcrumb) = breadcrumb_opt {
+                        self.inner.recorders.record(breadcrumb, options.max_breadcrumbs);
+                    }
+                }
+                return;
+            }
+
             self.inner.with_mut(|stack| {
                 let top = stack.top_mut();
-                if let Some(ref client) = top.client {
+                // a disabled client would never send the breadcrumbs, so avoid
+                // cloning the scope and constructing them in the first place.
+                if let Some(client) = top.client.as_ref().filter(|c| c.is_enabled()) {
                     let scope = Arc::make_mut(&mut top.scope);
                     let options = client.options();
                     let breadcrumbs = Arc::make_mut(&mut scope.breadcrumbs);
@@ -238,10 +389,10 @@ impl Hub {
                             None => Some(breadcrumb)
                         };
                         if let Some(breadcrumb) = breadcrumb_opt {
-                            breadcrumbs.push_back(breadcrumb);
-                        }
-                        while breadcrumbs.len() > options.max_breadcrumbs {
-                            breadcrumbs.pop_front();
+                            for observer in scope.observers.iter() {
+                                observer.add_breadcrumb(&breadcrumb);
+                            }
+                            breadcrumbs.push(breadcrumb, options.max_breadcrumbs);
                         }
                     }
                 }
@@ -1,9 +1,13 @@
 use std::cell::{Cell, UnsafeCell};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, PoisonError, RwLock};
 use std::thread;
 
+use crate::protocol::Event;
+use crate::scope::{BreadcrumbRecorders, Stack};
+use crate::types::Uuid;
 use crate::Scope;
-use crate::{scope::Stack, Client, Hub};
+use crate::{Client, Hint, Hub, PendingEvent};
 
 use once_cell::sync::Lazy;
 
@@ -14,6 +18,12 @@ static PROCESS_HUB: Lazy<(Arc<Hub>, thread::ThreadId)> = Lazy::new(|| {
     )
 });
 
+/// Set once an enabled client was bound to any hub.
+///
+/// Until then, the top-level API can skip looking up the thread-local hub.
+/// Clients without a DSN are never enabled, so they do not set this flag.
+static ENABLED_CLIENT_BOUND: AtomicBool = AtomicBool::new(false);
+
 thread_local! {
     static THREAD_HUB: (UnsafeCell<Arc<Hub>>, Cell<bool>) = (
         UnsafeCell::new(Arc::new(Hub::new_from_top(&PROCESS_HUB.0))),
@@ -66,6 +76,7 @@ impl Drop for SwitchGuard {
 #[derive(Debug)]
 pub(crate) struct HubImpl {
     pub(crate) stack: Arc<RwLock<Stack>>,
+    pub(crate) recorders: Arc<BreadcrumbRecorders>,
 }
 
 impl HubImpl {
@@ -79,6 +90,16 @@ impl HubImpl {
         f(&mut guard)
     }
 
+    /// Merges the breadcrumbs of the per-thread recorders into the top scope.
+    ///
+    /// This is a no-op unless breadcrumbs were recorded since the last merge.
+    pub(crate) fn merge_recorded_breadcrumbs(&self) {
+        let breadcrumbs = self.recorders.drain();
+        if !breadcrumbs.is_empty() {
+            self.with_mut(|stack| stack.merge_breadcrumbs(breadcrumbs));
+        }
+    }
+
     pub(crate) fn is_active_and_usage_safe(&self) -> bool {
         let guard = match self.stack.read() {
             Err(err) => err.into_inner(),
@@ -96,20 +117,32 @@ impl HubImpl {
 impl Hub {
     /// Creates a new hub from the given client and scope.
     pub fn new(client: Option<Arc<Client>>, scope: Arc<Scope>) -> Hub {
+        if client.as_ref().map_or(false, |c| c.is_enabled()) {
+            ENABLED_CLIENT_BOUND.store(true, Ordering::Relaxed);
+        }
         Hub {
             inner: HubImpl {
                 stack: Arc::new(RwLock::new(Stack::from_client_and_scope(client, scope))),
+                recorders: Default::default(),
             },
-            last_event_id: RwLock::new(None),
+            last_event_id: Default::default(),
         }
     }
 
     /// Creates a new hub based on the top scope of the given hub.
+    ///
+    /// The fields of the scope that are inherited can be configured with
+    /// [`ClientOptions::scope_inheritance`](crate::ClientOptions::scope_inheritance).
     pub fn new_from_top<H: AsRef<Hub>>(other: H) -> Hub {
         let hub = other.as_ref();
+        hub.inner.merge_recorded_breadcrumbs();
         hub.inner.with(|stack| {
             let top = stack.top();
-            Hub::new(top.client.clone(), top.scope.clone())
+            let scope = match top.client {
+                Some(ref client) => top.scope.inherit(&client.options().scope_inheritance),
+                None => top.scope.clone(),
+            };
+            Hub::new(top.client.clone(), scope)
         })
     }
 
@@ -142,17 +175,25 @@ impl Hub {
     ///
     /// This is a slightly more efficient version than [`Hub::current`] and
     /// also unavailable in minimal mode.
+    ///
+    /// When the thread-local hub was already destroyed, for example in an
+    /// `atexit` handler, the main hub is used instead.
     pub fn with<F, R>(f: F) -> R
     where
         F: FnOnce(&Arc<Hub>) -> R,
     {
-        THREAD_HUB.with(|(hub, is_process_hub)| {
+        let thread_hub = THREAD_HUB.try_with(|(hub, is_process_hub)| {
             if is_process_hub.get() {
-                f(&PROCESS_HUB.0)
+                None
             } else {
-                f(unsafe { &*hub.get() })
+                Some(hub.get() as *const Arc<Hub>)
             }
-        })
+        });
+        match thread_hub {
+            // SAFETY: the thread local hub lives as long as the current thread.
+            Ok(Some(hub)) => f(unsafe { &*hub }),
+            _ => f(&PROCESS_HUB.0),
+        }
     }
 
     /// Binds a hub to the current thread for the duration of the call.
@@ -168,6 +209,40 @@ impl Hub {
         f()
     }
 
+    /// Wraps a closure so that it runs with the current hub bound.
+    ///
+    /// This captures the [`Hub::current`] hub at the time of the call, and
+    /// returns a closure which binds that hub via [`Hub::run`] before
+    /// invoking `f`.  It is meant to carry the hub across the boundaries of
+    /// blocking sections in async applications, which may execute on a
+    /// different thread, such as `tokio::task::spawn_blocking`.  That way,
+    /// breadcrumbs and events recorded inside the blocking section end up on
+    /// the same hub as the surrounding request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let rt = tokio::runtime::Runtime::new().unwrap();
+    /// # rt.block_on(async {
+    /// use sentry::Hub;
+    ///
+    /// let result = tokio::task::spawn_blocking(Hub::run_sync(|| {
+    ///     sentry::add_breadcrumb(sentry::Breadcrumb {
+    ///         message: Some("inside the blocking section".into()),
+    ///         ..Default::default()
+    ///     });
+    ///     1_u32
+    /// }))
+    /// .await;
+    ///
+    /// assert_eq!(result.unwrap(), 1);
+    /// # });
+    /// ```
+    pub fn run_sync<F: FnOnce() -> R, R>(f: F) -> impl FnOnce() -> R {
+        let hub = Hub::current();
+        move || Hub::run(hub, f)
+    }
+
     /// Returns the currently bound client.
     pub fn client(&self) -> Option<Arc<Client>> {
         self.inner.with(|stack| stack.top().client.clone())
@@ -175,15 +250,55 @@ impl Hub {
 
     /// Binds a new client to the hub.
     pub fn bind_client(&self, client: Option<Arc<Client>>) {
+        if client.as_ref().map_or(false, |c| c.is_enabled()) {
+            ENABLED_CLIENT_BOUND.store(true, Ordering::Relaxed);
+        }
         self.inner.with_mut(|stack| {
             stack.top_mut().client = client;
         })
     }
 
+    /// Captures an event on the current scope, but defers sending it.
+    ///
+    /// Returns `None` if no client is bound.  See
+    /// [`Client::capture_event_deferred`] for more documentation.
+    pub fn capture_event_deferred(&self, event: Event<'static>) -> Option<PendingEvent> {
+        self.inner.merge_recorded_breadcrumbs();
+        self.inner.with(|stack| {
+            let top = stack.top();
+            let client = top.client.as_ref()?;
+            let timeout = client.options().deferred_event_timeout;
+            Some(client.defer_event(
+                event,
+                Hint::default(),
+                Some(&top.scope),
+                timeout,
+                Some(&self.last_event_id),
+            ))
+        })
+    }
+
+    /// Modifies an event that was captured, but is not sent yet.
+    ///
+    /// Returns `false` if no client is bound.  See [`Client::enrich_event`]
+    /// for more documentation.
+    pub fn enrich_event<F>(&self, event_id: Uuid, f: F) -> bool
+    where
+        F: FnOnce(&mut Event<'static>),
+    {
+        self.client()
+            .map_or(false, |client| client.enrich_event(event_id, f))
+    }
+
     pub(crate) fn is_active_and_usage_safe(&self) -> bool {
         self.inner.is_active_and_usage_safe()
     }
 
+    /// Returns `false` if no enabled client was ever bound to any hub.
+    pub(crate) fn any_enabled_client_bound() -> bool {
+        ENABLED_CLIENT_BOUND.load(Ordering::Relaxed)
+    }
+
     pub(crate) fn with_current_scope<F: FnOnce(&Scope) -> R, R>(&self, f: F) -> R {
         self.inner.with(|stack| f(&stack.top().scope))
     }
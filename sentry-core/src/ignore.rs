@@ -0,0 +1,102 @@
+//! Matching of events against the `ignore_errors` and `ignore_transactions`
+//! options.
+
+use std::borrow::Cow;
+
+use crate::filter::glob_matches;
+use crate::protocol::Event;
+
+fn any_matches(patterns: &[Cow<'static, str>], value: &str) -> bool {
+    patterns.iter().any(|pattern| glob_matches(pattern, value))
+}
+
+/// Checks whether any exception or the message of the event matches one of
+/// the `ignore_errors` patterns.
+///
+/// Exceptions are matched by their type, their value, and both in the form of
+/// `"Type: value"`.
+pub(crate) fn is_error_ignored(event: &Event<'_>, patterns: &[Cow<'static, str>]) -> bool {
+    if patterns.is_empty() {
+        return false;
+    }
+    let exception_matches = event.exception.iter().any(|exc| {
+        any_matches(patterns, &exc.ty)
+            || exc.value.as_deref().map_or(false, |value| {
+                any_matches(patterns, value)
+                    || any_matches(patterns, &format!("{}: {}", exc.ty, value))
+            })
+    });
+    let message_matches = event
+        .message
+        .iter()
+        .chain(event.logentry.as_ref().map(|entry| &entry.message))
+        .chain(
+            event
+                .logentry
+                .as_ref()
+                .and_then(|entry| entry.formatted.as_ref()),
+        )
+        .any(|message| any_matches(patterns, message));
+    exception_matches || message_matches
+}
+
+/// Checks whether the transaction name matches one of the
+/// `ignore_transactions` patterns.
+pub(crate) fn is_transaction_ignored(name: Option<&str>, patterns: &[Cow<'static, str>]) -> bool {
+    name.map_or(false, |name| any_matches(patterns, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Exception;
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("GET /health", "GET /health"));
+        assert!(!glob_matches("GET /health", "GET /healthz"));
+        assert!(glob_matches("GET /health*", "GET /healthz"));
+        assert!(glob_matches(
+            "*reset by peer*",
+            "connection reset by peer (os error 104)"
+        ));
+        assert!(glob_matches("*/v?/*", "GET /v1/users"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("a*b*c", "aXbYbZ"));
+        assert!(glob_matches("*", ""));
+    }
+
+    #[test]
+    fn test_is_error_ignored() {
+        let patterns = vec!["*connection reset by peer*".into(), "ParseIntError".into()];
+        let event = |ty: &str, value: &str| Event {
+            exception: vec![Exception {
+                ty: ty.into(),
+                value: Some(value.into()),
+                ..Default::default()
+            }]
+            .into(),
+            ..Default::default()
+        };
+
+        assert!(is_error_ignored(
+            &event("Os", "connection reset by peer (os error 104)"),
+            &patterns
+        ));
+        assert!(is_error_ignored(
+            &event("ParseIntError", "invalid digit"),
+            &patterns
+        ));
+        assert!(!is_error_ignored(
+            &event("Os", "connection refused"),
+            &patterns
+        ));
+        assert!(is_error_ignored(
+            &Event {
+                message: Some("connection reset by peer".into()),
+                ..Default::default()
+            },
+            &patterns
+        ));
+    }
+}
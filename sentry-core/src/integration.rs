@@ -1,7 +1,7 @@
 use std::any::{type_name, Any};
 
 use crate::protocol::Event;
-use crate::ClientOptions;
+use crate::{ClientOptions, Hint};
 
 /// Integration abstraction.
 ///
@@ -65,6 +65,19 @@ pub trait Integration: Sync + Send + Any + AsAny {
         let _ = options;
     }
 
+    /// Prepares an `Event` on the thread that captures it.
+    ///
+    /// This runs once the scope was applied to the event, before it is passed
+    /// to [`process_event`](Self::process_event).  Event processing may
+    /// happen later on another thread, with
+    /// [`background_processing`](ClientOptions::background_processing),
+    /// [`event_enrichment_window`](ClientOptions::event_enrichment_window)
+    /// or deferred events, so data that depends on the capturing thread, such
+    /// as its stacktrace, has to be collected here.
+    fn prepare_event(&self, event: &mut Event<'static>, options: &ClientOptions) {
+        let _ = (event, options);
+    }
+
     /// The Integrations Event Processor Hook.
     ///
     /// An integration can process, or even completely drop an `Event`.
@@ -78,6 +91,21 @@ pub trait Integration: Sync + Send + Any + AsAny {
         let _ = options;
         Some(event)
     }
+
+    /// The Integrations Event Processor Hook, along with the [`Hint`] of the
+    /// event.
+    ///
+    /// This defaults to [`process_event`](Self::process_event), and only needs
+    /// to be implemented by integrations that inspect the hint.
+    fn process_event_with_hint(
+        &self,
+        event: Event<'static>,
+        hint: &Hint,
+        options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        let _ = hint;
+        self.process_event(event, options)
+    }
 }
 
 // This is needed as a workaround to be able to safely downcast integrations
@@ -86,6 +86,21 @@
 //! the [`Client`] is not available and the [`Hub`] does not retain all API
 //! functionality.
 //!
+//! Libraries that only want to instrument their code should depend on
+//! `sentry-core` with `default-features = false` and without the `client`
+//! feature. This keeps the dependency cheap: no transport, no HTTP stack and
+//! no client are compiled in. The following API is guaranteed to be identical
+//! in both modes, so an application enabling the `client` feature anywhere in
+//! the dependency graph will not break such libraries:
+//!
+//! - the global functions such as [`capture_event`], [`capture_message`],
+//!   [`capture_error`], [`add_breadcrumb`], [`configure_scope`],
+//!   [`with_scope`], [`start_transaction`] and the session functions,
+//! - the corresponding methods on [`Hub`], as well as [`Hub::with_active`],
+//! - all modification methods of [`Scope`].
+//!
+//! In minimal mode these calls compile down to cheap no-ops.
+//!
 //! # Features
 //!
 //! - `feature = "client"`: Activates the [`Client`] type and certain
@@ -111,40 +126,90 @@
 mod macros;
 
 mod api;
+mod api_surface;
 mod breadcrumbs;
 mod clientoptions;
+mod clock;
 mod constants;
 mod error;
+mod feedback;
+mod filter;
 mod futures;
+mod hint;
 mod hub;
 mod integration;
 mod intodsn;
+mod metrics;
 mod performance;
+mod propagation;
 mod scope;
+mod sql;
 mod transport;
+mod unwrap;
 
 // public api or exports from this crate
 pub use crate::api::*;
 pub use crate::breadcrumbs::IntoBreadcrumbs;
-pub use crate::clientoptions::{ClientOptions, SessionMode};
-pub use crate::error::{capture_error, event_from_error, parse_type_from_debug};
+pub use crate::clientoptions::{
+    BackpressureCallback, CaptureHook, ClientOptions, EventRejectedCallback, FingerprintFn,
+    FlushProgressCallback, RetryPolicy, ScopeInheritance, SessionMode, ThreadStartHook,
+};
+pub use crate::clock::Clock;
+pub use crate::error::{
+    capture_error, capture_error_group, capture_exception, event_from_error,
+    event_from_error_group, event_from_exception, parse_type_from_debug, AsDynError,
+};
+pub use crate::feedback::{FeedbackError, UserFeedback, MAX_FEEDBACK_COMMENTS_LENGTH};
+pub use crate::filter::{default_event_filter, EventFilter, RecordFilter, RouteFilter};
 pub use crate::futures::{SentryFuture, SentryFutureExt};
+pub use crate::hint::{Hint, PanicHint};
 pub use crate::hub::Hub;
 pub use crate::integration::Integration;
 pub use crate::intodsn::IntoDsn;
+#[doc(hidden)]
+pub use crate::macros::__build_context;
+pub use crate::metrics::Metric;
 pub use crate::performance::*;
-pub use crate::scope::{Scope, ScopeGuard};
-pub use crate::transport::{Transport, TransportFactory};
+pub use crate::propagation::{
+    current_trace_headers, parse_baggage, parse_sentry_trace, Baggage, SentryTrace,
+};
+pub use crate::scope::{Scope, ScopeGuard, ScopeObserver};
+pub use crate::sql::sanitize_sql;
+pub use crate::transport::{
+    Backpressure, EventRejection, FlushProgress, RateLimits, RejectionReason, Transport,
+    TransportFactory,
+};
+pub use crate::unwrap::{unwrap_or_capture, OptionExt, ResultExt};
 
 // client feature
 #[cfg(feature = "client")]
 mod client;
 #[cfg(feature = "client")]
+mod deferred;
+#[cfg(feature = "client")]
 mod hub_impl;
 #[cfg(feature = "client")]
+mod ignore;
+#[cfg(feature = "client")]
+mod processing;
+#[cfg(feature = "client")]
+mod remote_config;
+#[cfg(feature = "client")]
 mod session;
 #[cfg(feature = "client")]
+mod spool;
+#[cfg(feature = "client")]
+mod spotlight;
+#[cfg(feature = "client")]
+mod validate;
+#[cfg(feature = "client")]
 pub use crate::client::Client;
+#[cfg(feature = "client")]
+pub use crate::deferred::PendingEvent;
+#[cfg(feature = "client")]
+pub use crate::remote_config::{RemoteConfig, RemoteConfigIntegration};
+#[cfg(feature = "client")]
+pub use crate::spool::{Spool, SpoolUploader};
 
 // test utilities
 #[cfg(feature = "test")]
@@ -34,6 +34,148 @@ macro_rules! release_name {
     }};
 }
 
+/// Returns the build configuration of the calling crate as a `Context`.
+///
+/// The context lists the enabled cargo features of the calling crate, along
+/// with the target OS and architecture, the optimization level, whether debug
+/// assertions are enabled and the name and version of the crate.
+///
+/// Cargo only tells build scripts about the enabled features and the
+/// optimization level. To record them, forward them from the `build.rs` of the
+/// calling crate:
+///
+/// ```ignore
+/// // build.rs
+/// fn main() {
+///     let features: Vec<String> = std::env::vars()
+///         .filter_map(|(key, _)| Some(key.strip_prefix("CARGO_FEATURE_")?.to_lowercase()))
+///         .collect();
+///     println!("cargo:rustc-env=SENTRY_BUILD_FEATURES={}", features.join(","));
+///     println!("cargo:rustc-env=OPT_LEVEL={}", std::env::var("OPT_LEVEL").unwrap());
+/// }
+/// ```
+///
+/// Cargo replaces dashes in feature names with underscores for build scripts,
+/// so features that are also passed to the macro are recorded with their
+/// actual name.  Without a build script, only the features passed to the
+/// macro are checked, and the optimization level is derived from whether
+/// debug assertions are enabled, as `"0"` or `"3"`.
+///
+/// Set it as the `"app.build"` context right after initializing Sentry, to
+/// tell apart issues that only occur with particular feature combinations.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate sentry;
+/// # fn main() {
+/// let _sentry = sentry::init(sentry::ClientOptions::default());
+/// sentry::configure_scope(|scope| {
+///     scope.set_context("app.build", sentry::build_context!("tls", "metrics"));
+/// });
+/// # }
+/// ```
+#[macro_export]
+macro_rules! build_context {
+    ($($feature:literal),* $(,)?) => {{
+        let features: &[(bool, &str)] = &[$((cfg!(feature = $feature), $feature)),*];
+        $crate::__build_context(
+            features,
+            option_env!("SENTRY_BUILD_FEATURES"),
+            option_env!("OPT_LEVEL"),
+            cfg!(debug_assertions),
+            option_env!("CARGO_PKG_NAME"),
+            option_env!("CARGO_PKG_VERSION"),
+        )
+    }};
+}
+
+/// Assembles the context of [`build_context!`].
+#[doc(hidden)]
+pub fn __build_context(
+    features: &[(bool, &str)],
+    collected_features: Option<&str>,
+    opt_level: Option<&str>,
+    debug_assertions: bool,
+    name: Option<&str>,
+    version: Option<&str>,
+) -> crate::protocol::Context {
+    let mut enabled: Vec<&str> = features
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, feature)| *feature)
+        .collect();
+    let collected = collected_features.unwrap_or_default().split(',');
+    for feature in collected.filter(|feature| !feature.is_empty()) {
+        let known = features
+            .iter()
+            .any(|(_, known)| known.replace('-', "_") == feature);
+        if !known {
+            enabled.push(feature);
+        }
+    }
+    let opt_level = opt_level.unwrap_or(if debug_assertions { "0" } else { "3" });
+
+    let mut context = crate::protocol::Map::new();
+    context.insert("features".into(), enabled.into());
+    context.insert("target_os".into(), std::env::consts::OS.into());
+    context.insert("target_arch".into(), std::env::consts::ARCH.into());
+    context.insert("opt_level".into(), opt_level.into());
+    context.insert("debug_assertions".into(), debug_assertions.into());
+    if let Some(name) = name {
+        context.insert("crate_name".into(), name.into());
+    }
+    if let Some(version) = version {
+        context.insert("crate_version".into(), version.into());
+    }
+    crate::protocol::Context::Other(context)
+}
+
+/// Captures an error along with the `Debug` representation of local values.
+///
+/// This works like [`capture_error`](crate::capture_error), but additionally
+/// attaches the given values to the `"locals"` extra of the event, which
+/// shows the state at the capture site similar to the local variables of
+/// stack frames in other languages.  Values are given either as the name of a
+/// variable, or as `name = expression`.
+///
+/// Returns the id of the captured event.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate sentry;
+/// # fn main() {
+/// let user_id = 42;
+/// let path = std::path::Path::new("config.toml");
+/// if let Err(err) = std::fs::read_to_string(path) {
+///     sentry::capture_error_with_locals!(&err, user_id, path, exists = path.exists());
+/// }
+/// # }
+/// ```
+#[macro_export]
+macro_rules! capture_error_with_locals {
+    (@value $name:ident) => {
+        &$name
+    };
+    (@value $name:ident = $value:expr) => {
+        &$value
+    };
+    ($error:expr $(, $name:ident $(= $value:expr)?)* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut locals = $crate::protocol::value::Map::new();
+        $(
+            let value = $crate::capture_error_with_locals!(@value $name $(= $value)?);
+            locals.insert(stringify!($name).into(), format!("{:?}", value).into());
+        )*
+        let mut event = $crate::event_from_error($error);
+        event
+            .extra
+            .insert("locals".into(), $crate::protocol::Value::Object(locals));
+        $crate::capture_event(event)
+    }};
+}
+
 // TODO: temporarily exported for use in `sentry` crate
 #[macro_export]
 #[doc(hidden)]
@@ -78,3 +220,35 @@ macro_rules! minimal_unreachable {
         );
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::{Context, Value};
+
+    #[test]
+    fn test_build_context() {
+        let context = match super::__build_context(
+            &[(true, "surf-h1"), (false, "tls")],
+            Some("surf_h1,metrics"),
+            None,
+            true,
+            Some("app"),
+            None,
+        ) {
+            Context::Other(context) => context,
+            _ => unreachable!(),
+        };
+        assert_eq!(context["features"], Value::from(vec!["surf-h1", "metrics"]));
+        assert_eq!(context["opt_level"], "0");
+        assert_eq!(context["debug_assertions"], true);
+        assert_eq!(context["crate_name"], "app");
+        assert!(!context.contains_key("crate_version"));
+
+        let context = match super::__build_context(&[], None, Some("s"), false, None, None) {
+            Context::Other(context) => context,
+            _ => unreachable!(),
+        };
+        assert_eq!(context["features"], Value::from(Vec::<&str>::new()));
+        assert_eq!(context["opt_level"], "s");
+    }
+}
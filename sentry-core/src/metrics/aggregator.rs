@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::client::TransportArc;
+use crate::protocol::{MetricBucket, MetricUnit, MetricValue};
+use crate::Envelope;
+
+/// The duration of the time window that metrics are aggregated for.
+const BUCKET_INTERVAL: Duration = Duration::from_secs(10);
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct BucketKey {
+    timestamp: SystemTime,
+    name: String,
+    unit: MetricUnit,
+    tags: BTreeMap<String, String>,
+}
+
+type Buckets = HashMap<BucketKey, MetricValue>;
+
+/// Background Metrics Aggregator
+///
+/// The aggregator merges metric values into buckets per time window, name,
+/// unit and tags.  It has its own background thread that sends the buckets
+/// once every `FLUSH_INTERVAL`.
+pub(crate) struct MetricsAggregator {
+    transport: TransportArc,
+    buckets: Arc<Mutex<Buckets>>,
+    shutdown: Arc<(Mutex<bool>, Condvar)>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl MetricsAggregator {
+    /// Creates a new Aggregator that will submit envelopes to the given `transport`.
+    pub fn new(transport: TransportArc) -> Self {
+        let buckets = Arc::new(Mutex::new(Buckets::new()));
+        #[allow(clippy::mutex_atomic)]
+        let shutdown = Arc::new((Mutex::new(false), Condvar::new()));
+
+        let worker_transport = transport.clone();
+        let worker_buckets = buckets.clone();
+        let worker_shutdown = shutdown.clone();
+        let worker = std::thread::Builder::new()
+            .name("sentry-metrics-aggregator".into())
+            .spawn(move || {
+                let (lock, cvar) = worker_shutdown.as_ref();
+                let mut shutdown = lock.lock().unwrap();
+                if *shutdown {
+                    return;
+                }
+                let mut last_flush = Instant::now();
+                loop {
+                    let timeout = FLUSH_INTERVAL.saturating_sub(last_flush.elapsed());
+                    shutdown = cvar.wait_timeout(shutdown, timeout).unwrap().0;
+                    if *shutdown {
+                        return;
+                    }
+                    if last_flush.elapsed() < FLUSH_INTERVAL {
+                        continue;
+                    }
+                    MetricsAggregator::flush_buckets_internal(
+                        worker_buckets.lock().unwrap(),
+                        &worker_transport,
+                    );
+                    last_flush = Instant::now();
+                }
+            })
+            .unwrap();
+
+        Self {
+            transport,
+            buckets,
+            shutdown,
+            worker: Some(worker),
+        }
+    }
+
+    /// Adds a value to the bucket of the time window containing `timestamp`.
+    pub fn add(
+        &self,
+        name: &str,
+        unit: MetricUnit,
+        value: MetricValue,
+        tags: BTreeMap<String, String>,
+        timestamp: SystemTime,
+    ) {
+        let secs = timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let timestamp =
+            SystemTime::UNIX_EPOCH + Duration::from_secs(secs - secs % BUCKET_INTERVAL.as_secs());
+        let key = BucketKey {
+            timestamp,
+            name: name.to_owned(),
+            unit,
+            tags,
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        match buckets.get_mut(&key) {
+            Some(existing) => {
+                if !existing.merge(value) {
+                    sentry_debug!("dropping value for metric {} of a different type", name);
+                }
+            }
+            None => {
+                buckets.insert(key, value);
+            }
+        }
+    }
+
+    /// Flushes the buckets to the transport.
+    pub fn flush(&self) {
+        MetricsAggregator::flush_buckets_internal(self.buckets.lock().unwrap(), &self.transport);
+    }
+
+    fn flush_buckets_internal(mut buckets: MutexGuard<Buckets>, transport: &TransportArc) {
+        let buckets = std::mem::take(&mut *buckets);
+        if buckets.is_empty() {
+            return;
+        }
+        let buckets: Vec<_> = buckets
+            .into_iter()
+            .map(|(key, value)| MetricBucket {
+                name: key.name,
+                unit: key.unit,
+                value,
+                tags: key.tags,
+                timestamp: key.timestamp,
+            })
+            .collect();
+
+        if let Some(ref transport) = *transport.read().unwrap() {
+            let mut envelope = Envelope::new();
+            envelope.add_item(buckets);
+            transport.send_envelope(envelope);
+        }
+    }
+}
+
+impl Drop for MetricsAggregator {
+    fn drop(&mut self) {
+        let (lock, cvar) = self.shutdown.as_ref();
+        *lock.lock().unwrap() = true;
+        cvar.notify_one();
+
+        if let Some(worker) = self.worker.take() {
+            worker.join().ok();
+        }
+        MetricsAggregator::flush_buckets_internal(self.buckets.lock().unwrap(), &self.transport);
+    }
+}
@@ -0,0 +1,123 @@
+//! Client-side Metrics Aggregation
+//!
+//! <https://develop.sentry.dev/sdk/metrics/>
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+
+use crate::protocol::{MetricUnit, MetricValue};
+use crate::Hub;
+
+#[cfg(feature = "client")]
+mod aggregator;
+
+#[cfg(feature = "client")]
+pub(crate) use self::aggregator::MetricsAggregator;
+
+/// A metric value that is aggregated and sent to Sentry.
+///
+/// Metrics are only recorded if the
+/// [`enable_metrics`](crate::ClientOptions::enable_metrics) option is set.
+/// Values are aggregated per name, unit and tags by the client, which sends
+/// the aggregates periodically.
+///
+/// The `release` and `environment` of the client, as well as the transaction
+/// of the current scope, are added as tags to every metric, unless the metric
+/// sets a tag of the same name itself.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::protocol::MetricUnit;
+/// use sentry::Metric;
+///
+/// Metric::count("requests").with_tag("method", "GET").send();
+///
+/// Metric::distribution("response.size", 1432.0)
+///     .with_unit(MetricUnit::Byte)
+///     .send();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metric {
+    pub(crate) name: Cow<'static, str>,
+    pub(crate) unit: MetricUnit,
+    pub(crate) value: MetricValue,
+    pub(crate) tags: BTreeMap<String, String>,
+    pub(crate) timestamp: Option<SystemTime>,
+}
+
+impl Metric {
+    fn new<N: Into<Cow<'static, str>>>(name: N, value: MetricValue) -> Self {
+        Self {
+            name: name.into(),
+            unit: MetricUnit::None,
+            value,
+            tags: BTreeMap::new(),
+            timestamp: None,
+        }
+    }
+
+    /// Creates a counter that is incremented by one.
+    pub fn count<N: Into<Cow<'static, str>>>(name: N) -> Self {
+        Self::incr(name, 1.0)
+    }
+
+    /// Creates a counter that is incremented by the given value.
+    pub fn incr<N: Into<Cow<'static, str>>>(name: N, value: f64) -> Self {
+        Self::new(name, MetricValue::Counter(value))
+    }
+
+    /// Creates a distribution with a single value.
+    pub fn distribution<N: Into<Cow<'static, str>>>(name: N, value: f64) -> Self {
+        Self::new(name, MetricValue::Distribution(vec![value]))
+    }
+
+    /// Creates a distribution of a duration, measured in seconds.
+    pub fn timing<N: Into<Cow<'static, str>>>(name: N, duration: Duration) -> Self {
+        Self::distribution(name, duration.as_secs_f64()).with_unit(MetricUnit::Second)
+    }
+
+    /// Sets the unit of the metric.
+    #[must_use]
+    pub fn with_unit<U: Into<MetricUnit>>(mut self, unit: U) -> Self {
+        self.unit = unit.into();
+        self
+    }
+
+    /// Adds a tag to the metric.
+    #[must_use]
+    pub fn with_tag<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the time at which the value was recorded.
+    ///
+    /// Defaults to the time the metric is sent.
+    #[must_use]
+    pub fn with_time(mut self, timestamp: SystemTime) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Returns the name of the metric.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the unit of the metric.
+    pub fn unit(&self) -> &MetricUnit {
+        &self.unit
+    }
+
+    /// Returns the tags of the metric.
+    pub fn tags(&self) -> &BTreeMap<String, String> {
+        &self.tags
+    }
+
+    /// Sends the metric to the client of the current hub.
+    pub fn send(self) {
+        Hub::with_active(|hub| hub.capture_metric(self))
+    }
+}
@@ -1,12 +1,17 @@
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
 #[cfg(all(feature = "profiling", target_family = "unix"))]
 use crate::profiling;
+use crate::propagation::{parse_sentry_trace, SentryTrace};
 use crate::{protocol, Hub};
 
+#[cfg(feature = "client")]
 #[cfg(feature = "client")]
 use crate::Client;
+#[cfg(feature = "client")]
+use crate::Metric;
 
 #[cfg(feature = "client")]
 const MAX_SPANS: usize = 1_000;
@@ -31,6 +36,41 @@ pub fn start_transaction(ctx: TransactionContext) -> Transaction {
     }
 }
 
+/// Start a new Span as a child of the Span of the current scope.
+///
+/// The span becomes the span of the current scope, and is finished when the
+/// returned guard is dropped, at which point the previous span of the scope
+/// is restored.  This makes sure the span is finished on early returns, and
+/// also when unwinding from a panic, in which case its status is set to
+/// [`InternalError`](protocol::SpanStatus::InternalError) unless it was
+/// already set.
+///
+/// If no client is bound or the current scope has no span, no span is started.
+///
+/// # Examples
+///
+/// ```
+/// fn get_cached(key: &str) -> Option<String> {
+///     let _span = sentry::start_span("cache.get", key);
+///     // the span is finished on every return
+///     None
+/// }
+/// # get_cached("user:1");
+/// ```
+pub fn start_span(op: &str, description: &str) -> SpanGuard {
+    let inner = Hub::with_active(|hub| {
+        let parent = hub.configure_scope(|scope| scope.get_span())?;
+        let span = parent.start_child(op, description);
+        hub.configure_scope(|scope| scope.set_span(Some(span.clone().into())));
+        Some(SpanGuardInner {
+            hub: hub.clone(),
+            span,
+            parent,
+        })
+    });
+    SpanGuard { inner }
+}
+
 // Hub API:
 
 impl Hub {
@@ -69,8 +109,11 @@ pub struct TransactionContext {
     op: String,
     trace_id: protocol::TraceId,
     parent_span_id: Option<protocol::SpanId>,
+    parent_sampled: Option<bool>,
     sampled: Option<bool>,
     custom: Option<CustomTransactionContext>,
+    source: Option<protocol::TransactionSource>,
+    data: protocol::Map<String, protocol::Value>,
 }
 
 impl TransactionContext {
@@ -105,7 +148,7 @@ impl TransactionContext {
             }
         }
 
-        let (trace_id, parent_span_id, sampled) = match trace {
+        let (trace_id, parent_span_id, parent_sampled) = match trace {
             Some(trace) => (trace.0, Some(trace.1), trace.2),
             None => (protocol::TraceId::default(), None, None),
         };
@@ -115,8 +158,11 @@ impl TransactionContext {
             op: op.into(),
             trace_id,
             parent_span_id,
-            sampled,
+            parent_sampled,
+            sampled: None,
             custom: None,
+            source: None,
+            data: Default::default(),
         }
     }
 
@@ -131,7 +177,7 @@ impl TransactionContext {
             None => return Self::new(name, op),
         };
 
-        let (trace_id, parent_span_id, sampled) = match span {
+        let (trace_id, parent_span_id, parent_sampled) = match span {
             TransactionOrSpan::Transaction(transaction) => {
                 let inner = transaction.inner.lock().unwrap();
                 (
@@ -152,22 +198,137 @@ impl TransactionContext {
             op: op.into(),
             trace_id,
             parent_span_id: Some(parent_span_id),
-            sampled,
+            parent_sampled,
+            sampled: None,
             custom: None,
+            source: None,
+            data: Default::default(),
+        }
+    }
+
+    /// Creates a Transaction Context for an HTTP request handled by a server.
+    ///
+    /// The transaction is named after the `method` and the parameterized
+    /// `route`, such as `/users/{id}`, and continues the trace of the
+    /// `headers` of the request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let headers = [("sentry-trace", "09e04486820349518ac7b5d2adbf6ba5-9cf635fa5b870b3a-1")];
+    /// let ctx = sentry::TransactionContext::http_server("GET", "/users/{id}", headers);
+    /// assert_eq!(ctx.name(), "GET /users/{id}");
+    /// assert_eq!(ctx.operation(), "http.server");
+    /// assert_eq!(ctx.data()["http.route"], "/users/{id}");
+    /// assert_eq!(ctx.parent_sampled(), Some(true));
+    /// ```
+    #[must_use = "this must be used with `start_transaction`"]
+    pub fn http_server<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(
+        method: &str,
+        route: &str,
+        headers: I,
+    ) -> Self {
+        let name = format!("{} {}", method, route);
+        let mut ctx = Self::continue_from_headers(&name, "http.server", headers);
+        ctx.set_source(protocol::TransactionSource::Route);
+        ctx.set_data("http.request.method", method.into());
+        ctx.set_data("http.route", route.into());
+        ctx
+    }
+
+    /// Creates a Transaction Context for a task processed from a queue.
+    ///
+    /// The trace is continued from the `headers` of the message.
+    #[must_use = "this must be used with `start_transaction`"]
+    pub fn queue_task<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(
+        task: &str,
+        queue: &str,
+        headers: I,
+    ) -> Self {
+        let mut ctx = Self::continue_from_headers(task, "queue.task", headers);
+        ctx.set_source(protocol::TransactionSource::Task);
+        ctx.set_data("messaging.destination.name", queue.into());
+        ctx
+    }
+
+    /// Creates a Transaction Context for a run of a scheduled job.
+    ///
+    /// The `monitor_slug` is the slug of the cron monitor of the job, if any.
+    #[must_use = "this must be used with `start_transaction`"]
+    pub fn cron_job(job: &str, monitor_slug: Option<&str>) -> Self {
+        let mut ctx = Self::new(job, "cron.job");
+        ctx.set_source(protocol::TransactionSource::Task);
+        if let Some(monitor_slug) = monitor_slug {
+            ctx.set_data("monitor.slug", monitor_slug.into());
         }
+        ctx
+    }
+
+    /// Creates a Transaction Context for a WebSocket connection handled by a
+    /// server.
+    ///
+    /// The transaction is named after the parameterized `route` of the
+    /// connection, and continues the trace of the `headers` of the upgrade
+    /// request.
+    #[must_use = "this must be used with `start_transaction`"]
+    pub fn websocket_server<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(
+        route: &str,
+        headers: I,
+    ) -> Self {
+        let mut ctx = Self::continue_from_headers(route, "websocket.server", headers);
+        ctx.set_source(protocol::TransactionSource::Route);
+        ctx.set_data("http.route", route.into());
+        ctx
+    }
+
+    /// Creates a Transaction Context for a remote procedure call handled by a
+    /// server.
+    ///
+    /// The transaction is named `service/method`, and continues the trace of
+    /// the `headers` or metadata of the call.
+    #[must_use = "this must be used with `start_transaction`"]
+    pub fn rpc_server<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(
+        service: &str,
+        method: &str,
+        headers: I,
+    ) -> Self {
+        let name = format!("{}/{}", service, method);
+        let mut ctx = Self::continue_from_headers(&name, "rpc.server", headers);
+        ctx.set_source(protocol::TransactionSource::Route);
+        ctx.set_data("rpc.service", service.into());
+        ctx.set_data("rpc.method", method.into());
+        ctx
     }
 
     /// Set the sampling decision for this Transaction.
     ///
-    /// This can be either an explicit boolean flag, or [`None`], which will fall
-    /// back to use the configured `traces_sample_rate` option.
+    /// An explicit boolean flag forces the transaction to be kept or dropped,
+    /// taking priority over the `traces_sampler`, the `traces_sample_rate` and
+    /// the decision of the parent.  [`None`] leaves the decision to those.
     pub fn set_sampled(&mut self, sampled: impl Into<Option<bool>>) {
         self.sampled = sampled.into();
     }
 
     /// Get the sampling decision for this Transaction.
+    ///
+    /// This is the decision set via [`set_sampled`], or else the decision of
+    /// the parent, see [`parent_sampled`].
+    ///
+    /// [`set_sampled`]: Self::set_sampled
+    /// [`parent_sampled`]: Self::parent_sampled
     pub fn sampled(&self) -> Option<bool> {
-        self.sampled
+        self.sampled.or(self.parent_sampled)
+    }
+
+    /// Get the sampling decision of the parent of this Transaction.
+    ///
+    /// This is inherited from the `sentry-trace` header or the span this
+    /// transaction continues, and is used unless a `traces_sampler` is
+    /// configured or the decision is overridden via [`set_sampled`].
+    ///
+    /// [`set_sampled`]: Self::set_sampled
+    pub fn parent_sampled(&self) -> Option<bool> {
+        self.parent_sampled
     }
 
     /// Get the name of this Transaction.
@@ -180,6 +341,38 @@ impl TransactionContext {
         &self.op
     }
 
+    /// Set the source of the name of this Transaction.
+    ///
+    /// Integrations should set this to [`TransactionSource::Route`] when the
+    /// name is a parameterized route template such as `/users/{id}`, and to
+    /// [`TransactionSource::Url`] when it is a raw URL path, which should be
+    /// sanitized using [`sanitize_url_path`] to avoid unbounded cardinality.
+    ///
+    /// [`TransactionSource::Route`]: protocol::TransactionSource::Route
+    /// [`TransactionSource::Url`]: protocol::TransactionSource::Url
+    pub fn set_source(&mut self, source: impl Into<Option<protocol::TransactionSource>>) {
+        self.source = source.into();
+    }
+
+    /// Get the source of the name of this Transaction.
+    pub fn source(&self) -> Option<protocol::TransactionSource> {
+        self.source
+    }
+
+    /// Sets a data field of this Transaction.
+    ///
+    /// The data is sent along with the transaction, like the data set with
+    /// [`Transaction::set_data`], and can also be inspected by the
+    /// `traces_sampler`.
+    pub fn set_data(&mut self, key: &str, value: protocol::Value) {
+        self.data.insert(key.into(), value);
+    }
+
+    /// Get the data fields of this Transaction.
+    pub fn data(&self) -> &protocol::Map<String, protocol::Value> {
+        &self.data
+    }
+
     /// Get the custom context of this Transaction.
     pub fn custom(&self) -> Option<&CustomTransactionContext> {
         self.custom.as_ref()
@@ -216,11 +409,55 @@ impl TransactionContext {
     }
 }
 
+/// Sanitizes a raw URL path for use as a transaction name.
+///
+/// The query string and fragment are removed, and path segments which look
+/// like identifiers, such as numbers, UUIDs or long hex strings, are replaced
+/// with an `{id}` placeholder. This should be used as a fallback when no route
+/// template is available, to avoid unbounded transaction name cardinality.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(
+///     sentry_core::sanitize_url_path("/users/123/posts/5f0c5b3e-4d3a-4e5b-8f2a-1c9d7e6b4a21?page=2"),
+///     "/users/{id}/posts/{id}"
+/// );
+/// ```
+pub fn sanitize_url_path(path: &str) -> String {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    path.split('/')
+        .map(|segment| {
+            if is_identifier_segment(segment) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_identifier_segment(segment: &str) -> bool {
+    if segment.is_empty() {
+        return false;
+    }
+    let is_number = segment.bytes().all(|b| b.is_ascii_digit());
+    let is_uuid = segment.len() == 36
+        && segment.bytes().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        });
+    let is_hex = segment.len() >= 16 && segment.bytes().all(|b| b.is_ascii_hexdigit());
+    is_number || is_uuid || is_hex
+}
+
 /// A function to be run for each new transaction, to determine the rate at which
 /// it should be sampled.
 ///
-/// This function may choose to respect the sampling of the parent transaction (`ctx.sampled`)
-/// or ignore it.
+/// This function may choose to respect the sampling of the parent transaction
+/// (`ctx.parent_sampled()`) or ignore it.  It is not called for transactions with
+/// an explicit sampling decision.
 pub type TracesSampler = dyn Fn(&TransactionContext) -> f32 + Send + Sync;
 
 // global API types:
@@ -255,6 +492,17 @@ impl TransactionOrSpan {
         }
     }
 
+    /// Returns whether the Transaction/Span is sampled, and will be sent to Sentry.
+    ///
+    /// Applications can use this to skip expensive instrumentation for
+    /// unsampled traces.
+    pub fn is_sampled(&self) -> bool {
+        match self {
+            TransactionOrSpan::Transaction(transaction) => transaction.is_sampled(),
+            TransactionOrSpan::Span(span) => span.is_sampled(),
+        }
+    }
+
     /// Set the status of the Transaction/Span.
     pub fn get_status(&self) -> Option<protocol::SpanStatus> {
         match self {
@@ -299,6 +547,16 @@ impl TransactionOrSpan {
         }
     }
 
+    /// Returns the ID of the trace this span belongs to.
+    pub fn trace_id(&self) -> protocol::TraceId {
+        match self {
+            TransactionOrSpan::Transaction(transaction) => {
+                transaction.inner.lock().unwrap().context.trace_id
+            }
+            TransactionOrSpan::Span(span) => span.span.lock().unwrap().trace_id,
+        }
+    }
+
     #[cfg(feature = "client")]
     pub(crate) fn apply_to_event(&self, event: &mut protocol::Event<'_>) {
         if event.contexts.contains_key("trace") {
@@ -314,7 +572,10 @@ impl TransactionOrSpan {
                 protocol::TraceContext {
                     span_id: span.span_id,
                     trace_id: span.trace_id,
-                    ..Default::default()
+                    parent_span_id: span.parent_span_id,
+                    op: span.op.clone(),
+                    description: span.description.clone(),
+                    status: span.status,
                 }
             }
         };
@@ -331,12 +592,30 @@ impl TransactionOrSpan {
             TransactionOrSpan::Span(span) => span.finish(),
         }
     }
+
+    /// Returns the name of the transaction that this belongs to.
+    #[cfg(feature = "client")]
+    pub(crate) fn transaction_name(&self) -> Option<String> {
+        let inner = match self {
+            TransactionOrSpan::Transaction(transaction) => &transaction.inner,
+            TransactionOrSpan::Span(span) => &span.transaction,
+        };
+        let inner = inner.lock().unwrap();
+        match inner.transaction {
+            Some(ref transaction) => transaction.name.clone(),
+            None => inner.metrics_only.as_ref().map(|(name, _)| name.clone()),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct TransactionInner {
     #[cfg(feature = "client")]
     client: Option<Arc<Client>>,
+    /// The name and start of an unsampled transaction, whose duration is
+    /// recorded as a metric on `finish`.
+    #[cfg(feature = "client")]
+    metrics_only: Option<(String, SystemTime)>,
     sampled: bool,
     pub(crate) context: protocol::TraceContext,
     pub(crate) transaction: Option<protocol::Transaction<'static>>,
@@ -344,6 +623,101 @@ pub(crate) struct TransactionInner {
     pub(crate) profiler_guard: Option<profiling::ProfilerGuard>,
 }
 
+impl TransactionInner {
+    /// Returns the current time according to the clock of the client.
+    fn now(&self) -> SystemTime {
+        #[cfg(feature = "client")]
+        if let Some(client) = self.client.as_ref() {
+            return client.now();
+        }
+        SystemTime::now()
+    }
+}
+
+/// Configures how runs of identical short spans are merged.
+///
+/// Chatty instrumentation, such as a database query per row, can produce
+/// hundreds of spans that carry the same information.  When compression is
+/// enabled via [`ClientOptions::span_compression`], at least `min_count`
+/// consecutive sibling spans with the same operation, description and status,
+/// each lasting no longer than `max_duration`, are merged into a single span
+/// before the transaction is sent.  The merged span covers the whole run, and
+/// records the number of merged spans in its `compression.count` data and
+/// their summed duration in seconds in `compression.duration`.
+///
+/// Spans that have child spans are never merged.
+///
+/// [`ClientOptions::span_compression`]: crate::ClientOptions::span_compression
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpanCompression {
+    /// The minimum number of consecutive identical spans that are merged.
+    pub min_count: usize,
+    /// The maximum duration of a span to be considered for merging.
+    pub max_duration: Duration,
+}
+
+impl Default for SpanCompression {
+    fn default() -> Self {
+        SpanCompression {
+            min_count: 5,
+            max_duration: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Merges runs of identical short spans according to `compression`.
+#[cfg(feature = "client")]
+fn compress_spans(spans: &mut Vec<protocol::Span>, compression: &SpanCompression) {
+    let parents: std::collections::HashSet<_> = spans
+        .iter()
+        .filter_map(|span| span.parent_span_id)
+        .collect();
+    let duration = |span: &protocol::Span| {
+        span.timestamp
+            .and_then(|end| end.duration_since(span.start_timestamp).ok())
+    };
+    let is_compressible = |span: &protocol::Span| {
+        !parents.contains(&span.span_id)
+            && duration(span).map_or(false, |d| d <= compression.max_duration)
+    };
+    let is_same = |a: &protocol::Span, b: &protocol::Span| {
+        a.parent_span_id == b.parent_span_id
+            && a.op == b.op
+            && a.description == b.description
+            && a.status == b.status
+    };
+
+    spans.sort_by_key(|span| span.start_timestamp);
+    let mut compressed = Vec::with_capacity(spans.len());
+    let mut iter = std::mem::take(spans).into_iter().peekable();
+    while let Some(mut span) = iter.next() {
+        if !is_compressible(&span) {
+            compressed.push(span);
+            continue;
+        }
+        let mut run = vec![];
+        while let Some(next) = iter.next_if(|next| is_compressible(next) && is_same(&span, next)) {
+            run.push(next);
+        }
+        if run.len() + 1 < compression.min_count.max(2) {
+            compressed.push(span);
+            compressed.extend(run);
+            continue;
+        }
+        let mut total = duration(&span).unwrap_or_default();
+        for merged in &run {
+            total += duration(merged).unwrap_or_default();
+            span.timestamp = span.timestamp.max(merged.timestamp);
+        }
+        span.data
+            .insert("compression.count".into(), (run.len() + 1).into());
+        span.data
+            .insert("compression.duration".into(), total.as_secs_f64().into());
+        compressed.push(span);
+    }
+    *spans = compressed;
+}
+
 type TransactionArc = Arc<Mutex<TransactionInner>>;
 
 /// Functional implementation of how a new transation's sample rate is chosen.
@@ -355,11 +729,13 @@ fn transaction_sample_rate(
     ctx: &TransactionContext,
     traces_sample_rate: f32,
 ) -> f32 {
-    match (traces_sampler, traces_sample_rate) {
-        (Some(traces_sampler), _) => traces_sampler(ctx),
-        (None, traces_sample_rate) => ctx
-            .sampled
-            .map(|sampled| if sampled { 1.0 } else { 0.0 })
+    let to_rate = |sampled| if sampled { 1.0 } else { 0.0 };
+    match (ctx.sampled, traces_sampler) {
+        (Some(sampled), _) => to_rate(sampled),
+        (None, Some(traces_sampler)) => traces_sampler(ctx),
+        (None, None) => ctx
+            .parent_sampled
+            .map(to_rate)
             .unwrap_or(traces_sample_rate),
     }
 }
@@ -372,7 +748,7 @@ impl Client {
         self.sample_should_send(transaction_sample_rate(
             client_options.traces_sampler.as_deref(),
             ctx,
-            client_options.traces_sample_rate,
+            self.traces_sample_rate(),
         ))
     }
 }
@@ -395,6 +771,11 @@ impl Transaction {
                 client.is_transaction_sampled(&ctx),
                 Some(protocol::Transaction {
                     name: Some(ctx.name),
+                    extra: ctx.data,
+                    start_timestamp: client.now(),
+                    transaction_info: ctx
+                        .source
+                        .map(|source| protocol::TransactionInfo { source }),
                     #[cfg(all(feature = "profiling", target_family = "unix"))]
                     active_thread_id: Some(
                         // NOTE: `pthread_t` is a `usize`, so clippy is wrong complaining about this cast
@@ -406,7 +787,7 @@ impl Transaction {
                     ..Default::default()
                 }),
             ),
-            None => (ctx.sampled.unwrap_or(false), None),
+            None => (ctx.sampled.or(ctx.parent_sampled).unwrap_or(false), None),
         };
 
         let context = protocol::TraceContext {
@@ -418,9 +799,16 @@ impl Transaction {
 
         // throw away the transaction here, which means there is nothing to send
         // on `finish`.
+        // Unsampled transactions may still record their duration as a metric.
+        let mut metrics_only = None;
         if !sampled {
-            transaction = None;
-            client = None;
+            let name = transaction.take().and_then(|transaction| transaction.name);
+            match (client.as_ref(), name) {
+                (Some(client), Some(name)) if client.options().unsampled_transaction_metrics => {
+                    metrics_only = Some((name, client.now()));
+                }
+                _ => client = None,
+            }
         }
         // if the transaction was sampled then a profile, linked to the transaction,
         // might as well be sampled
@@ -434,6 +822,7 @@ impl Transaction {
         Self {
             inner: Arc::new(Mutex::new(TransactionInner {
                 client,
+                metrics_only,
                 sampled,
                 context,
                 transaction,
@@ -451,7 +840,7 @@ impl Transaction {
             op: Some(ctx.op),
             ..Default::default()
         };
-        let sampled = ctx.sampled.unwrap_or(false);
+        let sampled = ctx.sampled.or(ctx.parent_sampled).unwrap_or(false);
 
         Self {
             inner: Arc::new(Mutex::new(TransactionInner {
@@ -472,6 +861,11 @@ impl Transaction {
         }
     }
 
+    /// Returns whether the Transaction is sampled, and will be sent to Sentry.
+    pub fn is_sampled(&self) -> bool {
+        self.inner.lock().unwrap().sampled
+    }
+
     /// Get the status of the Transaction.
     pub fn get_status(&self) -> Option<protocol::SpanStatus> {
         let inner = self.inner.lock().unwrap();
@@ -484,6 +878,22 @@ impl Transaction {
         inner.context.status = Some(status);
     }
 
+    /// Set the name of this Transaction, along with the source of the name.
+    ///
+    /// This is useful for integrations which only learn about the route
+    /// template of a request after the transaction was started.
+    pub fn set_name(&self, name: &str, source: protocol::TransactionSource) {
+        let mut inner = self.inner.lock().unwrap();
+        #[cfg(feature = "client")]
+        if let Some((metrics_name, _)) = inner.metrics_only.as_mut() {
+            *metrics_name = name.into();
+        }
+        if let Some(transaction) = inner.transaction.as_mut() {
+            transaction.name = Some(name.into());
+            transaction.transaction_info = Some(protocol::TransactionInfo { source });
+        }
+    }
+
     /// Set the HTTP request information for this Transaction.
     pub fn set_request(&self, request: protocol::Request) {
         let mut inner = self.inner.lock().unwrap();
@@ -512,9 +922,31 @@ impl Transaction {
     pub fn finish(self) {
         with_client_impl! {{
             let mut inner = self.inner.lock().unwrap();
+            if let Some((name, started)) = inner.metrics_only.take() {
+                if let Some(client) = inner.client.take() {
+                    let duration = client.now().duration_since(started).unwrap_or_default();
+                    let mut metric = Metric::distribution(
+                        "transaction.duration",
+                        duration.as_secs_f64() * 1000.0,
+                    )
+                    .with_unit(protocol::MetricUnit::Millisecond)
+                    .with_tag("transaction", name);
+                    if let Some(op) = inner.context.op.clone() {
+                        metric = metric.with_tag("transaction.op", op);
+                    }
+                    client.capture_metric(metric, None);
+                }
+            }
             if let Some(mut transaction) = inner.transaction.take() {
                 if let Some(client) = inner.client.take() {
-                    transaction.finish();
+                    if client.is_transaction_ignored(transaction.name.as_deref()) {
+                        sentry_debug!("ignore_transactions dropped transaction {:?}", transaction.name);
+                        return;
+                    }
+                    transaction.timestamp = Some(client.now());
+                    if let Some(compression) = client.options().span_compression.as_ref() {
+                        compress_spans(&mut transaction.spans, compression);
+                    }
                     transaction
                         .contexts
                         .insert("trace".into(), inner.context.clone().into());
@@ -523,7 +955,9 @@ impl Transaction {
                     let opts = client.options();
                     transaction.release = opts.release.clone();
                     transaction.environment = opts.environment.clone();
-                    transaction.sdk = Some(std::borrow::Cow::Owned(client.sdk_info.clone()));
+                    transaction.sdk = Some(std::borrow::Cow::Owned(
+                        protocol::ClientSdkInfo::clone(&client.sdk_info),
+                    ));
 
                     // if the profiler is running for the given transaction
                     // then call finish_profiling to return the profile
@@ -560,6 +994,7 @@ impl Transaction {
         let span = protocol::Span {
             trace_id: inner.context.trace_id,
             parent_span_id: Some(inner.context.span_id),
+            start_timestamp: inner.now(),
             op: Some(op.into()),
             description: if description.is_empty() {
                 None
@@ -596,6 +1031,13 @@ impl Span {
         span.data.insert(key.into(), value);
     }
 
+    /// Returns whether the Span is sampled, and will be sent to Sentry.
+    ///
+    /// Spans inherit the sampling decision of their transaction.
+    pub fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+
     /// Get the status of the Span.
     pub fn get_status(&self) -> Option<protocol::SpanStatus> {
         let span = self.span.lock().unwrap();
@@ -663,8 +1105,8 @@ impl Span {
                 // the span was already finished
                 return;
             }
-            span.finish();
             let mut inner = self.transaction.lock().unwrap();
+            span.timestamp = Some(inner.now());
             if let Some(transaction) = inner.transaction.as_mut() {
                 if transaction.spans.len() <= MAX_SPANS {
                     transaction.spans.push(span.clone());
@@ -679,9 +1121,11 @@ impl Span {
     #[must_use = "a span must be explicitly closed via `finish()`"]
     pub fn start_child(&self, op: &str, description: &str) -> Span {
         let span = self.span.lock().unwrap();
+        let start_timestamp = self.transaction.lock().unwrap().now();
         let span = protocol::Span {
             trace_id: span.trace_id,
             parent_span_id: Some(span.span_id),
+            start_timestamp,
             op: Some(op.into()),
             description: if description.is_empty() {
                 None
@@ -698,6 +1142,41 @@ impl Span {
     }
 }
 
+/// A guard that finishes a [`Span`] when it is dropped.
+///
+/// This is returned by [`start_span`].
+#[must_use = "the span is finished when the guard is dropped"]
+#[derive(Debug)]
+pub struct SpanGuard {
+    inner: Option<SpanGuardInner>,
+}
+
+#[derive(Debug)]
+struct SpanGuardInner {
+    hub: Arc<Hub>,
+    span: Span,
+    parent: TransactionOrSpan,
+}
+
+impl SpanGuard {
+    /// Returns the guarded Span, if one was started.
+    pub fn span(&self) -> Option<&Span> {
+        self.inner.as_ref().map(|inner| &inner.span)
+    }
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        if let Some(SpanGuardInner { hub, span, parent }) = self.inner.take() {
+            if std::thread::panicking() && span.get_status().is_none() {
+                span.set_status(protocol::SpanStatus::InternalError);
+            }
+            hub.configure_scope(|scope| scope.set_span(Some(parent)));
+            span.finish();
+        }
+    }
+}
+
 /// An Iterator over HTTP header names and values needed for distributed tracing.
 ///
 /// This currently only yields the `sentry-trace` header, but other headers
@@ -714,72 +1193,36 @@ impl Iterator for TraceHeadersIter {
     }
 }
 
-#[derive(Debug, PartialEq)]
-struct SentryTrace(protocol::TraceId, protocol::SpanId, Option<bool>);
-
-fn parse_sentry_trace(header: &str) -> Option<SentryTrace> {
-    let header = header.trim();
-    let mut parts = header.splitn(3, '-');
-
-    let trace_id = parts.next()?.parse().ok()?;
-    let parent_span_id = parts.next()?.parse().ok()?;
-    let parent_sampled = parts.next().and_then(|sampled| match sampled {
-        "1" => Some(true),
-        "0" => Some(false),
-        _ => None,
-    });
-
-    Some(SentryTrace(trace_id, parent_span_id, parent_sampled))
-}
-
-impl std::fmt::Display for SentryTrace {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}-{}", self.0, self.1)?;
-        if let Some(sampled) = self.2 {
-            write!(f, "-{}", if sampled { '1' } else { '0' })?;
-        }
-        Ok(())
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
-
     use super::*;
 
-    #[test]
-    fn parses_sentry_trace() {
-        let trace_id = protocol::TraceId::from_str("09e04486820349518ac7b5d2adbf6ba5").unwrap();
-        let parent_trace_id = protocol::SpanId::from_str("9cf635fa5b870b3a").unwrap();
-
-        let trace = parse_sentry_trace("09e04486820349518ac7b5d2adbf6ba5-9cf635fa5b870b3a-0");
-        assert_eq!(
-            trace,
-            Some(SentryTrace(trace_id, parent_trace_id, Some(false)))
-        );
-
-        let trace = SentryTrace(Default::default(), Default::default(), None);
-        let parsed = parse_sentry_trace(&format!("{}", trace));
-        assert_eq!(parsed, Some(trace));
-    }
-
     #[test]
     fn disabled_forwards_trace_id() {
         let headers = [(
             "SenTrY-TRAce",
             "09e04486820349518ac7b5d2adbf6ba5-9cf635fa5b870b3a-1",
         )];
-        let ctx = TransactionContext::continue_from_headers("noop", "noop", headers);
+        let mut ctx = TransactionContext::continue_from_headers("noop", "noop", headers);
+        assert_eq!(ctx.parent_sampled(), Some(true));
+        assert_eq!(ctx.sampled(), Some(true));
+        ctx.set_sampled(false);
+        assert_eq!(ctx.sampled(), Some(false));
+        assert_eq!(ctx.parent_sampled(), Some(true));
+        ctx.set_sampled(None);
         let trx = start_transaction(ctx);
 
         let span = trx.start_child("noop", "noop");
+        assert!(span.is_sampled());
 
         let header = span.iter_headers().next().unwrap().1;
         let parsed = parse_sentry_trace(&header).unwrap();
 
-        assert_eq!(&parsed.0.to_string(), "09e04486820349518ac7b5d2adbf6ba5");
-        assert_eq!(parsed.2, Some(true));
+        assert_eq!(
+            &parsed.trace_id().to_string(),
+            "09e04486820349518ac7b5d2adbf6ba5"
+        );
+        assert_eq!(parsed.sampled(), Some(true));
     }
 
     #[test]
@@ -793,6 +1236,24 @@ mod tests {
         assert_eq!(ctx.sampled(), Some(true));
     }
 
+    #[test]
+    fn transaction_context_presets() {
+        let ctx = TransactionContext::rpc_server("users.v1.Users", "GetUser", []);
+        assert_eq!(ctx.name(), "users.v1.Users/GetUser");
+        assert_eq!(ctx.operation(), "rpc.server");
+        assert_eq!(ctx.source(), Some(protocol::TransactionSource::Route));
+        assert_eq!(ctx.data()["rpc.method"], "GetUser");
+
+        let ctx = TransactionContext::queue_task("send_email", "emails", []);
+        assert_eq!(ctx.operation(), "queue.task");
+        assert_eq!(ctx.source(), Some(protocol::TransactionSource::Task));
+        assert_eq!(ctx.data()["messaging.destination.name"], "emails");
+
+        let ctx = TransactionContext::cron_job("cleanup", None);
+        assert_eq!(ctx.operation(), "cron.job");
+        assert!(ctx.data().is_empty());
+    }
+
     #[cfg(feature = "client")]
     #[test]
     fn compute_transaction_sample_rate() {
@@ -801,28 +1262,45 @@ mod tests {
         assert_eq!(transaction_sample_rate(None, &ctx, 0.3), 0.3);
         assert_eq!(transaction_sample_rate(None, &ctx, 0.7), 0.7);
 
-        // If only global rate, setting sampled overrides it
-        let mut ctx = TransactionContext::new("noop", "noop");
-        ctx.set_sampled(true);
-        assert_eq!(transaction_sample_rate(None, &ctx, 0.3), 1.0);
-        ctx.set_sampled(false);
-        assert_eq!(transaction_sample_rate(None, &ctx, 0.3), 0.0);
+        // If only global rate, the parent decision overrides it
+        let parent = |sampled| {
+            let header = format!(
+                "09e04486820349518ac7b5d2adbf6ba5-9cf635fa5b870b3a-{}",
+                sampled
+            );
+            TransactionContext::continue_from_headers("noop", "noop", [("sentry-trace", &*header)])
+        };
+        assert_eq!(transaction_sample_rate(None, &parent(1), 0.3), 1.0);
+        assert_eq!(transaction_sample_rate(None, &parent(0), 0.3), 0.0);
 
-        // If given, sampler function overrides everything else.
-        let mut ctx = TransactionContext::new("noop", "noop");
-        assert_eq!(transaction_sample_rate(Some(&|_| { 0.7 }), &ctx, 0.3), 0.7);
-        ctx.set_sampled(false);
+        // If given, sampler function overrides the rate and the parent decision.
+        let mut ctx = parent(0);
+        assert_eq!(ctx.parent_sampled(), Some(false));
         assert_eq!(transaction_sample_rate(Some(&|_| { 0.7 }), &ctx, 0.3), 0.7);
         // But the sampler may choose to inspect parent sampling
-        let sampler = |ctx: &TransactionContext| match ctx.sampled() {
+        let sampler = |ctx: &TransactionContext| match ctx.parent_sampled() {
             Some(true) => 0.8,
             Some(false) => 0.4,
             None => 0.6,
         };
+        assert_eq!(
+            transaction_sample_rate(Some(&sampler), &parent(1), 0.3),
+            0.8
+        );
+        assert_eq!(
+            transaction_sample_rate(Some(&sampler), &parent(0), 0.3),
+            0.4
+        );
+        let new = TransactionContext::new("noop", "noop");
+        assert_eq!(transaction_sample_rate(Some(&sampler), &new, 0.3), 0.6);
+
+        // An explicit decision overrides everything else.
         ctx.set_sampled(true);
-        assert_eq!(transaction_sample_rate(Some(&sampler), &ctx, 0.3), 0.8);
+        assert_eq!(transaction_sample_rate(Some(&sampler), &ctx, 0.3), 1.0);
+        ctx.set_sampled(false);
+        assert_eq!(transaction_sample_rate(Some(&sampler), &ctx, 1.0), 0.0);
         ctx.set_sampled(None);
-        assert_eq!(transaction_sample_rate(Some(&sampler), &ctx, 0.3), 0.6);
+        assert_eq!(transaction_sample_rate(Some(&sampler), &ctx, 0.3), 0.4);
 
         // Can use first-class and custom attributes of the context.
         let sampler = |ctx: &TransactionContext| {
@@ -850,4 +1328,40 @@ mod tests {
         ctx.custom_insert("rate".to_owned(), serde_json::json!(0.7));
         assert_eq!(transaction_sample_rate(Some(&sampler), &ctx, 0.3), 0.7);
     }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn compresses_identical_short_spans() {
+        use std::time::SystemTime;
+
+        let start = SystemTime::now();
+        let parent = protocol::SpanId::default();
+        let span = |offset_ms: u64, duration_ms: u64, op: &str| protocol::Span {
+            parent_span_id: Some(parent),
+            op: Some(op.into()),
+            description: Some("SELECT * FROM rows WHERE id = ?".into()),
+            start_timestamp: start + Duration::from_millis(offset_ms),
+            timestamp: Some(start + Duration::from_millis(offset_ms + duration_ms)),
+            ..Default::default()
+        };
+        let mut spans = vec![
+            span(0, 5, "db"),
+            span(10, 5, "db"),
+            span(20, 5, "db"),
+            span(30, 500, "db"),
+            span(600, 5, "http"),
+            span(610, 5, "http"),
+        ];
+        let compression = SpanCompression {
+            min_count: 3,
+            max_duration: Duration::from_millis(100),
+        };
+        compress_spans(&mut spans, &compression);
+
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans[0].data["compression.count"], 3);
+        assert_eq!(spans[0].data["compression.duration"], 0.015);
+        assert_eq!(spans[0].timestamp, Some(start + Duration::from_millis(25)));
+        assert!(spans[1..].iter().all(|span| span.data.is_empty()));
+    }
 }
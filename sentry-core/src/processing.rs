@@ -0,0 +1,70 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::Hub;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The number of jobs that can be queued before new events are dropped.
+const QUEUE_CAPACITY: usize = 30;
+
+/// Processes captured events on a background thread.
+///
+/// This is used when [`ClientOptions::background_processing`] is enabled, and
+/// shared by a client and its clones.  The thread exits once the worker is
+/// dropped and all queued jobs have run.  Events are dropped while the queue
+/// is full, so a slow `before_send` can not pile up unbounded memory.
+///
+/// [`ClientOptions::background_processing`]: crate::ClientOptions::background_processing
+pub(crate) struct EventWorker {
+    sender: Mutex<SyncSender<Job>>,
+}
+
+impl EventWorker {
+    pub fn new() -> Self {
+        let (sender, receiver) = sync_channel::<Job>(QUEUE_CAPACITY);
+        thread::Builder::new()
+            .name("sentry-event-processor".into())
+            .spawn(move || {
+                // without a client, panics of jobs are not captured as events,
+                // which would be processed here again
+                let hub = Arc::new(Hub::new(None, Default::default()));
+                Hub::run(hub, || {
+                    for job in receiver {
+                        // a panicking `before_send` or integration must not
+                        // stop the worker, or all later events would be lost
+                        if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                            sentry_debug!("event processing panicked, dropping the event");
+                        }
+                    }
+                })
+            })
+            .unwrap();
+        EventWorker {
+            sender: Mutex::new(sender),
+        }
+    }
+
+    /// Queues `job` to run on the worker thread.
+    ///
+    /// Returns `false` if the queue is full, in which case `job` is dropped.
+    pub fn enqueue(&self, job: Job) -> bool {
+        self.sender.lock().unwrap().try_send(job).is_ok()
+    }
+
+    /// Waits until all jobs queued so far have run.
+    ///
+    /// Returns `false` if that did not happen within `timeout`.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let (done_tx, done_rx) = sync_channel(1);
+        // unlike events, flushes wait for room in the queue
+        let sender = self.sender.lock().unwrap().clone();
+        let job = Box::new(move || {
+            let _ = done_tx.send(());
+        });
+        sender.send(job).is_ok() && done_rx.recv_timeout(timeout).is_ok()
+    }
+}
@@ -0,0 +1,326 @@
+//! Helpers to propagate traces across service boundaries.
+//!
+//! Integrations for supported frameworks extract and inject the tracing headers
+//! automatically.  These functions allow wiring up distributed tracing manually
+//! for everything else.
+
+use std::fmt;
+
+use crate::{protocol, Hub, TransactionOrSpan};
+
+/// The parsed value of a `sentry-trace` header.
+///
+/// The header has the form `{trace_id}-{span_id}-{sampled}`, where the
+/// sampling decision is optional.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SentryTrace(
+    pub(crate) protocol::TraceId,
+    pub(crate) protocol::SpanId,
+    pub(crate) Option<bool>,
+);
+
+impl SentryTrace {
+    /// Creates a new `sentry-trace` value from its parts.
+    pub fn new(
+        trace_id: protocol::TraceId,
+        span_id: protocol::SpanId,
+        sampled: Option<bool>,
+    ) -> Self {
+        SentryTrace(trace_id, span_id, sampled)
+    }
+
+    /// The ID of the trace.
+    pub fn trace_id(&self) -> protocol::TraceId {
+        self.0
+    }
+
+    /// The ID of the span that made the request, which becomes the parent span.
+    pub fn span_id(&self) -> protocol::SpanId {
+        self.1
+    }
+
+    /// The sampling decision of the upstream service, if it made one.
+    pub fn sampled(&self) -> Option<bool> {
+        self.2
+    }
+}
+
+impl fmt::Display for SentryTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.0, self.1)?;
+        if let Some(sampled) = self.2 {
+            write!(f, "-{}", if sampled { '1' } else { '0' })?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the value of a `sentry-trace` header.
+///
+/// Returns `None` if the value is malformed.
+///
+/// # Examples
+///
+/// ```
+/// let trace = sentry::parse_sentry_trace("09e04486820349518ac7b5d2adbf6ba5-9cf635fa5b870b3a-1")
+///     .unwrap();
+/// assert_eq!(trace.trace_id().to_string(), "09e04486820349518ac7b5d2adbf6ba5");
+/// assert_eq!(trace.span_id().to_string(), "9cf635fa5b870b3a");
+/// assert_eq!(trace.sampled(), Some(true));
+/// ```
+pub fn parse_sentry_trace(header: &str) -> Option<SentryTrace> {
+    let header = header.trim();
+    let mut parts = header.splitn(3, '-');
+
+    let trace_id = parts.next()?.parse().ok()?;
+    let parent_span_id = parts.next()?.parse().ok()?;
+    let parent_sampled = parts.next().and_then(|sampled| match sampled {
+        "1" => Some(true),
+        "0" => Some(false),
+        _ => None,
+    });
+
+    Some(SentryTrace(trace_id, parent_span_id, parent_sampled))
+}
+
+/// The entries of a W3C `baggage` header.
+///
+/// Sentry propagates its dynamic sampling context in entries prefixed with
+/// `sentry-`, which are available via [`Baggage::sentry_entries`].  Entries of
+/// other vendors are retained, so they can be forwarded unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Baggage {
+    entries: Vec<(String, String)>,
+}
+
+impl Baggage {
+    /// Creates an empty baggage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the (decoded) value of the entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Sets an entry, replacing an existing entry with the same key.
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        let key = key.into();
+        let value = value.into();
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key, value)),
+        }
+    }
+
+    /// Iterates over all entries, in the order they appeared in the header.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Iterates over the entries prefixed with `sentry-`, with the prefix removed.
+    pub fn sentry_entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.iter()
+            .filter_map(|(k, v)| Some((k.strip_prefix("sentry-")?, v)))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Formats the baggage as the value of a `baggage` header.
+impl fmt::Display for Baggage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.entries.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}=", key)?;
+            for byte in value.bytes() {
+                // everything but printable ASCII without `"%,;\` is encoded
+                match byte {
+                    b'!' | b'#'..=b'$' | b'&'..=b'+' | b'-'..=b':' | b'<'..=b'[' | b']'..=b'~' => {
+                        write!(f, "{}", byte as char)?
+                    }
+                    _ => write!(f, "%{:02X}", byte)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the value of a W3C `baggage` header.
+///
+/// Values are percent-decoded, and entry properties are discarded.  Malformed
+/// entries are skipped, so this never fails.
+///
+/// # Examples
+///
+/// ```
+/// let baggage = sentry::parse_baggage("sentry-environment=prod,other=a%20b;prop");
+/// assert_eq!(baggage.get("other"), Some("a b"));
+/// assert_eq!(
+///     baggage.sentry_entries().collect::<Vec<_>>(),
+///     [("environment", "prod")]
+/// );
+/// ```
+pub fn parse_baggage(header: &str) -> Baggage {
+    let mut baggage = Baggage::new();
+    for member in header.split(',') {
+        let member = member.split(';').next().unwrap_or_default();
+        let (key, value) = match member.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        if key.is_empty() {
+            continue;
+        }
+        baggage.insert(key, percent_decode(value));
+    }
+    baggage
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Returns the headers to continue the current trace in an outgoing request.
+///
+/// This yields the `sentry-trace` header of the span that is currently bound
+/// to the scope, along with a `baggage` header carrying the dynamic sampling
+/// context.  Without an active span or client, no headers are returned.
+///
+/// # Examples
+///
+/// ```
+/// # sentry::test::with_captured_events(|| {
+/// let ctx = sentry::TransactionContext::new("checkout", "http.server");
+/// let transaction = sentry::start_transaction(ctx);
+/// sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+///
+/// for (name, value) in sentry::current_trace_headers() {
+///     // add the header to the outgoing request
+/// #   assert!(name == "sentry-trace" || name == "baggage");
+/// }
+/// transaction.finish();
+/// # });
+/// ```
+pub fn current_trace_headers() -> Vec<(&'static str, String)> {
+    Hub::with_active(|hub| match hub.configure_scope(|scope| scope.get_span()) {
+        Some(span) => span
+            .iter_headers()
+            .chain(baggage_header(hub, &span))
+            .collect(),
+        None => Vec::new(),
+    })
+}
+
+/// Returns the `baggage` header with the dynamic sampling context of `span`.
+fn baggage_header(hub: &Hub, span: &TransactionOrSpan) -> Option<(&'static str, String)> {
+    #[cfg(feature = "client")]
+    {
+        let client = hub.client()?;
+        let options = client.options();
+        let mut baggage = Baggage::new();
+        baggage.insert("sentry-trace_id", span.trace_id().to_string());
+        if let Some(dsn) = options.dsn.as_ref() {
+            baggage.insert("sentry-public_key", dsn.public_key());
+        }
+        if let Some(release) = options.release.as_deref() {
+            baggage.insert("sentry-release", release);
+        }
+        if let Some(environment) = options.environment.as_deref() {
+            baggage.insert("sentry-environment", environment);
+        }
+        Some(("baggage", baggage.to_string()))
+    }
+    #[cfg(not(feature = "client"))]
+    {
+        let _ = (hub, span);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn parses_sentry_trace() {
+        let trace_id = protocol::TraceId::from_str("09e04486820349518ac7b5d2adbf6ba5").unwrap();
+        let parent_trace_id = protocol::SpanId::from_str("9cf635fa5b870b3a").unwrap();
+
+        let trace = parse_sentry_trace("09e04486820349518ac7b5d2adbf6ba5-9cf635fa5b870b3a-0");
+        assert_eq!(
+            trace,
+            Some(SentryTrace(trace_id, parent_trace_id, Some(false)))
+        );
+
+        let trace = SentryTrace(Default::default(), Default::default(), None);
+        let parsed = parse_sentry_trace(&format!("{}", trace));
+        assert_eq!(parsed, Some(trace));
+    }
+
+    #[test]
+    fn parses_baggage() {
+        let baggage = parse_baggage(
+            " sentry-trace_id=771a43a4192642f0b136d5159a501700 , sentry-release=1.0%2Bbuild,\
+             invalid,=empty,vendor=value;property=1",
+        );
+        assert_eq!(baggage.len(), 3);
+        assert_eq!(
+            baggage.get("sentry-trace_id"),
+            Some("771a43a4192642f0b136d5159a501700")
+        );
+        assert_eq!(baggage.get("sentry-release"), Some("1.0+build"));
+        assert_eq!(baggage.get("vendor"), Some("value"));
+        assert_eq!(
+            baggage.sentry_entries().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["trace_id", "release"]
+        );
+    }
+
+    #[test]
+    fn formats_baggage() {
+        let mut baggage = Baggage::new();
+        baggage.insert("sentry-release", "1.0 (beta),%");
+        baggage.insert("vendor", "value");
+        baggage.insert("vendor", "other");
+        let header = baggage.to_string();
+        assert_eq!(header, "sentry-release=1.0%20(beta)%2C%25,vendor=other");
+        assert_eq!(parse_baggage(&header), baggage);
+    }
+}
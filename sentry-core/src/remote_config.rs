@@ -0,0 +1,201 @@
+use std::any::type_name;
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{ClientOptions, Integration};
+
+/// Sampling and filtering settings that are fetched at runtime.
+///
+/// Every setting that is present overrides the corresponding option of the
+/// [`ClientOptions`], so a fleet of applications can be tuned without
+/// redeploying them.  The settings are deserialized from JSON documents like:
+///
+/// ```json
+/// {
+///   "sample_rate": 0.5,
+///   "traces_sample_rate": 0.01,
+///   "ignore_errors": ["ConnectionReset"],
+///   "ignore_transactions": ["GET /health"]
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    /// Overrides [`ClientOptions::sample_rate`].
+    pub sample_rate: Option<f32>,
+    /// Overrides [`ClientOptions::traces_sample_rate`].
+    pub traces_sample_rate: Option<f32>,
+    /// Patterns of errors that are dropped in addition to
+    /// [`ClientOptions::ignore_errors`].
+    pub ignore_errors: Vec<Cow<'static, str>>,
+    /// Patterns of transactions that are dropped in addition to
+    /// [`ClientOptions::ignore_transactions`].
+    pub ignore_transactions: Vec<Cow<'static, str>>,
+}
+
+type FetchFn = dyn Fn() -> Option<RemoteConfig> + Send + Sync;
+
+/// Periodically fetches a [`RemoteConfig`] and applies it to the client.
+///
+/// The config is fetched by the given function on a background thread, once
+/// when the client is created and then in the configured interval.  The
+/// function can load it from any source, such as an endpoint of the Sentry
+/// server or a URL of the application's own config service, with the HTTP
+/// client of the application.  When it returns `None`, for instance because
+/// the source is unreachable, the last config that was fetched stays in
+/// effect.  Until a config was fetched, the client options apply unchanged.
+///
+/// Sample rates outside of `0.0..=1.0` are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use sentry::{RemoteConfig, RemoteConfigIntegration};
+///
+/// let integration = RemoteConfigIntegration::new(|| {
+///     // fetch and parse the config, e.g. with
+///     // `reqwest::blocking::get(url).ok()?.json().ok()`
+///     Some(RemoteConfig {
+///         traces_sample_rate: Some(0.01),
+///         ..Default::default()
+///     })
+/// })
+/// .interval(Duration::from_secs(300));
+/// let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(integration));
+/// ```
+pub struct RemoteConfigIntegration {
+    fetch: Arc<FetchFn>,
+    interval: Duration,
+    shared: Arc<Shared>,
+    started: AtomicBool,
+}
+
+struct Shared {
+    config: RwLock<Option<Arc<RemoteConfig>>>,
+    shutdown: Mutex<bool>,
+    wakeup: Condvar,
+}
+
+impl RemoteConfigIntegration {
+    /// Creates a new integration fetching the config with `fetch`.
+    pub fn new<F>(fetch: F) -> Self
+    where
+        F: Fn() -> Option<RemoteConfig> + Send + Sync + 'static,
+    {
+        RemoteConfigIntegration {
+            fetch: Arc::new(fetch),
+            interval: Duration::from_secs(60),
+            shared: Arc::new(Shared {
+                config: RwLock::new(None),
+                shutdown: Mutex::new(false),
+                wakeup: Condvar::new(),
+            }),
+            started: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets how often the config is fetched.
+    /// (defaults to 60 seconds)
+    #[must_use]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Returns the config that was fetched last.
+    pub fn config(&self) -> Option<Arc<RemoteConfig>> {
+        self.shared.config.read().unwrap().clone()
+    }
+}
+
+impl fmt::Debug for RemoteConfigIntegration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("interval", &self.interval)
+            .field("config", &self.config())
+            .finish()
+    }
+}
+
+impl Integration for RemoteConfigIntegration {
+    fn name(&self) -> &'static str {
+        "remote-config"
+    }
+
+    fn setup(&self, _options: &mut ClientOptions) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let fetch = self.fetch.clone();
+        let shared = self.shared.clone();
+        let interval = self.interval;
+        let spawned = thread::Builder::new()
+            .name("sentry-remote-config".into())
+            .spawn(move || loop {
+                if let Some(config) = fetch() {
+                    sentry_debug!("applying remote config {:?}", config);
+                    *shared.config.write().unwrap() = Some(Arc::new(config));
+                }
+
+                let shutdown = shared.shutdown.lock().unwrap();
+                let (shutdown, _) = shared
+                    .wakeup
+                    .wait_timeout_while(shutdown, interval, |shutdown| !*shutdown)
+                    .unwrap();
+                if *shutdown {
+                    return;
+                }
+            });
+        if let Err(err) = spawned {
+            sentry_debug!("failed to spawn the remote config thread: {}", err);
+        }
+    }
+}
+
+impl Drop for RemoteConfigIntegration {
+    fn drop(&mut self) {
+        *self.shared.shutdown.lock().unwrap() = true;
+        self.shared.wakeup.notify_all();
+    }
+}
+
+/// Returns `rate` if it is a valid sample rate.
+pub(crate) fn valid_rate(rate: Option<f32>) -> Option<f32> {
+    rate.filter(|rate| (0.0..=1.0).contains(rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_partial_config() {
+        let config: RemoteConfig =
+            serde_json::from_str(r#"{"traces_sample_rate": 0.25, "ignore_errors": ["Timeout"]}"#)
+                .unwrap();
+        assert_eq!(
+            config,
+            RemoteConfig {
+                traces_sample_rate: Some(0.25),
+                ignore_errors: vec!["Timeout".into()],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_valid_rate() {
+        assert_eq!(valid_rate(Some(0.5)), Some(0.5));
+        assert_eq!(valid_rate(Some(1.5)), None);
+        assert_eq!(valid_rate(Some(-0.1)), None);
+        assert_eq!(valid_rate(None), None);
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::protocol::Breadcrumb;
+
+/// A bounded ring buffer of breadcrumbs.
+///
+/// The oldest breadcrumb is evicted once the buffer is full, which reuses its
+/// slot, so recording breadcrumbs in a hot loop stops reallocating once the
+/// buffer has grown to its capacity. The breadcrumbs themselves are shared
+/// behind `Arc`s, so cloning the buffer when a forked scope records a
+/// breadcrumb only bumps reference counts instead of deep-cloning every entry.
+///
+/// Captured events share the buffer of their scope, and the client only
+/// copies the breadcrumbs into an event once it processes the event.
+#[derive(Clone, Default)]
+pub(crate) struct BreadcrumbRing {
+    entries: VecDeque<Arc<Breadcrumb>>,
+}
+
+impl BreadcrumbRing {
+    /// Appends a breadcrumb, evicting the oldest ones beyond `capacity`.
+    pub fn push(&mut self, breadcrumb: Breadcrumb, capacity: usize) {
+        if capacity == 0 {
+            self.entries.clear();
+            return;
+        }
+        while self.entries.len() >= capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Arc::new(breadcrumb));
+    }
+
+    /// Iterates over the breadcrumbs from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = &Breadcrumb> {
+        self.entries.iter().map(|breadcrumb| &**breadcrumb)
+    }
+}
+
+impl fmt::Debug for BreadcrumbRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crumb(message: &str) -> Breadcrumb {
+        Breadcrumb {
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    fn messages(ring: &BreadcrumbRing) -> Vec<&str> {
+        ring.iter()
+            .map(|crumb| crumb.message.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_evicts_oldest() {
+        let mut ring = BreadcrumbRing::default();
+        for message in ["a", "b", "c", "d"] {
+            ring.push(crumb(message), 3);
+        }
+        assert_eq!(messages(&ring), ["b", "c", "d"]);
+
+        ring.push(crumb("e"), 0);
+        assert!(messages(&ring).is_empty());
+    }
+
+    #[test]
+    fn test_clone_shares_entries() {
+        let mut ring = BreadcrumbRing::default();
+        ring.push(crumb("a"), 3);
+        ring.push(crumb("b"), 3);
+
+        let mut forked = ring.clone();
+        assert!(Arc::ptr_eq(&ring.entries[0], &forked.entries[0]));
+        forked.push(crumb("c"), 3);
+        assert_eq!(messages(&ring), ["a", "b"]);
+        assert_eq!(messages(&forked), ["a", "b", "c"]);
+    }
+}
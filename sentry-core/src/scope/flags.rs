@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+
+use crate::protocol::{Context, Map, Value};
+
+/// The number of flag evaluations that are kept on a scope.
+const MAX_FLAGS: usize = 100;
+
+/// The most recent feature flag evaluations of a scope.
+///
+/// Every flag is kept once with its latest result, ordered by the time of the
+/// evaluation, and the least recently evaluated flags are evicted once more
+/// than 100 flags were evaluated.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FlagBuffer {
+    entries: VecDeque<(String, bool)>,
+}
+
+impl FlagBuffer {
+    /// Records the result of evaluating the flag.
+    pub fn record(&mut self, name: &str, result: bool) {
+        if let Some(index) = self.entries.iter().position(|(flag, _)| flag == name) {
+            self.entries.remove(index);
+        }
+        while self.entries.len() >= MAX_FLAGS {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((name.to_owned(), result));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the `flags` context with the evaluations.
+    pub fn to_context(&self) -> Context {
+        let values = self
+            .entries
+            .iter()
+            .map(|(flag, result)| serde_json::json!({ "flag": flag, "result": result }))
+            .collect();
+        let mut context = Map::new();
+        context.insert("values".into(), Value::Array(values));
+        Context::Other(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(buffer: &FlagBuffer) -> Vec<(&str, bool)> {
+        buffer
+            .entries
+            .iter()
+            .map(|(flag, result)| (flag.as_str(), *result))
+            .collect()
+    }
+
+    #[test]
+    fn test_reevaluation_moves_flag_to_end() {
+        let mut buffer = FlagBuffer::default();
+        buffer.record("a", true);
+        buffer.record("b", false);
+        buffer.record("a", false);
+        assert_eq!(flags(&buffer), [("b", false), ("a", false)]);
+    }
+
+    #[test]
+    fn test_evicts_least_recent_flags() {
+        let mut buffer = FlagBuffer::default();
+        for i in 0..=MAX_FLAGS {
+            buffer.record(&format!("flag-{}", i), true);
+        }
+        assert_eq!(buffer.entries.len(), MAX_FLAGS);
+        assert_eq!(buffer.entries[0].0, "flag-1");
+    }
+}
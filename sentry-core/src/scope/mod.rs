@@ -1,11 +1,25 @@
 #[cfg(feature = "client")]
+mod breadcrumbs;
+#[cfg(feature = "client")]
+mod flags;
+#[cfg(feature = "client")]
 mod real;
+#[cfg(feature = "client")]
+mod recorder;
 
 #[cfg(not(feature = "client"))]
 pub(crate) mod noop;
 
+mod observer;
+
+#[cfg(feature = "client")]
+pub(crate) use self::breadcrumbs::BreadcrumbRing;
 #[cfg(feature = "client")]
 pub use self::real::*;
+#[cfg(feature = "client")]
+pub(crate) use self::recorder::BreadcrumbRecorders;
 
 #[cfg(not(feature = "client"))]
 pub use self::noop::*;
+
+pub use self::observer::ScopeObserver;
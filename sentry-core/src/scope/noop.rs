@@ -1,13 +1,34 @@
 use std::fmt;
 
-use crate::protocol::{Context, Event, Level, User, Value};
-use crate::TransactionOrSpan;
+use crate::protocol::{Attachment, Context, Event, Level, RawEnvelopeItem, User, Value};
+use crate::{Hint, ScopeObserver, TransactionOrSpan};
 
 /// A minimal API scope guard.
 ///
-/// Doesn't do anything but can be debug formatted.
+/// Doesn't do anything but can be debug formatted.  Callbacks registered
+/// with [`on_pop`](Self::on_pop) still run when it is dropped.
 #[derive(Default)]
-pub struct ScopeGuard;
+pub struct ScopeGuard {
+    on_pop: Vec<Box<dyn FnOnce() + Send>>,
+}
+
+impl ScopeGuard {
+    /// Registers a callback that runs when the scope is popped.
+    pub fn on_pop<F>(&mut self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.on_pop.push(Box::new(f));
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        while let Some(f) = self.on_pop.pop() {
+            f();
+        }
+    }
+}
 
 impl fmt::Debug for ScopeGuard {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -32,6 +53,11 @@ impl Scope {
         minimal_unreachable!();
     }
 
+    /// Deletes current breadcrumbs from the scope.
+    pub fn clear_breadcrumbs(&mut self) {
+        minimal_unreachable!();
+    }
+
     /// Sets a level override.
     pub fn set_level(&mut self, level: Option<Level>) {
         let _level = level;
@@ -50,6 +76,11 @@ impl Scope {
         minimal_unreachable!();
     }
 
+    /// Returns the transaction that was set with [`set_transaction`](Self::set_transaction).
+    pub fn transaction(&self) -> Option<&str> {
+        None
+    }
+
     /// Sets the user for the current scope.
     pub fn set_user(&mut self, user: Option<User>) {
         let _user = user;
@@ -82,6 +113,13 @@ impl Scope {
         minimal_unreachable!();
     }
 
+    /// Records the result of evaluating a feature flag.
+    pub fn record_flag_evaluation(&mut self, name: &str, result: bool) {
+        let _name = name;
+        let _result = result;
+        minimal_unreachable!();
+    }
+
     /// Sets a extra to a specific value.
     pub fn set_extra(&mut self, key: &str, value: Value) {
         let _key = key;
@@ -104,6 +142,44 @@ impl Scope {
         minimal_unreachable!();
     }
 
+    /// Add an event processor to the scope that also receives the [`Hint`]
+    /// of the event.
+    pub fn add_event_processor_with_hint<F>(&mut self, f: F)
+    where
+        F: Fn(Event<'static>, &Hint) -> Option<Event<'static>> + Send + Sync + 'static,
+    {
+        let _f = f;
+        minimal_unreachable!();
+    }
+
+    /// Registers an observer that is notified about changes to the scope.
+    pub fn add_observer<O: ScopeObserver + 'static>(&mut self, observer: O) {
+        let _observer = observer;
+        minimal_unreachable!();
+    }
+
+    /// Adds an attachment to the scope
+    pub fn add_attachment(&mut self, attachment: Attachment) {
+        let _attachment = attachment;
+        minimal_unreachable!();
+    }
+
+    /// Clears attachments from the scope
+    pub fn clear_attachments(&mut self) {
+        minimal_unreachable!();
+    }
+
+    /// Adds a custom item that is sent along with every event of the scope.
+    pub fn add_envelope_item(&mut self, item: RawEnvelopeItem) {
+        let _item = item;
+        minimal_unreachable!();
+    }
+
+    /// Clears custom envelope items from the scope
+    pub fn clear_envelope_items(&mut self) {
+        minimal_unreachable!();
+    }
+
     /// Applies the contained scoped data to fill an event.
     pub fn apply_to_event(&self, event: Event<'static>) -> Option<Event<'static>> {
         let _event = event;
@@ -0,0 +1,57 @@
+use crate::protocol::{Breadcrumb, User};
+
+/// Receives changes made to a [`Scope`](crate::Scope).
+///
+/// An observer can be used to mirror the scope into an external crash handler,
+/// for example the annotation store of an out-of-process native crash reporter.
+/// This keeps crashes reported by that handler consistent with events captured
+/// in-process.
+///
+/// Observers are registered with [`Scope::add_observer`](crate::Scope::add_observer),
+/// and are inherited by all scopes pushed on top of that scope.  All methods
+/// are no-ops by default, so implementations only need to handle the changes
+/// they are interested in.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::ScopeObserver;
+///
+/// struct NativeAnnotations;
+///
+/// impl ScopeObserver for NativeAnnotations {
+///     fn set_tag(&self, key: &str, value: &str) {
+///         // forward to the native crash handler
+///         let _ = (key, value);
+///     }
+/// }
+///
+/// sentry::configure_scope(|scope| scope.add_observer(NativeAnnotations));
+/// ```
+pub trait ScopeObserver: Send + Sync {
+    /// Called when a tag is set on the scope.
+    fn set_tag(&self, key: &str, value: &str) {
+        let _ = (key, value);
+    }
+
+    /// Called when a tag is removed from the scope.
+    fn remove_tag(&self, key: &str) {
+        let _ = key;
+    }
+
+    /// Called when the user of the scope is set or unset.
+    fn set_user(&self, user: Option<&User>) {
+        let _ = user;
+    }
+
+    /// Called when a breadcrumb is recorded on the scope.
+    ///
+    /// This is only called for breadcrumbs which were not discarded by the
+    /// `before_breadcrumb` callback.
+    fn add_breadcrumb(&self, breadcrumb: &Breadcrumb) {
+        let _ = breadcrumb;
+    }
+
+    /// Called when the breadcrumbs of the scope are cleared.
+    fn clear_breadcrumbs(&self) {}
+}
@@ -1,19 +1,28 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::{Arc, Mutex, PoisonError, RwLock};
 
+use super::breadcrumbs::BreadcrumbRing;
+use super::flags::FlagBuffer;
+use super::BreadcrumbRecorders;
 use crate::performance::TransactionOrSpan;
-use crate::protocol::{Attachment, Breadcrumb, Context, Event, Level, User, Value};
+use crate::protocol::{
+    Attachment, Breadcrumb, Context, Event, Level, RawEnvelopeItem, TransactionInfo,
+    TransactionSource, User, Value,
+};
 use crate::session::Session;
-use crate::Client;
+use crate::{Client, Hint, ScopeInheritance, ScopeObserver};
 
 #[derive(Debug)]
 pub struct Stack {
     layers: Vec<StackLayer>,
 }
 
-pub type EventProcessor = Arc<dyn Fn(Event<'static>) -> Option<Event<'static>> + Send + Sync>;
+pub type EventProcessor =
+    Arc<dyn Fn(Event<'static>, &Hint) -> Option<Event<'static>> + Send + Sync>;
+
+pub(crate) type ScopeObservers = Arc<Vec<Arc<dyn ScopeObserver>>>;
 
 /// Holds contextual data for the current scope.
 ///
@@ -38,15 +47,18 @@ pub struct Scope {
     pub(crate) level: Option<Level>,
     pub(crate) fingerprint: Option<Arc<[Cow<'static, str>]>>,
     pub(crate) transaction: Option<Arc<str>>,
-    pub(crate) breadcrumbs: Arc<VecDeque<Breadcrumb>>,
+    pub(crate) breadcrumbs: Arc<BreadcrumbRing>,
     pub(crate) user: Option<Arc<User>>,
     pub(crate) extra: Arc<HashMap<String, Value>>,
     pub(crate) tags: Arc<HashMap<String, String>>,
     pub(crate) contexts: Arc<HashMap<String, Context>>,
+    pub(crate) flags: Arc<FlagBuffer>,
     pub(crate) event_processors: Arc<Vec<EventProcessor>>,
     pub(crate) session: Arc<Mutex<Option<Session>>>,
     pub(crate) span: Arc<Option<TransactionOrSpan>>,
     pub(crate) attachments: Arc<Vec<Attachment>>,
+    pub(crate) envelope_items: Arc<Vec<RawEnvelopeItem>>,
+    pub(crate) observers: ScopeObservers,
 }
 
 impl fmt::Debug for Scope {
@@ -60,10 +72,13 @@ impl fmt::Debug for Scope {
             .field("extra", &self.extra)
             .field("tags", &self.tags)
             .field("contexts", &self.contexts)
+            .field("flags", &self.flags)
             .field("event_processors", &self.event_processors.len())
             .field("session", &self.session)
             .field("span", &self.span)
             .field("attachments", &self.attachments.len())
+            .field("envelope_items", &self.envelope_items.len())
+            .field("observers", &self.observers.len())
             .finish()
     }
 }
@@ -105,6 +120,27 @@ impl Stack {
     pub fn depth(&self) -> usize {
         self.layers.len()
     }
+
+    /// Adds breadcrumbs that were buffered by a [`BreadcrumbRecorders`] to the
+    /// top scope.
+    pub fn merge_breadcrumbs(&mut self, breadcrumbs: Vec<Breadcrumb>) {
+        if breadcrumbs.is_empty() {
+            return;
+        }
+        let top = self.top_mut();
+        let max_breadcrumbs = match top.client {
+            Some(ref client) => client.options().max_breadcrumbs,
+            None => return,
+        };
+        let scope = Arc::make_mut(&mut top.scope);
+        let ring = Arc::make_mut(&mut scope.breadcrumbs);
+        for breadcrumb in breadcrumbs {
+            for observer in scope.observers.iter() {
+                observer.add_breadcrumb(&breadcrumb);
+            }
+            ring.push(breadcrumb, max_breadcrumbs);
+        }
+    }
 }
 
 /// A scope guard.
@@ -114,7 +150,54 @@ impl Stack {
 ///
 /// [`Hub::push_scope`]: struct.Hub.html#method.with_scope
 #[derive(Default)]
-pub struct ScopeGuard(pub(crate) Option<(Arc<RwLock<Stack>>, usize)>);
+pub struct ScopeGuard {
+    pub(crate) stack: Option<(Arc<RwLock<Stack>>, usize)>,
+    recorders: Option<Arc<BreadcrumbRecorders>>,
+    on_pop: Vec<Box<dyn FnOnce() + Send>>,
+}
+
+impl ScopeGuard {
+    pub(crate) fn new(
+        stack: Arc<RwLock<Stack>>,
+        depth: usize,
+        recorders: Arc<BreadcrumbRecorders>,
+    ) -> Self {
+        ScopeGuard {
+            stack: Some((stack, depth)),
+            recorders: Some(recorders),
+            on_pop: Vec::new(),
+        }
+    }
+
+    /// Registers a callback that runs when the scope is popped.
+    ///
+    /// This allows to keep other context systems in sync with the scope, for
+    /// instance to finish a span or to clear a correlation ID of the logging
+    /// framework.  The callbacks run after the scope was popped, in the
+    /// reverse order of their registration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let cleared = Arc::new(AtomicBool::new(false));
+    /// {
+    ///     let mut guard = sentry::Hub::current().push_scope();
+    ///     sentry::configure_scope(|scope| scope.set_tag("request_id", "r-42"));
+    ///     let cleared = cleared.clone();
+    ///     guard.on_pop(move || cleared.store(true, Ordering::SeqCst));
+    /// }
+    /// assert!(cleared.load(Ordering::SeqCst));
+    /// ```
+    pub fn on_pop<F>(&mut self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.on_pop.push(Box::new(f));
+    }
+}
 
 impl fmt::Debug for ScopeGuard {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -124,22 +207,55 @@ impl fmt::Debug for ScopeGuard {
 
 impl Drop for ScopeGuard {
     fn drop(&mut self) {
-        if let Some((stack, depth)) = self.0.take() {
+        if let Some((stack, depth)) = self.stack.take() {
             let mut stack = stack.write().unwrap_or_else(PoisonError::into_inner);
             if stack.depth() != depth {
                 panic!("Tried to pop guards out of order");
             }
+            // breadcrumbs recorded within the scope must not leak into its parent
+            if let Some(ref recorders) = self.recorders {
+                stack.merge_breadcrumbs(recorders.drain());
+            }
             stack.pop();
         }
+        // the stack is unlocked again, so the callbacks can use the hub
+        while let Some(f) = self.on_pop.pop() {
+            f();
+        }
     }
 }
 
 impl Scope {
+    /// Returns the scope that a derived hub starts out with.
+    pub(crate) fn inherit(self: &Arc<Self>, inheritance: &ScopeInheritance) -> Arc<Scope> {
+        if *inheritance == ScopeInheritance::default() {
+            return self.clone();
+        }
+        let mut scope = (**self).clone();
+        if !inheritance.breadcrumbs {
+            scope.breadcrumbs = Default::default();
+        }
+        if !inheritance.tags {
+            scope.tags = Default::default();
+        }
+        if !inheritance.user {
+            scope.user = None;
+        }
+        if !inheritance.contexts {
+            scope.contexts = Default::default();
+        }
+        if !inheritance.extra {
+            scope.extra = Default::default();
+        }
+        Arc::new(scope)
+    }
+
     /// Clear the scope.
     ///
     /// By default a scope will inherit all values from the higher scope.
     /// In some situations this might not be what a user wants.  Calling
-    /// this method will wipe all data contained within.
+    /// this method will wipe all data contained within.  This also removes
+    /// all registered [`ScopeObserver`]s.
     pub fn clear(&mut self) {
         *self = Default::default();
     }
@@ -147,6 +263,9 @@ impl Scope {
     /// Deletes current breadcrumbs from the scope.
     pub fn clear_breadcrumbs(&mut self) {
         self.breadcrumbs = Default::default();
+        for observer in self.observers.iter() {
+            observer.clear_breadcrumbs();
+        }
     }
 
     /// Sets a level override.
@@ -172,25 +291,44 @@ impl Scope {
 
             if let Some(trx) = trx.lock().unwrap().transaction.as_mut() {
                 trx.name = Some(name.into());
+                trx.transaction_info = Some(TransactionInfo {
+                    source: TransactionSource::Custom,
+                });
             }
         }
     }
 
+    /// Returns the transaction that was set with [`set_transaction`](Self::set_transaction).
+    pub fn transaction(&self) -> Option<&str> {
+        self.transaction.as_deref()
+    }
+
     /// Sets the user for the current scope.
     pub fn set_user(&mut self, user: Option<User>) {
         self.user = user.map(Arc::new);
+        for observer in self.observers.iter() {
+            observer.set_user(self.user.as_deref());
+        }
     }
 
     /// Sets a tag to a specific value.
     pub fn set_tag<V: ToString>(&mut self, key: &str, value: V) {
-        Arc::make_mut(&mut self.tags).insert(key.to_string(), value.to_string());
+        let value = value.to_string();
+        for observer in self.observers.iter() {
+            observer.set_tag(key, &value);
+        }
+        Arc::make_mut(&mut self.tags).insert(key.to_string(), value);
     }
 
     /// Removes a tag.
     ///
     /// If the tag is not set, does nothing.
     pub fn remove_tag(&mut self, key: &str) {
-        Arc::make_mut(&mut self.tags).remove(key);
+        if Arc::make_mut(&mut self.tags).remove(key).is_some() {
+            for observer in self.observers.iter() {
+                observer.remove_tag(key);
+            }
+        }
     }
 
     /// Sets a context for a key.
@@ -203,6 +341,14 @@ impl Scope {
         Arc::make_mut(&mut self.contexts).remove(key);
     }
 
+    /// Records the result of evaluating a feature flag.
+    ///
+    /// The 100 most recently evaluated flags are sent in the `flags` context
+    /// of events, unless that context is set explicitly.
+    pub fn record_flag_evaluation(&mut self, name: &str, result: bool) {
+        Arc::make_mut(&mut self.flags).record(name, result);
+    }
+
     /// Sets a extra to a specific value.
     pub fn set_extra(&mut self, key: &str, value: Value) {
         Arc::make_mut(&mut self.extra).insert(key.to_string(), value);
@@ -217,10 +363,26 @@ impl Scope {
     pub fn add_event_processor<F>(&mut self, f: F)
     where
         F: Fn(Event<'static>) -> Option<Event<'static>> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.event_processors).push(Arc::new(move |event, _| f(event)));
+    }
+
+    /// Add an event processor to the scope that also receives the [`Hint`]
+    /// of the event.
+    pub fn add_event_processor_with_hint<F>(&mut self, f: F)
+    where
+        F: Fn(Event<'static>, &Hint) -> Option<Event<'static>> + Send + Sync + 'static,
     {
         Arc::make_mut(&mut self.event_processors).push(Arc::new(f));
     }
 
+    /// Registers an observer that is notified about changes to the scope.
+    ///
+    /// See [`ScopeObserver`] for more information.
+    pub fn add_observer<O: ScopeObserver + 'static>(&mut self, observer: O) {
+        Arc::make_mut(&mut self.observers).push(Arc::new(observer));
+    }
+
     /// Adds an attachment to the scope
     pub fn add_attachment(&mut self, attachment: Attachment) {
         Arc::make_mut(&mut self.attachments).push(attachment);
@@ -231,8 +393,46 @@ impl Scope {
         Arc::make_mut(&mut self.attachments).clear();
     }
 
+    /// Adds a custom item that is sent along with every event of the scope.
+    ///
+    /// Like attachments, the items are added to the envelopes of events.  This
+    /// allows using features of Sentry that the SDK does not support yet.
+    pub fn add_envelope_item(&mut self, item: RawEnvelopeItem) {
+        Arc::make_mut(&mut self.envelope_items).push(item);
+    }
+
+    /// Clears custom envelope items from the scope
+    pub fn clear_envelope_items(&mut self) {
+        Arc::make_mut(&mut self.envelope_items).clear();
+    }
+
     /// Applies the contained scoped data to fill an event.
     pub fn apply_to_event(&self, mut event: Event<'static>) -> Option<Event<'static>> {
+        event.breadcrumbs.extend(self.breadcrumbs.iter().cloned());
+        self.apply_to_event_with_hint(event, &Hint::default())
+    }
+
+    /// Returns the name of the transaction of the scope.
+    ///
+    /// This is the explicitly set transaction, or the name of the transaction
+    /// of the current span.
+    pub(crate) fn transaction_name(&self) -> Option<String> {
+        match self.transaction.as_deref() {
+            Some(transaction) => Some(transaction.to_owned()),
+            None => self.span.as_ref().as_ref()?.transaction_name(),
+        }
+    }
+
+    /// Applies the contained scoped data to fill an event, passing the hint
+    /// to the event processors.
+    ///
+    /// The breadcrumbs are not copied into the event, the client does that
+    /// once it processes the event.
+    pub(crate) fn apply_to_event_with_hint(
+        &self,
+        mut event: Event<'static>,
+        hint: &Hint,
+    ) -> Option<Event<'static>> {
         // TODO: event really should have an optional level
         if self.level.is_some() {
             event.level = self.level.unwrap();
@@ -244,7 +444,6 @@ impl Scope {
             }
         }
 
-        event.breadcrumbs.extend(self.breadcrumbs.iter().cloned());
         event
             .extra
             .extend(self.extra.iter().map(|(k, v)| (k.to_owned(), v.to_owned())));
@@ -256,6 +455,12 @@ impl Scope {
                 .iter()
                 .map(|(k, v)| (k.to_owned(), v.to_owned())),
         );
+        if !self.flags.is_empty() {
+            event
+                .contexts
+                .entry("flags".to_owned())
+                .or_insert_with(|| self.flags.to_context());
+        }
 
         if let Some(span) = self.span.as_ref() {
             span.apply_to_event(&mut event);
@@ -277,7 +482,7 @@ impl Scope {
 
         for processor in self.event_processors.as_ref() {
             let id = event.event_id;
-            event = match processor(event) {
+            event = match processor(event, hint) {
                 Some(event) => event,
                 None => {
                     sentry_debug!("event processor dropped event {}", id);
@@ -290,18 +495,34 @@ impl Scope {
     }
 
     /// Set the given [`TransactionOrSpan`] as the active span for this scope.
+    ///
+    /// Events captured while a span is active carry its `trace` context, which
+    /// links them to the transaction in Sentry.
     pub fn set_span(&mut self, span: Option<TransactionOrSpan>) {
         self.span = Arc::new(span);
     }
 
     /// Returns the currently active span.
+    ///
+    /// Manual instrumentation can use this to parent new spans correctly:
+    ///
+    /// ```
+    /// let parent = sentry::configure_scope(|scope| scope.get_span());
+    /// let span: sentry::TransactionOrSpan = match &parent {
+    ///     Some(parent) => parent.start_child("subtask", "parse input").into(),
+    ///     None => {
+    ///         let ctx = sentry::TransactionContext::new("task", "parse input");
+    ///         sentry::start_transaction(ctx).into()
+    ///     }
+    /// };
+    /// sentry::configure_scope(|scope| scope.set_span(Some(span.clone())));
+    ///
+    /// // errors captured here are linked to `span`
+    ///
+    /// span.finish();
+    /// sentry::configure_scope(|scope| scope.set_span(parent));
+    /// ```
     pub fn get_span(&self) -> Option<TransactionOrSpan> {
         self.span.as_ref().clone()
     }
-
-    pub(crate) fn update_session_from_event(&self, event: &Event<'static>) {
-        if let Some(session) = self.session.lock().unwrap().as_mut() {
-            session.update_from_event(event);
-        }
-    }
 }
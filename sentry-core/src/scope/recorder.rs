@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use crate::protocol::Breadcrumb;
+
+type Recorder = Mutex<VecDeque<Breadcrumb>>;
+
+thread_local! {
+    /// The recorders of the current thread, one per hub that it recorded into.
+    static THREAD_RECORDERS: RefCell<Vec<(Weak<BreadcrumbRecorders>, Arc<Recorder>)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Per-thread buffers of breadcrumbs that are not yet merged into a scope.
+///
+/// Every thread records into its own buffer, so recording a breadcrumb only
+/// locks a mutex that is uncontended unless the buffers are being merged.  The
+/// buffers are merged by timestamp into the scope of the hub when an event is
+/// captured.
+#[derive(Default)]
+pub(crate) struct BreadcrumbRecorders {
+    recorders: Mutex<Vec<Arc<Recorder>>>,
+    pending: AtomicBool,
+}
+
+impl BreadcrumbRecorders {
+    /// Records a breadcrumb into the buffer of the current thread, evicting
+    /// the oldest ones beyond `capacity`.
+    pub fn record(self: &Arc<Self>, breadcrumb: Breadcrumb, capacity: usize) {
+        THREAD_RECORDERS.with(|thread_recorders| {
+            let mut thread_recorders = thread_recorders.borrow_mut();
+            let existing = thread_recorders.iter().position(|(owner, _)| {
+                owner.strong_count() > 0 && std::ptr::eq(owner.as_ptr(), Arc::as_ptr(self))
+            });
+            let index = match existing {
+                Some(index) => index,
+                None => {
+                    thread_recorders.retain(|(owner, _)| owner.strong_count() > 0);
+                    let recorder = Arc::new(Recorder::default());
+                    self.lock_recorders().push(recorder.clone());
+                    thread_recorders.push((Arc::downgrade(self), recorder));
+                    thread_recorders.len() - 1
+                }
+            };
+
+            let mut breadcrumbs = thread_recorders[index]
+                .1
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            while !breadcrumbs.is_empty() && breadcrumbs.len() >= capacity {
+                breadcrumbs.pop_front();
+            }
+            if capacity > 0 {
+                breadcrumbs.push_back(breadcrumb);
+            }
+        });
+        self.pending.store(true, Ordering::Release);
+    }
+
+    /// Takes the breadcrumbs of all threads, ordered by their timestamp.
+    pub fn drain(&self) -> Vec<Breadcrumb> {
+        if !self.pending.swap(false, Ordering::AcqRel) {
+            return Vec::new();
+        }
+        let mut recorders = self.lock_recorders();
+        let mut breadcrumbs = Vec::new();
+        for recorder in recorders.iter() {
+            breadcrumbs.extend(
+                recorder
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .drain(..),
+            );
+        }
+        // the recorders of threads that have exited are only referenced here
+        recorders.retain(|recorder| Arc::strong_count(recorder) > 1);
+        breadcrumbs.sort_by_key(|breadcrumb| breadcrumb.timestamp);
+        breadcrumbs
+    }
+
+    fn lock_recorders(&self) -> std::sync::MutexGuard<'_, Vec<Arc<Recorder>>> {
+        self.recorders
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for BreadcrumbRecorders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BreadcrumbRecorders")
+            .field("threads", &self.lock_recorders().len())
+            .field("pending", &self.pending.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    fn crumb(message: &str, secs: u64) -> Breadcrumb {
+        Breadcrumb {
+            message: Some(message.into()),
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merges_threads_by_timestamp() {
+        let recorders = Arc::new(BreadcrumbRecorders::default());
+        recorders.record(crumb("a", 1), 10);
+        recorders.record(crumb("c", 3), 10);
+        let other = recorders.clone();
+        std::thread::spawn(move || {
+            other.record(crumb("b", 2), 10);
+            other.record(crumb("d", 4), 10);
+        })
+        .join()
+        .unwrap();
+
+        let messages: Vec<_> = recorders
+            .drain()
+            .into_iter()
+            .map(|crumb| crumb.message.unwrap())
+            .collect();
+        assert_eq!(messages, ["a", "b", "c", "d"]);
+        assert!(recorders.drain().is_empty());
+        // the recorder of the exited thread was removed
+        assert_eq!(recorders.lock_recorders().len(), 1);
+    }
+}
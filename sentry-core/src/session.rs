@@ -54,7 +54,7 @@ impl Session {
                 distinct_id,
                 sequence: None,
                 timestamp: None,
-                started: SystemTime::now(),
+                started: client.now(),
                 init: true,
                 duration: None,
                 status: SessionStatus::Ok,
@@ -104,7 +104,17 @@ impl Session {
                 SessionStatus::Ok => SessionStatus::Exited,
                 s => s,
             };
-            self.session_update.duration = Some(self.started.elapsed().as_secs_f64());
+            // the system clock may jump, so the duration is measured with a
+            // monotonic clock, unless a custom clock is configured
+            let duration = if self.client.options().clock.is_some() {
+                self.client
+                    .now()
+                    .duration_since(self.session_update.started)
+                    .unwrap_or_default()
+            } else {
+                self.started.elapsed()
+            };
+            self.session_update.duration = Some(duration.as_secs_f64());
             self.session_update.status = status;
             self.dirty = true;
         }
@@ -397,6 +407,36 @@ mod tests {
         assert_eq!(items.next(), None);
     }
 
+    #[test]
+    fn test_session_duration_of_custom_clock() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let ticks = Arc::new(AtomicU64::new(0));
+        let clock = {
+            let ticks = ticks.clone();
+            move || SystemTime::UNIX_EPOCH + Duration::from_secs(ticks.load(Ordering::SeqCst))
+        };
+        let envelopes = crate::test::with_captured_envelopes_options(
+            || {
+                sentry::start_session();
+                ticks.store(90, Ordering::SeqCst);
+            },
+            crate::ClientOptions {
+                release: Some("some-release".into()),
+                clock: Some(Arc::new(clock)),
+                ..Default::default()
+            },
+        );
+
+        match envelopes[0].items().next() {
+            Some(EnvelopeItem::SessionUpdate(session)) => {
+                assert_eq!(session.started, SystemTime::UNIX_EPOCH);
+                assert_eq!(session.duration, Some(90.0));
+            }
+            _ => panic!("expected session"),
+        }
+    }
+
     #[test]
     fn test_session_batching() {
         let envelopes = capture_envelopes(|| {
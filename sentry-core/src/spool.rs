@@ -0,0 +1,391 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fs2::FileExt;
+
+use crate::types::Uuid;
+use crate::Envelope;
+
+/// The first line of every spooled envelope, which identifies the format.
+const MAGIC: &[u8] = b"sentry-spool 1\n";
+
+/// A directory of envelopes that are waiting to be sent.
+///
+/// A spool can be shared by many processes, such as forked workers, which
+/// all write envelopes to it, while a single uploader at a time reads them.
+/// The directory is laid out like a maildir:
+///
+/// * `tmp/` holds envelopes while they are being written.  Every envelope is
+///   written to a file with a unique name, so writers never contend.
+/// * `new/` holds complete envelopes, which are moved from `tmp/` with an
+///   atomic rename once they are fully written, so readers never see partial
+///   writes.  The files are named after the time they were written at.
+/// * `cur/` holds envelopes that an uploader claimed, again with an atomic
+///   rename.  They are removed once they were sent.
+/// * `uploader.lock` is locked exclusively by the uploader, so only one
+///   process at a time reads the spool.
+///
+/// Every file starts with a header containing the length and CRC-32 checksum
+/// of the envelope, so envelopes that were truncated or corrupted on disk,
+/// for instance by a crash of the machine, are detected and discarded.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::Spool;
+///
+/// # let dir = std::env::temp_dir().join(format!("sentry-spool-doc-{}", std::process::id()));
+/// let spool = Spool::open(&dir).unwrap();
+/// spool
+///     .write(&sentry::Envelope::from(sentry::protocol::Event::default()))
+///     .unwrap();
+///
+/// if let Some(uploader) = spool.lock_uploader().unwrap() {
+///     uploader
+///         .drain(|envelope| {
+///             // send the envelope, and return whether it was sent
+///             true
+///         })
+///         .unwrap();
+/// }
+/// # std::fs::remove_dir_all(&dir).ok();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Spool {
+    dir: PathBuf,
+}
+
+impl Spool {
+    /// Opens the spool in the given directory, creating it if needed.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Spool> {
+        let dir = dir.as_ref().to_owned();
+        for subdir in ["tmp", "new", "cur"] {
+            fs::create_dir_all(dir.join(subdir))?;
+        }
+        Ok(Spool { dir })
+    }
+
+    /// Returns the directory of the spool.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes an envelope to the spool.
+    ///
+    /// The envelope is visible to the uploader only once it was completely
+    /// written and synced to disk.
+    pub fn write(&self, envelope: &Envelope) -> io::Result<()> {
+        let mut payload = Vec::new();
+        envelope.to_writer(&mut payload)?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        // the zero-padded timestamp makes the names sort by time
+        let name = format!(
+            "{:020}-{}-{}.envelope",
+            timestamp.as_nanos(),
+            std::process::id(),
+            Uuid::new_v4().simple()
+        );
+        let tmp_path = self.dir.join("tmp").join(&name);
+
+        let result = (|| {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&tmp_path)?;
+            file.write_all(MAGIC)?;
+            file.write_all(format!("{} {:08x}\n", payload.len(), crc32(&payload)).as_bytes())?;
+            file.write_all(&payload)?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, self.dir.join("new").join(&name))
+        })();
+        if result.is_err() {
+            fs::remove_file(&tmp_path).ok();
+        }
+        result
+    }
+
+    /// Returns the number of envelopes that are waiting to be sent.
+    pub fn len(&self) -> io::Result<usize> {
+        Ok(fs::read_dir(self.dir.join("new"))?.count())
+    }
+
+    /// Returns `true` if no envelopes are waiting to be sent.
+    pub fn is_empty(&self) -> io::Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Becomes the uploader of the spool, unless another process already is.
+    ///
+    /// Returns `None` if the spool is locked by another uploader.  The lock
+    /// is released when the returned [`SpoolUploader`] is dropped.  It is an
+    /// advisory lock of the operating system, so it is also released when the
+    /// uploader crashes.
+    pub fn lock_uploader(&self) -> io::Result<Option<SpoolUploader>> {
+        let lock = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.dir.join("uploader.lock"))?;
+        match lock.try_lock_exclusive() {
+            Ok(()) => {}
+            Err(err) if err.kind() == fs2::lock_contended_error().kind() => return Ok(None),
+            Err(err) => return Err(err),
+        }
+
+        // envelopes claimed by a previous uploader that crashed were not sent
+        for entry in fs::read_dir(self.dir.join("cur"))? {
+            let entry = entry?;
+            fs::rename(entry.path(), self.dir.join("new").join(entry.file_name())).ok();
+        }
+
+        Ok(Some(SpoolUploader {
+            spool: self.clone(),
+            _lock: lock,
+        }))
+    }
+}
+
+/// The exclusive reader of a [`Spool`].
+///
+/// This is returned by [`Spool::lock_uploader`].
+#[derive(Debug)]
+pub struct SpoolUploader {
+    spool: Spool,
+    /// Holds the lock until the uploader is dropped.
+    _lock: File,
+}
+
+impl SpoolUploader {
+    /// Reads the envelopes of the spool, oldest first, and passes them to
+    /// `send`.
+    ///
+    /// An envelope is removed from the spool if `send` returns `true`.
+    /// Otherwise, it is kept and draining stops, for instance because the
+    /// network is unavailable.  Corrupted envelopes are discarded.
+    ///
+    /// Returns the number of envelopes that were sent.
+    pub fn drain<F>(&self, mut send: F) -> io::Result<usize>
+    where
+        F: FnMut(Envelope) -> bool,
+    {
+        let dir = &self.spool.dir;
+        let mut names: Vec<_> = fs::read_dir(dir.join("new"))?
+            .filter_map(|entry| Some(entry.ok()?.file_name()))
+            .collect();
+        names.sort();
+
+        let mut sent = 0;
+        for name in names {
+            let new_path = dir.join("new").join(&name);
+            let cur_path = dir.join("cur").join(&name);
+            if fs::rename(&new_path, &cur_path).is_err() {
+                continue;
+            }
+            let envelope = match read_envelope(&cur_path) {
+                Ok(envelope) => envelope,
+                Err(err) => {
+                    sentry_debug!("discarding corrupted envelope {:?}: {}", name, err);
+                    fs::remove_file(&cur_path).ok();
+                    continue;
+                }
+            };
+            if !send(envelope) {
+                fs::rename(&cur_path, &new_path)?;
+                break;
+            }
+            fs::remove_file(&cur_path)?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+}
+
+fn read_envelope(path: &Path) -> io::Result<Envelope> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut contents = Vec::new();
+    File::open(path)?.read_to_end(&mut contents)?;
+    let contents = contents
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| invalid("unknown format"))?;
+    let header_end = contents
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or_else(|| invalid("missing header"))?;
+    let header =
+        std::str::from_utf8(&contents[..header_end]).map_err(|_| invalid("invalid header"))?;
+    let (len, checksum) = header
+        .split_once(' ')
+        .ok_or_else(|| invalid("invalid header"))?;
+    let len: usize = len.parse().map_err(|_| invalid("invalid length"))?;
+    let checksum = u32::from_str_radix(checksum, 16).map_err(|_| invalid("invalid checksum"))?;
+
+    let payload = &contents[header_end + 1..];
+    if payload.len() != len {
+        return Err(invalid("truncated envelope"));
+    }
+    if crc32(payload) != checksum {
+        return Err(invalid("checksum mismatch"));
+    }
+    Envelope::from_slice(payload).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Computes the CRC-32 (IEEE) checksum of the data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Event;
+
+    fn temp_spool(name: &str) -> Spool {
+        let dir =
+            std::env::temp_dir().join(format!("sentry-spool-{}-{}", name, Uuid::new_v4().simple()));
+        Spool::open(dir).unwrap()
+    }
+
+    fn event_envelope(message: &str) -> Envelope {
+        Envelope::from(Event {
+            message: Some(message.into()),
+            ..Default::default()
+        })
+    }
+
+    fn message(envelope: &Envelope) -> String {
+        envelope.event().unwrap().message.clone().unwrap()
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_concurrent_writers() {
+        let spool = temp_spool("writers");
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let spool = spool.clone();
+                std::thread::spawn(move || {
+                    for j in 0..5 {
+                        spool
+                            .write(&event_envelope(&format!("{}-{}", i, j)))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(spool.len().unwrap(), 20);
+
+        let uploader = spool.lock_uploader().unwrap().unwrap();
+        let mut messages = Vec::new();
+        let sent = uploader
+            .drain(|envelope| {
+                messages.push(message(&envelope));
+                true
+            })
+            .unwrap();
+        assert_eq!(sent, 20);
+        messages.sort();
+        messages.dedup();
+        assert_eq!(messages.len(), 20);
+        assert!(spool.is_empty().unwrap());
+        fs::remove_dir_all(spool.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_uploader_lock_and_retry() {
+        let spool = temp_spool("uploader");
+        spool.write(&event_envelope("first")).unwrap();
+        spool.write(&event_envelope("second")).unwrap();
+
+        let uploader = spool.lock_uploader().unwrap().unwrap();
+        assert!(spool.lock_uploader().unwrap().is_none());
+
+        // a failed send keeps the envelope and stops draining
+        let mut attempts = Vec::new();
+        let sent = uploader
+            .drain(|envelope| {
+                attempts.push(message(&envelope));
+                false
+            })
+            .unwrap();
+        assert_eq!((sent, attempts), (0, vec!["first".to_owned()]));
+        assert_eq!(spool.len().unwrap(), 2);
+
+        drop(uploader);
+        let uploader = spool.lock_uploader().unwrap().unwrap();
+        let mut messages = Vec::new();
+        uploader
+            .drain(|envelope| {
+                messages.push(message(&envelope));
+                true
+            })
+            .unwrap();
+        assert_eq!(messages, ["first", "second"]);
+        drop(uploader);
+        fs::remove_dir_all(spool.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_uploader_lock_of_crashed_process() {
+        let spool = temp_spool("crashed");
+        // the lock file of a crashed uploader is left behind, but not locked
+        fs::write(spool.dir().join("uploader.lock"), "42").unwrap();
+
+        let uploader = spool.lock_uploader().unwrap();
+        assert!(uploader.is_some());
+        assert!(spool.lock_uploader().unwrap().is_none());
+        drop(uploader);
+        fs::remove_dir_all(spool.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_discards_corrupted_envelopes() {
+        let spool = temp_spool("corrupted");
+        spool.write(&event_envelope("intact")).unwrap();
+        spool.write(&event_envelope("corrupted")).unwrap();
+        fs::write(spool.dir().join("new").join("0-truncated"), MAGIC).unwrap();
+
+        for entry in fs::read_dir(spool.dir().join("new")).unwrap() {
+            let path = entry.unwrap().path();
+            let mut contents = fs::read(&path).unwrap();
+            let text = String::from_utf8_lossy(&contents);
+            if let Some(pos) = text.find("corrupted") {
+                contents[pos] = b'C';
+                fs::write(&path, contents).unwrap();
+            }
+        }
+
+        let uploader = spool.lock_uploader().unwrap().unwrap();
+        let mut messages = Vec::new();
+        uploader
+            .drain(|envelope| {
+                messages.push(message(&envelope));
+                true
+            })
+            .unwrap();
+        assert_eq!(messages, ["intact"]);
+        assert!(spool.is_empty().unwrap());
+        drop(uploader);
+        fs::remove_dir_all(spool.dir()).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{Backpressure, Envelope, Transport};
+
+/// The number of envelopes that are queued for the sidecar, after which
+/// envelopes are only sent to the inner transport.
+const QUEUE_CAPACITY: usize = 30;
+
+/// The timeout of connecting to and talking with the sidecar.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+enum Task {
+    Send(Envelope),
+    Flush(SyncSender<()>),
+}
+
+/// A [`Transport`] that forwards a copy of every envelope to a Spotlight
+/// sidecar.
+///
+/// This is used when [`ClientOptions::spotlight`] is enabled.  The envelopes
+/// are posted to the sidecar from a background thread, and are also sent by
+/// the inner transport, if there is one.
+///
+/// [`ClientOptions::spotlight`]: crate::ClientOptions::spotlight
+pub(crate) struct SpotlightTransport {
+    inner: Option<Arc<dyn Transport>>,
+    sender: Mutex<Option<SyncSender<Task>>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SpotlightTransport {
+    pub fn new(url: &str, inner: Option<Arc<dyn Transport>>) -> Self {
+        let sidecar = match Sidecar::parse(url) {
+            Some(sidecar) => sidecar,
+            None => {
+                sentry_debug!("invalid Spotlight URL {:?}", url);
+                return SpotlightTransport {
+                    inner,
+                    sender: Mutex::new(None),
+                    handle: Mutex::new(None),
+                };
+            }
+        };
+        let (sender, receiver) = sync_channel(QUEUE_CAPACITY);
+        let handle = thread::Builder::new()
+            .name("sentry-spotlight".into())
+            .spawn(move || sidecar.run(receiver));
+        let (sender, handle) = match handle {
+            Ok(handle) => (Some(sender), Some(handle)),
+            Err(err) => {
+                sentry_debug!("failed to spawn the Spotlight thread: {}", err);
+                (None, None)
+            }
+        };
+        SpotlightTransport {
+            inner,
+            sender: Mutex::new(sender),
+            handle: Mutex::new(handle),
+        }
+    }
+}
+
+impl Transport for SpotlightTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        if let Some(sender) = &*self.sender.lock().unwrap() {
+            if sender.try_send(Task::Send(envelope.clone())).is_err() {
+                sentry_debug!("Spotlight queue is full, dropping envelope");
+            }
+        }
+        if let Some(inner) = &self.inner {
+            inner.send_envelope(envelope);
+        }
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        let mut flushed = true;
+        if let Some(sender) = &*self.sender.lock().unwrap() {
+            let (done_tx, done_rx) = sync_channel(1);
+            flushed =
+                sender.send(Task::Flush(done_tx)).is_ok() && done_rx.recv_timeout(timeout).is_ok();
+        }
+        if let Some(inner) = &self.inner {
+            flushed &= inner.flush(timeout);
+        }
+        flushed
+    }
+
+    fn shutdown(&self, timeout: Duration) -> bool {
+        let flushed = self.flush(timeout);
+        // dropping the sender ends the thread
+        self.sender.lock().unwrap().take();
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+        match &self.inner {
+            Some(inner) => inner.shutdown(timeout) && flushed,
+            None => flushed,
+        }
+    }
+
+    fn backpressure(&self) -> Backpressure {
+        match &self.inner {
+            Some(inner) => inner.backpressure(),
+            None => Backpressure::default(),
+        }
+    }
+}
+
+/// The address of a Spotlight sidecar.
+#[derive(Debug, PartialEq)]
+struct Sidecar {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Sidecar {
+    /// Parses a plain `http` URL.
+    fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix("http://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Sidecar {
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+        })
+    }
+
+    fn run(self, receiver: Receiver<Task>) {
+        for task in receiver {
+            match task {
+                Task::Send(envelope) => {
+                    if let Err(err) = self.post(&envelope) {
+                        sentry_debug!("failed to send envelope to Spotlight: {}", err);
+                    }
+                }
+                Task::Flush(done) => {
+                    let _ = done.send(());
+                }
+            }
+        }
+    }
+
+    fn post(&self, envelope: &Envelope) -> io::Result<()> {
+        let mut body = Vec::new();
+        envelope.to_writer(&mut body)?;
+
+        // `localhost` resolves to both IPv6 and IPv4 addresses, and the
+        // sidecar might only listen on one of them
+        let mut result = Err(io::Error::new(io::ErrorKind::NotFound, "unresolved host"));
+        for addr in (self.host.as_str(), self.port).to_socket_addrs()? {
+            result = TcpStream::connect_timeout(&addr, TIMEOUT);
+            if result.is_ok() {
+                break;
+            }
+        }
+        let mut stream = result?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\n\
+             Host: {}:{}\r\n\
+             Content-Type: application/x-sentry-envelope\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n",
+            self.path,
+            self.host,
+            self.port,
+            body.len()
+        )?;
+        stream.write_all(&body)?;
+        // wait for the response, so the sidecar received the envelope before
+        // a flush returns
+        let mut status = [0; 12];
+        let _ = stream.read(&mut status)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            Sidecar::parse("http://localhost:8969/stream"),
+            Some(Sidecar {
+                host: "localhost".into(),
+                port: 8969,
+                path: "/stream".into(),
+            })
+        );
+        assert_eq!(
+            Sidecar::parse("http://spotlight"),
+            Some(Sidecar {
+                host: "spotlight".into(),
+                port: 80,
+                path: "/".into(),
+            })
+        );
+        assert_eq!(Sidecar::parse("https://localhost:8969/stream"), None);
+        assert_eq!(Sidecar::parse("http://:8969/stream"), None);
+    }
+}
@@ -0,0 +1,198 @@
+/// Sanitizes an SQL statement for use in spans and breadcrumbs.
+///
+/// String and numeric literals are replaced with `?` placeholders, comments
+/// are removed, whitespace is collapsed, and `IN` lists only consisting of
+/// placeholders are collapsed into `IN (...)`. Quoted identifiers and existing
+/// placeholders such as `$1` or `:name` are kept.
+///
+/// This makes sure that no sensitive values end up in performance data, and
+/// that statements which only differ by their parameters are grouped together.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(
+///     sentry_core::sanitize_sql(
+///         "SELECT * FROM users WHERE email = 'jane@example.com' AND id IN (1, 2, 3)"
+///     ),
+///     "SELECT * FROM users WHERE email = ? AND id IN (...)"
+/// );
+/// ```
+pub fn sanitize_sql(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // string literals, with `''` and backslash escapes
+            '\'' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '\'' if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                        }
+                        '\'' => break,
+                        _ => {}
+                    }
+                }
+                out.push('?');
+            }
+            // quoted identifiers
+            '"' | '`' => {
+                out.push(c);
+                for ident in chars.by_ref() {
+                    out.push(ident);
+                    if ident == c {
+                        break;
+                    }
+                }
+            }
+            // line comments
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                push_space(&mut out);
+            }
+            // block comments
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = None;
+                for c in chars.by_ref() {
+                    if prev == Some('*') && c == '/' {
+                        break;
+                    }
+                    prev = Some(c);
+                }
+                push_space(&mut out);
+            }
+            // numeric literals, unless part of an identifier or placeholder
+            c if c.is_ascii_digit() && !ends_with_word(&out) => {
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                out.push('?');
+            }
+            c if c.is_whitespace() => push_space(&mut out),
+            c => out.push(c),
+        }
+    }
+
+    collapse_in_lists(out.trim())
+}
+
+fn push_space(out: &mut String) {
+    if !out.is_empty() && !out.ends_with(' ') {
+        out.push(' ');
+    }
+}
+
+fn ends_with_word(out: &str) -> bool {
+    out.chars().next_back().map_or(false, |c| {
+        c.is_alphanumeric() || matches!(c, '_' | '$' | ':' | '@')
+    })
+}
+
+/// Collapses `IN (?, ?, ?)` and similar lists of placeholders into `IN (...)`.
+fn collapse_in_lists(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut rest = sql;
+
+    while let Some(pos) = find_in_keyword(rest) {
+        let (before, after) = rest.split_at(pos);
+        out.push_str(before);
+
+        let open = after.find('(').unwrap_or_default();
+        let list = &after[open + 1..];
+        match list.find(')') {
+            Some(close) if is_placeholder_list(&list[..close]) => {
+                out.push_str(&after[..open]);
+                out.push_str("(...)");
+                rest = &list[close + 1..];
+            }
+            _ => {
+                out.push_str(&after[..open + 1]);
+                rest = list;
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Finds the next `IN (` keyword, returning the position of `IN`.
+fn find_in_keyword(sql: &str) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    (0..bytes.len().saturating_sub(1)).find(|&i| {
+        bytes[i].eq_ignore_ascii_case(&b'i')
+            && bytes[i + 1].eq_ignore_ascii_case(&b'n')
+            && (i == 0 || !is_word_byte(bytes[i - 1]))
+            && sql[i + 2..].trim_start().starts_with('(')
+    })
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+fn is_placeholder_list(list: &str) -> bool {
+    let mut items = list.split(',').map(str::trim).peekable();
+    items.peek().is_some()
+        && items.all(|item| {
+            item == "?"
+                || (item.len() > 1
+                    && matches!(item.as_bytes()[0], b'$' | b':' | b'@')
+                    && item[1..].bytes().all(is_word_byte))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(
+            sanitize_sql("SELECT * FROM t WHERE a = 'it''s' AND b = 'x\\'y' AND c = 1.5e3"),
+            "SELECT * FROM t WHERE a = ? AND b = ? AND c = ?"
+        );
+        assert_eq!(
+            sanitize_sql("SELECT col1, \"col 2\" FROM t2 WHERE id = $1 LIMIT 10"),
+            "SELECT col1, \"col 2\" FROM t2 WHERE id = $1 LIMIT ?"
+        );
+    }
+
+    #[test]
+    fn test_comments_and_whitespace() {
+        assert_eq!(
+            sanitize_sql("SELECT a -- secret 42\n  FROM   t /* 'comment' */ WHERE b = 'c'"),
+            "SELECT a FROM t WHERE b = ?"
+        );
+    }
+
+    #[test]
+    fn test_in_lists() {
+        assert_eq!(
+            sanitize_sql("DELETE FROM t WHERE id IN (1, 2, 3) OR name in ('a','b')"),
+            "DELETE FROM t WHERE id IN (...) OR name in (...)"
+        );
+        assert_eq!(
+            sanitize_sql("SELECT * FROM t WHERE id IN ($1, $2) AND x IN (SELECT y FROM u)"),
+            "SELECT * FROM t WHERE id IN (...) AND x IN (SELECT y FROM u)"
+        );
+        assert_eq!(
+            sanitize_sql("SELECT min(a) FROM join_in(b)"),
+            "SELECT min(a) FROM join_in(b)"
+        );
+    }
+}
@@ -18,14 +18,38 @@
 //! assert_eq!(events.len(), 1);
 //! assert_eq!(events[0].message.as_ref().unwrap(), "Hello World!");
 //! ```
+//!
+//! Captured events can be checked concisely with the [`assert_event!`] macro:
+//!
+//! ```
+//! use sentry::test::with_captured_events;
+//!
+//! let events = with_captured_events(|| {
+//!     sentry::configure_scope(|scope| scope.set_tag("shard", 7));
+//!     sentry::capture_message("boom goes the dynamite", sentry::Level::Error);
+//! });
+//! sentry::assert_event!(events[0], level: Error, message contains "boom", tag "shard" == "7");
+//! ```
+//!
+//! To verify the real transport path end-to-end, point the DSN at a local
+//! [`TestServer`] instead.
+//!
+//! [`assert_event!`]: crate::assert_event
 
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use once_cell::sync::Lazy;
 
 use crate::protocol::Event;
-use crate::types::Dsn;
-use crate::{ClientOptions, Envelope, Hub, Transport};
+use crate::types::{Dsn, Uuid};
+use crate::{Client, ClientOptions, Envelope, Hub, Transport};
+
+mod server;
+
+pub use self::server::{TestResponse, TestServer};
 
 static TEST_DSN: Lazy<Dsn> = Lazy::new(|| "https://public@sentry.invalid/1".parse().unwrap());
 
@@ -125,12 +149,215 @@ pub fn with_captured_envelopes_options<F: FnOnce(), O: Into<ClientOptions>>(
     let mut options = options.into();
     options.dsn = Some(options.dsn.unwrap_or_else(|| TEST_DSN.clone()));
     options.transport = Some(Arc::new(transport.clone()));
+    let client: Arc<Client> = Arc::new(options.into());
     Hub::run(
-        Arc::new(Hub::new(
-            Some(Arc::new(options.into())),
-            Arc::new(Default::default()),
-        )),
+        Arc::new(Hub::new(Some(client.clone()), Arc::new(Default::default()))),
         f,
     );
+    // wait for events that are processed in the background
+    client.flush(None);
     transport.fetch_and_clear_envelopes()
 }
+
+/// The `server_name` set on events by [`deterministic`].
+pub const DETERMINISTIC_SERVER_NAME: &str = "sentry-test";
+
+/// The SDK version set on events by [`deterministic`].
+pub const DETERMINISTIC_SDK_VERSION: &str = "0.0.0";
+
+/// The timestamp set on events and breadcrumbs by [`deterministic`].
+///
+/// This is `2020-01-01T00:00:00Z`.
+pub fn deterministic_timestamp() -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(1_577_836_800)
+}
+
+/// Makes all events captured with the returned options deterministic.
+///
+/// Event IDs are numbered sequentially starting at `1`, all event and
+/// breadcrumb timestamps are set to [`deterministic_timestamp`], the
+/// `server_name` is set to [`DETERMINISTIC_SERVER_NAME`] and the SDK version
+/// to [`DETERMINISTIC_SDK_VERSION`].  This allows snapshot testing of the
+/// fully serialized event without flaky diffs.
+///
+/// The normalization runs after any `before_send` callback on the options.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::test::{deterministic, with_captured_events_options};
+/// use sentry::types::Uuid;
+///
+/// let events = with_captured_events_options(
+///     || {
+///         sentry::capture_message("Hello World!", sentry::Level::Warning);
+///     },
+///     deterministic(sentry::ClientOptions::default()),
+/// );
+/// assert_eq!(events[0].event_id, Uuid::from_u128(1));
+/// assert_eq!(events[0].server_name.as_deref(), Some("sentry-test"));
+/// ```
+pub fn deterministic<O: Into<ClientOptions>>(options: O) -> ClientOptions {
+    let mut options = options.into();
+    let before_send = options.before_send.take();
+    let sequence = AtomicU64::new(0);
+    options.before_send = Some(Arc::new(move |event| {
+        let mut event = match before_send {
+            Some(ref before_send) => before_send(event)?,
+            None => event,
+        };
+        let seq = sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        make_deterministic(&mut event, seq);
+        Some(event)
+    }));
+    options
+}
+
+fn make_deterministic(event: &mut Event<'static>, seq: u64) {
+    event.event_id = Uuid::from_u128(seq.into());
+    event.timestamp = deterministic_timestamp();
+    event.server_name = Some(DETERMINISTIC_SERVER_NAME.into());
+    for breadcrumb in event.breadcrumbs.values.iter_mut() {
+        breadcrumb.timestamp = deterministic_timestamp();
+    }
+    if let Some(sdk) = event.sdk.as_mut() {
+        let sdk = Cow::to_mut(sdk);
+        sdk.version = DETERMINISTIC_SDK_VERSION.into();
+        for package in sdk.packages.iter_mut() {
+            package.version = DETERMINISTIC_SDK_VERSION.into();
+        }
+    }
+}
+
+/// Asserts that a captured event matches a list of matchers.
+///
+/// The first argument is the event (or a reference to it), followed by a comma
+/// separated list of matchers.  The following matchers are supported:
+///
+/// - `level: <Level variant>`: the event has the given [`Level`](crate::Level).
+/// - `message == <expr>`, `message contains <expr>`: the message of the event,
+///   falling back to the formatted log entry and the value of the outermost
+///   exception, is equal to or contains the given string.
+/// - `tag <key> == <expr>`: the tag `<key>` is set to the given value.
+/// - `tag <key>`: the tag `<key>` is set.
+/// - `transaction == <expr>`: the transaction name of the event.
+/// - `exception == <expr>`: the type of the outermost exception.
+/// - `release == <expr>`, `environment == <expr>`: release and environment.
+///
+/// On failure the message of the panic names the failing matcher.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::test::with_captured_events;
+///
+/// let events = with_captured_events(|| {
+///     sentry::with_scope(
+///         |scope| scope.set_tag("shard", "7"),
+///         || sentry::capture_message("boom", sentry::Level::Error),
+///     );
+/// });
+/// sentry::assert_event!(
+///     events[0],
+///     level: Error,
+///     message == "boom",
+///     tag "shard" == "7",
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_event {
+    ($event:expr, $($matchers:tt)+) => {{
+        let event: &$crate::protocol::Event<'_> = &$event;
+        $crate::__assert_event_matchers!(event; $($matchers)+);
+    }};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __assert_event_matchers {
+    ($event:ident; $(,)?) => {};
+    ($event:ident; level: $level:ident $(, $($rest:tt)*)?) => {
+        assert_eq!($event.level, $crate::Level::$level, "event level does not match");
+        $crate::__assert_event_matchers!($event; $($($rest)*)?);
+    };
+    ($event:ident; message == $expected:expr $(, $($rest:tt)*)?) => {
+        assert_eq!(
+            $crate::test::event_message($event),
+            Some(&*$expected),
+            "event message does not match"
+        );
+        $crate::__assert_event_matchers!($event; $($($rest)*)?);
+    };
+    ($event:ident; message contains $expected:expr $(, $($rest:tt)*)?) => {
+        let message = $crate::test::event_message($event);
+        assert!(
+            message.map_or(false, |message| message.contains(&*$expected)),
+            "event message {:?} does not contain {:?}",
+            message,
+            $expected
+        );
+        $crate::__assert_event_matchers!($event; $($($rest)*)?);
+    };
+    ($event:ident; tag $key:literal == $expected:expr $(, $($rest:tt)*)?) => {
+        assert_eq!(
+            $event.tags.get($key).map(String::as_str),
+            Some(&*$expected),
+            "event tag {:?} does not match",
+            $key
+        );
+        $crate::__assert_event_matchers!($event; $($($rest)*)?);
+    };
+    ($event:ident; tag $key:literal $(, $($rest:tt)*)?) => {
+        assert!(
+            $event.tags.contains_key($key),
+            "event tag {:?} is not set",
+            $key
+        );
+        $crate::__assert_event_matchers!($event; $($($rest)*)?);
+    };
+    ($event:ident; transaction == $expected:expr $(, $($rest:tt)*)?) => {
+        assert_eq!(
+            $event.transaction.as_deref(),
+            Some(&*$expected),
+            "event transaction does not match"
+        );
+        $crate::__assert_event_matchers!($event; $($($rest)*)?);
+    };
+    ($event:ident; exception == $expected:expr $(, $($rest:tt)*)?) => {
+        assert_eq!(
+            $event.exception.last().map(|exc| exc.ty.as_str()),
+            Some(&*$expected),
+            "event exception type does not match"
+        );
+        $crate::__assert_event_matchers!($event; $($($rest)*)?);
+    };
+    ($event:ident; release == $expected:expr $(, $($rest:tt)*)?) => {
+        assert_eq!(
+            $event.release.as_deref(),
+            Some(&*$expected),
+            "event release does not match"
+        );
+        $crate::__assert_event_matchers!($event; $($($rest)*)?);
+    };
+    ($event:ident; environment == $expected:expr $(, $($rest:tt)*)?) => {
+        assert_eq!(
+            $event.environment.as_deref(),
+            Some(&*$expected),
+            "event environment does not match"
+        );
+        $crate::__assert_event_matchers!($event; $($($rest)*)?);
+    };
+}
+
+/// Returns the message of an event as used by [`assert_event!`](crate::assert_event).
+///
+/// This is the `message`, the formatted log entry, or the value of the
+/// outermost exception, in that order.
+#[doc(hidden)]
+pub fn event_message<'a>(event: &'a Event<'_>) -> Option<&'a str> {
+    event
+        .message
+        .as_deref()
+        .or_else(|| event.logentry.as_ref().map(|entry| entry.message.as_str()))
+        .or_else(|| event.exception.last().and_then(|exc| exc.value.as_deref()))
+}
@@ -0,0 +1,419 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::protocol::Event;
+use crate::types::Dsn;
+use crate::Envelope;
+
+#[derive(Default)]
+struct Received {
+    envelopes: Mutex<Vec<Envelope>>,
+    condvar: Condvar,
+    requests: AtomicUsize,
+}
+
+type Respond = dyn Fn(usize) -> TestResponse + Send + Sync;
+
+/// A response of a [`TestServer`] to a store or envelope request.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::test::{TestResponse, TestServer};
+///
+/// // fails the first request, and rate limits all further ones
+/// let server = TestServer::start_with(|request| match request {
+///     0 => TestResponse::new(503),
+///     _ => TestResponse::new(429).with_header("Retry-After", "60"),
+/// });
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl TestResponse {
+    /// Creates a response with the given status code and an empty JSON
+    /// object as body.
+    pub fn new(status: u16) -> TestResponse {
+        TestResponse {
+            status,
+            headers: Vec::new(),
+            body: "{}".into(),
+        }
+    }
+
+    /// Adds a header to the response.
+    pub fn with_header<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the body of the response.
+    pub fn with_body<B: Into<String>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+/// A tiny HTTP server that accepts Sentry store and envelope requests.
+///
+/// Unlike the [`TestTransport`](super::TestTransport), this server lets the
+/// real transport of an application send its payloads over HTTP, so the whole
+/// transport path can be verified end-to-end without a Sentry account.
+///
+/// The server listens on a random local port and runs on a background thread
+/// until it is dropped.  Requests to `/api/<project>/envelope/` and
+/// `/api/<project>/store/` are parsed and recorded; everything else is
+/// answered with `404`.  Gzip-compressed request bodies are decoded with the
+/// `compression` feature, and answered with `415` otherwise.  Recorded
+/// requests are answered with `200`, or with the responses scripted with
+/// [`TestServer::start_with`].
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use sentry::test::TestServer;
+///
+/// let server = TestServer::start();
+/// let client = sentry::init(sentry::ClientOptions {
+///     dsn: Some(server.dsn()),
+///     ..Default::default()
+/// });
+///
+/// sentry::capture_message("Hello over HTTP", sentry::Level::Info);
+/// client.flush(Some(Duration::from_secs(2)));
+///
+/// let envelopes = server.wait_for_envelopes(1, Duration::from_secs(2));
+/// assert_eq!(envelopes.len(), 1);
+/// assert_eq!(
+///     server.events()[0].message.as_deref(),
+///     Some("Hello over HTTP")
+/// );
+/// ```
+pub struct TestServer {
+    addr: SocketAddr,
+    received: Arc<Received>,
+    print: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TestServer {
+    /// Starts a new server on a random local port.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no local port can be bound.
+    pub fn start() -> TestServer {
+        TestServer::start_with(|_| TestResponse::new(200))
+    }
+
+    /// Starts a new server on a random local port, which answers store and
+    /// envelope requests with the responses of `respond`.
+    ///
+    /// `respond` is called with the number of such requests that were
+    /// received before, and may block to simulate a slow server.  The payload
+    /// is recorded before `respond` is called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no local port can be bound.
+    pub fn start_with<F>(respond: F) -> TestServer
+    where
+        F: Fn(usize) -> TestResponse + Send + Sync + 'static,
+    {
+        let respond: Arc<Respond> = Arc::new(respond);
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind test server");
+        let addr = listener.local_addr().unwrap();
+        let received = Arc::new(Received::default());
+        let print = Arc::new(AtomicBool::new(false));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let received = received.clone();
+            let print = print.clone();
+            let shutdown = shutdown.clone();
+            let respond = respond.clone();
+            thread::Builder::new()
+                .name("sentry-test-server".into())
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if shutdown.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        if let Ok(stream) = stream {
+                            let received = received.clone();
+                            let print = print.clone();
+                            let respond = respond.clone();
+                            thread::spawn(move || {
+                                let _ = handle_connection(stream, &received, &print, &*respond);
+                            });
+                        }
+                    }
+                })
+                .unwrap()
+        };
+
+        TestServer {
+            addr,
+            received,
+            print,
+            shutdown,
+            handle: Some(handle),
+        }
+    }
+
+    /// Enables pretty-printing of every received payload to stderr.
+    pub fn print_received(&self, enabled: bool) {
+        self.print.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns the address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns a DSN pointing to this server.
+    pub fn dsn(&self) -> Dsn {
+        format!("http://public@{}/1", self.addr).parse().unwrap()
+    }
+
+    /// Returns the number of store and envelope requests received so far,
+    /// including requests that were answered with an error.
+    pub fn requests(&self) -> usize {
+        self.received.requests.load(Ordering::SeqCst)
+    }
+
+    /// Returns all envelopes received so far.
+    ///
+    /// Events sent to the store endpoint are converted to envelopes.  An
+    /// envelope that was sent again, for instance after the server answered
+    /// with an error, is returned once for every time it was received.
+    pub fn envelopes(&self) -> Vec<Envelope> {
+        self.received.envelopes.lock().unwrap().clone()
+    }
+
+    /// Returns all events received so far.
+    pub fn events(&self) -> Vec<Event<'static>> {
+        self.envelopes()
+            .iter()
+            .filter_map(|envelope| envelope.event().cloned())
+            .collect()
+    }
+
+    /// Waits until at least `count` envelopes were received or `timeout`
+    /// elapsed, and returns all envelopes received so far.
+    pub fn wait_for_envelopes(&self, count: usize, timeout: Duration) -> Vec<Envelope> {
+        let deadline = Instant::now() + timeout;
+        let mut envelopes = self.received.envelopes.lock().unwrap();
+        while envelopes.len() < count {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            envelopes = self
+                .received
+                .condvar
+                .wait_timeout(envelopes, deadline - now)
+                .unwrap()
+                .0;
+        }
+        envelopes.clone()
+    }
+
+    /// Removes and returns all envelopes received so far.
+    pub fn fetch_and_clear_envelopes(&self) -> Vec<Envelope> {
+        std::mem::take(&mut *self.received.envelopes.lock().unwrap())
+    }
+}
+
+impl std::fmt::Debug for TestServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TestServer")
+            .field("addr", &self.addr)
+            .finish()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // wake up the accept loop so it notices the shutdown flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    received: &Received,
+    print: &AtomicBool,
+    respond: &Respond,
+) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line)? == 0 {
+            return Ok(());
+        }
+        let path = request_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or_default()
+            .to_owned();
+
+        let mut content_length = 0;
+        let mut chunked = false;
+        let mut gzip = false;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.parse().unwrap_or(0);
+                } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                    chunked = value.eq_ignore_ascii_case("chunked");
+                } else if name.eq_ignore_ascii_case("content-encoding") {
+                    gzip = value.eq_ignore_ascii_case("gzip");
+                }
+            }
+        }
+
+        let body = if chunked {
+            read_chunked(&mut reader)?
+        } else {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            body
+        };
+        let body = if gzip {
+            match decode_gzip(&body) {
+                Some(body) => body,
+                None => {
+                    write!(
+                        writer,
+                        "HTTP/1.1 415 Unsupported Media Type\r\nContent-Length: 0\r\n\r\n"
+                    )?;
+                    writer.flush()?;
+                    continue;
+                }
+            }
+        } else {
+            body
+        };
+
+        let envelope = if path.ends_with("/envelope/") {
+            Envelope::from_slice(&body).ok()
+        } else if path.ends_with("/store/") {
+            serde_json::from_slice::<Event<'static>>(&body)
+                .ok()
+                .map(Envelope::from)
+        } else {
+            None
+        };
+
+        let response = match envelope {
+            Some(envelope) => {
+                if print.load(Ordering::Relaxed) {
+                    print_payload(&path, &body);
+                }
+                let request = received.requests.fetch_add(1, Ordering::SeqCst);
+                received.envelopes.lock().unwrap().push(envelope);
+                received.condvar.notify_all();
+                respond(request)
+            }
+            None if path.ends_with("/envelope/") || path.ends_with("/store/") => {
+                received.requests.fetch_add(1, Ordering::SeqCst);
+                TestResponse::new(400)
+            }
+            None => TestResponse::new(404),
+        };
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+            response.status,
+            reason_phrase(response.status),
+            response.body.len()
+        )?;
+        for (name, value) in &response.headers {
+            write!(writer, "{}: {}\r\n", name, value)?;
+        }
+        write!(writer, "\r\n{}", response.body)?;
+        writer.flush()?;
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(feature = "compression")]
+fn decode_gzip(body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(body)
+        .read_to_end(&mut decoded)
+        .ok()?;
+    Some(decoded)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decode_gzip(_body: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+fn read_chunked<R: BufRead>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let mut size = String::new();
+        reader.read_line(&mut size)?;
+        let size = size.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let mut chunk = vec![0; size + 2];
+        reader.read_exact(&mut chunk)?;
+        if size == 0 {
+            return Ok(body);
+        }
+        body.extend_from_slice(&chunk[..size]);
+    }
+}
+
+fn print_payload(path: &str, body: &[u8]) {
+    eprintln!("[sentry-test-server] POST {}", path);
+    for line in body.split(|b| *b == b'\n') {
+        match serde_json::from_slice::<serde_json::Value>(line) {
+            Ok(value) => eprintln!("{}", serde_json::to_string_pretty(&value).unwrap()),
+            Err(_) if line.is_empty() => {}
+            Err(_) => eprintln!("<{} bytes of binary data>", line.len()),
+        }
+    }
+}
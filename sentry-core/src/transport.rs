@@ -1,6 +1,8 @@
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
+use crate::protocol::Value;
+use crate::types::Uuid;
 use crate::{ClientOptions, Envelope};
 
 /// The trait for transports.
@@ -8,6 +10,38 @@ use crate::{ClientOptions, Envelope};
 /// A transport is responsible for sending events to Sentry.  Custom implementations
 /// can be created to use a different abstraction to send events.  This is for instance
 /// used for the test system.
+///
+/// A transport is passed to the client via [`ClientOptions::transport`],
+/// either directly in an `Arc` or through a [`TransportFactory`].
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::mpsc::{channel, Sender};
+/// use std::sync::{Arc, Mutex};
+///
+/// use sentry::{Envelope, Transport};
+///
+/// /// Hands envelopes to another part of the application, such as the
+/// /// producer of a message queue.
+/// struct ChannelTransport(Mutex<Sender<Envelope>>);
+///
+/// impl Transport for ChannelTransport {
+///     fn send_envelope(&self, envelope: Envelope) {
+///         let _ = self.0.lock().unwrap().send(envelope);
+///     }
+/// }
+///
+/// let (sender, receiver) = channel();
+/// let _sentry = sentry::init(sentry::ClientOptions {
+///     dsn: "https://public@example.com/1".parse().ok(),
+///     transport: Some(Arc::new(Arc::new(ChannelTransport(Mutex::new(sender))))),
+///     ..Default::default()
+/// });
+///
+/// sentry::capture_message("hello", sentry::Level::Info);
+/// assert!(receiver.recv().unwrap().event().is_some());
+/// ```
 pub trait Transport: Send + Sync + 'static {
     /// Sends an [`Envelope`].
     ///
@@ -27,6 +61,153 @@ pub trait Transport: Send + Sync + 'static {
     fn shutdown(&self, timeout: Duration) -> bool {
         self.flush(timeout)
     }
+
+    /// Reports whether the transport is currently under pressure.
+    ///
+    /// Transports without a queue or rate limiting can rely on the default,
+    /// which reports no pressure at all.
+    fn backpressure(&self) -> Backpressure {
+        Backpressure::default()
+    }
+}
+
+/// The backpressure state of a [`Transport`].
+///
+/// Applications can query this via [`backpressure`](crate::backpressure) to
+/// downgrade their own capture volume during incidents, for instance by no
+/// longer recording a breadcrumb per processed item.  The built-in transports
+/// also report changes to [`ClientOptions::on_backpressure_change`].
+///
+/// [`ClientOptions::on_backpressure_change`]: crate::ClientOptions::on_backpressure_change
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Backpressure {
+    /// The transport queue is close to its capacity, so sending new envelopes
+    /// may soon block or drop them.
+    pub queue_nearly_full: bool,
+    /// The rate limits of the server for each category of payloads.
+    pub rate_limits: RateLimits,
+    /// The number of envelopes that were dropped because of rate limits,
+    /// since the transport was created.
+    pub rate_limited_envelopes: u64,
+}
+
+impl Backpressure {
+    /// Returns `true` if the transport is under any kind of pressure.
+    pub fn is_under_pressure(&self) -> bool {
+        self.queue_nearly_full || self.is_rate_limited()
+    }
+
+    /// Returns `true` if any category of payloads is currently rate limited.
+    pub fn is_rate_limited(&self) -> bool {
+        self.rate_limits.is_any_active()
+    }
+}
+
+/// The points in time until which the server rate limits each category of
+/// payloads.
+///
+/// Payloads of a rate limited category are dropped without being sent.
+/// Limits for all categories are included in each of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    /// The rate limit of error events.
+    pub errors: Option<SystemTime>,
+    /// The rate limit of sessions.
+    pub sessions: Option<SystemTime>,
+    /// The rate limit of transactions.
+    pub transactions: Option<SystemTime>,
+    /// The rate limit of attachments.
+    pub attachments: Option<SystemTime>,
+    /// The rate limit of profiles.
+    pub profiles: Option<SystemTime>,
+}
+
+impl RateLimits {
+    /// Returns `true` if any category is currently rate limited.
+    pub fn is_any_active(&self) -> bool {
+        let now = SystemTime::now();
+        [
+            self.errors,
+            self.sessions,
+            self.transactions,
+            self.attachments,
+            self.profiles,
+        ]
+        .iter()
+        .any(|until| until.map_or(false, |until| until > now))
+    }
+}
+
+/// The progress of flushing a [`Transport`].
+///
+/// This is reported to [`ClientOptions::on_flush_progress`] by the built-in
+/// transports.
+///
+/// [`ClientOptions::on_flush_progress`]: crate::ClientOptions::on_flush_progress
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlushProgress {
+    /// The number of envelopes that are queued or currently being sent.
+    pub envelopes_remaining: usize,
+    /// The serialized size of these envelopes in bytes.
+    pub bytes_remaining: usize,
+}
+
+/// The reason why the server rejected an envelope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The envelope is malformed or contains an invalid payload (`400`).
+    Invalid,
+    /// The envelope exceeds the size limits of the server (`413`).
+    TooLarge,
+    /// The envelope was rejected with another client error, for instance
+    /// because of an unknown or disabled DSN (`401`, `403`).
+    Other,
+}
+
+/// An envelope that was rejected by the server.
+///
+/// See [`ClientOptions::on_event_rejected`] for more information.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventRejection {
+    /// The id of the event in the envelope, if any.
+    pub event_id: Option<Uuid>,
+    /// The HTTP status code of the response.
+    pub status: u16,
+    /// The reason why the envelope was rejected.
+    pub reason: RejectionReason,
+    /// The explanation of the server, if it sent one.
+    pub detail: Option<String>,
+}
+
+impl EventRejection {
+    /// Creates a rejection from the response of the server.
+    ///
+    /// Returns `None` if the envelope was not rejected, which includes rate
+    /// limited and failed requests that may succeed when retried.
+    pub fn from_response(event_id: Option<Uuid>, status: u16, body: &str) -> Option<Self> {
+        let reason = match status {
+            400 => RejectionReason::Invalid,
+            413 => RejectionReason::TooLarge,
+            429 => return None,
+            _ if (400..500).contains(&status) => RejectionReason::Other,
+            _ => return None,
+        };
+        // the server explains the rejection in a JSON body, like
+        // `{"detail":"envelope exceeded size limits"}`
+        let detail = match serde_json::from_str::<Value>(body) {
+            Ok(value) => value
+                .get("detail")
+                .and_then(Value::as_str)
+                .map(String::from),
+            Err(_) => Some(body.trim().to_owned()).filter(|body| !body.is_empty()),
+        };
+        Some(EventRejection {
+            event_id,
+            status,
+            reason,
+            detail,
+        })
+    }
 }
 
 /// A factory creating transport instances.
@@ -64,6 +245,10 @@ impl<T: Transport> Transport for Arc<T> {
     fn shutdown(&self, timeout: Duration) -> bool {
         (**self).shutdown(timeout)
     }
+
+    fn backpressure(&self) -> Backpressure {
+        (**self).backpressure()
+    }
 }
 
 impl<T: Transport> TransportFactory for Arc<T> {
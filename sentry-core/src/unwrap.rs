@@ -0,0 +1,131 @@
+use std::error::Error;
+use std::panic::{self, Location};
+use std::thread;
+
+use crate::protocol::{Event, Level, Map, Value};
+use crate::types::Uuid;
+use crate::{capture_event, event_from_error};
+
+/// Extension methods for `Result` that capture the error before panicking or
+/// propagating it.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::ResultExt;
+///
+/// fn read_config() -> std::io::Result<String> {
+///     let config = std::fs::read_to_string("config.toml")
+///         .capture_err("failed to read the configuration")?;
+///     Ok(config)
+/// }
+///
+/// let port: u16 = "8080".parse().unwrap_or_capture("invalid port");
+/// ```
+pub trait ResultExt<T, E> {
+    /// Returns the contained `Ok` value, or captures the error and panics.
+    ///
+    /// This works like `expect`, with `context` as the message of the
+    /// captured event, which also records the location of the call.
+    ///
+    /// The panic does not invoke the panic hook, so it is not captured a
+    /// second time by the panic integration.
+    fn unwrap_or_capture(self, context: &str) -> T;
+
+    /// Captures the error, if any, and returns the result unchanged.
+    fn capture_err(self, context: &str) -> Self;
+}
+
+impl<T, E: Error + 'static> ResultExt<T, E> for Result<T, E> {
+    #[track_caller]
+    fn unwrap_or_capture(self, context: &str) -> T {
+        unwrap_or_capture(self, context)
+    }
+
+    #[track_caller]
+    fn capture_err(self, context: &str) -> Self {
+        if let Err(ref err) = self {
+            capture_error_with_context(err, context, Level::Error, Location::caller());
+        }
+        self
+    }
+}
+
+/// Extension methods for `Option` that capture an event before panicking.
+///
+/// See [`ResultExt`] for more documentation.
+pub trait OptionExt<T> {
+    /// Returns the contained `Some` value, or captures an event and panics.
+    fn unwrap_or_capture(self, context: &str) -> T;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    #[track_caller]
+    fn unwrap_or_capture(self, context: &str) -> T {
+        match self {
+            Some(value) => value,
+            None => {
+                let location = Location::caller();
+                capture_event(Event {
+                    message: Some(context.to_owned()),
+                    level: Level::Fatal,
+                    extra: location_extra(location),
+                    ..Default::default()
+                });
+                panic_without_hook(format!("{}: found `None`", context), location)
+            }
+        }
+    }
+}
+
+/// Returns the contained `Ok` value, or captures the error and panics.
+///
+/// See [`ResultExt::unwrap_or_capture`] for more documentation.
+#[track_caller]
+pub fn unwrap_or_capture<T, E: Error + 'static>(result: Result<T, E>, context: &str) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => {
+            let location = Location::caller();
+            capture_error_with_context(&err, context, Level::Fatal, location);
+            panic_without_hook(format!("{}: {:?}", context, err), location)
+        }
+    }
+}
+
+fn location_extra(location: &Location<'_>) -> Map<String, Value> {
+    let mut extra = Map::new();
+    extra.insert("location".into(), location.to_string().into());
+    extra
+}
+
+fn capture_error_with_context<E: Error + 'static>(
+    err: &E,
+    context: &str,
+    level: Level,
+    location: &Location<'_>,
+) -> Uuid {
+    let mut event = event_from_error(err);
+    event.message = Some(context.to_owned());
+    event.level = level;
+    event.extra = location_extra(location);
+    capture_event(event)
+}
+
+/// Flushes pending events and starts unwinding with the given message.
+fn panic_without_hook(message: String, location: &Location<'_>) -> ! {
+    with_client_impl! {{
+        crate::Hub::with_active(|hub| {
+            if let Some(client) = hub.client() {
+                client.flush(None);
+            }
+        })
+    }}
+    eprintln!(
+        "thread '{}' panicked at {}:\n{}",
+        thread::current().name().unwrap_or("<unnamed>"),
+        location,
+        message
+    );
+    panic::resume_unwind(Box::new(message))
+}
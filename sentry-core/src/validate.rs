@@ -0,0 +1,236 @@
+//! Local validation of events against known Sentry schema constraints.
+//!
+//! Relay rejects or silently trims events that violate its schema, which is
+//! hard to debug from the client side.  In debug builds with
+//! [`ClientOptions::debug`](crate::ClientOptions::debug) enabled, the client
+//! runs the serialized event through [`validate_event`] and logs every problem
+//! it finds before the event is sent.
+
+use serde_json::{Map, Value};
+
+use crate::protocol::{Breadcrumb, Event};
+
+/// Maximum length of tag keys and tag values.
+const MAX_TAG_LENGTH: usize = 200;
+/// Maximum length of the release name.
+const MAX_RELEASE_LENGTH: usize = 200;
+/// Maximum length of the environment name.
+const MAX_ENVIRONMENT_LENGTH: usize = 64;
+/// The levels accepted by Relay.
+const LEVELS: &[&str] = &["debug", "info", "warning", "error", "fatal"];
+
+/// Validates the serialized form of `event` and returns a description of
+/// every schema violation found.
+pub(crate) fn validate_event(event: &Event<'static>) -> Vec<String> {
+    let mut problems = Vec::new();
+    let value = match serde_json::to_value(event) {
+        Ok(Value::Object(value)) => value,
+        Ok(_) => {
+            problems.push("event does not serialize to an object".to_owned());
+            return problems;
+        }
+        Err(err) => {
+            problems.push(format!("event failed to serialize: {}", err));
+            return problems;
+        }
+    };
+
+    check_level(&value, "level", &mut problems);
+    check_timestamp(&value, "timestamp", &mut problems);
+    check_timestamp(&value, "start_timestamp", &mut problems);
+    if let (Some(start), Some(end)) = (
+        value.get("start_timestamp").and_then(Value::as_f64),
+        value.get("timestamp").and_then(Value::as_f64),
+    ) {
+        if start > end {
+            problems.push("`start_timestamp` is after `timestamp`".to_owned());
+        }
+    }
+
+    if let Some(tags) = value.get("tags").and_then(Value::as_object) {
+        for (key, tag_value) in tags {
+            check_tag(key, tag_value, &mut problems);
+        }
+    }
+
+    if let Some(release) = value.get("release").and_then(Value::as_str) {
+        if release.len() > MAX_RELEASE_LENGTH {
+            problems.push(format!(
+                "`release` is longer than {} characters",
+                MAX_RELEASE_LENGTH
+            ));
+        }
+        if matches!(release.trim(), "" | "." | "..")
+            || release.contains(['\n', '\r', '\t', '/', '\\'])
+        {
+            problems.push(format!(
+                "`release` {:?} is not a valid release name",
+                release
+            ));
+        }
+    }
+
+    if let Some(environment) = value.get("environment").and_then(Value::as_str) {
+        if environment.len() > MAX_ENVIRONMENT_LENGTH {
+            problems.push(format!(
+                "`environment` is longer than {} characters",
+                MAX_ENVIRONMENT_LENGTH
+            ));
+        }
+        if environment.contains(['\n', '\r', '\t', '/']) {
+            problems.push(format!(
+                "`environment` {:?} contains invalid characters",
+                environment
+            ));
+        }
+    }
+
+    let breadcrumbs = value
+        .get("breadcrumbs")
+        .and_then(|b| b.get("values"))
+        .and_then(Value::as_array);
+    for (idx, breadcrumb) in breadcrumbs.into_iter().flatten().enumerate() {
+        if let Some(breadcrumb) = breadcrumb.as_object() {
+            let mut crumb_problems = Vec::new();
+            check_level(breadcrumb, "level", &mut crumb_problems);
+            check_timestamp(breadcrumb, "timestamp", &mut crumb_problems);
+            problems.extend(
+                crumb_problems
+                    .into_iter()
+                    .map(|problem| format!("breadcrumb #{}: {}", idx, problem)),
+            );
+        }
+    }
+
+    problems
+}
+
+fn check_level(object: &Map<String, Value>, key: &str, problems: &mut Vec<String>) {
+    match object.get(key) {
+        None | Some(Value::Null) => {}
+        Some(Value::String(level)) if LEVELS.contains(&level.as_str()) => {}
+        Some(other) => problems.push(format!(
+            "`{}` must be one of {:?}, got {}",
+            key, LEVELS, other
+        )),
+    }
+}
+
+fn check_timestamp(object: &Map<String, Value>, key: &str, problems: &mut Vec<String>) {
+    match object.get(key) {
+        None | Some(Value::Null) => {}
+        Some(Value::Number(ts)) => {
+            if !ts.as_f64().map_or(false, |ts| ts.is_finite() && ts >= 0.0) {
+                problems.push(format!("`{}` is not a valid unix timestamp: {}", key, ts));
+            }
+        }
+        Some(Value::String(ts)) => {
+            // Breadcrumb timestamps are parsed with the same RFC 3339 rules the
+            // protocol uses everywhere else.
+            let mut crumb = Map::new();
+            crumb.insert("timestamp".into(), Value::String(ts.clone()));
+            if serde_json::from_value::<Breadcrumb>(Value::Object(crumb)).is_err() {
+                problems.push(format!(
+                    "`{}` is not a valid RFC 3339 timestamp: {}",
+                    key, ts
+                ));
+            }
+        }
+        Some(other) => problems.push(format!("`{}` has an invalid type: {}", key, other)),
+    }
+}
+
+fn check_tag(key: &str, value: &Value, problems: &mut Vec<String>) {
+    if key.is_empty() {
+        problems.push("tag with an empty key".to_owned());
+    } else if key.chars().count() > MAX_TAG_LENGTH {
+        problems.push(format!(
+            "tag key {:?} is longer than {} characters",
+            key, MAX_TAG_LENGTH
+        ));
+    }
+
+    match value.as_str() {
+        Some(value) => {
+            if value.chars().count() > MAX_TAG_LENGTH {
+                problems.push(format!(
+                    "value of tag {:?} is longer than {} characters",
+                    key, MAX_TAG_LENGTH
+                ));
+            }
+            if value.contains('\n') {
+                problems.push(format!("value of tag {:?} contains a newline", key));
+            }
+        }
+        None => problems.push(format!("value of tag {:?} is not a string", key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Level;
+
+    #[test]
+    fn test_valid_event() {
+        let mut event = Event::new();
+        event.tags.insert("os".into(), "linux".into());
+        event.release = Some("my-app@1.0.0".into());
+        event.environment = Some("production".into());
+        event.breadcrumbs.values.push(Breadcrumb {
+            level: Level::Warning,
+            ..Default::default()
+        });
+
+        assert!(validate_event(&event).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_tags() {
+        let mut event = Event::new();
+        event.tags.insert("".into(), "value".into());
+        event.tags.insert("k".repeat(201), "value".into());
+        event.tags.insert("multiline".into(), "a\nb".into());
+
+        let problems = validate_event(&event);
+        assert_eq!(problems.len(), 3);
+        assert!(problems.iter().any(|p| p == "tag with an empty key"));
+        assert!(problems.iter().any(|p| p.contains("longer than 200")));
+        assert!(problems
+            .iter()
+            .any(|p| p == "value of tag \"multiline\" contains a newline"));
+    }
+
+    #[test]
+    fn test_invalid_release_and_environment() {
+        let mut event = Event::new();
+        event.release = Some("feature/branch".into());
+        event.environment = Some("e".repeat(65).into());
+
+        let problems = validate_event(&event);
+        assert_eq!(
+            problems,
+            vec![
+                "`release` \"feature/branch\" is not a valid release name".to_owned(),
+                "`environment` is longer than 64 characters".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_level_and_timestamp() {
+        let mut object = Map::new();
+        object.insert("level".into(), "critical".into());
+        object.insert("timestamp".into(), "yesterday".into());
+
+        let mut problems = Vec::new();
+        check_level(&object, "level", &mut problems);
+        check_timestamp(&object, "timestamp", &mut problems);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("`level` must be one of"));
+        assert_eq!(
+            problems[1],
+            "`timestamp` is not a valid RFC 3339 timestamp: yesterday"
+        );
+    }
+}
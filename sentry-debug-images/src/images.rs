@@ -1,10 +1,15 @@
 use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
 
-use sentry_core::protocol::{DebugImage, SymbolicDebugImage};
+use once_cell::sync::Lazy;
+use sentry_core::protocol::{DebugImage, DebugMeta, SymbolicDebugImage};
 use sentry_core::types::{CodeId, DebugId, Uuid};
 
 use findshlibs::{SharedLibrary, SharedLibraryId, TargetSharedLibrary, TARGET_SUPPORTED};
 
+use crate::loader::library_generation;
+
 const UUID_SIZE: usize = 16;
 
 /// Converts an ELF object identifier into a `DebugId`.
@@ -92,3 +97,114 @@ pub fn debug_images() -> Vec<DebugImage> {
 
     images
 }
+
+/// The debug meta attached to events, along with the loader generation it
+/// was computed at.
+struct Cache {
+    meta: Arc<DebugMeta>,
+    generation: Option<u64>,
+}
+
+/// The debug meta attached to events, shared by all events until refreshed.
+static CACHE: Lazy<RwLock<Cache>> = Lazy::new(|| {
+    let generation = library_generation();
+    RwLock::new(Cache {
+        meta: Arc::new(DebugMeta {
+            images: debug_images(),
+            ..Default::default()
+        }),
+        generation,
+    })
+});
+
+/// Set by [`notify_library_loaded`] to refresh the cache lazily.
+static STALE: AtomicBool = AtomicBool::new(false);
+
+/// Returns the cached debug meta, refreshing it first if a library was loaded
+/// or unloaded since it was computed.
+pub(crate) fn cached_debug_meta() -> Arc<DebugMeta> {
+    let stale = STALE.swap(false, Ordering::AcqRel);
+    let generation = library_generation();
+    {
+        let cache = CACHE.read().unwrap_or_else(PoisonError::into_inner);
+        if !stale && cache.generation == generation {
+            return cache.meta.clone();
+        }
+    }
+    refresh_debug_images();
+    CACHE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .meta
+        .clone()
+}
+
+/// Recomputes the list of loaded libraries attached to events.
+///
+/// The list is computed once and cached. On Linux, Android and Apple
+/// platforms, libraries loaded at runtime are detected when the next event is
+/// captured. Elsewhere, libraries loaded later on, such as plugins opened with
+/// `LoadLibrary`, only appear in events captured after calling this function
+/// or [`notify_library_loaded`]. The cached list is only replaced if it
+/// actually changed.
+pub fn refresh_debug_images() {
+    // read the generation first, so that a library loaded concurrently causes
+    // another refresh
+    let generation = library_generation();
+    let images = debug_images();
+    let mut cache = CACHE.write().unwrap_or_else(PoisonError::into_inner);
+    cache.generation = generation;
+    if cache.meta.images != images {
+        cache.meta = Arc::new(DebugMeta {
+            images,
+            ..Default::default()
+        });
+    }
+}
+
+/// Notifies the integration that a shared library was loaded or unloaded.
+///
+/// This is cheap and can be called right after every `LoadLibrary`, for
+/// instance from a plugin loader. The list of loaded libraries is recomputed
+/// once, when the next event is captured. This is only needed on platforms
+/// where library loads are not detected automatically, see
+/// [`refresh_debug_images`].
+pub fn notify_library_loaded() {
+    STALE.store(true, Ordering::Release);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(unsafe_code)]
+    fn test_refreshes_on_library_load() {
+        let meta = cached_debug_meta();
+        assert!(Arc::ptr_eq(&meta, &cached_debug_meta()));
+
+        // the list did not change, so it is not replaced
+        notify_library_loaded();
+        assert!(Arc::ptr_eq(&meta, &cached_debug_meta()));
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::ffi::CString;
+
+            // libraries that are usually not linked into the test binary
+            let loaded = ["libz.so.1", "libresolv.so.2", "libanl.so.1"]
+                .iter()
+                .any(|name| {
+                    let generation = library_generation();
+                    let name = CString::new(*name).unwrap();
+                    let handle = unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW) };
+                    !handle.is_null() && library_generation() != generation
+                });
+            if loaded {
+                let refreshed = cached_debug_meta();
+                assert!(!Arc::ptr_eq(&meta, &refreshed));
+                assert!(refreshed.images.len() > meta.images.len());
+            }
+        }
+    }
+}
@@ -1,6 +1,5 @@
 use std::borrow::Cow;
 
-use once_cell::sync::Lazy;
 use sentry_core::protocol::{DebugMeta, Event};
 use sentry_core::{ClientOptions, Integration};
 
@@ -56,13 +55,9 @@ impl Integration for DebugImagesIntegration {
         mut event: Event<'static>,
         _opts: &ClientOptions,
     ) -> Option<Event<'static>> {
-        static DEBUG_META: Lazy<DebugMeta> = Lazy::new(|| DebugMeta {
-            images: crate::debug_images(),
-            ..Default::default()
-        });
-
         if event.debug_meta.is_empty() && (self.filter)(&event) {
-            event.debug_meta = Cow::Borrowed(&DEBUG_META);
+            let meta = crate::images::cached_debug_meta();
+            event.debug_meta = Cow::Owned(DebugMeta::clone(&meta));
         }
 
         Some(event)
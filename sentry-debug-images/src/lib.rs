@@ -14,6 +14,20 @@
 //!     .filter(|event| event.level >= Level::Warning);
 //! ```
 //!
+//! # Dynamically loaded libraries
+//!
+//! The list of loaded libraries is computed once and then shared by all events.
+//! On Linux, Android and Apple platforms, libraries loaded later on, for
+//! instance plugins opened with `dlopen`, are detected and picked up when the
+//! next event is captured. On other platforms, call [`notify_library_loaded`],
+//! which refreshes the list when the next event is captured, or
+//! [`refresh_debug_images`], which does so immediately.
+//!
+//! ```rust
+//! // after loading a plugin with `LoadLibrary`:
+//! sentry_debug_images::notify_library_loaded();
+//! ```
+//!
 //! [`Event`]: sentry_core::protocol::Event
 
 #![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
@@ -23,6 +37,7 @@
 
 mod images;
 mod integration;
+mod loader;
 
-pub use images::debug_images;
+pub use images::{debug_images, notify_library_loaded, refresh_debug_images};
 pub use integration::DebugImagesIntegration;
@@ -0,0 +1,75 @@
+//! Detects when the dynamic loader loads or unloads a library.
+#![allow(unsafe_code)]
+
+/// Returns a number that changes whenever a library is loaded or unloaded.
+///
+/// This is `None` on platforms where library loads can not be observed, which
+/// have to rely on [`notify_library_loaded`](crate::notify_library_loaded).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn library_generation() -> Option<u64> {
+    use std::os::raw::{c_int, c_void};
+
+    unsafe extern "C" fn callback(
+        info: *mut libc::dl_phdr_info,
+        _size: libc::size_t,
+        data: *mut c_void,
+    ) -> c_int {
+        let info = &*info;
+        *(data as *mut u64) = info.dlpi_adds.wrapping_add(info.dlpi_subs);
+        // the counters are the same for all objects, so stop after the first
+        1
+    }
+
+    let mut generation = 0u64;
+    // SAFETY: the callback only writes to `generation`, which outlives the call.
+    unsafe {
+        libc::dl_iterate_phdr(Some(callback), &mut generation as *mut u64 as *mut c_void);
+    }
+    Some(generation)
+}
+
+/// Returns a number that changes whenever a library is loaded or unloaded.
+///
+/// This is `None` on platforms where library loads can not be observed, which
+/// have to rely on [`notify_library_loaded`](crate::notify_library_loaded).
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn library_generation() -> Option<u64> {
+    use std::os::raw::c_void;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Once;
+
+    static GENERATION: AtomicU64 = AtomicU64::new(0);
+    static REGISTER: Once = Once::new();
+
+    extern "C" {
+        fn _dyld_register_func_for_add_image(func: extern "C" fn(*const c_void, isize));
+        fn _dyld_register_func_for_remove_image(func: extern "C" fn(*const c_void, isize));
+    }
+
+    extern "C" fn image_changed(_header: *const c_void, _slide: isize) {
+        GENERATION.fetch_add(1, Ordering::AcqRel);
+    }
+
+    // SAFETY: the callback can be called from any thread and only touches an
+    // atomic. The callbacks can not be unregistered, so they are only
+    // registered once.
+    REGISTER.call_once(|| unsafe {
+        _dyld_register_func_for_add_image(image_changed);
+        _dyld_register_func_for_remove_image(image_changed);
+    });
+    Some(GENERATION.load(Ordering::Acquire))
+}
+
+/// Returns a number that changes whenever a library is loaded or unloaded.
+///
+/// This is `None` on platforms where library loads can not be observed, which
+/// have to rely on [`notify_library_loaded`](crate::notify_library_loaded).
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+pub fn library_generation() -> Option<u64> {
+    None
+}
@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use sentry_core::protocol::{Breadcrumb, Context, Event, Level, Map, Value};
+use sentry_core::{ClientOptions, Integration};
+
+/// Keeps statistics of frame times, and adds them as `frames` context to
+/// events.
+///
+/// See the [crate level documentation](crate) for an example.
+#[derive(Debug)]
+pub struct FrameIntegration {
+    stats: Arc<Mutex<FrameStats>>,
+}
+
+/// Reports the frames of a loop to a [`FrameIntegration`].
+///
+/// The timer can be cloned, and all clones report to the same statistics.
+#[derive(Clone, Debug)]
+pub struct FrameTimer {
+    stats: Arc<Mutex<FrameStats>>,
+    last_tick: Arc<Mutex<Option<Instant>>>,
+}
+
+#[derive(Debug)]
+struct FrameStats {
+    slow_frame_threshold: Duration,
+    frozen_frame_threshold: Duration,
+    history: usize,
+    total_frames: u64,
+    slow_frames: u64,
+    frozen_frames: u64,
+    recent: VecDeque<Duration>,
+}
+
+impl Default for FrameIntegration {
+    fn default() -> Self {
+        let stats = FrameStats {
+            // a frame at 60 fps takes 16.7ms, so slower frames dropped at
+            // least one frame
+            slow_frame_threshold: Duration::from_micros(33_334),
+            frozen_frame_threshold: Duration::from_millis(700),
+            history: 120,
+            total_frames: 0,
+            slow_frames: 0,
+            frozen_frames: 0,
+            recent: VecDeque::new(),
+        };
+        Self {
+            stats: Arc::new(Mutex::new(stats)),
+        }
+    }
+}
+
+impl FrameIntegration {
+    /// Creates a new Frame Integration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the duration after which a frame is slow.
+    /// (defaults to 33.3ms, which drops at least one frame at 60 fps)
+    #[must_use]
+    pub fn slow_frame_threshold(self, threshold: Duration) -> Self {
+        self.stats.lock().unwrap().slow_frame_threshold = threshold;
+        self
+    }
+
+    /// Sets the duration after which a frame is frozen.
+    /// (defaults to 700ms)
+    #[must_use]
+    pub fn frozen_frame_threshold(self, threshold: Duration) -> Self {
+        self.stats.lock().unwrap().frozen_frame_threshold = threshold;
+        self
+    }
+
+    /// Sets the number of recent frames the statistics of the `frames`
+    /// context are computed from.
+    /// (defaults to `120`)
+    #[must_use]
+    pub fn history(self, frames: usize) -> Self {
+        self.stats.lock().unwrap().history = frames;
+        self
+    }
+
+    /// Returns a timer that reports frames to this integration.
+    pub fn timer(&self) -> FrameTimer {
+        FrameTimer {
+            stats: self.stats.clone(),
+            last_tick: Default::default(),
+        }
+    }
+}
+
+impl Integration for FrameIntegration {
+    fn name(&self) -> &'static str {
+        "frames"
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        let context = self.stats.lock().unwrap().context();
+        if let Some(context) = context {
+            event
+                .contexts
+                .entry("frames".into())
+                .or_insert_with(|| Context::Other(context));
+        }
+        Some(event)
+    }
+}
+
+impl FrameTimer {
+    /// Marks the end of a frame.
+    ///
+    /// The frame time is measured from the previous call, so the first call
+    /// only starts the timer.
+    pub fn tick(&self) {
+        let now = Instant::now();
+        let last_tick = self.last_tick.lock().unwrap().replace(now);
+        if let Some(last_tick) = last_tick {
+            self.record_frame(now - last_tick);
+        }
+    }
+
+    /// Records a frame with the given duration, for loops that measure their
+    /// frame times themselves.
+    pub fn record_frame(&self, duration: Duration) {
+        let level = self.stats.lock().unwrap().record(duration);
+        if let Some(level) = level {
+            let message = match level {
+                Level::Error => "frozen frame",
+                _ => "slow frame",
+            };
+            let mut breadcrumb = Breadcrumb {
+                ty: "default".into(),
+                category: Some("frame".into()),
+                level,
+                message: Some(message.into()),
+                ..Default::default()
+            };
+            breadcrumb
+                .data
+                .insert("duration_ms".into(), millis(duration).into());
+            sentry_core::add_breadcrumb(breadcrumb);
+        }
+    }
+}
+
+impl FrameStats {
+    /// Records the frame, and returns the level of the breadcrumb for slow
+    /// and frozen frames.
+    fn record(&mut self, duration: Duration) -> Option<Level> {
+        self.total_frames += 1;
+        if self.history > 0 {
+            if self.recent.len() >= self.history {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(duration);
+        }
+        if duration > self.frozen_frame_threshold {
+            self.frozen_frames += 1;
+            Some(Level::Error)
+        } else if duration > self.slow_frame_threshold {
+            self.slow_frames += 1;
+            Some(Level::Warning)
+        } else {
+            None
+        }
+    }
+
+    fn context(&self) -> Option<Map<String, Value>> {
+        if self.total_frames == 0 {
+            return None;
+        }
+        let mut context = Map::new();
+        context.insert("total_frames".into(), self.total_frames.into());
+        context.insert("slow_frames".into(), self.slow_frames.into());
+        context.insert("frozen_frames".into(), self.frozen_frames.into());
+
+        if !self.recent.is_empty() {
+            let mut recent: Vec<_> = self.recent.iter().copied().collect();
+            recent.sort_unstable();
+            let total: Duration = recent.iter().sum();
+            let p95 = recent[(recent.len() * 95 / 100).min(recent.len() - 1)];
+            context.insert("recent_frames".into(), recent.len().into());
+            context.insert(
+                "recent_avg_ms".into(),
+                millis(total / recent.len() as u32).into(),
+            );
+            context.insert("recent_p95_ms".into(), millis(p95).into());
+            context.insert(
+                "recent_max_ms".into(),
+                millis(recent[recent.len() - 1]).into(),
+            );
+        }
+        Some(context)
+    }
+}
+
+/// Returns the duration in milliseconds, rounded to microseconds.
+fn millis(duration: Duration) -> f64 {
+    duration.as_micros() as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_frame_breadcrumbs() {
+        let frames = FrameIntegration::new().history(4);
+        let timer = frames.timer();
+        let events = sentry::test::with_captured_events_options(
+            || {
+                for ms in [16, 17, 40, 16, 900, 16] {
+                    timer.record_frame(Duration::from_millis(ms));
+                }
+                sentry::capture_message("crashed", Level::Error);
+            },
+            sentry::ClientOptions::new().add_integration(frames),
+        );
+
+        let breadcrumbs: Vec<_> = events[0]
+            .breadcrumbs
+            .iter()
+            .map(|breadcrumb| (breadcrumb.level, breadcrumb.data["duration_ms"].clone()))
+            .collect();
+        assert_eq!(
+            breadcrumbs,
+            [(Level::Warning, 40.0.into()), (Level::Error, 900.0.into())]
+        );
+
+        let context = match &events[0].contexts["frames"] {
+            Context::Other(context) => context,
+            context => panic!("unexpected context: {:?}", context),
+        };
+        assert_eq!(context["total_frames"], 6);
+        assert_eq!(context["slow_frames"], 1);
+        assert_eq!(context["frozen_frames"], 1);
+        assert_eq!(context["recent_frames"], 4);
+        assert_eq!(context["recent_avg_ms"], 243.0);
+        assert_eq!(context["recent_max_ms"], 900.0);
+    }
+
+    #[test]
+    fn test_no_frames() {
+        let frames = FrameIntegration::new();
+        let timer = frames.timer();
+        let events = sentry::test::with_captured_events_options(
+            || {
+                // the first tick only starts the timer
+                timer.tick();
+                sentry::capture_message("started", Level::Info);
+            },
+            sentry::ClientOptions::new().add_integration(frames),
+        );
+        assert!(!events[0].contexts.contains_key("frames"));
+    }
+}
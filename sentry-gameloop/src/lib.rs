@@ -0,0 +1,43 @@
+//! Sentry integration for frame timings of games and interactive applications.
+//!
+//! Stutters and frozen frames often precede crashes of real-time
+//! applications, for instance when the application runs out of memory or
+//! waits on a deadlocked resource.  The [`FrameIntegration`] keeps statistics
+//! of the frame times of the main loop, which is reported through a
+//! [`FrameTimer`]:
+//!
+//! - Slow frames, which took longer than the
+//!   [`slow_frame_threshold`](FrameIntegration::slow_frame_threshold), are
+//!   recorded as `warning` breadcrumbs, and frozen frames, which took longer
+//!   than the [`frozen_frame_threshold`](FrameIntegration::frozen_frame_threshold),
+//!   as `error` breadcrumbs.
+//! - All events get a `frames` context with the number of total, slow and
+//!   frozen frames, and the average, 95th percentile and maximum of the most
+//!   recent frame times, in milliseconds.
+//!
+//! # Example
+//!
+//! ```
+//! use sentry_gameloop::FrameIntegration;
+//!
+//! let frames = FrameIntegration::new();
+//! let timer = frames.timer();
+//! let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(frames));
+//!
+//! # let mut frames_left = 3;
+//! loop {
+//!     // update and render the frame
+//! #   frames_left -= 1;
+//! #   if frames_left == 0 { break; }
+//!     timer.tick();
+//! }
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+#![deny(unsafe_code)]
+
+mod frames;
+
+pub use crate::frames::{FrameIntegration, FrameTimer};
@@ -0,0 +1,180 @@
+//! A minimal HTTP/1.1 implementation, which is just enough to accept envelopes
+//! from the transports of Sentry SDKs.
+
+use std::io::{self, BufRead, Read, Write};
+
+/// The maximum length of the request line and of each header.
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+/// The maximum number of headers of a request.
+const MAX_HEADERS: usize = 100;
+
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+pub(crate) struct Response {
+    pub status: u16,
+    pub body: String,
+    pub retry_after: Option<u64>,
+}
+
+impl Response {
+    pub fn new(status: u16, body: String) -> Self {
+        Response {
+            status,
+            body,
+            retry_after: None,
+        }
+    }
+
+    pub fn error(status: u16) -> Self {
+        Response::new(status, format!(r#"{{"detail":"{}"}}"#, reason(status)))
+    }
+
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            reason(self.status),
+            self.body.len()
+        );
+        if let Some(retry_after) = self.retry_after {
+            head.push_str(&format!("Retry-After: {}\r\n", retry_after));
+        }
+        head.push_str("\r\n");
+        writer.write_all(head.as_bytes())?;
+        writer.write_all(self.body.as_bytes())?;
+        writer.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> Result<(), u16> {
+    line.clear();
+    match reader.take(MAX_LINE_LENGTH).read_line(line) {
+        Ok(_) if line.ends_with('\n') => Ok(()),
+        _ => Err(400),
+    }
+}
+
+/// Reads a request, or returns the status code of the error response.
+///
+/// Only requests with a `Content-Length` are supported, since the transports
+/// of Sentry SDKs send envelopes as a whole.
+pub(crate) fn read_request<R: BufRead>(reader: &mut R, max_body: usize) -> Result<Request, u16> {
+    let mut line = String::new();
+    read_line(reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or(400u16)?.to_owned();
+    let path = parts.next().ok_or(400u16)?.to_owned();
+
+    let mut content_length = None;
+    let mut gzip = false;
+    let mut headers = 0;
+    loop {
+        read_line(reader, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return Err(400);
+        }
+        let (name, value) = header.split_once(':').ok_or(400u16)?;
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = Some(value.parse().map_err(|_| 400u16)?),
+            "transfer-encoding" => return Err(411),
+            "content-encoding" if value.eq_ignore_ascii_case("gzip") => gzip = true,
+            "content-encoding" if !value.eq_ignore_ascii_case("identity") => return Err(415),
+            _ => {}
+        }
+    }
+
+    let body = match content_length {
+        Some(length) if length > max_body => return Err(413),
+        Some(length) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).map_err(|_| 400u16)?;
+            body
+        }
+        None if method == "POST" => return Err(411),
+        None => Vec::new(),
+    };
+    let body = if gzip {
+        decode_gzip(&body, max_body)?
+    } else {
+        body
+    };
+
+    Ok(Request { method, path, body })
+}
+
+/// Decompresses a gzip-encoded body, which may not exceed `max_body` bytes
+/// when decompressed either.
+#[cfg(feature = "compression")]
+fn decode_gzip(body: &[u8], max_body: usize) -> Result<Vec<u8>, u16> {
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(body)
+        .take(max_body as u64 + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|_| 400u16)?;
+    if decoded.len() > max_body {
+        return Err(413);
+    }
+    Ok(decoded)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decode_gzip(_body: &[u8], _max_body: usize) -> Result<Vec<u8>, u16> {
+    Err(415)
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::read_request;
+
+    fn gzip_request(body: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut request = format!(
+            "POST /api/1/envelope/ HTTP/1.1\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+            compressed.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(&compressed);
+        request
+    }
+
+    #[test]
+    fn test_gzip_body() {
+        let request = gzip_request(b"{}\n");
+        let request = read_request(&mut &request[..], 1024).ok().unwrap();
+        assert_eq!(request.body, b"{}\n");
+
+        let request = gzip_request(&[b'x'; 2048]);
+        assert_eq!(read_request(&mut &request[..], 1024).err(), Some(413));
+    }
+}
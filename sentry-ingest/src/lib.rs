@@ -0,0 +1,541 @@
+//! Sentry integration that forwards envelopes of other local processes.
+//!
+//! Helper processes, such as crash handlers, sidecars or short-lived scripts,
+//! can send their envelopes to a long-running process instead of talking to
+//! Sentry directly. That process forwards them upstream through its own
+//! client, so they share its transport, rate limits and configuration.
+//!
+//! The [`IngestServer`] accepts envelopes over HTTP on a loopback address,
+//! which lets other Sentry SDKs use it as their DSN host, or over a unix
+//! socket. Events of forwarded envelopes are captured on the hub of the
+//! server, which applies its scope, `before_send` callback and sampling.
+//! All other items, like sessions or transactions, are sent as they are.
+//!
+//! # Example
+//!
+//! ```no_run
+//! let _sentry = sentry::init("https://key@sentry.io/42");
+//!
+//! let server = sentry_ingest::IngestServer::bind_tcp("127.0.0.1:9000").unwrap();
+//! let _handle = server.spawn().unwrap();
+//!
+//! // other processes now send their envelopes to
+//! // `http://key@127.0.0.1:9000/42`
+//! ```
+//!
+//! With the `compression` feature, gzip-compressed envelopes are accepted as
+//! well, as sent by the `compression` feature of the `sentry` crate.
+//!
+//! The server also answers health checks, so orchestrators can manage its
+//! lifecycle. `GET /health` reports whether the server is alive, and
+//! `GET /ready` whether it accepts envelopes, with the statuses of the gRPC
+//! health checking protocol: `{"status":"SERVING"}`, or a `503` response with
+//! `{"status":"NOT_SERVING"}` once the server is drained with
+//! [`IngestHandle::drain`]. With the `signals` feature,
+//! `IngestHandle::drain_on_sigterm` drains the server when the process
+//! receives `SIGTERM`, as orchestrators send it before they stop a process.
+//!
+//! A raw envelope can also be written to a unix socket, which is forwarded
+//! once the writing side of the connection is closed:
+//!
+//! ```no_run
+//! # #[cfg(unix)] {
+//! let server = sentry_ingest::IngestServer::bind_unix("/run/myapp/sentry.sock").unwrap();
+//! let _handle = server.spawn().unwrap();
+//! # }
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use sentry_core::protocol::{Envelope, EnvelopeItem};
+use sentry_core::types::Uuid;
+use sentry_core::{Hint, Hub};
+
+mod http;
+
+/// The maximum size of an envelope that the server accepts.
+pub const MAX_ENVELOPE_SIZE: usize = 20 * 1024 * 1024;
+
+/// How long the server waits for a client to send more data.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The outcome of forwarding an envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardOutcome {
+    /// The envelope was forwarded, along with the id of the event it
+    /// contained, unless there was none or it was discarded.
+    Forwarded(Option<Uuid>),
+    /// The envelope was dropped, because events are rate limited until the
+    /// given point in time.
+    RateLimited(SystemTime),
+}
+
+/// Forwards an envelope of another process through the client of `hub`.
+///
+/// An event of the envelope is captured on `hub`, along with its attachments,
+/// so the current scope, `before_send` callback and sampling of the client
+/// apply to it. All other items are sent as they are.
+///
+/// Nothing is forwarded while the transport of the client is rate limited.
+///
+/// The attachments are added to a scope that is pushed onto `hub` while the
+/// event is captured, so `hub` must not be used by other threads at the same
+/// time.  The server gives each of its connections a separate hub.
+pub fn forward_envelope(hub: &Hub, envelope: Envelope) -> ForwardOutcome {
+    if let Some(until) = hub.backpressure().rate_limits.errors {
+        if until > SystemTime::now() {
+            return ForwardOutcome::RateLimited(until);
+        }
+    }
+
+    let mut event = None;
+    let mut attachments = Vec::new();
+    let mut rest = Envelope::new();
+    let has_event = envelope.event().is_some();
+    for item in envelope.items() {
+        match item {
+            EnvelopeItem::Event(e) if event.is_none() => event = Some(e.clone()),
+            EnvelopeItem::Attachment(attachment) if has_event => {
+                attachments.push(attachment.clone())
+            }
+            item => rest.add_item(item.clone()),
+        }
+    }
+
+    if rest.items().next().is_some() {
+        if let Some(client) = hub.client() {
+            client.send_envelope(rest);
+        }
+    }
+
+    let event_id = event
+        .map(|event| {
+            hub.with_scope(
+                |scope| {
+                    for attachment in attachments {
+                        scope.add_attachment(attachment);
+                    }
+                },
+                || hub.capture_event_with_hint(event, Hint::new().forwarded()),
+            )
+        })
+        .filter(|event_id| !event_id.is_nil());
+    ForwardOutcome::Forwarded(event_id)
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+/// A connection of the server, over either TCP or a unix socket.
+trait Connection: Read + Write + Send {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// The state of a server, which is shared with the threads of its
+/// connections.
+#[derive(Default)]
+struct State {
+    stopped: AtomicBool,
+    draining: AtomicBool,
+    connections: Mutex<usize>,
+    idle: Condvar,
+}
+
+impl State {
+    fn connection_started(&self) {
+        *self.connections.lock().unwrap() += 1;
+    }
+
+    fn connection_finished(&self) {
+        let mut connections = self.connections.lock().unwrap();
+        *connections -= 1;
+        if *connections == 0 {
+            self.idle.notify_all();
+        }
+    }
+
+    /// Waits until no connections are handled anymore.
+    fn wait_idle(&self, timeout: Duration) -> bool {
+        let connections = self.connections.lock().unwrap();
+        let (connections, _) = self
+            .idle
+            .wait_timeout_while(connections, timeout, |connections| *connections > 0)
+            .unwrap();
+        *connections == 0
+    }
+}
+
+/// Marks a connection as finished once it is dropped, even if its thread
+/// panicked.
+struct ConnectionGuard(Arc<State>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.connection_finished();
+    }
+}
+
+/// A server that accepts envelopes of other local processes.
+///
+/// See the [crate documentation](crate) for more information.
+pub struct IngestServer {
+    listener: Listener,
+    hub: Option<Arc<Hub>>,
+}
+
+impl IngestServer {
+    /// Binds the server to a TCP address.
+    ///
+    /// Only loopback addresses are accepted, since the server does not
+    /// authenticate its clients.
+    pub fn bind_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let addrs: Vec<_> = addr.to_socket_addrs()?.collect();
+        if addrs.iter().any(|addr| !addr.ip().is_loopback()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the ingest server can only listen on loopback addresses",
+            ));
+        }
+        Ok(Self::new(Listener::Tcp(TcpListener::bind(&addrs[..])?)))
+    }
+
+    /// Binds the server to a unix socket at the given path.
+    ///
+    /// The socket file is removed again when the server is stopped.
+    #[cfg(unix)]
+    pub fn bind_unix<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let listener = UnixListener::bind(path)?;
+        Ok(Self::new(Listener::Unix(listener, path.to_owned())))
+    }
+
+    fn new(listener: Listener) -> Self {
+        IngestServer {
+            listener,
+            hub: None,
+        }
+    }
+
+    /// Forwards envelopes through the client of the given hub.
+    ///
+    /// By default, the hub that is current on the thread that runs or spawns
+    /// the server is used.
+    pub fn with_hub(mut self, hub: Arc<Hub>) -> Self {
+        self.hub = Some(hub);
+        self
+    }
+
+    /// Returns the address of the server, if it listens on TCP.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match self.listener {
+            Listener::Tcp(ref listener) => listener.local_addr().ok(),
+            #[cfg(unix)]
+            Listener::Unix(..) => None,
+        }
+    }
+
+    /// Runs the server on the current thread.
+    ///
+    /// Every connection is handled on a thread of its own. This only returns
+    /// if accepting connections fails.
+    pub fn run(self) -> io::Result<()> {
+        let hub = self.hub.unwrap_or_else(Hub::current);
+        serve(&self.listener, &hub, &Arc::new(State::default()))
+    }
+
+    /// Runs the server on a background thread.
+    ///
+    /// The server is stopped when the returned handle is dropped.
+    pub fn spawn(self) -> io::Result<IngestHandle> {
+        let hub = self.hub.unwrap_or_else(Hub::current);
+        let wake_addr = match self.listener {
+            Listener::Tcp(ref listener) => WakeAddr::Tcp(listener.local_addr()?),
+            #[cfg(unix)]
+            Listener::Unix(_, ref path) => WakeAddr::Unix(path.clone()),
+        };
+        let state = Arc::new(State::default());
+        let thread_state = state.clone();
+        let thread_hub = hub.clone();
+        let listener = self.listener;
+        let thread = thread::Builder::new()
+            .name("sentry-ingest".into())
+            .spawn(move || serve(&listener, &thread_hub, &thread_state))?;
+        Ok(IngestHandle {
+            hub,
+            state,
+            wake_addr,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl fmt::Debug for IngestServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("IngestServer");
+        match self.listener {
+            Listener::Tcp(ref listener) => debug.field("addr", &listener.local_addr().ok()),
+            #[cfg(unix)]
+            Listener::Unix(_, ref path) => debug.field("path", path),
+        };
+        debug.finish()
+    }
+}
+
+#[derive(Debug)]
+enum WakeAddr {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// A handle to a server that runs on a background thread.
+///
+/// Dropping the handle stops the server.
+pub struct IngestHandle {
+    hub: Arc<Hub>,
+    state: Arc<State>,
+    wake_addr: WakeAddr,
+    thread: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl IngestHandle {
+    /// Stops the server and waits for its thread to exit.
+    ///
+    /// Connections that are currently handled are not interrupted.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    /// Drains the server and stops it.
+    ///
+    /// The server first reports that it is not ready anymore, but keeps
+    /// accepting connections for `grace_period`, so the orchestrator has time
+    /// to notice and route envelopes elsewhere. Then it stops accepting
+    /// connections and waits for the connections that are currently handled.
+    /// Finally, the client of its hub is flushed, so the forwarded envelopes
+    /// are sent before the process exits. This should be called when the
+    /// orchestrator asks the process to terminate, for instance with
+    /// [`drain_on_sigterm`](Self::drain_on_sigterm).
+    ///
+    /// Returns `false` if the connections or the flush did not finish within
+    /// `timeout`, which starts once the grace period is over.
+    pub fn drain(mut self, grace_period: Duration, timeout: Duration) -> bool {
+        self.state.draining.store(true, Ordering::SeqCst);
+        thread::sleep(grace_period);
+        let deadline = Instant::now() + timeout;
+        self.stop();
+        let idle = self.state.wait_idle(timeout);
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let flushed = self
+            .hub
+            .client()
+            .map_or(true, |client| client.flush(Some(remaining)));
+        idle && flushed
+    }
+
+    /// Drains the server once the process receives `SIGTERM`.
+    ///
+    /// This spawns a thread that waits for the signal and [drains](Self::drain)
+    /// the server. Afterwards, the process is terminated like it would have
+    /// been without the handler, so destructors of other threads do not run.
+    ///
+    /// This requires the `signals` feature.
+    #[cfg(all(unix, feature = "signals"))]
+    pub fn drain_on_sigterm(self, grace_period: Duration, timeout: Duration) -> io::Result<()> {
+        use signal_hook::consts::SIGTERM;
+
+        let mut signals = signal_hook::iterator::Signals::new([SIGTERM])?;
+        thread::Builder::new()
+            .name("sentry-ingest-signals".into())
+            .spawn(move || {
+                if signals.forever().next().is_some() {
+                    self.drain(grace_period, timeout);
+                    signal_hook::low_level::emulate_default_handler(SIGTERM).ok();
+                }
+            })?;
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        let thread = match self.thread.take() {
+            Some(thread) => thread,
+            None => return,
+        };
+        self.state.stopped.store(true, Ordering::SeqCst);
+        // wake up the accept loop, so it notices the shutdown
+        let woken = match self.wake_addr {
+            WakeAddr::Tcp(addr) => TcpStream::connect(addr).is_ok(),
+            #[cfg(unix)]
+            WakeAddr::Unix(ref path) => UnixStream::connect(path).is_ok(),
+        };
+        if woken {
+            thread.join().ok();
+        }
+        #[cfg(unix)]
+        if let WakeAddr::Unix(ref path) = self.wake_addr {
+            std::fs::remove_file(path).ok();
+        }
+    }
+}
+
+impl fmt::Debug for IngestHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IngestHandle")
+            .field("wake_addr", &self.wake_addr)
+            .field("draining", &self.state.draining.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+impl Drop for IngestHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn serve(listener: &Listener, hub: &Arc<Hub>, state: &Arc<State>) -> io::Result<()> {
+    loop {
+        let accepted: io::Result<Box<dyn Connection>> = match *listener {
+            Listener::Tcp(ref listener) => listener
+                .accept()
+                .map(|(stream, _)| Box::new(stream) as Box<dyn Connection>),
+            #[cfg(unix)]
+            Listener::Unix(ref listener, _) => listener
+                .accept()
+                .map(|(stream, _)| Box::new(stream) as Box<dyn Connection>),
+        };
+        if state.stopped.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let stream = match accepted {
+            Ok(stream) => stream,
+            Err(err) if is_fatal(&err) => return Err(err),
+            Err(_) => {
+                // for instance, too many open files; try again later
+                thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+        };
+        // every connection gets its own hub, so the scopes that are pushed
+        // while forwarding do not interleave with those of other connections
+        let hub = Arc::new(Hub::new_from_top(hub));
+        state.connection_started();
+        let guard = ConnectionGuard(state.clone());
+        // if spawning fails, the guard is dropped along with the closure
+        let _ = thread::Builder::new()
+            .name("sentry-ingest-connection".into())
+            .spawn(move || {
+                let guard = guard;
+                Hub::run(hub.clone(), || {
+                    handle_connection(stream, &hub, &guard.0).ok()
+                });
+            });
+    }
+}
+
+fn is_fatal(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::InvalidInput | io::ErrorKind::PermissionDenied
+    )
+}
+
+fn handle_connection(stream: Box<dyn Connection>, hub: &Hub, state: &State) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+
+    // a raw envelope starts with its JSON headers, a request with its method
+    if reader.fill_buf()?.first() == Some(&b'{') {
+        let mut body = Vec::new();
+        reader
+            .by_ref()
+            .take(MAX_ENVELOPE_SIZE as u64 + 1)
+            .read_to_end(&mut body)?;
+        if body.len() <= MAX_ENVELOPE_SIZE {
+            if let Ok(envelope) = Envelope::from_slice(&body) {
+                forward_envelope(hub, envelope);
+            }
+        }
+        return Ok(());
+    }
+
+    let response = match http::read_request(&mut reader, MAX_ENVELOPE_SIZE) {
+        Ok(request) => handle_request(request, hub, state),
+        Err(status) => http::Response::error(status),
+    };
+    let stream = reader.get_mut();
+    response.write_to(stream)?;
+    stream.flush()
+}
+
+fn handle_request(request: http::Request, hub: &Hub, state: &State) -> http::Response {
+    let path = request.path.split('?').next().unwrap_or_default();
+    if path == "/health" || path == "/ready" {
+        if request.method != "GET" {
+            return http::Response::error(405);
+        }
+        return if path == "/ready" && state.draining.load(Ordering::SeqCst) {
+            http::Response::new(503, r#"{"status":"NOT_SERVING"}"#.into())
+        } else {
+            http::Response::new(200, r#"{"status":"SERVING"}"#.into())
+        };
+    }
+    if !path.trim_end_matches('/').ends_with("/envelope") {
+        return http::Response::error(404);
+    }
+    if request.method != "POST" {
+        return http::Response::error(405);
+    }
+    let envelope = match Envelope::from_slice(&request.body) {
+        Ok(envelope) => envelope,
+        Err(_) => return http::Response::error(400),
+    };
+
+    match forward_envelope(hub, envelope) {
+        ForwardOutcome::Forwarded(event_id) => {
+            let body = match event_id {
+                Some(event_id) => format!(r#"{{"id":"{}"}}"#, event_id),
+                None => "{}".into(),
+            };
+            http::Response::new(200, body)
+        }
+        ForwardOutcome::RateLimited(until) => {
+            let retry_after = until
+                .duration_since(SystemTime::now())
+                .map_or(0, |duration| duration.as_secs() + 1);
+            let mut response = http::Response::error(429);
+            response.retry_after = Some(retry_after);
+            response
+        }
+    }
+}
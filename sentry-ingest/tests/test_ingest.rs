@@ -0,0 +1,246 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use sentry::protocol::{Attachment, Envelope, EnvelopeItem, Event, SessionStatus};
+use sentry::types::Uuid;
+use sentry::Hub;
+use sentry_ingest::IngestServer;
+
+fn envelope_bytes(envelope: &Envelope) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    envelope.to_writer(&mut bytes).unwrap();
+    bytes
+}
+
+fn post(addr: std::net::SocketAddr, path: &str, body: &[u8]) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/x-sentry-envelope\r\nContent-Length: {}\r\n\r\n",
+        path,
+        body.len()
+    )
+    .unwrap();
+    stream.write_all(body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn test_forwards_envelopes_over_http() {
+    let event_id = Uuid::new_v4();
+    let mut envelope = Envelope::from(Event {
+        event_id,
+        message: Some("from a helper".into()),
+        ..Default::default()
+    });
+    envelope.add_item(Attachment {
+        buffer: b"core".to_vec(),
+        filename: "minidump.dmp".into(),
+        ..Default::default()
+    });
+    let body = envelope_bytes(&envelope);
+
+    let envelopes = sentry::test::with_captured_envelopes(|| {
+        sentry::configure_scope(|scope| scope.set_tag("forwarded-by", "server"));
+        let server = IngestServer::bind_tcp("127.0.0.1:0")
+            .unwrap()
+            .with_hub(Hub::current());
+        let addr = server.local_addr().unwrap();
+        let handle = server.spawn().unwrap();
+
+        let response = post(addr, "/api/42/envelope/", &body);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&format!(r#"{{"id":"{}"}}"#, event_id)));
+
+        let response = post(addr, "/api/42/store/", &body);
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        let response = post(addr, "/api/42/envelope/", b"not an envelope");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        handle.shutdown();
+    });
+
+    assert_eq!(envelopes.len(), 1);
+    let event = envelopes[0].event().unwrap();
+    assert_eq!(event.event_id, event_id);
+    assert_eq!(event.message.as_deref(), Some("from a helper"));
+    assert_eq!(event.tags["forwarded-by"], "server");
+    let attachments: Vec<_> = envelopes[0]
+        .items()
+        .filter_map(|item| match item {
+            EnvelopeItem::Attachment(attachment) => Some(attachment.filename.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(attachments, ["minidump.dmp"]);
+}
+
+fn get(stream: &mut TcpStream, path: &str) -> String {
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn test_forwards_concurrent_connections() {
+    let options = sentry::ClientOptions {
+        // keeps the scopes of the connections pushed for a while
+        before_send: Some(std::sync::Arc::new(|event| {
+            std::thread::sleep(Duration::from_millis(5));
+            Some(event)
+        })),
+        ..Default::default()
+    };
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let server = IngestServer::bind_tcp("127.0.0.1:0")
+                .unwrap()
+                .with_hub(Hub::current());
+            let addr = server.local_addr().unwrap();
+            let handle = server.spawn().unwrap();
+
+            let clients: Vec<_> = (0..16)
+                .map(|_| {
+                    std::thread::spawn(move || {
+                        let event_id = Uuid::new_v4();
+                        let mut envelope = Envelope::from(Event {
+                            event_id,
+                            ..Default::default()
+                        });
+                        envelope.add_item(Attachment {
+                            buffer: b"core".to_vec(),
+                            filename: event_id.to_string(),
+                            ..Default::default()
+                        });
+                        let response = post(addr, "/api/42/envelope/", &envelope_bytes(&envelope));
+                        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+                    })
+                })
+                .collect();
+            for client in clients {
+                client.join().unwrap();
+            }
+
+            assert!(handle.drain(Duration::ZERO, Duration::from_secs(5)));
+        },
+        options,
+    );
+
+    assert_eq!(envelopes.len(), 16);
+    for envelope in &envelopes {
+        let event_id = envelope.event().unwrap().event_id.to_string();
+        let attachments: Vec<_> = envelope
+            .items()
+            .filter_map(|item| match item {
+                EnvelopeItem::Attachment(attachment) => Some(attachment.filename.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(attachments, [event_id.as_str()]);
+    }
+}
+
+#[test]
+fn test_health_and_drain() {
+    sentry::test::with_captured_envelopes(|| {
+        let server = IngestServer::bind_tcp("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = server.spawn().unwrap();
+
+        let response = get(&mut TcpStream::connect(addr).unwrap(), "/health");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#"{"status":"SERVING"}"#));
+        let response = get(&mut TcpStream::connect(addr).unwrap(), "/ready");
+        assert!(response.ends_with(r#"{"status":"SERVING"}"#));
+        let response = post(addr, "/health", b"");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+
+        // a connection that is handled while the server drains
+        let mut in_flight = TcpStream::connect(addr).unwrap();
+        // connections are accepted in order, so this one is accepted as well
+        // once the next one was answered
+        get(&mut TcpStream::connect(addr).unwrap(), "/health");
+        let drain = std::thread::spawn(move || {
+            handle.drain(Duration::from_secs(1), Duration::from_secs(5))
+        });
+        // connections are still accepted during the grace period
+        while get(&mut TcpStream::connect(addr).unwrap(), "/ready")
+            .starts_with("HTTP/1.1 200 OK\r\n")
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let response = post(
+            addr,
+            "/api/42/envelope/",
+            &envelope_bytes(&Event::default().into()),
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        while TcpStream::connect(addr).is_ok() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let response = get(&mut in_flight, "/ready");
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.ends_with(r#"{"status":"NOT_SERVING"}"#));
+        assert!(drain.join().unwrap());
+    });
+}
+
+#[test]
+fn test_rejects_public_addresses() {
+    let err = IngestServer::bind_tcp("0.0.0.0:0").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_forwards_raw_envelopes_over_unix_sockets() {
+    use std::net::Shutdown;
+    use std::os::unix::net::UnixStream;
+
+    let path = std::env::temp_dir().join(format!("sentry-ingest-{}.sock", Uuid::new_v4()));
+    let session = sentry::protocol::SessionUpdate {
+        session_id: Uuid::new_v4(),
+        distinct_id: None,
+        sequence: None,
+        timestamp: None,
+        started: std::time::SystemTime::now(),
+        init: true,
+        duration: None,
+        status: SessionStatus::Exited,
+        errors: 0,
+        attributes: sentry::protocol::SessionAttributes {
+            release: "helper@1.0".into(),
+            environment: None,
+            ip_address: None,
+            user_agent: None,
+        },
+    };
+    let mut envelope = Envelope::new();
+    envelope.add_item(session);
+    let body = envelope_bytes(&envelope);
+
+    let envelopes = sentry::test::with_captured_envelopes(|| {
+        let handle = IngestServer::bind_unix(&path).unwrap().spawn().unwrap();
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream.write_all(&body).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        // the server closes the connection once the envelope is forwarded
+        stream.read_to_end(&mut Vec::new()).unwrap();
+
+        handle.shutdown();
+    });
+
+    assert!(!path.exists());
+    assert_eq!(envelopes.len(), 1);
+    match envelopes[0].items().next() {
+        Some(EnvelopeItem::SessionUpdate(session)) => {
+            assert_eq!(session.attributes.release, "helper@1.0");
+        }
+        item => panic!("unexpected item: {:?}", item),
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::{instrument, IoStats};
+
+/// A [`File`] which records spans and breadcrumbs for its IO.
+///
+/// See the [crate level documentation](crate) for details on what is recorded.
+#[derive(Debug)]
+pub struct InstrumentedFile {
+    inner: File,
+    stats: IoStats,
+}
+
+impl InstrumentedFile {
+    /// Opens a file in read-only mode.
+    ///
+    /// See [`File::open`].
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let description = path.display().to_string();
+        let file = instrument("file.open", &description, || File::open(path))?;
+        Ok(Self::new(file, path))
+    }
+
+    /// Opens a file in write-only mode, creating or truncating it.
+    ///
+    /// See [`File::create`].
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let description = path.display().to_string();
+        let file = instrument("file.open", &description, || File::create(path))?;
+        Ok(Self::new(file, path))
+    }
+
+    /// Wraps an already opened file.
+    ///
+    /// The `path` is used to describe the recorded spans and breadcrumbs.
+    pub fn new<P: AsRef<Path>>(file: File, path: P) -> Self {
+        let description = path.as_ref().display().to_string();
+        Self {
+            inner: file,
+            stats: IoStats::new("file", description, "opened"),
+        }
+    }
+
+    /// Returns a reference to the wrapped file.
+    pub fn get_ref(&self) -> &File {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped file.
+    ///
+    /// IO performed directly on the wrapped file is not recorded.
+    pub fn get_mut(&mut self) -> &mut File {
+        &mut self.inner
+    }
+
+    /// Unwraps the file.
+    pub fn into_inner(self) -> File {
+        self.inner
+    }
+}
+
+impl Read for InstrumentedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        let stats = &mut self.stats;
+        stats
+            .read
+            .record("file.read", &stats.description, || inner.read(buf))
+    }
+}
+
+impl Write for InstrumentedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        let stats = &mut self.stats;
+        stats
+            .written
+            .record("file.write", &stats.description, || inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Seek for InstrumentedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
@@ -0,0 +1,178 @@
+//! Adds instrumented wrappers around `std` file and network IO types.
+//!
+//! The [`InstrumentedFile`] and [`InstrumentedTcpStream`] types behave exactly like
+//! the [`File`](std::fs::File) and [`TcpStream`](std::net::TcpStream) they wrap, but
+//! additionally record the IO they perform:
+//!
+//! - Opening a file or connecting a stream creates a child span of the span that is
+//!   currently bound to the scope.  When no span is bound, no spans are recorded.
+//! - All reads of a wrapper are aggregated into a single child span, which starts with the
+//!   first read and finishes when the wrapper is dropped, with the total number of bytes
+//!   read as span data.  The same goes for writes, so reading or writing in many small
+//!   chunks does not flood the transaction with spans.
+//! - Opening a file or connecting a stream records a breadcrumb, and so does dropping the
+//!   wrapper, including the total number of bytes read and written.
+//!
+//! This gives visibility into IO hotspots inside of transactions without having to depend
+//! on `tracing`.
+//!
+//! Subprocesses can be recorded in the same way with the [`process::Command`] wrapper.
+//!
+//! # Example
+//!
+//! ```
+//! use std::io::Write;
+//!
+//! use sentry_io::InstrumentedFile;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let ctx = sentry::TransactionContext::new("write report", "task");
+//! let transaction = sentry::start_transaction(ctx);
+//! sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+//!
+//! let mut file = InstrumentedFile::create(std::env::temp_dir().join("report.txt"))?;
+//! file.write_all(b"hello world")?;
+//! drop(file);
+//!
+//! transaction.finish();
+//! # Ok(())
+//! # }
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+#![deny(unsafe_code)]
+
+use std::io;
+
+use sentry_core::protocol::{Breadcrumb, Map, SpanStatus};
+use sentry_core::Span;
+
+mod fs;
+mod net;
+pub mod process;
+
+pub use crate::fs::InstrumentedFile;
+pub use crate::net::InstrumentedTcpStream;
+
+/// Runs `f` within a child span of the currently active span, if there is one.
+fn instrument<T>(op: &str, description: &str, f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    let span = start_span(op, description);
+    let result = f();
+    if let Some(span) = span {
+        match result {
+            Ok(_) => span.set_status(SpanStatus::Ok),
+            Err(ref err) => span.set_status(span_status_from_error(err)),
+        }
+        span.finish();
+    }
+    result
+}
+
+fn start_span(op: &str, description: &str) -> Option<Span> {
+    sentry_core::configure_scope(|scope| scope.get_span())
+        .map(|parent| parent.start_child(op, description))
+}
+
+fn span_status_from_error(err: &io::Error) -> SpanStatus {
+    match err.kind() {
+        io::ErrorKind::NotFound => SpanStatus::NotFound,
+        io::ErrorKind::PermissionDenied => SpanStatus::PermissionDenied,
+        io::ErrorKind::AlreadyExists => SpanStatus::AlreadyExists,
+        io::ErrorKind::TimedOut => SpanStatus::DeadlineExceeded,
+        io::ErrorKind::Interrupted => SpanStatus::Cancelled,
+        io::ErrorKind::InvalidInput => SpanStatus::InvalidArgument,
+        _ => SpanStatus::UnknownError,
+    }
+}
+
+/// Aggregates the IO of a wrapper in one direction into a single span.
+#[derive(Debug, Default)]
+struct Transfer {
+    bytes: u64,
+    span: Option<Span>,
+}
+
+impl Transfer {
+    fn record(
+        &mut self,
+        op: &str,
+        description: &str,
+        f: impl FnOnce() -> io::Result<usize>,
+    ) -> io::Result<usize> {
+        if self.span.is_none() {
+            self.span = start_span(op, description);
+        }
+        let result = f();
+        match result {
+            Ok(bytes) => self.bytes += bytes as u64,
+            // these are retried by the caller, and do not fail the transfer
+            Err(ref err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+                ) => {}
+            Err(ref err) => {
+                if let Some(ref span) = self.span {
+                    span.set_status(span_status_from_error(err));
+                }
+            }
+        }
+        result
+    }
+
+    fn finish(&mut self) {
+        if let Some(span) = self.span.take() {
+            span.set_data("bytes", self.bytes.into());
+            if span.get_status().is_none() {
+                span.set_status(SpanStatus::Ok);
+            }
+            span.finish();
+        }
+    }
+}
+
+/// Keeps track of the IO performed by a wrapper, and finishes its spans and
+/// records a breadcrumb with the totals once it is dropped.
+#[derive(Debug)]
+struct IoStats {
+    category: &'static str,
+    description: String,
+    read: Transfer,
+    written: Transfer,
+}
+
+impl IoStats {
+    fn new(category: &'static str, description: String, message: &str) -> Self {
+        sentry_core::add_breadcrumb(|| Breadcrumb {
+            category: Some(category.into()),
+            message: Some(format!("{} {}", message, description)),
+            ..Default::default()
+        });
+        Self {
+            category,
+            description,
+            read: Transfer::default(),
+            written: Transfer::default(),
+        }
+    }
+}
+
+impl Drop for IoStats {
+    fn drop(&mut self) {
+        self.read.finish();
+        self.written.finish();
+        sentry_core::add_breadcrumb(|| {
+            let mut data = Map::new();
+            data.insert("bytes_read".into(), self.read.bytes.into());
+            data.insert("bytes_written".into(), self.written.bytes.into());
+            Breadcrumb {
+                category: Some(self.category.into()),
+                message: Some(format!("closed {}", self.description)),
+                data,
+                ..Default::default()
+            }
+        });
+    }
+}
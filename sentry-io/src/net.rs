@@ -0,0 +1,83 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+
+use crate::{instrument, IoStats};
+
+/// A [`TcpStream`] which records spans and breadcrumbs for its IO.
+///
+/// See the [crate level documentation](crate) for details on what is recorded.
+#[derive(Debug)]
+pub struct InstrumentedTcpStream {
+    inner: TcpStream,
+    stats: IoStats,
+}
+
+impl InstrumentedTcpStream {
+    /// Opens a TCP connection to a remote host.
+    ///
+    /// See [`TcpStream::connect`].
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        let description = match addrs.first() {
+            Some(addr) => addr.to_string(),
+            None => String::new(),
+        };
+        let stream = instrument("net.connect", &description, || {
+            TcpStream::connect(&addrs[..])
+        })?;
+        Ok(Self::new(stream))
+    }
+
+    /// Wraps an already connected stream.
+    pub fn new(stream: TcpStream) -> Self {
+        let description = stream
+            .peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+        Self {
+            inner: stream,
+            stats: IoStats::new("net", description, "connected to"),
+        }
+    }
+
+    /// Returns a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped stream.
+    ///
+    /// IO performed directly on the wrapped stream is not recorded.
+    pub fn get_mut(&mut self) -> &mut TcpStream {
+        &mut self.inner
+    }
+
+    /// Unwraps the stream.
+    pub fn into_inner(self) -> TcpStream {
+        self.inner
+    }
+}
+
+impl Read for InstrumentedTcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        let stats = &mut self.stats;
+        stats
+            .read
+            .record("net.read", &stats.description, || inner.read(buf))
+    }
+}
+
+impl Write for InstrumentedTcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        let stats = &mut self.stats;
+        stats
+            .written
+            .record("net.write", &stats.description, || inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -32,6 +32,19 @@
 //!     _ => LogFilter::Ignore,
 //! });
 //! ```
+//!
+//! Filters can also be composed from rules based on the level and target of
+//! records using a [`RecordFilter`](sentry_core::RecordFilter), which works
+//! the same way with all integrations:
+//!
+//! ```
+//! use sentry_core::{EventFilter, Level, RecordFilter};
+//!
+//! let filter = RecordFilter::new()
+//!     .level(Level::Warning, EventFilter::Event)
+//!     .target("hyper", EventFilter::Ignore);
+//! let logger = sentry_log::SentryLogger::new().record_filter(filter);
+//! ```
 
 #![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
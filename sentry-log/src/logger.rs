@@ -1,20 +1,13 @@
 use log::Record;
 use sentry_core::protocol::{Breadcrumb, Event};
+use sentry_core::{EventFilter, RecordFilter};
 
-use crate::converters::{breadcrumb_from_record, event_from_record, exception_from_record};
+use crate::converters::{
+    breadcrumb_from_record, convert_log_level, event_from_record, exception_from_record,
+};
 
 /// The action that Sentry should perform for a [`log::Metadata`].
-#[derive(Debug)]
-pub enum LogFilter {
-    /// Ignore the [`Record`].
-    Ignore,
-    /// Create a [`Breadcrumb`] from this [`Record`].
-    Breadcrumb,
-    /// Create a message [`Event`] from this [`Record`].
-    Event,
-    /// Create an exception [`Event`] from this [`Record`].
-    Exception,
-}
+pub type LogFilter = EventFilter;
 
 /// The type of Data Sentry should ingest for a [`log::Record`].
 #[derive(Debug)]
@@ -108,6 +101,16 @@ impl<L: log::Log> SentryLogger<L> {
         self
     }
 
+    /// Sets a [`RecordFilter`] as the filter.
+    ///
+    /// The filter classifies [`Record`]s based on their level and target.
+    #[must_use]
+    pub fn record_filter(self, filter: RecordFilter) -> Self {
+        self.filter(move |metadata| {
+            filter.filter(convert_log_level(metadata.level()), metadata.target())
+        })
+    }
+
     /// Sets a custom mapper function.
     ///
     /// The mapper is responsible for creating either breadcrumbs or events
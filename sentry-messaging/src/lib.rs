@@ -0,0 +1,261 @@
+//! Sentry integration for publish/subscribe messaging systems.
+//!
+//! This crate offers generic helpers to trace messages across services of an
+//! event-driven architecture:
+//!
+//! * On publish, [`start_publish`] starts a `queue.publish` span and injects its
+//!   distributed tracing headers into the properties of the outgoing message.
+//! * On consume, [`start_process`] continues the trace from the properties of
+//!   the incoming message, and starts a new `queue.process` transaction.
+//!   [`process_message`] does the same for a synchronous handler, binding the
+//!   transaction to the current scope while it runs.
+//!
+//! The helpers work with any type of message properties that implements the
+//! [`MessageHeaders`] trait, which is implemented for string maps out of the
+//! box. Transports without native message headers, such as ZeroMQ, can carry
+//! such a map as a separate frame of a multipart message.
+//!
+//! # NATS
+//!
+//! With the `nats` feature, the [`nats`] module provides an adapter for the
+//! [`async-nats`](https://crates.io/crates/async-nats) client.
+//!
+//! **Note**: The `nats` feature is an exception to the MSRV of 1.60.0, as no
+//! release of `async-nats` supports that Rust version. Enabling the feature
+//! requires a recent stable Rust, and it is not covered by the MSRV checks.
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//!
+//! // publishing side
+//! let mut headers = HashMap::new();
+//! let span = sentry_messaging::start_publish("orders", &mut headers);
+//! // send the message along with its `headers`…
+//! if let Some(span) = span {
+//!     span.finish();
+//! }
+//!
+//! // consuming side
+//! sentry_messaging::process_message("orders", &headers, || {
+//!     // handle the message…
+//! });
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::collections::{BTreeMap, HashMap};
+
+use sentry_core::protocol::SpanStatus;
+use sentry_core::{Span, Transaction, TransactionContext, TransactionOrSpan};
+
+#[cfg(feature = "nats")]
+pub mod nats;
+
+/// The properties of a message, which can carry distributed tracing headers.
+pub trait MessageHeaders {
+    /// Returns the value of the header with the given name.
+    fn get_header(&self, name: &str) -> Option<&str>;
+
+    /// Sets the header with the given name, replacing any previous value.
+    fn set_header(&mut self, name: &str, value: &str);
+}
+
+impl MessageHeaders for HashMap<String, String> {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.get(name).map(String::as_str)
+    }
+
+    fn set_header(&mut self, name: &str, value: &str) {
+        self.insert(name.into(), value.into());
+    }
+}
+
+impl MessageHeaders for BTreeMap<String, String> {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.get(name).map(String::as_str)
+    }
+
+    fn set_header(&mut self, name: &str, value: &str) {
+        self.insert(name.into(), value.into());
+    }
+}
+
+/// Starts a `queue.publish` span for a message sent to `destination`.
+///
+/// The span is started as a child of the span bound to the current scope, and
+/// its distributed tracing headers are injected into `headers`. Returns `None`
+/// without touching `headers` if no span is bound to the current scope.
+///
+/// The returned span should be finished once the message was published.
+pub fn start_publish<H: MessageHeaders + ?Sized>(
+    destination: &str,
+    headers: &mut H,
+) -> Option<Span> {
+    let parent = sentry_core::configure_scope(|scope| scope.get_span())?;
+    let span = parent.start_child("queue.publish", destination);
+    span.set_data("messaging.destination", destination.into());
+    for (name, value) in span.iter_headers() {
+        headers.set_header(name, &value);
+    }
+    Some(span)
+}
+
+/// Starts a `queue.process` transaction for a message received from `destination`.
+///
+/// The transaction continues the trace from the distributed tracing headers
+/// found in `headers`. It is not bound to the current scope.
+pub fn start_process<H: MessageHeaders + ?Sized>(destination: &str, headers: &H) -> Transaction {
+    let trace_headers = headers
+        .get_header("sentry-trace")
+        .map(|value| ("sentry-trace", value));
+    let ctx =
+        TransactionContext::continue_from_headers(destination, "queue.process", trace_headers);
+    let transaction = sentry_core::start_transaction(ctx);
+    transaction.set_data("messaging.destination", destination.into());
+    transaction
+}
+
+/// Processes a message received from `destination` within a `queue.process` transaction.
+///
+/// The transaction is started using [`start_process`], and bound to the
+/// current scope while `f` runs. It is finished once `f` returns, or with an
+/// `internal_error` status if `f` panics.
+pub fn process_message<H, F, R>(destination: &str, headers: &H, f: F) -> R
+where
+    H: MessageHeaders + ?Sized,
+    F: FnOnce() -> R,
+{
+    let transaction = start_process(destination, headers);
+    let parent_span = sentry_core::configure_scope(|scope| {
+        let parent_span = scope.get_span();
+        scope.set_span(Some(transaction.clone().into()));
+        parent_span
+    });
+    let _guard = ProcessGuard {
+        transaction,
+        parent_span,
+    };
+
+    f()
+}
+
+/// Finishes the transaction of [`process_message`] and restores the previous
+/// span of the scope, even if the handler panics.
+struct ProcessGuard {
+    transaction: Transaction,
+    parent_span: Option<TransactionOrSpan>,
+}
+
+impl Drop for ProcessGuard {
+    fn drop(&mut self) {
+        if std::thread::panicking() && self.transaction.get_status().is_none() {
+            self.transaction.set_status(SpanStatus::InternalError);
+        }
+        self.transaction.clone().finish();
+        let parent_span = self.parent_span.take();
+        sentry_core::configure_scope(|scope| scope.set_span(parent_span));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_and_process() {
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let mut headers = HashMap::new();
+                assert!(start_publish("orders", &mut headers).is_none());
+                assert!(headers.is_empty());
+
+                let transaction =
+                    sentry::start_transaction(sentry::TransactionContext::new("publisher", "test"));
+                sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+                let span = start_publish("orders", &mut headers).unwrap();
+                span.finish();
+                transaction.finish();
+                sentry::configure_scope(|scope| scope.set_span(None));
+
+                let ran = process_message("orders", &headers, || {
+                    assert!(sentry::configure_scope(|scope| scope.get_span()).is_some());
+                    true
+                });
+                assert!(ran);
+                assert!(sentry::configure_scope(|scope| scope.get_span()).is_none());
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(envelopes.len(), 2);
+        let transactions: Vec<_> = envelopes
+            .iter()
+            .map(|envelope| match envelope.items().next().unwrap() {
+                sentry::protocol::EnvelopeItem::Transaction(transaction) => transaction,
+                _ => panic!("expected a transaction"),
+            })
+            .collect();
+        let publisher = transactions[0];
+        let consumer = transactions[1];
+
+        assert_eq!(publisher.spans.len(), 1);
+        let publish_span = &publisher.spans[0];
+        assert_eq!(publish_span.op.as_deref(), Some("queue.publish"));
+
+        let trace = match consumer.contexts.get("trace") {
+            Some(sentry::protocol::Context::Trace(trace)) => trace,
+            _ => panic!("expected a trace context"),
+        };
+        assert_eq!(trace.op.as_deref(), Some("queue.process"));
+        assert_eq!(trace.trace_id, publish_span.trace_id);
+        assert_eq!(trace.parent_span_id, Some(publish_span.span_id));
+    }
+
+    #[test]
+    fn test_process_panic() {
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let transaction =
+                    sentry::start_transaction(sentry::TransactionContext::new("outer", "test"));
+                sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+
+                let result = std::panic::catch_unwind(|| {
+                    process_message("orders", &HashMap::new(), || panic!("handler failed"))
+                });
+                assert!(result.is_err());
+
+                let span = sentry::configure_scope(|scope| scope.get_span()).unwrap();
+                assert_eq!(
+                    span.iter_headers().collect::<Vec<_>>(),
+                    transaction.iter_headers().collect::<Vec<_>>()
+                );
+                transaction.finish();
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(envelopes.len(), 2);
+        let trace = match envelopes[0].items().next().unwrap() {
+            sentry::protocol::EnvelopeItem::Transaction(transaction) => {
+                match transaction.contexts.get("trace") {
+                    Some(sentry::protocol::Context::Trace(trace)) => trace.clone(),
+                    _ => panic!("expected a trace context"),
+                }
+            }
+            _ => panic!("expected a transaction"),
+        };
+        assert_eq!(trace.op.as_deref(), Some("queue.process"));
+        assert_eq!(
+            trace.status,
+            Some(sentry::protocol::SpanStatus::InternalError)
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Adapter for the [`async-nats`](https://crates.io/crates/async-nats) client.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn example(client: async_nats::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! use futures::StreamExt;
+//!
+//! sentry_messaging::nats::publish(&client, "orders", "payload".into()).await?;
+//!
+//! let mut subscriber = client.subscribe("orders").await?;
+//! while let Some(message) = subscriber.next().await {
+//!     let transaction = sentry_messaging::nats::start_process(&message);
+//!     // handle the message…
+//!     transaction.finish();
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use async_nats::header::HeaderMap;
+use async_nats::{Client, Message, PublishError};
+use bytes::Bytes;
+use sentry_core::protocol::SpanStatus;
+use sentry_core::Transaction;
+
+use crate::MessageHeaders;
+
+impl MessageHeaders for HeaderMap {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.get(name).map(|value| value.as_str())
+    }
+
+    fn set_header(&mut self, name: &str, value: &str) {
+        self.insert(name, value);
+    }
+}
+
+/// Publishes a message, carrying the distributed tracing headers of a `queue.publish` span.
+///
+/// See [`start_publish`](crate::start_publish) for details.
+pub async fn publish(client: &Client, subject: &str, payload: Bytes) -> Result<(), PublishError> {
+    let mut headers = HeaderMap::new();
+    let span = crate::start_publish(subject, &mut headers);
+
+    let result = client
+        .publish_with_headers(subject.to_owned(), headers, payload)
+        .await;
+
+    if let Some(span) = span {
+        span.set_status(match result {
+            Ok(_) => SpanStatus::Ok,
+            Err(_) => SpanStatus::InternalError,
+        });
+        span.finish();
+    }
+    result
+}
+
+/// Starts a `queue.process` transaction for a received message.
+///
+/// See [`start_process`](crate::start_process) for details.
+pub fn start_process(message: &Message) -> Transaction {
+    match message.headers {
+        Some(ref headers) => crate::start_process(&message.subject, headers),
+        None => crate::start_process(&message.subject, &HeaderMap::new()),
+    }
+}
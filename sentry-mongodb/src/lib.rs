@@ -0,0 +1,224 @@
+//! Sentry integration for the [MongoDB](https://crates.io/crates/mongodb) driver.
+//!
+//! The [`SentryCommandEventHandler`] listens to the command monitoring events
+//! of the driver. For each command, it records a breadcrumb with the database,
+//! collection and command name, and when a span is bound to the current scope,
+//! it also records a `db.mongodb` child span.
+//!
+//! The command documents and replies are never recorded, as they can contain
+//! sensitive data.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! let mut options = mongodb::options::ClientOptions::default();
+//! options.command_event_handler = Some(Arc::new(sentry_mongodb::SentryCommandEventHandler::new()));
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use mongodb::bson::Document;
+use mongodb::event::command::{
+    CommandEventHandler, CommandFailedEvent, CommandStartedEvent, CommandSucceededEvent,
+};
+use sentry_core::protocol::{Breadcrumb, Level, Map, SpanStatus, Value};
+
+/// A running command, waiting for its completion event.
+struct PendingCommand {
+    span: Option<sentry_core::Span>,
+    data: Map<String, Value>,
+}
+
+/// A MongoDB command event handler that records breadcrumbs and spans.
+///
+/// Set it as the `command_event_handler` of the client options. Started and
+/// completed commands are correlated using their request id.
+#[derive(Default)]
+pub struct SentryCommandEventHandler {
+    pending: Mutex<HashMap<i32, PendingCommand>>,
+}
+
+impl std::fmt::Debug for SentryCommandEventHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SentryCommandEventHandler").finish()
+    }
+}
+
+impl SentryCommandEventHandler {
+    /// Creates a new command event handler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn command_started(
+        &self,
+        request_id: i32,
+        db: &str,
+        command_name: &str,
+        command: &Document,
+        address: String,
+    ) {
+        // By convention, the first element of a command document is the
+        // command name, with the collection name as its value.
+        let collection = command
+            .iter()
+            .next()
+            .and_then(|(_, value)| value.as_str())
+            .map(str::to_owned);
+
+        let mut data: Map<String, Value> = Map::new();
+        data.insert("db.system".into(), "mongodb".into());
+        data.insert("db.name".into(), db.into());
+        data.insert("db.operation".into(), command_name.into());
+        if let Some(ref collection) = collection {
+            data.insert("db.mongodb.collection".into(), collection.as_str().into());
+        }
+        data.insert("server.address".into(), address.into());
+
+        let span = sentry_core::configure_scope(|scope| scope.get_span()).map(|parent| {
+            let description = match collection {
+                Some(collection) => format!("{} {}", command_name, collection),
+                None => command_name.to_owned(),
+            };
+            let span = parent.start_child("db.mongodb", &description);
+            for (key, value) in &data {
+                span.set_data(key, value.clone());
+            }
+            span
+        });
+
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(request_id, PendingCommand { span, data });
+    }
+
+    fn command_finished(&self, request_id: i32, duration: Duration, error: Option<String>) {
+        let pending = match self.pending.lock().unwrap().remove(&request_id) {
+            Some(pending) => pending,
+            None => return,
+        };
+        let PendingCommand { span, mut data } = pending;
+
+        if let Some(span) = span {
+            span.set_status(match error {
+                Some(_) => SpanStatus::InternalError,
+                None => SpanStatus::Ok,
+            });
+            span.finish();
+        }
+
+        data.insert("duration_ms".into(), (duration.as_millis() as u64).into());
+        let level = match error {
+            Some(error) => {
+                data.insert("error".into(), error.into());
+                Level::Error
+            }
+            None => Level::Info,
+        };
+        sentry_core::add_breadcrumb(Breadcrumb {
+            ty: "query".into(),
+            category: Some("db.mongodb".into()),
+            level,
+            data,
+            ..Default::default()
+        });
+    }
+}
+
+impl CommandEventHandler for SentryCommandEventHandler {
+    fn handle_command_started_event(&self, event: CommandStartedEvent) {
+        self.command_started(
+            event.request_id,
+            &event.db,
+            &event.command_name,
+            &event.command,
+            event.connection.address.to_string(),
+        );
+    }
+
+    fn handle_command_succeeded_event(&self, event: CommandSucceededEvent) {
+        self.command_finished(event.request_id, event.duration, None);
+    }
+
+    fn handle_command_failed_event(&self, event: CommandFailedEvent) {
+        self.command_finished(
+            event.request_id,
+            event.duration,
+            Some(event.failure.to_string()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::doc;
+
+    use super::*;
+
+    #[test]
+    fn test_breadcrumbs_and_spans() {
+        let handler = SentryCommandEventHandler::new();
+        let command = doc! { "find": "users", "filter": { "email": "secret@example.com" } };
+
+        let events = sentry::test::with_captured_events(|| {
+            handler.command_started(1, "app", "find", &command, "localhost:27017".into());
+            handler.command_finished(1, Duration::from_millis(3), None);
+            handler.command_started(2, "app", "find", &command, "localhost:27017".into());
+            handler.command_finished(2, Duration::from_millis(3), Some("failed".into()));
+            sentry::capture_message("after commands", sentry::Level::Info);
+        });
+        assert_eq!(events.len(), 1);
+        let breadcrumbs = &events[0].breadcrumbs;
+        assert_eq!(breadcrumbs.len(), 2);
+        assert_eq!(breadcrumbs[0].category.as_deref(), Some("db.mongodb"));
+        assert_eq!(breadcrumbs[0].data["db.operation"], "find");
+        assert_eq!(breadcrumbs[0].data["db.mongodb.collection"], "users");
+        assert_eq!(breadcrumbs[1].level, Level::Error);
+        let serialized = serde_json_string(&events[0]);
+        assert!(!serialized.contains("secret@example.com"));
+
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let transaction =
+                    sentry::start_transaction(sentry::TransactionContext::new("test", "test"));
+                sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+                handler.command_started(3, "app", "find", &command, "localhost:27017".into());
+                handler.command_finished(3, Duration::from_millis(3), None);
+                transaction.finish();
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(envelopes.len(), 1);
+        let transaction = match envelopes[0].items().next().unwrap() {
+            sentry::protocol::EnvelopeItem::Transaction(transaction) => transaction,
+            _ => panic!("expected a transaction"),
+        };
+        assert_eq!(transaction.spans.len(), 1);
+        assert_eq!(transaction.spans[0].op.as_deref(), Some("db.mongodb"));
+        assert_eq!(
+            transaction.spans[0].description.as_deref(),
+            Some("find users")
+        );
+        assert_eq!(transaction.spans[0].status, Some(SpanStatus::Ok));
+    }
+
+    fn serde_json_string(event: &sentry::protocol::Event<'static>) -> String {
+        let mut buf = Vec::new();
+        sentry::Envelope::from(event.clone())
+            .to_writer(&mut buf)
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+}
@@ -13,6 +13,16 @@
 //! ```
 //! let integration = sentry_panic::PanicIntegration::default().add_extractor(|info| None);
 //! ```
+//!
+//! A filter decides how a panic is reported, for instance as a breadcrumb
+//! only:
+//!
+//! ```
+//! use sentry_core::EventFilter;
+//!
+//! let integration =
+//!     sentry_panic::PanicIntegration::default().filter(|info| EventFilter::Breadcrumb);
+//! ```
 
 #![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
@@ -22,9 +32,9 @@
 use std::panic::{self, PanicInfo};
 use std::sync::Once;
 
-use sentry_backtrace::current_stacktrace;
-use sentry_core::protocol::{Event, Exception, Level, Mechanism};
-use sentry_core::{ClientOptions, Integration};
+use sentry_backtrace::{current_stacktrace, current_thread};
+use sentry_core::protocol::{Breadcrumb, Context, Event, Exception, Level, Map, Mechanism, Value};
+use sentry_core::{ClientOptions, EventFilter, Hint, Integration, PanicHint};
 
 /// A panic handler that sends to Sentry.
 ///
@@ -33,7 +43,36 @@ use sentry_core::{ClientOptions, Integration};
 /// Sentry panic handler.
 pub fn panic_handler(info: &PanicInfo<'_>) {
     sentry_core::with_integration(|integration: &PanicIntegration, hub| {
-        hub.capture_event(integration.event_from_panic_info(info));
+        let filter = match integration.filter {
+            Some(ref filter) => filter(info),
+            None => EventFilter::Exception,
+        };
+        let event = match filter {
+            EventFilter::Ignore => return,
+            EventFilter::Breadcrumb => {
+                hub.add_breadcrumb(Breadcrumb {
+                    ty: "error".into(),
+                    category: Some("panic".into()),
+                    level: Level::Fatal,
+                    message: Some(message_from_panic_info(info).to_owned()),
+                    ..Default::default()
+                });
+                return;
+            }
+            EventFilter::Event => {
+                let mut event = Event {
+                    message: Some(message_from_panic_info(info).to_owned()),
+                    level: Level::Fatal,
+                    ..Default::default()
+                };
+                event
+                    .contexts
+                    .insert("panic".into(), Context::Other(panic_data(info)));
+                event
+            }
+            EventFilter::Exception => integration.event_from_panic_info(info),
+        };
+        hub.capture_event_with_hint(event, hint_from_panic_info(info));
         if let Some(client) = hub.client() {
             client.flush(None);
         }
@@ -41,17 +80,21 @@ pub fn panic_handler(info: &PanicInfo<'_>) {
 }
 
 type PanicExtractor = dyn Fn(&PanicInfo<'_>) -> Option<Event<'static>> + Send + Sync;
+#[allow(deprecated)]
+type PanicFilter = dyn Fn(&PanicInfo<'_>) -> EventFilter + Send + Sync;
 
 /// The Sentry Panic handler Integration.
 #[derive(Default)]
 pub struct PanicIntegration {
     extractors: Vec<Box<PanicExtractor>>,
+    filter: Option<Box<PanicFilter>>,
 }
 
 impl std::fmt::Debug for PanicIntegration {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PanicIntegration")
             .field("extractors", &self.extractors.len())
+            .field("filter", &self.filter.is_some())
             .finish()
     }
 }
@@ -85,6 +128,52 @@ pub fn message_from_panic_info<'a>(info: &'a PanicInfo<'_>) -> &'a str {
     }
 }
 
+/// The messages of the panics that the runtime raises when a panic occurs
+/// while a thread is already unwinding, or escapes a function that cannot
+/// unwind.  These panics abort the process.
+const NESTED_PANIC_MESSAGES: &[&str] = &[
+    "panic in a destructor during cleanup",
+    "panic in a function that cannot unwind",
+];
+
+/// Returns structured information about a panic.
+///
+/// This contains the location of the panic, the thread it occurred on, and
+/// whether it is a nested panic or one that aborts the process.  It is added
+/// as the data of the `panic` mechanism of panic events.
+#[allow(deprecated)]
+pub fn panic_data(info: &PanicInfo<'_>) -> Map<String, Value> {
+    let mut data = Map::new();
+    if let Some(location) = info.location() {
+        data.insert("file".into(), location.file().into());
+        data.insert("line".into(), location.line().into());
+        data.insert("column".into(), location.column().into());
+    }
+    let thread = current_thread(false);
+    if let Some(id) = thread.id {
+        data.insert("thread_id".into(), id.to_string().into());
+    }
+    if let Some(name) = thread.name {
+        data.insert("thread_name".into(), name.into());
+    }
+    let nested = NESTED_PANIC_MESSAGES.contains(&message_from_panic_info(info));
+    data.insert("nested".into(), nested.into());
+    data.insert("abort".into(), (nested || cfg!(panic = "abort")).into());
+    data
+}
+
+/// Creates the [`Hint`] of the event of a panic.
+#[allow(deprecated)]
+pub fn hint_from_panic_info(info: &PanicInfo<'_>) -> Hint {
+    let location = info.location();
+    Hint::new().with_integration("panic").with_panic(PanicHint {
+        message: message_from_panic_info(info).to_owned(),
+        file: location.map(|location| location.file().to_owned()),
+        line: location.map(|location| location.line()),
+        column: location.map(|location| location.column()),
+    })
+}
+
 impl PanicIntegration {
     /// Creates a new Panic Integration.
     pub fn new() -> Self {
@@ -101,6 +190,22 @@ impl PanicIntegration {
         self
     }
 
+    /// Sets a filter that decides how panics are reported.
+    ///
+    /// By default, every panic is captured as an exception event.  With
+    /// [`EventFilter::Event`] a panic is captured as a message event without
+    /// stacktrace, and with [`EventFilter::Breadcrumb`] it is only recorded as
+    /// a breadcrumb.
+    #[must_use]
+    #[allow(deprecated)]
+    pub fn filter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&PanicInfo<'_>) -> EventFilter + Send + Sync + 'static,
+    {
+        self.filter = Some(Box::new(f));
+        self
+    }
+
     /// Creates an event from the given panic info.
     ///
     /// The stacktrace is calculated from the current frame.
@@ -122,6 +227,7 @@ impl PanicIntegration {
                 mechanism: Some(Mechanism {
                     ty: "panic".into(),
                     handled: Some(false),
+                    data: panic_data(info),
                     ..Default::default()
                 }),
                 value: Some(msg.to_string()),
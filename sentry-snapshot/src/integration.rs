@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use sentry_core::protocol::{Breadcrumb, User};
+use sentry_core::{ClientOptions, Hub, Integration, ScopeObserver};
+
+use crate::ScopeSnapshot;
+
+/// The default interval at which snapshots are written.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The Sentry Scope Snapshot Integration.
+///
+/// See the [crate level documentation](crate) for details.
+#[derive(Debug)]
+pub struct ScopeSnapshotIntegration {
+    path: PathBuf,
+    interval: Duration,
+    previous: Option<ScopeSnapshot>,
+    state: Arc<SnapshotState>,
+}
+
+impl ScopeSnapshotIntegration {
+    /// Creates a new integration that persists snapshots to `path`.
+    ///
+    /// The snapshot left behind by the previous run is loaded right away, so
+    /// it is not overwritten by this run.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let previous = ScopeSnapshot::load(&path).ok();
+        Self {
+            path,
+            interval: DEFAULT_INTERVAL,
+            previous,
+            state: Arc::new(SnapshotState::default()),
+        }
+    }
+
+    /// Sets the interval at which snapshots are written. (defaults to 5 seconds)
+    ///
+    /// A snapshot is only written if the scope changed since the last one.
+    #[must_use]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The path snapshots are written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the snapshot written by the previous run, if there was one.
+    pub fn previous_snapshot(&self) -> Option<&ScopeSnapshot> {
+        self.previous.as_ref()
+    }
+
+    /// Writes the current snapshot immediately, even if it did not change.
+    pub fn persist(&self) -> std::io::Result<()> {
+        self.state.persist(&self.path)
+    }
+}
+
+impl Integration for ScopeSnapshotIntegration {
+    fn name(&self) -> &'static str {
+        "scope-snapshot"
+    }
+
+    fn setup(&self, options: &mut ClientOptions) {
+        self.state
+            .max_breadcrumbs
+            .store(options.max_breadcrumbs, Ordering::Relaxed);
+        if self.state.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let observer = SnapshotObserver(self.state.clone());
+        Hub::with(|hub| hub.configure_scope(|scope| scope.add_observer(observer)));
+
+        let state = Arc::downgrade(&self.state);
+        let path = self.path.clone();
+        let interval = self.interval;
+        thread::Builder::new()
+            .name("sentry-scope-snapshot".into())
+            .spawn(move || write_periodically(state, path, interval))
+            .ok();
+    }
+}
+
+/// Writes the snapshot whenever it changed, until the integration is gone.
+fn write_periodically(state: Weak<SnapshotState>, path: PathBuf, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        let state = match state.upgrade() {
+            Some(state) => state,
+            None => return,
+        };
+        if state.dirty.swap(false, Ordering::SeqCst) {
+            // retry failed writes on the next tick
+            if state.persist(&path).is_err() {
+                state.dirty.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SnapshotState {
+    snapshot: Mutex<ScopeSnapshot>,
+    dirty: AtomicBool,
+    started: AtomicBool,
+    max_breadcrumbs: AtomicUsize,
+}
+
+impl SnapshotState {
+    fn update(&self, f: impl FnOnce(&mut ScopeSnapshot)) {
+        f(&mut self.snapshot.lock().unwrap());
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    fn persist(&self, path: &Path) -> std::io::Result<()> {
+        let mut snapshot = self.snapshot.lock().unwrap().clone();
+        snapshot.timestamp = Some(SystemTime::now());
+        snapshot.store(path)
+    }
+}
+
+/// Mirrors scope changes into the snapshot.
+struct SnapshotObserver(Arc<SnapshotState>);
+
+impl ScopeObserver for SnapshotObserver {
+    fn set_tag(&self, key: &str, value: &str) {
+        self.0.update(|snapshot| {
+            snapshot.tags.insert(key.to_owned(), value.to_owned());
+        });
+    }
+
+    fn remove_tag(&self, key: &str) {
+        self.0.update(|snapshot| {
+            snapshot.tags.remove(key);
+        });
+    }
+
+    fn set_user(&self, user: Option<&User>) {
+        self.0.update(|snapshot| snapshot.user = user.cloned());
+    }
+
+    fn add_breadcrumb(&self, breadcrumb: &Breadcrumb) {
+        let max_breadcrumbs = self.0.max_breadcrumbs.load(Ordering::Relaxed);
+        self.0.update(|snapshot| {
+            snapshot.breadcrumbs.push_back(breadcrumb.clone());
+            while snapshot.breadcrumbs.len() > max_breadcrumbs {
+                snapshot.breadcrumbs.pop_front();
+            }
+        });
+    }
+
+    fn clear_breadcrumbs(&self) {
+        self.0.update(|snapshot| snapshot.breadcrumbs.clear());
+    }
+}
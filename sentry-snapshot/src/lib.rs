@@ -0,0 +1,45 @@
+//! Persists a snapshot of the scope to disk, to enrich crashes reported on the next run.
+//!
+//! Crashes that are captured outside of the SDK, such as minidumps written by a
+//! native crash handler or events written from a signal handler, are usually only
+//! uploaded when the application starts up the next time.  At that point the
+//! breadcrumbs, tags and user of the crashed run are long gone.
+//!
+//! The [`ScopeSnapshotIntegration`] mirrors these parts of the scope of the hub
+//! that Sentry is initialized on into a compact [`ScopeSnapshot`], which is written to a file at a configurable
+//! interval whenever it changed.  On the next run, the integration loads the snapshot
+//! of the previous run before overwriting it, and
+//! [`ScopeSnapshot::apply_to_event`] enriches the crash events with its state.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use sentry_snapshot::ScopeSnapshotIntegration;
+//!
+//! let path = std::env::temp_dir().join("sentry-scope-snapshot.json");
+//! let integration = ScopeSnapshotIntegration::new(&path).interval(Duration::from_secs(1));
+//!
+//! // a crash of the previous run that is uploaded now
+//! let mut crash = sentry::protocol::Event::default();
+//! if let Some(snapshot) = integration.previous_snapshot() {
+//!     snapshot.apply_to_event(&mut crash);
+//! }
+//!
+//! let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(integration));
+//! # sentry::with_integration(|integration: &ScopeSnapshotIntegration, _| {
+//! #     integration.persist().unwrap();
+//! # });
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+#![deny(unsafe_code)]
+
+mod integration;
+mod snapshot;
+
+pub use crate::integration::ScopeSnapshotIntegration;
+pub use crate::snapshot::ScopeSnapshot;
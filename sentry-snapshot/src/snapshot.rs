@@ -0,0 +1,68 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use sentry_core::protocol::{Breadcrumb, Event, User};
+
+/// A compact copy of the scope state at some point in time.
+///
+/// Snapshots are written by the [`ScopeSnapshotIntegration`] and can be
+/// applied to crashes of that run that are only reported later on.
+///
+/// [`ScopeSnapshotIntegration`]: crate::ScopeSnapshotIntegration
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScopeSnapshot {
+    /// The time at which the snapshot was written.
+    pub timestamp: Option<SystemTime>,
+    /// The user that was set on the scope.
+    pub user: Option<User>,
+    /// The tags that were set on the scope.
+    pub tags: BTreeMap<String, String>,
+    /// The most recent breadcrumbs, oldest first.
+    pub breadcrumbs: VecDeque<Breadcrumb>,
+}
+
+impl ScopeSnapshot {
+    /// Loads a snapshot from the given file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        Ok(serde_json::from_reader(io::BufReader::new(file))?)
+    }
+
+    /// Writes the snapshot to the given file.
+    ///
+    /// The snapshot is first written to a temporary file next to `path`, which
+    /// then replaces `path`, so a crash while writing never leaves a truncated
+    /// snapshot behind.
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Enriches an event with the state captured in this snapshot.
+    ///
+    /// Data that is already present on the event takes precedence: tags are
+    /// only added if the event does not have them yet, and the user and
+    /// breadcrumbs are only set if the event has none.
+    pub fn apply_to_event(&self, event: &mut Event<'static>) {
+        for (key, value) in &self.tags {
+            event
+                .tags
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        if event.user.is_none() {
+            event.user = self.user.clone();
+        }
+        if event.breadcrumbs.is_empty() {
+            event.breadcrumbs = self.breadcrumbs.iter().cloned().collect();
+        }
+    }
+}
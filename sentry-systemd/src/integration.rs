@@ -0,0 +1,109 @@
+use std::fs;
+
+use sentry_core::protocol::{Context, Event, Map, Value};
+use sentry_core::{ClientOptions, Integration};
+
+/// An integration that adds the systemd unit of the process to events.
+///
+/// The context is only added if the process runs in a systemd unit.
+#[derive(Debug)]
+pub struct SystemdIntegration {
+    context: Map<String, Value>,
+}
+
+impl Default for SystemdIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemdIntegration {
+    /// Creates a new integration, reading the unit of the process.
+    pub fn new() -> Self {
+        let mut context = Map::new();
+        let cgroup = fs::read_to_string("/proc/self/cgroup").unwrap_or_default();
+        if let Some(unit) = unit_from_cgroup(&cgroup) {
+            context.insert("unit".into(), unit.into());
+            if let Ok(invocation_id) = std::env::var("INVOCATION_ID") {
+                context.insert("invocation_id".into(), invocation_id.into());
+            }
+            if let Ok(boot_id) = fs::read_to_string("/proc/sys/kernel/random/boot_id") {
+                // the journal records the boot id without dashes
+                let boot_id = boot_id.trim().replace('-', "");
+                context.insert("boot_id".into(), boot_id.into());
+            }
+        }
+        SystemdIntegration { context }
+    }
+}
+
+impl Integration for SystemdIntegration {
+    fn name(&self) -> &'static str {
+        "systemd"
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        if !self.context.is_empty() {
+            event
+                .contexts
+                .entry("systemd".into())
+                .or_insert_with(|| Context::Other(self.context.clone()));
+        }
+        Some(event)
+    }
+}
+
+/// Returns the name of the systemd unit from the contents of
+/// `/proc/self/cgroup`.
+///
+/// This is the innermost `.service` or `.scope` of the systemd hierarchy,
+/// which is the unified hierarchy `0::` on cgroup v2, and `name=systemd` on
+/// cgroup v1.
+fn unit_from_cgroup(cgroup: &str) -> Option<String> {
+    let path = cgroup.lines().find_map(|line| {
+        let mut parts = line.splitn(3, ':');
+        let (_id, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+        if controllers.is_empty() || controllers == "name=systemd" {
+            Some(path)
+        } else {
+            None
+        }
+    })?;
+    path.rsplit('/')
+        .find(|component| component.ends_with(".service") || component.ends_with(".scope"))
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_from_cgroup() {
+        assert_eq!(
+            unit_from_cgroup("0::/system.slice/nginx.service\n").as_deref(),
+            Some("nginx.service")
+        );
+        assert_eq!(
+            unit_from_cgroup(
+                "0::/user.slice/user-1000.slice/user@1000.service/app.slice/worker.service"
+            )
+            .as_deref(),
+            Some("worker.service")
+        );
+        assert_eq!(
+            unit_from_cgroup(
+                "12:cpu,cpuacct:/system.slice/db.service\n1:name=systemd:/system.slice/db.service"
+            )
+            .as_deref(),
+            Some("db.service")
+        );
+        // containers and processes outside of systemd have no unit
+        assert_eq!(unit_from_cgroup("0::/\n"), None);
+        assert_eq!(unit_from_cgroup(""), None);
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::{self, Write};
+
+use sentry_core::protocol::{Breadcrumb, Level};
+
+/// The priority of lines without a prefix, which is `info` in journald.
+const DEFAULT_PRIORITY: u8 = 6;
+
+/// The maximum length of a line that is recorded as breadcrumb.
+const MAX_LINE_LENGTH: usize = 8 * 1024;
+
+/// A writer that records the lines written to it as breadcrumbs.
+///
+/// Lines can start with a `<N>` prefix with the syslog priority of the line,
+/// from `<0>` (emerg) to `<7>` (debug), which journald understands when the
+/// output of a service is connected to the journal.  Lines without a prefix
+/// have the `info` priority.  Everything is passed on to the inner writer
+/// unchanged.
+///
+/// Lines with a priority above the [`max_priority`](Self::max_priority) are
+/// not recorded.  Lines are truncated to 8KiB.
+#[derive(Debug)]
+pub struct JournalWriter<W> {
+    inner: W,
+    max_priority: u8,
+    line: Vec<u8>,
+}
+
+impl<W: Write> JournalWriter<W> {
+    /// Wraps the writer.
+    pub fn new(inner: W) -> Self {
+        JournalWriter {
+            inner,
+            max_priority: DEFAULT_PRIORITY,
+            line: Vec::new(),
+        }
+    }
+
+    /// Sets the highest priority that is recorded as breadcrumb.
+    /// (defaults to `6`, which is `info`)
+    #[must_use]
+    pub fn max_priority(mut self, priority: u8) -> Self {
+        self.max_priority = priority;
+        self
+    }
+
+    /// Returns a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwraps the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let len = bytes
+            .len()
+            .min(MAX_LINE_LENGTH.saturating_sub(self.line.len()));
+        self.line.extend_from_slice(&bytes[..len]);
+    }
+
+    fn record_line(&mut self) {
+        let line = String::from_utf8_lossy(&self.line);
+        let (priority, message) = parse_priority(line.trim_end_matches('\r'));
+        if priority <= self.max_priority && !message.is_empty() {
+            let mut breadcrumb = Breadcrumb {
+                ty: "default".into(),
+                category: Some("journal".into()),
+                level: level_from_priority(priority),
+                message: Some(message.to_owned()),
+                ..Default::default()
+            };
+            breadcrumb.data.insert("priority".into(), priority.into());
+            sentry_core::add_breadcrumb(breadcrumb);
+        }
+        self.line.clear();
+    }
+}
+
+impl<W: Write> Write for JournalWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        let mut rest = &buf[..written];
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            self.push(&rest[..end]);
+            self.record_line();
+            rest = &rest[end + 1..];
+        }
+        self.push(rest);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Splits the `<N>` priority prefix from the line.
+fn parse_priority(line: &str) -> (u8, &str) {
+    let bytes = line.as_bytes();
+    match bytes {
+        [b'<', digit @ b'0'..=b'7', b'>', ..] => (digit - b'0', &line[3..]),
+        _ => (DEFAULT_PRIORITY, line),
+    }
+}
+
+fn level_from_priority(priority: u8) -> Level {
+    match priority {
+        0..=2 => Level::Fatal,
+        3 => Level::Error,
+        4 => Level::Warning,
+        5 | 6 => Level::Info,
+        _ => Level::Debug,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_priority() {
+        assert_eq!(parse_priority("<3>failed"), (3, "failed"));
+        assert_eq!(parse_priority("<8>unknown"), (6, "<8>unknown"));
+        assert_eq!(parse_priority("plain"), (6, "plain"));
+        assert_eq!(parse_priority("<4"), (6, "<4"));
+    }
+
+    #[test]
+    fn test_journal_breadcrumbs() {
+        let events = sentry::test::with_captured_events(|| {
+            let mut writer = JournalWriter::new(Vec::new());
+            writer.write_all(b"<3>connection ").unwrap();
+            writer
+                .write_all(b"lost\n<7>debug output\nstarted\n")
+                .unwrap();
+            writer.write_all(b"<4>incomplete").unwrap();
+            assert_eq!(
+                writer.get_ref().as_slice(),
+                b"<3>connection lost\n<7>debug output\nstarted\n<4>incomplete"
+            );
+            sentry::capture_message("failed", Level::Error);
+        });
+
+        let breadcrumbs: Vec<_> = events[0]
+            .breadcrumbs
+            .iter()
+            .map(|breadcrumb| (breadcrumb.level, breadcrumb.message.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            breadcrumbs,
+            [(Level::Error, "connection lost"), (Level::Info, "started")]
+        );
+    }
+}
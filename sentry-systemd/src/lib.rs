@@ -0,0 +1,36 @@
+//! Sentry integration for systemd services and the journal.
+//!
+//! The [`SystemdIntegration`] adds a `systemd` context to every event, with
+//! the name of the unit the process runs in, the invocation id of the unit
+//! and the boot id of the machine.  These are the `_SYSTEMD_UNIT`,
+//! `_SYSTEMD_INVOCATION_ID` and `_BOOT_ID` fields of the journal entries of
+//! the service, so events can be matched with the journal, for instance with
+//! `journalctl _SYSTEMD_INVOCATION_ID=<id>`.
+//!
+//! Services that log to stderr with the `<N>` priority prefixes understood by
+//! journald can wrap stderr in a [`JournalWriter`], which records the log
+//! lines as breadcrumbs.
+//!
+//! # Example
+//!
+//! ```
+//! use std::io::Write;
+//!
+//! use sentry_systemd::{JournalWriter, SystemdIntegration};
+//!
+//! let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(SystemdIntegration::new()));
+//!
+//! let mut stderr = JournalWriter::new(std::io::stderr());
+//! writeln!(stderr, "<4>disk usage above 90%").unwrap();
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+#![deny(unsafe_code)]
+
+mod integration;
+mod journal;
+
+pub use crate::integration::SystemdIntegration;
+pub use crate::journal::JournalWriter;
@@ -0,0 +1,115 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Redirects the stdout and stderr of the process into a file.
+///
+/// The redirection is undone when the capture is dropped.  Output is only
+/// captured on unix, elsewhere it is passed through.
+pub(crate) struct OutputCapture {
+    path: PathBuf,
+    #[cfg(unix)]
+    saved: unix::SavedFds,
+}
+
+impl OutputCapture {
+    /// Starts capturing the output into a new file at `path`.
+    pub fn start(path: PathBuf) -> io::Result<Self> {
+        let file = File::create(&path)?;
+        flush_output();
+        #[cfg(unix)]
+        let saved = match unix::redirect(&file) {
+            Ok(saved) => saved,
+            Err(err) => {
+                fs::remove_file(&path).ok();
+                return Err(err);
+            }
+        };
+        #[cfg(not(unix))]
+        drop(file);
+        Ok(Self {
+            path,
+            #[cfg(unix)]
+            saved,
+        })
+    }
+
+    /// The path of the file that the output is captured into.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stops capturing and returns the captured output.
+    pub fn finish(self) -> Vec<u8> {
+        read_output(&self.path)
+    }
+}
+
+impl Drop for OutputCapture {
+    fn drop(&mut self) {
+        flush_output();
+        #[cfg(unix)]
+        unix::restore(&self.saved);
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+fn flush_output() {
+    io::stdout().flush().ok();
+    io::stderr().flush().ok();
+}
+
+/// Reads the output that was captured into the file at `path` so far.
+pub(crate) fn read_output(path: &Path) -> Vec<u8> {
+    flush_output();
+    fs::read(path).unwrap_or_default()
+}
+
+#[cfg(unix)]
+#[allow(unsafe_code)]
+mod unix {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::{AsRawFd, RawFd};
+
+    use libc::{STDERR_FILENO, STDOUT_FILENO};
+
+    /// Duplicates of the original stdout and stderr.
+    pub struct SavedFds {
+        stdout: RawFd,
+        stderr: RawFd,
+    }
+
+    pub fn redirect(file: &File) -> io::Result<SavedFds> {
+        unsafe {
+            let stdout = libc::dup(STDOUT_FILENO);
+            if stdout < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let stderr = libc::dup(STDERR_FILENO);
+            if stderr < 0 {
+                let err = io::Error::last_os_error();
+                libc::close(stdout);
+                return Err(err);
+            }
+            let saved = SavedFds { stdout, stderr };
+            if libc::dup2(file.as_raw_fd(), STDOUT_FILENO) < 0
+                || libc::dup2(file.as_raw_fd(), STDERR_FILENO) < 0
+            {
+                let err = io::Error::last_os_error();
+                restore(&saved);
+                return Err(err);
+            }
+            Ok(saved)
+        }
+    }
+
+    pub fn restore(saved: &SavedFds) {
+        unsafe {
+            libc::dup2(saved.stdout, STDOUT_FILENO);
+            libc::dup2(saved.stderr, STDERR_FILENO);
+            libc::close(saved.stdout);
+            libc::close(saved.stderr);
+        }
+    }
+}
@@ -0,0 +1,378 @@
+//! A test harness that reports failing tests to Sentry.
+//!
+//! This crate provides a replacement for the default `libtest` harness, which
+//! runs tests much like `cargo test` does, but additionally reports every
+//! failing test as a Sentry event with the output of the test attached.  This
+//! makes it possible to aggregate and triage flaky tests of a large test fleet
+//! in Sentry.
+//!
+//! Failing tests are reported by the panic integration, so `sentry` has to be
+//! initialized with it, which is the default, before the harness is run.  While
+//! a test runs, its name is set as the `test.name` tag and as the transaction
+//! of the scope, and when it panics, the output that the test has written so
+//! far is attached to the event as `output.txt`.
+//!
+//! # Usage
+//!
+//! Disable the default harness for the test target in `Cargo.toml`:
+//!
+//! ```toml
+//! [[test]]
+//! name = "integration"
+//! harness = false
+//! ```
+//!
+//! And run the tests from the `main` function of the target:
+//!
+//! ```no_run
+//! use sentry_test_harness::Test;
+//!
+//! fn parses_config() {
+//!     assert_eq!("42".parse::<u32>(), Ok(42));
+//! }
+//!
+//! fn main() {
+//!     let _sentry = sentry::init(sentry::ClientOptions {
+//!         release: sentry::release_name!(),
+//!         ..Default::default()
+//!     });
+//!
+//!     sentry_test_harness::run(vec![
+//!         Test::new("parses_config", parses_config),
+//!         Test::new("connects_to_db", || { /* ... */ }).ignored(true),
+//!     ]);
+//! }
+//! ```
+//!
+//! The harness understands the common arguments of `libtest`, namely a test
+//! name filter, `--exact`, `--nocapture`, `--list`, `--ignored` and
+//! `--include-ignored`.  Other arguments are accepted but ignored.
+//!
+//! Capturing the output of tests redirects the stdout and stderr of the whole
+//! process, so tests always run one at a time.  Output is only captured on
+//! unix platforms.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+#![deny(unsafe_code)]
+
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Once};
+use std::{fmt, panic, process, thread};
+
+use once_cell::sync::Lazy;
+use sentry_core::protocol::Attachment;
+use sentry_core::Hub;
+
+use crate::capture::{read_output, OutputCapture};
+
+mod capture;
+
+/// The path of the file that the output of the running test is captured into.
+static CURRENT_OUTPUT: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(Default::default);
+static INSTALL_HOOK: Once = Once::new();
+
+thread_local! {
+    static IS_TEST_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A test that is run by the harness.
+pub struct Test {
+    name: String,
+    run: Box<dyn FnOnce() + Send>,
+    ignored: bool,
+}
+
+impl Test {
+    /// Creates a new test with the given name and function.
+    ///
+    /// The test fails if the function panics.
+    pub fn new<N, F>(name: N, run: F) -> Self
+    where
+        N: Into<String>,
+        F: FnOnce() + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            run: Box::new(run),
+            ignored: false,
+        }
+    }
+
+    /// Marks the test as ignored, like the `#[ignore]` attribute.
+    #[must_use]
+    pub fn ignored(mut self, ignored: bool) -> Self {
+        self.ignored = ignored;
+        self
+    }
+
+    /// Returns the name of the test.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Debug for Test {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Test")
+            .field("name", &self.name)
+            .field("ignored", &self.ignored)
+            .finish()
+    }
+}
+
+/// The command line arguments of the harness.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Arguments {
+    /// Only run tests whose name contains this filter.
+    pub filter: Option<String>,
+    /// Only run the test whose name is exactly the filter.
+    pub exact: bool,
+    /// Do not capture the output of tests.
+    pub nocapture: bool,
+    /// List the tests instead of running them.
+    pub list: bool,
+    /// Only run ignored tests.
+    pub ignored: bool,
+    /// Run ignored tests as well.
+    pub include_ignored: bool,
+}
+
+impl Arguments {
+    /// Parses the arguments of the current process.
+    pub fn from_args() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Parses the given arguments, excluding the program name.
+    pub fn parse<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut arguments = Self::default();
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--exact" => arguments.exact = true,
+                "--nocapture" => arguments.nocapture = true,
+                "--list" => arguments.list = true,
+                "--ignored" => arguments.ignored = true,
+                "--include-ignored" => arguments.include_ignored = true,
+                // options of libtest that take a value
+                "--test-threads" | "--color" | "--format" | "--logfile" | "--skip" | "-Z" => {
+                    args.next();
+                }
+                _ if arg.starts_with('-') => {}
+                _ => arguments.filter = Some(arg),
+            }
+        }
+        arguments
+    }
+
+    fn matches(&self, test: &Test) -> bool {
+        match self.filter {
+            Some(ref filter) if self.exact => test.name == *filter,
+            Some(ref filter) => test.name.contains(filter.as_str()),
+            None => true,
+        }
+    }
+
+    fn should_run(&self, test: &Test) -> bool {
+        if self.ignored {
+            test.ignored
+        } else {
+            self.include_ignored || !test.ignored
+        }
+    }
+}
+
+/// The outcome of a test run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Conclusion {
+    /// The number of tests that passed.
+    pub passed: u64,
+    /// The number of tests that failed.
+    pub failed: u64,
+    /// The number of tests that were ignored.
+    pub ignored: u64,
+    /// The number of tests that did not match the filter.
+    pub filtered_out: u64,
+}
+
+impl Conclusion {
+    /// Returns `true` if any test failed.
+    pub fn has_failed(&self) -> bool {
+        self.failed > 0
+    }
+}
+
+/// Runs the tests with the arguments of the current process.
+///
+/// If any test fails, this flushes the current client and exits the process
+/// with the exit code `101`, like the default harness does.
+pub fn run(tests: Vec<Test>) {
+    let conclusion = run_with_args(&Arguments::from_args(), tests);
+    if conclusion.has_failed() {
+        if let Some(client) = Hub::current().client() {
+            client.flush(None);
+        }
+        process::exit(101);
+    }
+}
+
+/// Runs the tests with the given arguments.
+///
+/// This prints the progress and results like the default harness does, but
+/// leaves it to the caller to act on the returned [`Conclusion`].
+pub fn run_with_args(args: &Arguments, tests: Vec<Test>) -> Conclusion {
+    install_panic_hook();
+
+    let total = tests.len() as u64;
+    let tests: Vec<_> = tests
+        .into_iter()
+        .filter(|test| args.matches(test))
+        .collect();
+    let mut conclusion = Conclusion {
+        filtered_out: total - tests.len() as u64,
+        ..Default::default()
+    };
+
+    if args.list {
+        for test in &tests {
+            println!("{}: test", test.name);
+        }
+        println!();
+        println!("{} tests, 0 benchmarks", tests.len());
+        return conclusion;
+    }
+
+    println!();
+    println!("running {} tests", tests.len());
+    let mut failures = Vec::new();
+    for test in tests {
+        if !args.should_run(&test) {
+            println!("test {} ... ignored", test.name);
+            conclusion.ignored += 1;
+            continue;
+        }
+
+        print!("test {} ... ", test.name);
+        io::stdout().flush().ok();
+        let capture = if args.nocapture {
+            None
+        } else {
+            let path = std::env::temp_dir().join(format!(
+                "sentry-test-output-{}-{}.txt",
+                process::id(),
+                conclusion.passed + conclusion.failed
+            ));
+            OutputCapture::start(path).ok()
+        };
+
+        *CURRENT_OUTPUT.lock().unwrap() = capture.as_ref().map(|c| c.path().to_owned());
+        let passed = run_test(test.name.clone(), test.run);
+        *CURRENT_OUTPUT.lock().unwrap() = None;
+        let output = capture.map(OutputCapture::finish).unwrap_or_default();
+
+        if passed {
+            println!("ok");
+            conclusion.passed += 1;
+        } else {
+            println!("FAILED");
+            conclusion.failed += 1;
+            failures.push((test.name, output));
+        }
+    }
+
+    if !failures.is_empty() {
+        println!();
+        println!("failures:");
+        println!();
+        for (name, output) in &failures {
+            println!("---- {} stdout ----", name);
+            print!("{}", String::from_utf8_lossy(output));
+            println!();
+        }
+        println!();
+        println!("failures:");
+        for (name, _) in &failures {
+            println!("    {}", name);
+        }
+    }
+
+    println!();
+    println!(
+        "test result: {}. {} passed; {} failed; {} ignored; 0 measured; {} filtered out",
+        if conclusion.has_failed() {
+            "FAILED"
+        } else {
+            "ok"
+        },
+        conclusion.passed,
+        conclusion.failed,
+        conclusion.ignored,
+        conclusion.filtered_out,
+    );
+    println!();
+    conclusion
+}
+
+/// Runs a single test on a thread named after it, returning whether it passed.
+fn run_test(name: String, test: Box<dyn FnOnce() + Send>) -> bool {
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    let thread = thread::Builder::new().name(name.clone()).spawn(move || {
+        Hub::run(hub, || {
+            IS_TEST_THREAD.with(|is_test| is_test.set(true));
+            sentry_core::configure_scope(|scope| {
+                scope.set_tag("test.name", &name);
+                scope.set_transaction(Some(&name));
+            });
+            test();
+        })
+    });
+    match thread {
+        Ok(thread) => thread.join().is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Installs a panic hook that attaches the output of a test to the scope
+/// before the panic integration captures it.
+///
+/// The hook is chained in front of the existing hook, which is why `sentry`
+/// has to be initialized before the harness runs.
+fn install_panic_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let next = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            attach_test_output();
+            next(info);
+        }));
+    });
+}
+
+fn attach_test_output() {
+    if !IS_TEST_THREAD.with(Cell::get) {
+        return;
+    }
+    let path = match *CURRENT_OUTPUT.lock().unwrap() {
+        Some(ref path) => path.clone(),
+        None => return,
+    };
+    let output = read_output(&path);
+    if output.is_empty() {
+        return;
+    }
+    sentry_core::configure_scope(|scope| {
+        scope.add_attachment(Attachment {
+            buffer: output,
+            filename: "output.txt".into(),
+            content_type: Some("text/plain".into()),
+            ..Default::default()
+        })
+    });
+}
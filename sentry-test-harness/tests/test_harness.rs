@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use sentry::protocol::EnvelopeItem;
+use sentry_test_harness::{run_with_args, Arguments, Conclusion, Test};
+
+fn main() {
+    let options = sentry::ClientOptions {
+        integrations: vec![Arc::new(
+            sentry::integrations::panic::PanicIntegration::new(),
+        )],
+        ..Default::default()
+    };
+
+    let mut conclusion = Conclusion::default();
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let args = Arguments::parse(["harness_", "--test-threads", "1"]);
+            conclusion = run_with_args(
+                &args,
+                vec![
+                    Test::new("harness_passes", || println!("all good")),
+                    Test::new("harness_fails", || {
+                        println!("connecting to the database");
+                        panic!("database unavailable");
+                    }),
+                    Test::new("harness_ignored", || unreachable!()).ignored(true),
+                    Test::new("filtered_out", || unreachable!()),
+                ],
+            );
+        },
+        options,
+    );
+
+    assert_eq!(
+        conclusion,
+        Conclusion {
+            passed: 1,
+            failed: 1,
+            ignored: 1,
+            filtered_out: 1,
+        }
+    );
+
+    assert_eq!(envelopes.len(), 1);
+    let event = envelopes[0].event().unwrap();
+    assert_eq!(event.tags["test.name"], "harness_fails");
+    assert_eq!(event.transaction.as_deref(), Some("harness_fails"));
+    assert_eq!(
+        event.exception[0].value.as_deref(),
+        Some("database unavailable")
+    );
+
+    let output = envelopes[0]
+        .items()
+        .find_map(|item| match item {
+            EnvelopeItem::Attachment(attachment) => Some(attachment),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(output.filename, "output.txt");
+    assert_eq!(output.buffer, b"connecting to the database\n");
+}
@@ -0,0 +1,163 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use http::{Request, Response};
+use sentry_core::protocol::{Breadcrumb, Level, Map};
+use sentry_core::Hub;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Tower Layer that records a breadcrumb for each completed Http Request.
+///
+/// The breadcrumb contains the method, the route, the status code and the
+/// duration of the request, so that an event shows the traffic that led up
+/// to it.  The route is the path of the request, with segments that look
+/// like unique IDs replaced by a placeholder (see
+/// [`sanitize_url_path`](sentry_core::sanitize_url_path)).
+///
+/// The breadcrumbs are added to the Hub that is current when a request is
+/// received, or to the Hub given to [`with_hub`](Self::with_hub).  In servers
+/// that bind a new Hub for each request with a [`SentryLayer`](crate::SentryLayer),
+/// this layer has to come *before* it, so the breadcrumbs land on the
+/// long-lived Hub that the Hubs of later requests are created from.
+///
+/// # Examples
+///
+/// ```
+/// # use tower::ServiceExt;
+/// # type Request = http::Request<String>;
+/// # let handler = tower::service_fn(|_: Request| async {
+/// #     Ok::<_, std::convert::Infallible>(http::Response::new(String::new()))
+/// # });
+/// # let events = sentry::test::with_captured_events(|| {
+/// let service = tower::ServiceBuilder::new()
+///     .layer(sentry_tower::SentryAccessLogLayer::new())
+///     .layer(sentry_tower::NewSentryLayer::<Request>::new_from_top())
+///     .layer(sentry_tower::SentryHttpLayer::with_transaction())
+///     .service(handler);
+/// # let request = http::Request::get("/users/42").body(String::new()).unwrap();
+/// # let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// # rt.block_on(service.oneshot(request)).unwrap();
+/// # sentry::capture_message("later", sentry::Level::Error);
+/// # });
+/// # let breadcrumb = &events[0].breadcrumbs[0];
+/// # assert_eq!(breadcrumb.data["route"], "/users/{id}");
+/// # assert_eq!(breadcrumb.data["status_code"], 200);
+/// ```
+#[derive(Clone, Default)]
+pub struct SentryAccessLogLayer {
+    hub: Option<Arc<Hub>>,
+}
+
+impl SentryAccessLogLayer {
+    /// Creates a new Layer that records breadcrumbs on the current Hub.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new Layer that records breadcrumbs on the given Hub.
+    pub fn with_hub(hub: Arc<Hub>) -> Self {
+        Self { hub: Some(hub) }
+    }
+}
+
+/// Tower Service that records a breadcrumb for each completed Http Request.
+///
+/// See [`SentryAccessLogLayer`] for details.
+#[derive(Clone)]
+pub struct SentryAccessLogService<S> {
+    service: S,
+    hub: Option<Arc<Hub>>,
+}
+
+impl<S> Layer<S> for SentryAccessLogLayer {
+    type Service = SentryAccessLogService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        Self::Service {
+            service,
+            hub: self.hub.clone(),
+        }
+    }
+}
+
+/// The Future returned from [`SentryAccessLogService`].
+#[pin_project::pin_project]
+pub struct SentryAccessLogFuture<F> {
+    hub: Arc<Hub>,
+    method: String,
+    route: String,
+    start: Instant,
+    #[pin]
+    future: F,
+}
+
+impl<F, ResBody, Error> Future for SentryAccessLogFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, Error>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let slf = self.project();
+        match slf.future.poll(cx) {
+            Poll::Ready(res) => {
+                let duration = slf.start.elapsed();
+                let status_code = res.as_ref().ok().map(|res| res.status());
+                let level = match status_code {
+                    Some(status) if status.is_server_error() => Level::Error,
+                    Some(status) if status.is_client_error() => Level::Warning,
+                    Some(_) => Level::Info,
+                    None => Level::Error,
+                };
+
+                let mut data = Map::new();
+                data.insert("method".into(), std::mem::take(slf.method).into());
+                data.insert("route".into(), std::mem::take(slf.route).into());
+                if let Some(status_code) = status_code {
+                    data.insert("status_code".into(), status_code.as_u16().into());
+                }
+                data.insert(
+                    "duration_ms".into(),
+                    (duration.as_secs_f64() * 1000.0).into(),
+                );
+                slf.hub.add_breadcrumb(Breadcrumb {
+                    ty: "http".into(),
+                    category: Some("http.server".into()),
+                    level,
+                    data,
+                    ..Default::default()
+                });
+
+                Poll::Ready(res)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SentryAccessLogService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SentryAccessLogFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        SentryAccessLogFuture {
+            hub: self.hub.clone().unwrap_or_else(Hub::current),
+            method: request.method().to_string(),
+            route: sentry_core::sanitize_url_path(request.uri().path()),
+            start: Instant::now(),
+            future: self.service.call(request),
+        }
+    }
+}
@@ -3,8 +3,9 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use http::{header, uri, Request, Response, StatusCode};
-use sentry_core::protocol;
+use http::header::{HeaderName, HeaderValue};
+use http::{header, uri, Request, Response};
+use sentry_core::{protocol, RouteFilter};
 use tower_layer::Layer;
 use tower_service::Service;
 
@@ -14,14 +15,17 @@ use tower_service::Service;
 /// performance monitoring transaction for each incoming request,
 /// continuing the trace based on incoming distributed tracing headers.
 ///
-/// The created transaction will automatically use the request URI as its name.
-/// This is sometimes not desirable in case the request URI contains unique IDs
-/// or similar. In this case, users should manually override the transaction name
-/// in the request handler using the [`Scope::set_transaction`](sentry_core::Scope::set_transaction)
+/// The created transaction will automatically use the request URI path as its
+/// name, with segments that look like unique IDs replaced by a placeholder (see
+/// [`sanitize_url_path`](sentry_core::sanitize_url_path)). When the route template
+/// of the request is known, users should override the transaction name in the
+/// request handler using the [`Scope::set_transaction`](sentry_core::Scope::set_transaction)
 /// method.
 #[derive(Clone, Default)]
 pub struct SentryHttpLayer {
     start_transaction: bool,
+    route_filter: RouteFilter,
+    emit_correlation_headers: bool,
 }
 
 impl SentryHttpLayer {
@@ -35,8 +39,31 @@ impl SentryHttpLayer {
     pub fn with_transaction() -> Self {
         Self {
             start_transaction: true,
+            ..Self::default()
         }
     }
+
+    /// Sets the filter for the requests that transactions are started for.
+    ///
+    /// Requests are matched by the path of their URI.
+    #[must_use]
+    pub fn route_filter(mut self, filter: RouteFilter) -> Self {
+        self.route_filter = filter;
+        self
+    }
+
+    /// If enabled, responses of requests during which an event was captured
+    /// get `X-Sentry-Event-Id` and `X-Sentry-Trace-Id` headers.
+    ///
+    /// Events are attributed to a request by the last event id of its Hub, so
+    /// this should be combined with a [`SentryLayer`](crate::SentryLayer)
+    /// that creates a new Hub for each request.  The trace id is only known
+    /// if the request has a transaction or span.
+    #[must_use]
+    pub fn emit_correlation_headers(mut self, emit: bool) -> Self {
+        self.emit_correlation_headers = emit;
+        self
+    }
 }
 
 /// Tower Service that logs Http Request Headers.
@@ -48,6 +75,8 @@ impl SentryHttpLayer {
 pub struct SentryHttpService<S> {
     service: S,
     start_transaction: bool,
+    route_filter: RouteFilter,
+    emit_correlation_headers: bool,
 }
 
 impl<S> Layer<S> for SentryHttpLayer {
@@ -57,6 +86,8 @@ impl<S> Layer<S> for SentryHttpLayer {
         Self::Service {
             service,
             start_transaction: self.start_transaction,
+            route_filter: self.route_filter.clone(),
+            emit_correlation_headers: self.emit_correlation_headers,
         }
     }
 }
@@ -72,6 +103,9 @@ pub struct SentryHttpFuture<F> {
         sentry_core::TransactionOrSpan,
         Option<sentry_core::TransactionOrSpan>,
     )>,
+    emit_correlation_headers: bool,
+    /// The last event id of the Hub when the request started.
+    last_event_id: Option<sentry_core::types::Uuid>,
     #[pin]
     future: F,
 }
@@ -85,6 +119,9 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let slf = self.project();
         if let Some((sentry_req, trx_ctx)) = slf.on_first_poll.take() {
+            if *slf.emit_correlation_headers {
+                *slf.last_event_id = sentry_core::Hub::current().last_event_id();
+            }
             sentry_core::configure_scope(|scope| {
                 scope.add_event_processor(move |mut event| {
                     if event.request.is_none() {
@@ -103,11 +140,18 @@ where
             });
         }
         match slf.future.poll(cx) {
-            Poll::Ready(res) => {
+            Poll::Ready(mut res) => {
+                if *slf.emit_correlation_headers {
+                    if let Ok(ref mut res) = res {
+                        insert_correlation_headers(res, *slf.last_event_id);
+                    }
+                }
                 if let Some((transaction, parent_span)) = slf.transaction.take() {
                     if transaction.get_status().is_none() {
                         let status = match &res {
-                            Ok(res) => map_status(res.status()),
+                            Ok(res) => {
+                                protocol::SpanStatus::from_http_status(res.status().as_u16())
+                            }
                             Err(_) => protocol::SpanStatus::UnknownError,
                         };
                         transaction.set_status(status);
@@ -150,41 +194,55 @@ where
                 .collect(),
             ..Default::default()
         };
-        let trx_ctx = if self.start_transaction {
-            let headers = request.headers().into_iter().flat_map(|(header, value)| {
-                value.to_str().ok().map(|value| (header.as_str(), value))
-            });
-            let tx_name = format!("{} {}", request.method(), request.uri().path());
-            Some(sentry_core::TransactionContext::continue_from_headers(
-                &tx_name,
-                "http.server",
-                headers,
-            ))
-        } else {
-            None
-        };
+        let trx_ctx =
+            if self.start_transaction && self.route_filter.is_allowed(request.uri().path()) {
+                let headers = request.headers().into_iter().flat_map(|(header, value)| {
+                    value.to_str().ok().map(|value| (header.as_str(), value))
+                });
+                let tx_name = format!(
+                    "{} {}",
+                    request.method(),
+                    sentry_core::sanitize_url_path(request.uri().path())
+                );
+                let mut trx_ctx = sentry_core::TransactionContext::continue_from_headers(
+                    &tx_name,
+                    "http.server",
+                    headers,
+                );
+                trx_ctx.set_source(protocol::TransactionSource::Url);
+                Some(trx_ctx)
+            } else {
+                None
+            };
 
         SentryHttpFuture {
             on_first_poll: Some((sentry_req, trx_ctx)),
             transaction: None,
+            emit_correlation_headers: self.emit_correlation_headers,
+            last_event_id: None,
             future: self.service.call(request),
         }
     }
 }
 
-fn map_status(status: StatusCode) -> protocol::SpanStatus {
-    match status {
-        StatusCode::UNAUTHORIZED => protocol::SpanStatus::Unauthenticated,
-        StatusCode::FORBIDDEN => protocol::SpanStatus::PermissionDenied,
-        StatusCode::NOT_FOUND => protocol::SpanStatus::NotFound,
-        StatusCode::TOO_MANY_REQUESTS => protocol::SpanStatus::ResourceExhausted,
-        status if status.is_client_error() => protocol::SpanStatus::InvalidArgument,
-        StatusCode::NOT_IMPLEMENTED => protocol::SpanStatus::Unimplemented,
-        StatusCode::SERVICE_UNAVAILABLE => protocol::SpanStatus::Unavailable,
-        status if status.is_server_error() => protocol::SpanStatus::InternalError,
-        StatusCode::CONFLICT => protocol::SpanStatus::AlreadyExists,
-        status if status.is_success() => protocol::SpanStatus::Ok,
-        _ => protocol::SpanStatus::UnknownError,
+/// Adds the ids of the event captured during the request, if any.
+fn insert_correlation_headers<B>(
+    response: &mut Response<B>,
+    previous_event_id: Option<sentry_core::types::Uuid>,
+) {
+    let hub = sentry_core::Hub::current();
+    let event_id = match hub.last_event_id() {
+        Some(event_id) if Some(event_id) != previous_event_id => event_id,
+        _ => return,
+    };
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&event_id.simple().to_string()) {
+        headers.insert(HeaderName::from_static("x-sentry-event-id"), value);
+    }
+    if let Some(span) = hub.configure_scope(|scope| scope.get_span()) {
+        if let Ok(value) = HeaderValue::from_str(&span.trace_id().to_string()) {
+            headers.insert(HeaderName::from_static("x-sentry-trace-id"), value);
+        }
     }
 }
 
@@ -0,0 +1,185 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use http::{HeaderValue, Request, Response};
+use sentry_core::protocol::{self, Breadcrumb, Map};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Tower Layer that instruments outgoing Http Requests.
+///
+/// The Service created by this Layer wraps an HTTP client service, such as a
+/// `hyper::Client`, and records a breadcrumb for each request that was made.
+/// The query string of the URL is only recorded if
+/// [`send_default_pii`](sentry_core::ClientOptions::send_default_pii) is
+/// enabled.
+///
+/// When a span is currently bound to the scope, an `http.client` child span
+/// is started for each request, and its distributed tracing headers are
+/// injected into the outgoing request.
+///
+/// # Examples
+///
+/// ```
+/// # use tower::ServiceExt;
+/// # type Request = http::Request<String>;
+/// # let client = tower::service_fn(|request: Request| async move {
+/// #     assert!(request.headers().contains_key("sentry-trace"));
+/// #     let response = http::Response::builder().status(404).body(String::new());
+/// #     Ok::<_, std::convert::Infallible>(response.unwrap())
+/// # });
+/// # let options = sentry::ClientOptions {
+/// #     traces_sample_rate: 1.0,
+/// #     ..Default::default()
+/// # };
+/// # let envelopes = sentry::test::with_captured_envelopes_options(|| {
+/// # let transaction = sentry::start_transaction(sentry::TransactionContext::new("test", "test"));
+/// # sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+/// let client = tower::ServiceBuilder::new()
+///     .layer(sentry_tower::SentryHttpClientLayer::new())
+///     .service(client);
+/// # let request = http::Request::get("http://example.com/").body(String::new()).unwrap();
+/// # let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// # rt.block_on(client.oneshot(request)).unwrap();
+/// # transaction.finish();
+/// # }, options);
+/// # let transaction = match envelopes[0].items().next().unwrap() {
+/// #     sentry::protocol::EnvelopeItem::Transaction(transaction) => transaction,
+/// #     _ => panic!("expected a transaction"),
+/// # };
+/// # let span = &transaction.spans[0];
+/// # assert_eq!(span.op.as_deref(), Some("http.client"));
+/// # assert_eq!(span.description.as_deref(), Some("GET http://example.com/"));
+/// # assert_eq!(span.status, Some(sentry::protocol::SpanStatus::NotFound));
+/// # assert_eq!(span.data["status_code"], 404);
+/// ```
+#[derive(Clone, Default)]
+pub struct SentryHttpClientLayer {}
+
+impl SentryHttpClientLayer {
+    /// Creates a new Layer that instruments outgoing requests.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Tower Service that instruments outgoing Http Requests.
+///
+/// See [`SentryHttpClientLayer`] for details.
+#[derive(Clone)]
+pub struct SentryHttpClientService<S> {
+    service: S,
+}
+
+impl<S> Layer<S> for SentryHttpClientLayer {
+    type Service = SentryHttpClientService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        Self::Service { service }
+    }
+}
+
+/// The Future returned from [`SentryHttpClientService`].
+#[pin_project::pin_project]
+pub struct SentryHttpClientFuture<F> {
+    method: String,
+    url: String,
+    span: Option<sentry_core::Span>,
+    #[pin]
+    future: F,
+}
+
+impl<F, ResBody, Error> Future for SentryHttpClientFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, Error>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let slf = self.project();
+        match slf.future.poll(cx) {
+            Poll::Ready(res) => {
+                let status_code = res.as_ref().ok().map(|res| res.status());
+                if let Some(span) = slf.span.take() {
+                    let status = match status_code {
+                        Some(status_code) => {
+                            span.set_data("status_code", status_code.as_u16().into());
+                            protocol::SpanStatus::from_http_status(status_code.as_u16())
+                        }
+                        None => protocol::SpanStatus::UnknownError,
+                    };
+                    span.set_status(status);
+                    span.finish();
+                }
+
+                let method = std::mem::take(slf.method);
+                let url = std::mem::take(slf.url);
+                sentry_core::add_breadcrumb(|| {
+                    let mut data = Map::new();
+                    data.insert("method".into(), method.into());
+                    data.insert("url".into(), url.into());
+                    if let Some(status_code) = status_code {
+                        data.insert("status_code".into(), status_code.as_u16().into());
+                    }
+                    Breadcrumb {
+                        ty: "http".into(),
+                        category: Some("http".into()),
+                        data,
+                        ..Default::default()
+                    }
+                });
+
+                Poll::Ready(res)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SentryHttpClientService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SentryHttpClientFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let method = request.method().to_string();
+        let send_default_pii = sentry_core::Hub::current()
+            .client()
+            .map_or(false, |client| client.options().send_default_pii);
+        let url = if send_default_pii {
+            request.uri().to_string()
+        } else {
+            strip_query(&request.uri().to_string()).to_owned()
+        };
+
+        let span = sentry_core::configure_scope(|scope| scope.get_span()).map(|parent| {
+            let span = parent.start_child("http.client", &format!("{} {}", method, url));
+            for (header, value) in span.iter_headers() {
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    request.headers_mut().insert(header, value);
+                }
+            }
+            span
+        });
+
+        SentryHttpClientFuture {
+            method,
+            url,
+            span,
+            future: self.service.call(request),
+        }
+    }
+}
+
+/// Removes the query string and fragment from a URL.
+fn strip_query(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or_default()
+}
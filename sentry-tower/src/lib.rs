@@ -128,7 +128,50 @@
 //! # }
 //! ```
 //!
+//! The `http` feature also offers a layer which attaches the beginning of
+//! request bodies to captured errors when
+//! [`send_default_pii`](sentry_core::ClientOptions::send_default_pii) is
+//! enabled, see [`SentryRequestBodyLayer`].
+//!
+//! ## Instrumenting HTTP clients
+//!
+//! The `http` feature also offers a layer for outgoing requests, which can wrap
+//! any HTTP client implementing [`tower::Service`], for example a `hyper::Client`.
+//! It records a breadcrumb for each request, and when a span is bound to the
+//! scope, starts an `http.client` child span and injects its distributed tracing
+//! headers into the request.
+//!
+//! ```rust
+//! # #[cfg(feature = "http")] {
+//! # type Request = http::Request<String>;
+//! # let client = tower::service_fn(|_: Request| async {
+//! #     Ok::<_, std::convert::Infallible>(http::Response::new(String::new()))
+//! # });
+//! let client = tower::ServiceBuilder::new()
+//!     .layer(sentry_tower::SentryHttpClientLayer::new())
+//!     .service(client);
+//! # }
+//! ```
+//!
+//! ## Access log breadcrumbs
+//!
+//! The `http` feature also offers a layer that records a breadcrumb with the
+//! method, route, status code and duration of each completed request.  When it
+//! comes before the `Hub` layer, the breadcrumbs land on the long-lived hub, so
+//! an error shows the recent traffic that led up to it.
+//!
+//! ```rust
+//! # #[cfg(feature = "http")] {
+//! # type Request = http::Request<String>;
+//! let layer = tower::ServiceBuilder::new()
+//!     .layer(sentry_tower::SentryAccessLogLayer::new())
+//!     .layer(sentry_tower::NewSentryLayer::<Request>::new_from_top())
+//!     .layer(sentry_tower::SentryHttpLayer::with_transaction());
+//! # }
+//! ```
+//!
 //! [`tower::ServiceBuilder`]: https://docs.rs/tower/latest/tower/struct.ServiceBuilder.html
+//! [`tower::Service`]: https://docs.rs/tower/latest/tower/trait.Service.html
 
 #![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
@@ -142,10 +185,22 @@ use sentry_core::{Hub, SentryFuture, SentryFutureExt};
 use tower_layer::Layer;
 use tower_service::Service;
 
+#[cfg(feature = "http")]
+mod access_log;
 #[cfg(feature = "http")]
 mod http;
 #[cfg(feature = "http")]
+mod http_client;
+#[cfg(feature = "http")]
+mod request_body;
+#[cfg(feature = "http")]
+pub use crate::access_log::*;
+#[cfg(feature = "http")]
 pub use crate::http::*;
+#[cfg(feature = "http")]
+pub use crate::http_client::*;
+#[cfg(feature = "http")]
+pub use crate::request_body::*;
 
 /// Provides a hub for each request
 pub trait HubProvider<H, Request>
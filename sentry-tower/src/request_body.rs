@@ -0,0 +1,216 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+
+use bytes::{Buf, Bytes};
+use http::{HeaderMap, Request};
+use http_body::{Body, SizeHint};
+use sentry_core::{Hub, Level};
+use tower_layer::Layer;
+use tower_service::Service;
+
+type CapturedBody = Arc<Mutex<Vec<u8>>>;
+
+/// Tower Layer that attaches the beginning of request bodies to the errors
+/// captured during a request.
+///
+/// The body is copied as the handler reads it, up to the given number of
+/// bytes, so large bodies are never buffered, and bodies that the handler
+/// does not read are not captured.  It is only attached to events with a
+/// level of at least [`Level::Error`](sentry_core::Level::Error), which
+/// report the failure of a request.  As bodies may contain personal data,
+/// they are only captured if
+/// [`send_default_pii`](sentry_core::ClientOptions::send_default_pii) is
+/// enabled.
+///
+/// The events are captured by the handler before its response exists, so
+/// unlike `sentry-actix`, this layer does not capture response bodies.
+///
+/// The body is attached to the events of the Hub that is current when a
+/// request is received, so this layer has to come after a
+/// [`SentryLayer`](crate::SentryLayer) that creates a new Hub for each
+/// request, and after the [`SentryHttpLayer`](crate::SentryHttpLayer).  It
+/// changes the body type of the requests to [`SentryRequestBody`].
+///
+/// # Examples
+///
+/// ```
+/// # use tower::ServiceExt;
+/// # type Request = http::Request<String>;
+/// # async fn read_body<B: http_body::Body + Unpin>(mut body: B) {
+/// #     while let Some(_) = body.data().await {}
+/// # }
+/// # let handler = tower::service_fn(|request: http::Request<_>| async {
+/// #     read_body(request.into_body()).await;
+/// #     sentry::capture_message("invalid order", sentry::Level::Error);
+/// #     Ok::<_, std::convert::Infallible>(http::Response::new(String::new()))
+/// # });
+/// # let options = sentry::ClientOptions {
+/// #     send_default_pii: true,
+/// #     ..Default::default()
+/// # };
+/// # let events = sentry::test::with_captured_events_options(|| {
+/// let service = tower::ServiceBuilder::new()
+///     .layer(sentry_tower::NewSentryLayer::<Request>::new_from_top())
+///     .layer(sentry_tower::SentryHttpLayer::new())
+///     .layer(sentry_tower::SentryRequestBodyLayer::new(4 * 1024))
+///     .service(handler);
+/// # let request = http::Request::post("/orders").body(r#"{"id":42}"#.to_owned()).unwrap();
+/// # let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// # rt.block_on(service.oneshot(request)).unwrap();
+/// # }, options);
+/// # let request = events[0].request.as_ref().unwrap();
+/// # assert_eq!(request.method.as_deref(), Some("POST"));
+/// # assert_eq!(request.data.as_deref(), Some(r#"{"id":42}"#));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SentryRequestBodyLayer {
+    max_bytes: usize,
+}
+
+impl SentryRequestBodyLayer {
+    /// Creates a new Layer that captures up to `max_bytes` of request bodies.
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes }
+    }
+}
+
+/// Tower Service that attaches the beginning of request bodies to the errors
+/// captured during a request.
+///
+/// See [`SentryRequestBodyLayer`] for details.
+#[derive(Clone)]
+pub struct SentryRequestBodyService<S> {
+    service: S,
+    max_bytes: usize,
+}
+
+impl<S> Layer<S> for SentryRequestBodyLayer {
+    type Service = SentryRequestBodyService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        Self::Service {
+            service,
+            max_bytes: self.max_bytes,
+        }
+    }
+}
+
+/// The request body passed on by [`SentryRequestBodyService`].
+///
+/// It copies the data that is read from the original body.  The chunks of the
+/// original body are passed on as [`Bytes`], which does not copy them if
+/// they already are.
+#[pin_project::pin_project]
+pub struct SentryRequestBody<B> {
+    #[pin]
+    inner: B,
+    captured: Option<CapturedBody>,
+    max_bytes: usize,
+}
+
+impl<B: Body> Body for SentryRequestBody<B> {
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let slf = self.project();
+        let data = match slf.inner.poll_data(cx) {
+            Poll::Ready(data) => data,
+            Poll::Pending => return Poll::Pending,
+        };
+        // a chunk may consist of several segments, so it is copied as a whole
+        let data = data.map(|data| data.map(|mut data| data.copy_to_bytes(data.remaining())));
+        if let (Some(Ok(data)), Some(captured)) = (&data, slf.captured.as_ref()) {
+            let mut captured = captured.lock().unwrap_or_else(PoisonError::into_inner);
+            let len = data.len().min(*slf.max_bytes - captured.len());
+            captured.extend_from_slice(&data[..len]);
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// The Future returned from [`SentryRequestBodyService`].
+#[pin_project::pin_project]
+pub struct SentryRequestBodyFuture<F> {
+    on_first_poll: Option<CapturedBody>,
+    #[pin]
+    future: F,
+}
+
+impl<F: Future> Future for SentryRequestBodyFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let slf = self.project();
+        // added on the first poll, so this runs after the event processor of
+        // the `SentryHttpLayer`, which attaches the request
+        if let Some(captured) = slf.on_first_poll.take() {
+            sentry_core::configure_scope(|scope| {
+                scope.add_event_processor(move |mut event| {
+                    if event.level < Level::Error {
+                        return Some(event);
+                    }
+                    let captured = captured.lock().unwrap_or_else(PoisonError::into_inner);
+                    if !captured.is_empty() {
+                        let request = event.request.get_or_insert_with(Default::default);
+                        if request.data.is_none() {
+                            request.data = Some(String::from_utf8_lossy(&captured).into_owned());
+                        }
+                    }
+                    Some(event)
+                })
+            });
+        }
+        slf.future.poll(cx)
+    }
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for SentryRequestBodyService<S>
+where
+    S: Service<Request<SentryRequestBody<ReqBody>>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SentryRequestBodyFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let send_default_pii = Hub::current()
+            .client()
+            .map_or(false, |client| client.options().send_default_pii);
+        let captured = (self.max_bytes > 0 && send_default_pii).then(CapturedBody::default);
+        let max_bytes = self.max_bytes;
+        let request = request.map(|inner| SentryRequestBody {
+            inner,
+            captured: captured.clone(),
+            max_bytes,
+        });
+        SentryRequestBodyFuture {
+            on_first_poll: captured,
+            future: self.service.call(request),
+        }
+    }
+}
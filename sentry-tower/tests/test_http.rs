@@ -0,0 +1,224 @@
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::buf::Chain;
+use bytes::{Buf, Bytes};
+use http::{HeaderMap, Request, Response, StatusCode};
+use sentry::protocol::EnvelopeItem;
+use sentry::RouteFilter;
+use sentry_tower::{NewSentryLayer, SentryHttpLayer, SentryRequestBodyLayer};
+use tower::{Service, ServiceBuilder, ServiceExt};
+
+/// A request body made of a single chunk with two segments.
+struct ChainBody(Option<Chain<Bytes, Bytes>>);
+
+impl ChainBody {
+    fn new(first: &'static str, second: &'static str) -> Self {
+        Self(Some(Bytes::from(first).chain(Bytes::from(second))))
+    }
+}
+
+impl http_body::Body for ChainBody {
+    type Data = Chain<Bytes, Bytes>;
+    type Error = Infallible;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.get_mut().0.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+}
+
+/// Sends the requests through the service, one after the other, and returns
+/// the responses.
+fn send_all<S, B>(mut service: S, requests: Vec<Request<B>>) -> Vec<Response<String>>
+where
+    S: Service<Request<B>, Response = Response<String>, Error = Infallible>,
+{
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    requests
+        .into_iter()
+        .map(|request| {
+            rt.block_on(async {
+                service.ready().await.unwrap();
+                service.call(request).await.unwrap()
+            })
+        })
+        .collect()
+}
+
+/// A handler that fails requests to `/fail` with an error event.
+async fn handler<B>(request: Request<B>) -> Result<Response<String>, Infallible>
+where
+    B: http_body::Body,
+    B::Error: std::fmt::Debug,
+{
+    let path = request.uri().path().to_owned();
+    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+    assert_eq!(body, "hello world");
+    sentry::capture_message("handling request", sentry::Level::Info);
+    if path != "/fail" {
+        return Ok(Response::new(String::new()));
+    }
+    sentry::capture_message("request failed", sentry::Level::Error);
+    let mut response = Response::new(String::new());
+    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    Ok(response)
+}
+
+fn request(path: &str) -> Request<ChainBody> {
+    Request::post(path)
+        .body(ChainBody::new("hello ", "world"))
+        .unwrap()
+}
+
+#[test]
+fn test_request_body() {
+    for send_default_pii in [true, false] {
+        let events = sentry::test::with_captured_events_options(
+            || {
+                let service = ServiceBuilder::new()
+                    .layer(NewSentryLayer::<Request<ChainBody>>::new_from_top())
+                    .layer(SentryHttpLayer::new())
+                    .layer(SentryRequestBodyLayer::new(1024))
+                    .service_fn(handler);
+                send_all(service, vec![request("/fail")]);
+            },
+            sentry::ClientOptions {
+                send_default_pii,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(events.len(), 2);
+        // only the error gets the body, and all segments of the chunk are copied
+        let data = |index: usize| events[index].request.as_ref().unwrap().data.as_deref();
+        assert_eq!(data(0), None);
+        let expected = if send_default_pii {
+            Some("hello world")
+        } else {
+            None
+        };
+        assert_eq!(data(1), expected);
+    }
+}
+
+#[test]
+fn test_request_body_is_capped() {
+    let events = sentry::test::with_captured_events_options(
+        || {
+            let service = ServiceBuilder::new()
+                .layer(NewSentryLayer::<Request<ChainBody>>::new_from_top())
+                .layer(SentryHttpLayer::new())
+                .layer(SentryRequestBodyLayer::new(8))
+                .service_fn(handler);
+            send_all(service, vec![request("/fail")]);
+        },
+        sentry::ClientOptions {
+            send_default_pii: true,
+            ..Default::default()
+        },
+    );
+
+    let request = events[1].request.as_ref().unwrap();
+    assert_eq!(request.data.as_deref(), Some("hello wo"));
+}
+
+#[test]
+fn test_route_filter() {
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let service = ServiceBuilder::new()
+                .layer(NewSentryLayer::<Request<ChainBody>>::new_from_top())
+                .layer(
+                    SentryHttpLayer::with_transaction()
+                        .route_filter(RouteFilter::new().deny("/healthz")),
+                )
+                .service_fn(handler);
+            send_all(service, vec![request("/healthz"), request("/users")]);
+        },
+        sentry::ClientOptions {
+            traces_sample_rate: 1.0,
+            ..Default::default()
+        },
+    );
+
+    let transactions: Vec<_> = envelopes
+        .iter()
+        .flat_map(|envelope| envelope.items())
+        .filter_map(|item| match item {
+            EnvelopeItem::Transaction(transaction) => transaction.name.as_deref(),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(transactions, ["POST /users"]);
+}
+
+#[test]
+fn test_correlation_headers() {
+    let mut responses = Vec::new();
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let service = ServiceBuilder::new()
+                .layer(NewSentryLayer::<Request<ChainBody>>::new_from_top())
+                .layer(SentryHttpLayer::with_transaction().emit_correlation_headers(true))
+                .service_fn(handler);
+            responses = send_all(service, vec![request("/fail")]);
+        },
+        sentry::ClientOptions {
+            traces_sample_rate: 1.0,
+            ..Default::default()
+        },
+    );
+
+    let (event, transaction) = match (
+        envelopes[1].items().next().unwrap(),
+        envelopes[2].items().next().unwrap(),
+    ) {
+        (EnvelopeItem::Event(event), EnvelopeItem::Transaction(transaction)) => {
+            (event, transaction)
+        }
+        _ => panic!("expected an event and a transaction"),
+    };
+    assert_eq!(event.message.as_deref(), Some("request failed"));
+
+    let headers = responses[0].headers();
+    assert_eq!(
+        headers["x-sentry-event-id"],
+        event.event_id.simple().to_string()
+    );
+    let trace_id = match transaction.contexts.get("trace") {
+        Some(sentry::protocol::Context::Trace(trace)) => trace.trace_id,
+        _ => panic!("expected a trace context"),
+    };
+    assert_eq!(headers["x-sentry-trace-id"], trace_id.to_string());
+}
+
+#[test]
+fn test_no_correlation_headers_without_events() {
+    let mut responses = Vec::new();
+    sentry::test::with_captured_events(|| {
+        let service = ServiceBuilder::new()
+            .layer(NewSentryLayer::<Request<ChainBody>>::new_from_top())
+            .layer(SentryHttpLayer::new().emit_correlation_headers(true))
+            .service_fn(|request: Request<ChainBody>| async move {
+                drop(request);
+                Ok::<_, Infallible>(Response::new(String::new()))
+            });
+        responses = send_all(service, vec![request("/ok")]);
+    });
+
+    assert!(!responses[0].headers().contains_key("x-sentry-event-id"));
+    assert!(!responses[0].headers().contains_key("x-sentry-trace-id"));
+}
@@ -0,0 +1,107 @@
+use std::sync::{Arc, Mutex};
+
+use http::{Request, StatusCode};
+use hyper::Body;
+use sentry::protocol::{EnvelopeItem, SpanStatus};
+use sentry::test::TestServer;
+use sentry_tower::SentryHttpClientLayer;
+use tower::ServiceExt;
+
+/// Sends a GET request through a `hyper::Client` with the Sentry layer, and
+/// returns the `sentry-trace` header of the outgoing request.
+fn get(url: &str) -> Option<String> {
+    let header = Arc::new(Mutex::new(None));
+    let recorded = header.clone();
+    let client = tower::ServiceBuilder::new()
+        .layer(SentryHttpClientLayer::new())
+        .map_request(move |request: Request<Body>| {
+            *recorded.lock().unwrap() = request
+                .headers()
+                .get("sentry-trace")
+                .map(|value| value.to_str().unwrap().to_owned());
+            request
+        })
+        .service(hyper::Client::new());
+
+    let request = Request::get(url).body(Body::empty()).unwrap();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let response = rt
+        .block_on(async move { client.oneshot(request).await })
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let header = header.lock().unwrap().take();
+    header
+}
+
+#[test]
+fn test_breadcrumbs() {
+    let server = TestServer::start();
+    let url = format!("http://{}/path?token=secret", server.addr());
+
+    for send_default_pii in [false, true] {
+        let mut header = None;
+        let events = sentry::test::with_captured_events_options(
+            || {
+                header = get(&url);
+                sentry::capture_message("after request", sentry::Level::Info);
+            },
+            sentry::ClientOptions {
+                send_default_pii,
+                ..Default::default()
+            },
+        );
+
+        // no span is bound, so no trace headers are injected
+        assert_eq!(header, None);
+        assert_eq!(events.len(), 1);
+        let breadcrumbs = &events[0].breadcrumbs;
+        assert_eq!(breadcrumbs.len(), 1);
+        assert_eq!(breadcrumbs[0].ty, "http");
+        assert_eq!(breadcrumbs[0].data["method"], "GET");
+        assert_eq!(breadcrumbs[0].data["status_code"], 404);
+        let expected_url = if send_default_pii {
+            url.clone()
+        } else {
+            format!("http://{}/path", server.addr())
+        };
+        assert_eq!(breadcrumbs[0].data["url"], expected_url.as_str());
+    }
+}
+
+#[test]
+fn test_spans() {
+    let server = TestServer::start();
+    let url = format!("http://{}/path", server.addr());
+
+    let mut header = None;
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let transaction =
+                sentry::start_transaction(sentry::TransactionContext::new("test", "test"));
+            sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+            header = get(&url);
+            transaction.finish();
+        },
+        sentry::ClientOptions {
+            traces_sample_rate: 1.0,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(envelopes.len(), 1);
+    let transaction = match envelopes[0].items().next().unwrap() {
+        EnvelopeItem::Transaction(transaction) => transaction,
+        _ => panic!("expected a transaction"),
+    };
+    assert_eq!(transaction.spans.len(), 1);
+    let span = &transaction.spans[0];
+    assert_eq!(span.op.as_deref(), Some("http.client"));
+    assert_eq!(span.description, Some(format!("GET {}", url)));
+    assert_eq!(span.status, Some(SpanStatus::NotFound));
+    assert_eq!(span.data["status_code"], 404);
+    assert_eq!(
+        header,
+        Some(format!("{}-{}-1", span.trace_id, span.span_id))
+    );
+}
@@ -9,7 +9,7 @@ use tracing_subscriber::layer::Context;
 use tracing_subscriber::registry::LookupSpan;
 
 /// Converts a [`tracing_core::Level`] to a Sentry [`Level`]
-fn convert_tracing_level(level: &tracing_core::Level) -> Level {
+pub(crate) fn convert_tracing_level(level: &tracing_core::Level) -> Level {
     match level {
         &tracing_core::Level::TRACE | &tracing_core::Level::DEBUG => Level::Debug,
         &tracing_core::Level::INFO => Level::Info,
@@ -1,4 +1,4 @@
-use sentry_core::{Breadcrumb, TransactionOrSpan};
+use sentry_core::{Breadcrumb, RecordFilter, TransactionOrSpan};
 use tracing_core::{span, Event, Level, Metadata, Subscriber};
 use tracing_subscriber::layer::{Context, Layer};
 use tracing_subscriber::registry::LookupSpan;
@@ -6,17 +6,7 @@ use tracing_subscriber::registry::LookupSpan;
 use crate::converters::*;
 
 /// The action that Sentry should perform for a [`Metadata`]
-#[derive(Debug, Clone, Copy)]
-pub enum EventFilter {
-    /// Ignore the [`Event`]
-    Ignore,
-    /// Create a [`Breadcrumb`] from this [`Event`]
-    Breadcrumb,
-    /// Create a message [`sentry_core::protocol::Event`] from this [`Event`]
-    Event,
-    /// Create an exception [`sentry_core::protocol::Event`] from this [`Event`]
-    Exception,
-}
+pub use sentry_core::EventFilter;
 
 /// The type of data Sentry should ingest for a [`Event`]
 #[derive(Debug)]
@@ -77,6 +67,16 @@ impl<S> SentryLayer<S> {
         self
     }
 
+    /// Sets a [`RecordFilter`] as the event filter.
+    ///
+    /// The filter classifies [`Event`]s based on their level and target.
+    #[must_use]
+    pub fn record_filter(self, filter: RecordFilter) -> Self {
+        self.event_filter(move |metadata| {
+            filter.filter(convert_tracing_level(metadata.level()), metadata.target())
+        })
+    }
+
     /// Sets a custom event mapper function.
     ///
     /// The mapper is responsible for creating either breadcrumbs or events from
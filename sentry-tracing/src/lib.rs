@@ -75,6 +75,19 @@
 //!
 //! tracing_subscriber::registry().with(layer).init();
 //! ```
+//!
+//! Filters can also be composed from rules based on the level and target of
+//! events using a [`RecordFilter`](sentry_core::RecordFilter), which works
+//! the same way with all integrations:
+//!
+//! ```rust
+//! use sentry_core::{EventFilter, Level, RecordFilter};
+//!
+//! let filter = RecordFilter::new()
+//!     .level(Level::Warning, EventFilter::Event)
+//!     .target("hyper", EventFilter::Ignore);
+//! let layer = sentry_tracing::layer::<tracing_subscriber::Registry>().record_filter(filter);
+//! ```
 
 #![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
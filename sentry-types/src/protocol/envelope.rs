@@ -1,4 +1,4 @@
-use std::{io::Write, path::Path};
+use std::{fmt, io::Write, path::Path};
 
 use serde::Deserialize;
 use thiserror::Error;
@@ -6,7 +6,10 @@ use uuid::Uuid;
 
 use super::{
     attachment::AttachmentType,
-    v7::{Attachment, Event, SampleProfile, SessionAggregates, SessionUpdate, Transaction},
+    v7::{
+        Attachment, Event, MetricBucket, SampleProfile, SessionAggregates, SessionUpdate,
+        Transaction, UserReport,
+    },
 };
 
 /// Raised if a envelope cannot be parsed from a given input.
@@ -61,6 +64,21 @@ enum EnvelopeItemType {
     /// A Profile Item Type
     #[serde(rename = "profile")]
     Profile,
+    /// A User Report Item Type
+    #[serde(rename = "user_report")]
+    UserReport,
+    /// A Metrics Item Type
+    #[serde(rename = "statsd")]
+    Statsd,
+    /// Any other Item Type, which is parsed as a raw item.
+    #[serde(other)]
+    Unknown,
+}
+
+/// The type of an Envelope Item Header, used to parse unknown item types.
+#[derive(Deserialize)]
+struct RawItemType {
+    r#type: String,
 }
 
 /// An Envelope Item Header.
@@ -109,10 +127,91 @@ pub enum EnvelopeItem {
     Attachment(Attachment),
     /// An Profile Item.
     Profile(SampleProfile),
+    /// A User Report Item.
+    ///
+    /// See the [User Feedback documentation](https://develop.sentry.dev/sdk/envelopes/#user-feedback)
+    /// for more details.
+    UserReport(UserReport),
+    /// A Metrics Item, holding aggregated metric buckets.
+    ///
+    /// See the [Metrics documentation](https://develop.sentry.dev/sdk/metrics/)
+    /// for more details.
+    Statsd(Vec<MetricBucket>),
+    /// A custom Item with an arbitrary type and payload.
+    ///
+    /// This is also used for items of types that this crate does not know
+    /// when parsing Envelopes.
+    Raw(RawEnvelopeItem),
     // TODO:
     // etc…
 }
 
+/// A custom Envelope Item with an arbitrary type and payload.
+///
+/// This allows sending items that the SDK does not support yet, so new
+/// features of Sentry can be used before they gain first-class support.  The
+/// payload is sent as-is.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RawEnvelopeItem {
+    /// The item type, such as `"check_in"`.
+    pub ty: String,
+    /// The payload of the item.
+    pub payload: Vec<u8>,
+    /// The content type of the payload, if any.
+    pub content_type: Option<String>,
+}
+
+impl RawEnvelopeItem {
+    /// Creates a new item of the given type and payload.
+    pub fn new<T: Into<String>, P: Into<Vec<u8>>>(ty: T, payload: P) -> Self {
+        RawEnvelopeItem {
+            ty: ty.into(),
+            payload: payload.into(),
+            content_type: None,
+        }
+    }
+
+    /// Creates a new item of the given type with a JSON payload.
+    pub fn from_json<T: Into<String>, V: serde::Serialize>(
+        ty: T,
+        payload: &V,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(RawEnvelopeItem {
+            content_type: Some("application/json".into()),
+            ..RawEnvelopeItem::new(ty, serde_json::to_vec(payload)?)
+        })
+    }
+
+    fn to_writer<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        write!(
+            writer,
+            r#"{{"type":{},"length":{}"#,
+            serde_json::to_string(&self.ty)?,
+            self.payload.len()
+        )?;
+        if let Some(ref content_type) = self.content_type {
+            write!(
+                writer,
+                r#","content_type":{}"#,
+                serde_json::to_string(content_type)?
+            )?;
+        }
+        writeln!(writer, "}}")?;
+        writer.write_all(&self.payload)
+    }
+}
+
+// Implement Debug manually, like for `Attachment`, to not dump the payload.
+impl fmt::Debug for RawEnvelopeItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawEnvelopeItem")
+            .field("ty", &self.ty)
+            .field("payload", &self.payload.len())
+            .field("content_type", &self.content_type)
+            .finish()
+    }
+}
+
 impl From<Event<'static>> for EnvelopeItem {
     fn from(event: Event<'static>) -> Self {
         EnvelopeItem::Event(event)
@@ -149,6 +248,24 @@ impl From<SampleProfile> for EnvelopeItem {
     }
 }
 
+impl From<Vec<MetricBucket>> for EnvelopeItem {
+    fn from(buckets: Vec<MetricBucket>) -> Self {
+        EnvelopeItem::Statsd(buckets)
+    }
+}
+
+impl From<UserReport> for EnvelopeItem {
+    fn from(report: UserReport) -> Self {
+        EnvelopeItem::UserReport(report)
+    }
+}
+
+impl From<RawEnvelopeItem> for EnvelopeItem {
+    fn from(item: RawEnvelopeItem) -> Self {
+        EnvelopeItem::Raw(item)
+    }
+}
+
 /// An Iterator over the items of an Envelope.
 #[derive(Clone)]
 pub struct EnvelopeItemIter<'s> {
@@ -293,15 +410,31 @@ impl Envelope {
                     writeln!(writer)?;
                     continue;
                 }
+                EnvelopeItem::Raw(item) => {
+                    item.to_writer(&mut writer)?;
+                    writeln!(writer)?;
+                    continue;
+                }
                 EnvelopeItem::Profile(profile) => serde_json::to_writer(&mut item_buf, profile)?,
+                EnvelopeItem::UserReport(report) => serde_json::to_writer(&mut item_buf, report)?,
+                EnvelopeItem::Statsd(buckets) => {
+                    for (i, bucket) in buckets.iter().enumerate() {
+                        if i > 0 {
+                            writeln!(item_buf)?;
+                        }
+                        write!(item_buf, "{}", bucket)?;
+                    }
+                }
             }
             let item_type = match item {
                 EnvelopeItem::Event(_) => "event",
                 EnvelopeItem::SessionUpdate(_) => "session",
                 EnvelopeItem::SessionAggregates(_) => "sessions",
                 EnvelopeItem::Transaction(_) => "transaction",
-                EnvelopeItem::Attachment(_) => unreachable!(),
+                EnvelopeItem::Attachment(_) | EnvelopeItem::Raw(_) => unreachable!(),
                 EnvelopeItem::Profile(_) => "profile",
+                EnvelopeItem::UserReport(_) => "user_report",
+                EnvelopeItem::Statsd(_) => "statsd",
             };
             writeln!(
                 writer,
@@ -426,6 +559,21 @@ impl Envelope {
                 ty: header.attachment_type,
             })),
             EnvelopeItemType::Profile => serde_json::from_slice(payload).map(EnvelopeItem::Profile),
+            EnvelopeItemType::UserReport => {
+                serde_json::from_slice(payload).map(EnvelopeItem::UserReport)
+            }
+            EnvelopeItemType::Statsd => {
+                Ok(EnvelopeItem::Statsd(MetricBucket::parse_statsd(payload)))
+            }
+            EnvelopeItemType::Unknown => {
+                serde_json::from_slice::<RawItemType>(&slice[..header_end]).map(|raw| {
+                    EnvelopeItem::Raw(RawEnvelopeItem {
+                        ty: raw.r#type,
+                        payload: payload.to_owned(),
+                        content_type: header.content_type,
+                    })
+                })
+            }
         }
         .map_err(EnvelopeError::InvalidItemPayload)?;
 
@@ -465,7 +613,7 @@ mod test {
     use time::OffsetDateTime;
 
     use super::*;
-    use crate::protocol::v7::{Level, SessionAttributes, SessionStatus, Span};
+    use crate::protocol::v7::{Level, MetricValue, SessionAttributes, SessionStatus, Span};
 
     fn to_str(envelope: Envelope) -> String {
         let mut vec = Vec::new();
@@ -593,6 +741,56 @@ some content
         )
     }
 
+    #[test]
+    fn test_user_report() {
+        let event_id = Uuid::parse_str("22d00b3f-d1b1-4b5d-8d20-49d138cd8a9c").unwrap();
+        let mut envelope = Envelope::new();
+        envelope.add_item(UserReport {
+            event_id,
+            name: "Jane".into(),
+            email: "jane@example.com".into(),
+            comments: "It broke".into(),
+        });
+
+        let serialized = to_str(envelope.clone());
+        assert_eq!(
+            serialized,
+            r#"{}
+{"type":"user_report","length":110}
+{"event_id":"22d00b3fd1b14b5d8d2049d138cd8a9c","name":"Jane","email":"jane@example.com","comments":"It broke"}
+"#
+        );
+        assert_eq!(
+            Envelope::from_slice(serialized.as_bytes()).unwrap(),
+            envelope
+        );
+    }
+
+    #[test]
+    fn test_statsd() {
+        let mut envelope = Envelope::new();
+        envelope.add_item(vec![MetricBucket {
+            name: "requests".into(),
+            unit: "none".into(),
+            value: MetricValue::Counter(3.0),
+            tags: Default::default(),
+            timestamp: timestamp("2020-07-20T14:51:14Z"),
+        }]);
+
+        let serialized = to_str(envelope.clone());
+        assert_eq!(
+            serialized,
+            r#"{}
+{"type":"statsd","length":29}
+requests@none:3|c|T1595256674
+"#
+        );
+        assert_eq!(
+            Envelope::from_slice(serialized.as_bytes()).unwrap(),
+            envelope
+        );
+    }
+
     #[test]
     fn test_deserialize_envelope_empty() {
         // Without terminating newline after header
@@ -763,6 +961,28 @@ some content
         }
     }
 
+    #[test]
+    fn test_raw_item_roundtrip() {
+        let mut envelope = Envelope::new();
+        envelope.add_item(RawEnvelopeItem::new("custom", b"hello\nworld".to_vec()));
+        envelope.add_item(
+            RawEnvelopeItem::from_json("check_in", &serde_json::json!({"status": "ok"})).unwrap(),
+        );
+
+        let serialized = to_str(envelope.clone());
+        assert_eq!(
+            serialized,
+            "{}\n\
+             {\"type\":\"custom\",\"length\":11}\nhello\nworld\n\
+             {\"type\":\"check_in\",\"length\":15,\"content_type\":\"application/json\"}\n\
+             {\"status\":\"ok\"}\n"
+        );
+        assert_eq!(
+            Envelope::from_slice(serialized.as_bytes()).unwrap(),
+            envelope
+        );
+    }
+
     // Test all possible item types in a single envelope
     #[test]
     fn test_deserialize_serialized() {
@@ -0,0 +1,393 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The unit of the values of a metric.
+///
+/// Sentry uses the unit to render and convert values in dashboards.  Units
+/// that are not known to Sentry can be given as [`MetricUnit::Custom`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum MetricUnit {
+    /// A nanosecond, 10^-9 seconds.
+    Nanosecond,
+    /// A microsecond, 10^-6 seconds.
+    Microsecond,
+    /// A millisecond, 10^-3 seconds.
+    Millisecond,
+    /// A full second.
+    Second,
+    /// A minute, 60 seconds.
+    Minute,
+    /// An hour, 3600 seconds.
+    Hour,
+    /// A day, 86,400 seconds.
+    Day,
+    /// A week, 604,800 seconds.
+    Week,
+    /// A bit, 1/8 of a byte.
+    Bit,
+    /// A byte, 8 bits.
+    Byte,
+    /// A kilobyte, 10^3 bytes.
+    Kilobyte,
+    /// A kibibyte, 2^10 bytes.
+    Kibibyte,
+    /// A megabyte, 10^6 bytes.
+    Megabyte,
+    /// A mebibyte, 2^20 bytes.
+    Mebibyte,
+    /// A gigabyte, 10^9 bytes.
+    Gigabyte,
+    /// A gibibyte, 2^30 bytes.
+    Gibibyte,
+    /// A terabyte, 10^12 bytes.
+    Terabyte,
+    /// A tebibyte, 2^40 bytes.
+    Tebibyte,
+    /// A floating point fraction of `1`.
+    Ratio,
+    /// A ratio expressed as a fraction of `100`.
+    Percent,
+    /// A unit that is not known to Sentry.
+    Custom(String),
+    /// The values have no unit.
+    None,
+}
+
+impl MetricUnit {
+    /// Returns the name of the unit, as used in the statsd format.
+    pub fn as_str(&self) -> &str {
+        match self {
+            MetricUnit::Nanosecond => "nanosecond",
+            MetricUnit::Microsecond => "microsecond",
+            MetricUnit::Millisecond => "millisecond",
+            MetricUnit::Second => "second",
+            MetricUnit::Minute => "minute",
+            MetricUnit::Hour => "hour",
+            MetricUnit::Day => "day",
+            MetricUnit::Week => "week",
+            MetricUnit::Bit => "bit",
+            MetricUnit::Byte => "byte",
+            MetricUnit::Kilobyte => "kilobyte",
+            MetricUnit::Kibibyte => "kibibyte",
+            MetricUnit::Megabyte => "megabyte",
+            MetricUnit::Mebibyte => "mebibyte",
+            MetricUnit::Gigabyte => "gigabyte",
+            MetricUnit::Gibibyte => "gibibyte",
+            MetricUnit::Terabyte => "terabyte",
+            MetricUnit::Tebibyte => "tebibyte",
+            MetricUnit::Ratio => "ratio",
+            MetricUnit::Percent => "percent",
+            MetricUnit::Custom(unit) => unit,
+            MetricUnit::None => "none",
+        }
+    }
+}
+
+impl Default for MetricUnit {
+    fn default() -> Self {
+        MetricUnit::None
+    }
+}
+
+impl fmt::Display for MetricUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MetricUnit {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "nanosecond" | "ns" => MetricUnit::Nanosecond,
+            "microsecond" => MetricUnit::Microsecond,
+            "millisecond" | "ms" => MetricUnit::Millisecond,
+            "second" | "s" => MetricUnit::Second,
+            "minute" => MetricUnit::Minute,
+            "hour" => MetricUnit::Hour,
+            "day" => MetricUnit::Day,
+            "week" => MetricUnit::Week,
+            "bit" => MetricUnit::Bit,
+            "byte" => MetricUnit::Byte,
+            "kilobyte" => MetricUnit::Kilobyte,
+            "kibibyte" => MetricUnit::Kibibyte,
+            "megabyte" => MetricUnit::Megabyte,
+            "mebibyte" => MetricUnit::Mebibyte,
+            "gigabyte" => MetricUnit::Gigabyte,
+            "gibibyte" => MetricUnit::Gibibyte,
+            "terabyte" => MetricUnit::Terabyte,
+            "tebibyte" => MetricUnit::Tebibyte,
+            "ratio" => MetricUnit::Ratio,
+            "percent" => MetricUnit::Percent,
+            "" | "none" => MetricUnit::None,
+            other => MetricUnit::Custom(other.to_owned()),
+        })
+    }
+}
+
+impl From<&str> for MetricUnit {
+    fn from(unit: &str) -> Self {
+        match unit.parse() {
+            Ok(unit) => unit,
+            Err(never) => match never {},
+        }
+    }
+}
+
+/// The aggregated value of a [`MetricBucket`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetricValue {
+    /// A counter, the sum of all recorded values.
+    Counter(f64),
+    /// A distribution of all recorded values, from which Sentry computes
+    /// percentiles.
+    Distribution(Vec<f64>),
+}
+
+impl MetricValue {
+    /// Merges the values of `other` into this value.
+    ///
+    /// Returns `false` without changing anything if the metric types differ.
+    pub fn merge(&mut self, other: MetricValue) -> bool {
+        match (self, other) {
+            (MetricValue::Counter(sum), MetricValue::Counter(value)) => *sum += value,
+            (MetricValue::Distribution(values), MetricValue::Distribution(other)) => {
+                values.extend(other)
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn ty(&self) -> &'static str {
+        match self {
+            MetricValue::Counter(_) => "c",
+            MetricValue::Distribution(_) => "d",
+        }
+    }
+}
+
+/// Metric values that were aggregated for a time window, as sent in a
+/// `statsd` envelope item.
+///
+/// See the [Metrics documentation](https://develop.sentry.dev/sdk/metrics/)
+/// for more details.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricBucket {
+    /// The name of the metric.
+    pub name: String,
+    /// The unit of the values.
+    pub unit: MetricUnit,
+    /// The aggregated value.
+    pub value: MetricValue,
+    /// The tags of the metric.
+    pub tags: BTreeMap<String, String>,
+    /// The start of the time window of the bucket.
+    pub timestamp: SystemTime,
+}
+
+impl MetricBucket {
+    /// Parses metric buckets from the payload of a `statsd` envelope item.
+    ///
+    /// Malformed lines are skipped.
+    pub fn parse_statsd(payload: &[u8]) -> Vec<MetricBucket> {
+        String::from_utf8_lossy(payload)
+            .lines()
+            .filter_map(parse_statsd_line)
+            .collect()
+    }
+}
+
+/// Formats the bucket as a line of the statsd format, without newline.
+impl fmt::Display for MetricBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_sanitized(f, &self.name, |c| {
+            c.is_ascii_alphanumeric() || "_-./".contains(c)
+        })?;
+        f.write_str("@")?;
+        write_sanitized(f, self.unit.as_str(), |c| {
+            c.is_ascii_alphanumeric() || c == '_'
+        })?;
+        match &self.value {
+            MetricValue::Counter(value) => write!(f, ":{}", value)?,
+            MetricValue::Distribution(values) => {
+                for value in values {
+                    write!(f, ":{}", value)?;
+                }
+            }
+        }
+        write!(f, "|{}", self.value.ty())?;
+        for (i, (key, value)) in self.tags.iter().enumerate() {
+            f.write_str(if i == 0 { "|#" } else { "," })?;
+            write_sanitized(f, key, |c| c.is_ascii_alphanumeric() || "_-./".contains(c))?;
+            f.write_str(":")?;
+            for c in value.chars() {
+                match c {
+                    '\n' => f.write_str("\\n")?,
+                    '\r' => f.write_str("\\r")?,
+                    '\t' => f.write_str("\\t")?,
+                    '\\' => f.write_str("\\\\")?,
+                    '|' => f.write_str("\\u{7c}")?,
+                    ',' => f.write_str("\\u{2c}")?,
+                    c => write!(f, "{}", c)?,
+                }
+            }
+        }
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        write!(f, "|T{}", timestamp.as_secs())
+    }
+}
+
+fn write_sanitized(
+    f: &mut fmt::Formatter<'_>,
+    value: &str,
+    is_allowed: impl Fn(char) -> bool,
+) -> fmt::Result {
+    for c in value.chars() {
+        write!(f, "{}", if is_allowed(c) { c } else { '_' })?;
+    }
+    Ok(())
+}
+
+fn parse_statsd_line(line: &str) -> Option<MetricBucket> {
+    let mut sections = line.split('|');
+    let (name, values) = sections.next()?.split_once(':')?;
+    let (name, unit) = name.split_once('@').unwrap_or((name, "none"));
+    let values = values
+        .split(':')
+        .map(|value| value.parse().ok())
+        .collect::<Option<Vec<f64>>>()?;
+    let value = match sections.next()? {
+        "c" => MetricValue::Counter(values.iter().sum()),
+        "d" => MetricValue::Distribution(values),
+        _ => return None,
+    };
+
+    let mut tags = BTreeMap::new();
+    let mut timestamp = UNIX_EPOCH;
+    for section in sections {
+        if let Some(section) = section.strip_prefix('#') {
+            for tag in section.split(',') {
+                let (key, value) = tag.split_once(':').unwrap_or((tag, ""));
+                tags.insert(key.to_owned(), unescape_tag_value(value));
+            }
+        } else if let Some(secs) = section.strip_prefix('T') {
+            timestamp = UNIX_EPOCH + Duration::from_secs(secs.parse().ok()?);
+        }
+    }
+
+    Some(MetricBucket {
+        name: name.to_owned(),
+        unit: MetricUnit::from(unit),
+        value,
+        tags,
+        timestamp,
+    })
+}
+
+fn unescape_tag_value(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(index) = rest.find('\\') {
+        unescaped.push_str(&rest[..index]);
+        let escape = &rest[index..];
+        let (c, len) = if escape.starts_with("\\u{2c}") {
+            (',', 6)
+        } else if escape.starts_with("\\u{7c}") {
+            ('|', 6)
+        } else {
+            match escape[1..].chars().next() {
+                Some('n') => ('\n', 2),
+                Some('r') => ('\r', 2),
+                Some('t') => ('\t', 2),
+                Some('\\') => ('\\', 2),
+                // not an escape sequence, keep the backslash
+                _ => ('\\', 1),
+            }
+        };
+        unescaped.push(c);
+        rest = &escape[len..];
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statsd_roundtrip() {
+        let bucket = MetricBucket {
+            name: "transaction.duration".into(),
+            unit: MetricUnit::Millisecond,
+            value: MetricValue::Distribution(vec![12.5, 30.0]),
+            tags: [
+                (
+                    "transaction".to_owned(),
+                    "GET /users|all, sorted".to_owned(),
+                ),
+                ("transaction.op".to_owned(), "http.server".to_owned()),
+            ]
+            .into_iter()
+            .collect(),
+            timestamp: UNIX_EPOCH + Duration::from_secs(1_690_000_000),
+        };
+
+        let line = bucket.to_string();
+        assert_eq!(
+            line,
+            "transaction.duration@millisecond:12.5:30|d\
+             |#transaction:GET /users\\u{7c}all\\u{2c} sorted,transaction.op:http.server\
+             |T1690000000"
+        );
+        assert_eq!(
+            MetricBucket::parse_statsd(format!("{}\ninvalid\n", line).as_bytes()),
+            [bucket]
+        );
+    }
+
+    #[test]
+    fn test_tag_value_escaping() {
+        for value in ["C:\\new\\table", "\\u{2c}", "a,b|c\nd\\", "\\", "tab\t\\t"] {
+            let bucket = MetricBucket {
+                name: "files".into(),
+                unit: MetricUnit::None,
+                value: MetricValue::Counter(1.0),
+                tags: [("path".to_owned(), value.to_owned())]
+                    .into_iter()
+                    .collect(),
+                timestamp: UNIX_EPOCH,
+            };
+            let parsed = MetricBucket::parse_statsd(bucket.to_string().as_bytes());
+            assert_eq!(parsed, [bucket]);
+        }
+    }
+
+    #[test]
+    fn test_metric_unit() {
+        assert_eq!(MetricUnit::from("second"), MetricUnit::Second);
+        assert_eq!(MetricUnit::from("none"), MetricUnit::None);
+        assert_eq!(
+            MetricUnit::from("request"),
+            MetricUnit::Custom("request".into())
+        );
+        assert_eq!(MetricUnit::Gibibyte.to_string(), "gibibyte");
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut value = MetricValue::Counter(1.0);
+        assert!(value.merge(MetricValue::Counter(2.0)));
+        assert!(!value.merge(MetricValue::Distribution(vec![1.0])));
+        assert_eq!(value, MetricValue::Counter(3.0));
+    }
+}
@@ -15,5 +15,6 @@ pub use v7 as latest;
 
 mod attachment;
 mod envelope;
+mod metrics;
 mod profile;
 mod session;
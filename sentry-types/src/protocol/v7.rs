@@ -26,6 +26,7 @@ use crate::utils::{ts_rfc3339_opt, ts_seconds_float};
 
 pub use super::attachment::*;
 pub use super::envelope::*;
+pub use super::metrics::*;
 pub use super::profile::*;
 pub use super::session::*;
 
@@ -158,7 +159,9 @@ impl<T> IntoIterator for Values<T> {
 /// Represents a log entry message.
 ///
 /// A log message is similar to the `message` attribute on the event itself but
-/// can additionally hold optional parameters.
+/// can additionally hold optional parameters.  Sentry groups events by the
+/// message template rather than the formatted message, so messages that only
+/// differ in their parameters end up in the same issue.
 #[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
 pub struct LogEntry {
     /// The log message with parameters replaced by `%s`
@@ -166,6 +169,74 @@ pub struct LogEntry {
     /// Positional parameters to be inserted into the log entry.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub params: Vec<Value>,
+    /// The message with the parameters inserted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formatted: Option<String>,
+}
+
+impl LogEntry {
+    /// Creates a parameterized log entry from a message template.
+    ///
+    /// Placeholders in the `template` are either `%s` or `{}`, where the
+    /// braces may contain a name such as `{path}`.  They are replaced by the
+    /// `params` in order to produce the `formatted` message.  `%%`, `{{` and
+    /// `}}` produce literal characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sentry_types::protocol::v7::LogEntry;
+    /// let entry = LogEntry::with_params("failed to read {path}: {}", vec!["a.txt".into(), 2.into()]);
+    /// assert_eq!(entry.formatted.as_deref(), Some("failed to read a.txt: 2"));
+    /// ```
+    pub fn with_params<S: Into<String>>(template: S, params: Vec<Value>) -> LogEntry {
+        let message = template.into();
+        let mut formatted = String::with_capacity(message.len());
+        let mut params_iter = params.iter();
+        let mut next_param = |formatted: &mut String| match params_iter.next() {
+            Some(Value::String(param)) => formatted.push_str(param),
+            Some(param) => formatted.push_str(&param.to_string()),
+            None => formatted.push_str("<missing>"),
+        };
+
+        let mut chars = message.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('%', Some('%')) | ('{', Some('{')) | ('}', Some('}')) => {
+                    formatted.push(c);
+                    chars.next();
+                }
+                ('%', Some('s')) => {
+                    chars.next();
+                    next_param(&mut formatted);
+                }
+                ('{', _) => {
+                    let mut placeholder = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        placeholder.push(c);
+                    }
+                    if closed {
+                        next_param(&mut formatted);
+                    } else {
+                        formatted.push('{');
+                        formatted.push_str(&placeholder);
+                    }
+                }
+                _ => formatted.push(c),
+            }
+        }
+
+        LogEntry {
+            message,
+            params,
+            formatted: Some(formatted),
+        }
+    }
 }
 
 /// Represents a frame.
@@ -279,6 +350,13 @@ pub struct Stacktrace {
     /// Optional register values of the thread.
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub registers: Map<String, RegVal>,
+    /// Whether the frames were captured in this process without resolving
+    /// their symbols, which is deferred to the processing of the event.
+    ///
+    /// This is never serialized, so stacktraces of other processes are not
+    /// resolved against the symbols of this one.
+    #[serde(skip)]
+    pub deferred_resolution: bool,
 }
 
 impl Stacktrace {
@@ -608,6 +686,22 @@ pub struct Mechanism {
     /// An optional flag indicating a synthetic exception.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub synthetic: Option<bool>,
+    /// An optional description of how this exception relates to its parent,
+    /// such as `"source"` or `"errors[0]"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// An optional flag indicating that this exception is a group of other
+    /// exceptions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_exception_group: Option<bool>,
+    /// An optional identifier of this exception within the event, referred
+    /// to by the `parent_id` of related exceptions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exception_id: Option<u64>,
+    /// An optional `exception_id` of the exception this exception is
+    /// related to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<u64>,
     /// Additional attributes depending on the mechanism type.
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub data: Map<String, Value>,
@@ -1786,6 +1880,36 @@ impl fmt::Display for Span {
 #[error("invalid status")]
 pub struct ParseStatusError;
 
+/// Describes how the name of a transaction was determined.
+///
+/// Names with a low cardinality source, such as `Route`, can be grouped
+/// directly, while names with a `Url` source may be further sanitized by the
+/// server.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TransactionSource {
+    /// The name was set manually by the user.
+    Custom,
+    /// The name is the raw URL path of a request, which may contain identifiers.
+    Url,
+    /// The name is a parameterized route template, such as `/users/{id}`.
+    Route,
+    /// The name is the name of a view or handler.
+    View,
+    /// The name is the name of a component, such as a function.
+    Component,
+    /// The name is the name of a background task.
+    Task,
+}
+
+/// Additional information about the name of a transaction.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TransactionInfo {
+    /// How the transaction name was determined.
+    pub source: TransactionSource,
+}
+
 /// The status of a Span.
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[non_exhaustive]
@@ -1894,6 +2018,25 @@ impl fmt::Display for SpanStatus {
     }
 }
 
+impl SpanStatus {
+    /// Maps the status code of an HTTP response to a span status.
+    pub fn from_http_status(status: u16) -> SpanStatus {
+        match status {
+            401 => SpanStatus::Unauthenticated,
+            403 => SpanStatus::PermissionDenied,
+            404 => SpanStatus::NotFound,
+            409 => SpanStatus::AlreadyExists,
+            429 => SpanStatus::ResourceExhausted,
+            400..=499 => SpanStatus::InvalidArgument,
+            501 => SpanStatus::Unimplemented,
+            503 => SpanStatus::Unavailable,
+            500..=599 => SpanStatus::InternalError,
+            200..=299 => SpanStatus::Ok,
+            _ => SpanStatus::UnknownError,
+        }
+    }
+}
+
 /// Represents a tracing transaction.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Transaction<'a> {
@@ -1949,6 +2092,9 @@ pub struct Transaction<'a> {
     /// ID of the thread where the transaction was started
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub active_thread_id: Option<u64>,
+    /// Additional information about the transaction name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_info: Option<TransactionInfo>,
 }
 
 impl<'a> Default for Transaction<'a> {
@@ -1968,6 +2114,7 @@ impl<'a> Default for Transaction<'a> {
             contexts: Default::default(),
             request: Default::default(),
             active_thread_id: Default::default(),
+            transaction_info: Default::default(),
         }
     }
 }
@@ -1995,6 +2142,7 @@ impl<'a> Transaction<'a> {
             contexts: self.contexts,
             request: self.request,
             active_thread_id: self.active_thread_id,
+            transaction_info: self.transaction_info,
         }
     }
 
@@ -2014,3 +2162,23 @@ impl<'a> fmt::Display for Transaction<'a> {
         )
     }
 }
+
+/// Feedback submitted by a user about an event.
+///
+/// See the [User Feedback documentation](https://develop.sentry.dev/sdk/envelopes/#user-feedback)
+/// for more details.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserReport {
+    /// The ID of the event the feedback is about.
+    #[serde(serialize_with = "event::serialize_id")]
+    pub event_id: Uuid,
+    /// The name of the user.
+    #[serde(default)]
+    pub name: String,
+    /// The email address of the user.
+    #[serde(default)]
+    pub email: String,
+    /// The comments of the user.
+    #[serde(default)]
+    pub comments: String,
+}
@@ -245,6 +245,7 @@ mod test_logentry {
             logentry: Some(v7::LogEntry {
                 message: "Hello %s!".to_string(),
                 params: vec!["World".into()],
+                formatted: None,
             }),
             culprit: Some("foo in bar".to_string()),
             level: v7::Level::Debug,
@@ -267,6 +268,7 @@ mod test_logentry {
             logentry: Some(v7::LogEntry {
                 message: "Hello World!".to_string(),
                 params: vec![],
+                formatted: None,
             }),
             ..Default::default()
         };
@@ -277,6 +279,26 @@ mod test_logentry {
              World!\"},\"timestamp\":1514103120}"
         );
     }
+
+    #[test]
+    fn test_logentry_with_params() {
+        let entry = v7::LogEntry::with_params(
+            "read {path} (%s of {}) 100%% {{ok}}",
+            vec!["/tmp/a.txt".into(), 1.into(), 2.5.into()],
+        );
+        assert_eq!(entry.message, "read {path} (%s of {}) 100%% {{ok}}");
+        assert_eq!(
+            entry.formatted.as_deref(),
+            Some("read /tmp/a.txt (1 of 2.5) 100% {ok}")
+        );
+
+        let entry = v7::LogEntry::with_params("{a} and {b", vec![]);
+        assert_eq!(entry.formatted.as_deref(), Some("<missing> and {b"));
+        assert_eq!(
+            serde_json::to_string(&entry).unwrap(),
+            "{\"message\":\"{a} and {b\",\"formatted\":\"<missing> and {b\"}"
+        );
+    }
 }
 
 #[test]
@@ -997,6 +1019,7 @@ mod test_exception {
                         m.insert("x12".into(), v7::RegVal(0x1_b3b3_7b1d));
                         m
                     },
+                    deferred_resolution: false,
                 }),
                 raw_stacktrace: Some(v7::Stacktrace {
                     frames: vec![v7::Frame {
@@ -1060,6 +1083,10 @@ mod test_exception {
                     ),
                     handled: Some(false),
                     synthetic: None,
+                    source: None,
+                    is_exception_group: None,
+                    exception_id: None,
+                    parent_id: None,
                     data: {
                         let mut map = v7::Map::new();
                         map.insert("relevant_address".into(), "0x1".into());
@@ -1454,3 +1481,19 @@ fn test_orientation() {
         "\"portrait\""
     );
 }
+
+#[test]
+fn test_span_status_from_http_status() {
+    use v7::SpanStatus;
+    assert_eq!(SpanStatus::from_http_status(200), SpanStatus::Ok);
+    assert_eq!(SpanStatus::from_http_status(204), SpanStatus::Ok);
+    assert_eq!(SpanStatus::from_http_status(302), SpanStatus::UnknownError);
+    assert_eq!(SpanStatus::from_http_status(404), SpanStatus::NotFound);
+    assert_eq!(SpanStatus::from_http_status(409), SpanStatus::AlreadyExists);
+    assert_eq!(
+        SpanStatus::from_http_status(422),
+        SpanStatus::InvalidArgument
+    );
+    assert_eq!(SpanStatus::from_http_status(503), SpanStatus::Unavailable);
+    assert_eq!(SpanStatus::from_http_status(504), SpanStatus::InternalError);
+}
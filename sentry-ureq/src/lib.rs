@@ -0,0 +1,98 @@
+//! Sentry integration for the [ureq](https://crates.io/crates/ureq) HTTP client.
+//!
+//! The [`SentryMiddleware`] records a breadcrumb for each outgoing request made
+//! through a [`ureq::Agent`]. When a span is bound to the current scope, it
+//! also starts an `http.client` child span for the request, and injects the
+//! distributed tracing headers into it. The query string of the URL is only
+//! recorded if [`send_default_pii`](sentry_core::ClientOptions::send_default_pii)
+//! is enabled.
+//!
+//! # Example
+//!
+//! ```
+//! let agent = ureq::AgentBuilder::new()
+//!     .middleware(sentry_ureq::SentryMiddleware::new())
+//!     .build();
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use sentry_core::protocol::{Breadcrumb, Map, SpanStatus};
+use ureq::{Error, MiddlewareNext, Request, Response};
+
+/// A ureq Middleware that instruments outgoing requests.
+#[derive(Clone, Debug, Default)]
+pub struct SentryMiddleware {}
+
+impl SentryMiddleware {
+    /// Creates a new Middleware.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ureq::Middleware for SentryMiddleware {
+    fn handle(&self, mut request: Request, next: MiddlewareNext) -> Result<Response, Error> {
+        let method = request.method().to_owned();
+        let send_default_pii = sentry_core::Hub::current()
+            .client()
+            .map_or(false, |client| client.options().send_default_pii);
+        let url = if send_default_pii {
+            request.url()
+        } else {
+            strip_query(request.url())
+        }
+        .to_owned();
+
+        let span = sentry_core::configure_scope(|scope| scope.get_span())
+            .map(|parent| parent.start_child("http.client", &format!("{} {}", method, url)));
+        if let Some(span) = &span {
+            for (header, value) in span.iter_headers() {
+                request = request.set(header, &value);
+            }
+        }
+
+        let result = next.handle(request);
+        let status_code = match &result {
+            Ok(response) => Some(response.status()),
+            Err(Error::Status(status_code, _)) => Some(*status_code),
+            Err(_) => None,
+        };
+
+        if let Some(span) = span {
+            let status = match status_code {
+                Some(status_code) => {
+                    span.set_data("status_code", status_code.into());
+                    SpanStatus::from_http_status(status_code)
+                }
+                None => SpanStatus::UnknownError,
+            };
+            span.set_status(status);
+            span.finish();
+        }
+
+        sentry_core::add_breadcrumb(|| {
+            let mut data = Map::new();
+            data.insert("method".into(), method.into());
+            data.insert("url".into(), url.into());
+            if let Some(status_code) = status_code {
+                data.insert("status_code".into(), status_code.into());
+            }
+            Breadcrumb {
+                ty: "http".into(),
+                category: Some("http".into()),
+                data,
+                ..Default::default()
+            }
+        });
+
+        result
+    }
+}
+
+/// Removes the query string and fragment from a URL.
+fn strip_query(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or_default()
+}
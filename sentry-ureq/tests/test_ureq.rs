@@ -0,0 +1,99 @@
+use std::sync::{Arc, Mutex};
+
+use sentry::protocol::{EnvelopeItem, SpanStatus};
+use sentry::test::TestServer;
+use sentry_ureq::SentryMiddleware;
+use ureq::{Error, MiddlewareNext, Request, Response};
+
+/// Returns an agent with the Sentry middleware, which records the
+/// `sentry-trace` header of the outgoing requests into `headers`.
+#[allow(clippy::result_large_err)]
+fn agent(headers: Arc<Mutex<Vec<Option<String>>>>) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .middleware(SentryMiddleware::new())
+        .middleware(
+            move |request: Request, next: MiddlewareNext| -> Result<Response, Error> {
+                let header = request.header("sentry-trace").map(String::from);
+                headers.lock().unwrap().push(header);
+                next.handle(request)
+            },
+        )
+        .build()
+}
+
+#[test]
+fn test_breadcrumbs() {
+    let server = TestServer::start();
+    let url = format!("http://{}/path?token=secret", server.addr());
+    let headers = Arc::new(Mutex::new(Vec::new()));
+    let agent = agent(headers.clone());
+
+    for send_default_pii in [false, true] {
+        let events = sentry::test::with_captured_events_options(
+            || {
+                let result = agent.get(&url).call();
+                assert!(matches!(result, Err(Error::Status(404, _))));
+                sentry::capture_message("after request", sentry::Level::Info);
+            },
+            sentry::ClientOptions {
+                send_default_pii,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(events.len(), 1);
+        let breadcrumbs = &events[0].breadcrumbs;
+        assert_eq!(breadcrumbs.len(), 1);
+        assert_eq!(breadcrumbs[0].ty, "http");
+        assert_eq!(breadcrumbs[0].data["method"], "GET");
+        assert_eq!(breadcrumbs[0].data["status_code"], 404);
+        let expected_url = if send_default_pii {
+            url.clone()
+        } else {
+            format!("http://{}/path", server.addr())
+        };
+        assert_eq!(breadcrumbs[0].data["url"], expected_url.as_str());
+    }
+
+    // no span is bound, so no trace headers are injected
+    assert_eq!(*headers.lock().unwrap(), [None, None]);
+}
+
+#[test]
+fn test_spans() {
+    let server = TestServer::start();
+    let url = format!("http://{}/path", server.addr());
+    let headers = Arc::new(Mutex::new(Vec::new()));
+    let agent = agent(headers.clone());
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let transaction =
+                sentry::start_transaction(sentry::TransactionContext::new("test", "test"));
+            sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+            let result = agent.get(&url).call();
+            assert!(matches!(result, Err(Error::Status(404, _))));
+            transaction.finish();
+        },
+        sentry::ClientOptions {
+            traces_sample_rate: 1.0,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(envelopes.len(), 1);
+    let transaction = match envelopes[0].items().next().unwrap() {
+        EnvelopeItem::Transaction(transaction) => transaction,
+        _ => panic!("expected a transaction"),
+    };
+    assert_eq!(transaction.spans.len(), 1);
+    let span = &transaction.spans[0];
+    assert_eq!(span.op.as_deref(), Some("http.client"));
+    assert_eq!(span.description, Some(format!("GET {}", url)));
+    assert_eq!(span.status, Some(SpanStatus::NotFound));
+    assert_eq!(span.data["status_code"], 404);
+
+    let headers = headers.lock().unwrap();
+    let expected = format!("{}-{}-1", span.trace_id, span.span_id);
+    assert_eq!(headers[0].as_deref(), Some(expected.as_str()));
+}
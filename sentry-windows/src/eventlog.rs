@@ -0,0 +1,138 @@
+use std::ffi::{c_void, OsStr};
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+
+use sentry_core::protocol::{Breadcrumb, Level};
+
+type Handle = *mut c_void;
+
+const EVENTLOG_ERROR_TYPE: u16 = 0x0001;
+const EVENTLOG_WARNING_TYPE: u16 = 0x0002;
+const EVENTLOG_INFORMATION_TYPE: u16 = 0x0004;
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegisterEventSourceW(server: *const u16, source: *const u16) -> Handle;
+    fn DeregisterEventSource(handle: Handle) -> i32;
+    fn ReportEventW(
+        handle: Handle,
+        ty: u16,
+        category: u16,
+        event_id: u32,
+        user_sid: *mut c_void,
+        num_strings: u16,
+        data_size: u32,
+        strings: *const *const u16,
+        raw_data: *mut c_void,
+    ) -> i32;
+}
+
+fn to_wide(value: &str) -> Vec<u16> {
+    OsStr::new(value).encode_wide().chain(Some(0)).collect()
+}
+
+/// An event source of the Windows Event Log.
+///
+/// Entries written with [`write`](Self::write) are mirrored as breadcrumbs,
+/// unless disabled with [`mirror_breadcrumbs`](Self::mirror_breadcrumbs).
+///
+/// # Examples
+///
+/// ```no_run
+/// use sentry::Level;
+/// use sentry_windows::EventLog;
+///
+/// let log = EventLog::register("my-service").unwrap();
+/// log.write(Level::Warning, 1, "configuration file not found, using defaults")
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct EventLog {
+    source: String,
+    handle: Handle,
+    mirror_breadcrumbs: bool,
+}
+
+// Event source handles can be used from any thread.
+unsafe impl Send for EventLog {}
+unsafe impl Sync for EventLog {}
+
+impl EventLog {
+    /// Registers the event source with the given name on the local computer.
+    pub fn register(source: &str) -> io::Result<Self> {
+        let name = to_wide(source);
+        let handle = unsafe { RegisterEventSourceW(ptr::null(), name.as_ptr()) };
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(EventLog {
+            source: source.to_owned(),
+            handle,
+            mirror_breadcrumbs: true,
+        })
+    }
+
+    /// Sets whether entries are mirrored as breadcrumbs.  (defaults to `true`)
+    #[must_use]
+    pub fn mirror_breadcrumbs(mut self, mirror: bool) -> Self {
+        self.mirror_breadcrumbs = mirror;
+        self
+    }
+
+    /// Writes an entry with the given event id and message.
+    ///
+    /// `Error` and `Fatal` are written as error entries, `Warning` as warning
+    /// entries, and all other levels as information entries.
+    pub fn write(&self, level: Level, event_id: u32, message: &str) -> io::Result<()> {
+        let ty = match level {
+            Level::Fatal | Level::Error => EVENTLOG_ERROR_TYPE,
+            Level::Warning => EVENTLOG_WARNING_TYPE,
+            Level::Info | Level::Debug => EVENTLOG_INFORMATION_TYPE,
+        };
+        let message_wide = to_wide(message);
+        let strings = [message_wide.as_ptr()];
+        let written = unsafe {
+            ReportEventW(
+                self.handle,
+                ty,
+                0,
+                event_id,
+                ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                ptr::null_mut(),
+            )
+        };
+        if written == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if self.mirror_breadcrumbs {
+            sentry_core::add_breadcrumb(|| {
+                let mut breadcrumb = Breadcrumb {
+                    ty: "default".into(),
+                    category: Some("eventlog".into()),
+                    level,
+                    message: Some(message.to_owned()),
+                    ..Default::default()
+                };
+                breadcrumb
+                    .data
+                    .insert("source".into(), self.source.clone().into());
+                breadcrumb.data.insert("event_id".into(), event_id.into());
+                breadcrumb
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        unsafe {
+            DeregisterEventSource(self.handle);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Sentry integration for Windows services and the Windows Event Log.
+//!
+//! The [`WindowsServiceIntegration`] adds a `windows_service` context with the
+//! name and the current state of the service to every event.  The control
+//! handler of the service reports the control requests it receives with
+//! [`record_service_control`], which also records them as breadcrumbs, so
+//! that crashes during a stop or a pause can be told apart from crashes while
+//! running.
+//!
+//! On Windows, the [`EventLog`] writes entries to the Windows Event Log, and
+//! mirrors them as breadcrumbs.
+//!
+//! # Example
+//!
+//! ```
+//! use sentry_windows::{record_service_control, ServiceControl, WindowsServiceIntegration};
+//!
+//! let _sentry = sentry::init(
+//!     sentry::ClientOptions::new()
+//!         .add_integration(WindowsServiceIntegration::new("my-service")),
+//! );
+//!
+//! // in the service main function
+//! record_service_control(ServiceControl::Start);
+//!
+//! // in the service control handler, for instance of the `windows-service`
+//! // crate, with the `dwControl` code of the request
+//! # let code = 1;
+//! record_service_control(ServiceControl::from_code(code));
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+#[cfg(windows)]
+mod eventlog;
+mod service;
+
+#[cfg(windows)]
+pub use crate::eventlog::EventLog;
+pub use crate::service::{
+    record_service_control, ServiceControl, ServiceState, WindowsServiceIntegration,
+};
@@ -0,0 +1,238 @@
+use std::borrow::Cow;
+use std::sync::Mutex;
+
+use sentry_core::protocol::{Breadcrumb, Context, Event, Level, Map};
+use sentry_core::{ClientOptions, Integration};
+
+/// A control request of the service control manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceControl {
+    /// The service main function started.
+    ///
+    /// This is not a control request, but marks the start of the service.
+    Start,
+    /// `SERVICE_CONTROL_STOP`
+    Stop,
+    /// `SERVICE_CONTROL_PAUSE`
+    Pause,
+    /// `SERVICE_CONTROL_CONTINUE`
+    Continue,
+    /// `SERVICE_CONTROL_INTERROGATE`
+    Interrogate,
+    /// `SERVICE_CONTROL_SHUTDOWN`
+    Shutdown,
+    /// `SERVICE_CONTROL_PARAMCHANGE`
+    ParamChange,
+    /// `SERVICE_CONTROL_POWEREVENT`
+    PowerEvent,
+    /// `SERVICE_CONTROL_SESSIONCHANGE`
+    SessionChange,
+    /// `SERVICE_CONTROL_PRESHUTDOWN`
+    Preshutdown,
+    /// Any other control code.
+    Other(u32),
+}
+
+impl ServiceControl {
+    /// Returns the control for the `dwControl` code passed to the control
+    /// handler of a service.
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            0x01 => ServiceControl::Stop,
+            0x02 => ServiceControl::Pause,
+            0x03 => ServiceControl::Continue,
+            0x04 => ServiceControl::Interrogate,
+            0x05 => ServiceControl::Shutdown,
+            0x06 => ServiceControl::ParamChange,
+            0x0D => ServiceControl::PowerEvent,
+            0x0E => ServiceControl::SessionChange,
+            0x0F => ServiceControl::Preshutdown,
+            code => ServiceControl::Other(code),
+        }
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        match self {
+            ServiceControl::Start => "start".into(),
+            ServiceControl::Stop => "stop".into(),
+            ServiceControl::Pause => "pause".into(),
+            ServiceControl::Continue => "continue".into(),
+            ServiceControl::Interrogate => "interrogate".into(),
+            ServiceControl::Shutdown => "shutdown".into(),
+            ServiceControl::ParamChange => "paramchange".into(),
+            ServiceControl::PowerEvent => "powerevent".into(),
+            ServiceControl::SessionChange => "sessionchange".into(),
+            ServiceControl::Preshutdown => "preshutdown".into(),
+            ServiceControl::Other(code) => format!("0x{:x}", code).into(),
+        }
+    }
+
+    /// The state the service transitions to when handling the control.
+    fn next_state(&self) -> Option<ServiceState> {
+        match self {
+            ServiceControl::Start | ServiceControl::Continue => Some(ServiceState::Running),
+            ServiceControl::Pause => Some(ServiceState::Paused),
+            ServiceControl::Stop | ServiceControl::Shutdown | ServiceControl::Preshutdown => {
+                Some(ServiceState::Stopping)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The state of a service, as far as it is known from its control requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceState {
+    /// The service did not report its start yet.
+    Starting,
+    /// The service is running.
+    Running,
+    /// The service was paused.
+    Paused,
+    /// The service was asked to stop.
+    Stopping,
+}
+
+impl ServiceState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ServiceState::Starting => "starting",
+            ServiceState::Running => "running",
+            ServiceState::Paused => "paused",
+            ServiceState::Stopping => "stopping",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ServiceStatus {
+    state: ServiceState,
+    last_control: Option<ServiceControl>,
+}
+
+/// An integration that adds the state of a Windows service to events.
+///
+/// The state is updated by [`record_service_control`].
+#[derive(Debug)]
+pub struct WindowsServiceIntegration {
+    name: String,
+    status: Mutex<ServiceStatus>,
+}
+
+impl WindowsServiceIntegration {
+    /// Creates the integration for the service with the given name.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        WindowsServiceIntegration {
+            name: name.into(),
+            status: Mutex::new(ServiceStatus {
+                state: ServiceState::Starting,
+                last_control: None,
+            }),
+        }
+    }
+
+    /// Returns the current state of the service.
+    pub fn state(&self) -> ServiceState {
+        self.status.lock().unwrap().state
+    }
+
+    fn record(&self, control: ServiceControl) {
+        let mut status = self.status.lock().unwrap();
+        if let Some(state) = control.next_state() {
+            status.state = state;
+        }
+        status.last_control = Some(control);
+    }
+}
+
+impl Integration for WindowsServiceIntegration {
+    fn name(&self) -> &'static str {
+        "windows-service"
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        let status = self.status.lock().unwrap();
+        let mut context = Map::new();
+        context.insert("name".into(), self.name.clone().into());
+        context.insert("state".into(), status.state.as_str().into());
+        if let Some(control) = status.last_control {
+            context.insert("last_control".into(), control.name().into());
+        }
+        event
+            .contexts
+            .entry("windows_service".into())
+            .or_insert_with(|| Context::Other(context));
+        Some(event)
+    }
+}
+
+/// Records a control request that the service received.
+///
+/// This adds a breadcrumb for the control, and updates the state reported by
+/// the [`WindowsServiceIntegration`].
+pub fn record_service_control(control: ServiceControl) {
+    sentry_core::with_integration(|integration: &WindowsServiceIntegration, _| {
+        integration.record(control)
+    });
+    sentry_core::add_breadcrumb(|| Breadcrumb {
+        ty: "default".into(),
+        category: Some("service".into()),
+        level: match control {
+            ServiceControl::Stop | ServiceControl::Shutdown | ServiceControl::Preshutdown => {
+                Level::Warning
+            }
+            _ => Level::Info,
+        },
+        message: Some(format!("service control: {}", control.name())),
+        ..Default::default()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_control_from_code() {
+        assert_eq!(ServiceControl::from_code(1), ServiceControl::Stop);
+        assert_eq!(ServiceControl::from_code(0x0F), ServiceControl::Preshutdown);
+        assert_eq!(ServiceControl::from_code(200), ServiceControl::Other(200));
+        assert_eq!(ServiceControl::Other(200).name(), "0xc8");
+    }
+
+    #[test]
+    fn test_service_context() {
+        let events = sentry::test::with_captured_events_options(
+            || {
+                record_service_control(ServiceControl::Start);
+                record_service_control(ServiceControl::from_code(2));
+                sentry::capture_message("paused", Level::Info);
+            },
+            ClientOptions::new().add_integration(WindowsServiceIntegration::new("my-service")),
+        );
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        match event.contexts.get("windows_service") {
+            Some(Context::Other(context)) => {
+                assert_eq!(context["name"], "my-service");
+                assert_eq!(context["state"], "paused");
+                assert_eq!(context["last_control"], "pause");
+            }
+            context => panic!("expected a service context, got {:?}", context),
+        }
+        let messages: Vec<_> = event
+            .breadcrumbs
+            .iter()
+            .map(|breadcrumb| breadcrumb.message.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            messages,
+            ["service control: start", "service control: pause"]
+        );
+    }
+}
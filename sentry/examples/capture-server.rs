@@ -0,0 +1,18 @@
+//! Runs a local server that accepts and pretty-prints Sentry payloads.
+//!
+//! Point the `SENTRY_DSN` of any application at the printed DSN to inspect
+//! what it would send to Sentry.
+
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let server = sentry::test::TestServer::start();
+    server.print_received(true);
+    eprintln!("Listening for Sentry payloads. Use this DSN:");
+    println!("{}", server.dsn());
+
+    loop {
+        thread::sleep(Duration::from_secs(60));
+    }
+}
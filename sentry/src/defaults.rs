@@ -1,6 +1,7 @@
 use std::env;
 use std::{borrow::Cow, sync::Arc};
 
+use crate::properties::FileOptions;
 use crate::transports::DefaultTransportFactory;
 use crate::types::Dsn;
 use crate::{ClientOptions, Integration};
@@ -9,8 +10,18 @@ use crate::{ClientOptions, Integration};
 ///
 /// Extends the given `ClientOptions` with default options such as a default
 /// transport, a set of default integrations if not requested otherwise, and
-/// also sets the `dsn`, `release`, `environment`, and proxy settings based on
-/// environment variables.
+/// also sets the `dsn`, `release`, `environment`, proxy and Spotlight settings
+/// based on environment variables.  `SENTRY_SPOTLIGHT` enables Spotlight when
+/// it is `true` or `1`, or when it is the URL of the sidecar.
+///
+/// The `dsn`, `release` and `environment` can also be read from a
+/// `sentry.properties` or `.sentryclirc` file, so that builds can ship with a
+/// DSN next to the executable without recompiling.  The file named by the
+/// `SENTRY_PROPERTIES` environment variable is used if it is set, otherwise
+/// the first of these files that exists in the directory of the executable or
+/// in the current working directory.  Both `defaults.dsn=...` keys and
+/// `dsn=...` keys in a `[defaults]` section are supported.  The environment
+/// variables take precedence over the file.
 ///
 /// When the [`ClientOptions::default_integrations`] option is set to
 /// `true` (the default), the following integrations will be added *before*
@@ -37,6 +48,14 @@ use crate::{ClientOptions, Integration};
 /// let options = sentry::apply_defaults(options);
 /// assert_eq!(options.release, Some("release-from-env".into()));
 /// assert!(options.transport.is_some());
+///
+/// let path = std::env::temp_dir().join("sentry-doc.properties");
+/// std::fs::write(&path, "defaults.dsn=https://public@sentry.example.com/1\n").unwrap();
+/// std::env::set_var("SENTRY_PROPERTIES", &path);
+///
+/// let options = sentry::apply_defaults(sentry::ClientOptions::default());
+/// assert_eq!(options.dsn.unwrap().project_id().value(), "1");
+/// # std::fs::remove_file(&path).ok();
 /// ```
 ///
 /// [`AttachStacktraceIntegration`]: integrations/backtrace/struct.AttachStacktraceIntegration.html
@@ -81,24 +100,29 @@ pub fn apply_defaults(mut opts: ClientOptions) -> ClientOptions {
         integrations.extend(opts.integrations.into_iter());
         opts.integrations = integrations;
     }
+    let file = FileOptions::discover();
     if opts.dsn.is_none() {
         opts.dsn = env::var("SENTRY_DSN")
             .ok()
+            .or(file.dsn)
             .and_then(|dsn| dsn.parse::<Dsn>().ok());
     }
     if opts.release.is_none() {
-        opts.release = env::var("SENTRY_RELEASE").ok().map(Cow::Owned);
+        opts.release = env::var("SENTRY_RELEASE")
+            .ok()
+            .or(file.release)
+            .map(Cow::Owned);
     }
     if opts.environment.is_none() {
-        opts.environment =
-            env::var("SENTRY_ENVIRONMENT")
-                .ok()
-                .map(Cow::Owned)
-                .or(Some(Cow::Borrowed(if cfg!(debug_assertions) {
-                    "development"
-                } else {
-                    "production"
-                })));
+        opts.environment = env::var("SENTRY_ENVIRONMENT")
+            .ok()
+            .or(file.environment)
+            .map(Cow::Owned)
+            .or(Some(Cow::Borrowed(if cfg!(debug_assertions) {
+                "development"
+            } else {
+                "production"
+            })));
     }
     if opts.http_proxy.is_none() {
         opts.http_proxy = std::env::var("HTTP_PROXY")
@@ -113,6 +137,18 @@ pub fn apply_defaults(mut opts: ClientOptions) -> ClientOptions {
             .or_else(|| std::env::var("https_proxy").ok().map(Cow::Owned))
             .or_else(|| opts.http_proxy.clone());
     }
+    if !opts.spotlight {
+        if let Ok(spotlight) = env::var("SENTRY_SPOTLIGHT") {
+            match spotlight.as_str() {
+                "true" | "1" => opts.spotlight = true,
+                url if url.starts_with("http://") => {
+                    opts.spotlight = true;
+                    opts.spotlight_url = Cow::Owned(spotlight);
+                }
+                _ => {}
+            }
+        }
+    }
     if let Ok(accept_invalid_certs) = std::env::var("SSL_VERIFY") {
         opts.accept_invalid_certs = !accept_invalid_certs.parse().unwrap_or(true);
     }
@@ -140,4 +176,21 @@ mod tests {
         let opts = apply_defaults(Default::default());
         assert_eq!(opts.environment.unwrap(), "env-from-env");
     }
+
+    #[test]
+    fn test_dsn_query_from_env() {
+        env::set_var(
+            "SENTRY_DSN",
+            "https://public@sentry.invalid/1?timeout=5&verify_ssl=0",
+        );
+        let opts = apply_defaults(ClientOptions {
+            accept_invalid_certs_from_dsn: true,
+            ..Default::default()
+        });
+        env::remove_var("SENTRY_DSN");
+
+        let client = sentry_core::Client::from(opts);
+        assert_eq!(client.options().transport_timeout.unwrap().as_secs(), 5);
+        assert!(client.options().accept_invalid_certs);
+    }
 }
@@ -0,0 +1,81 @@
+use std::os::raw::c_int;
+use std::sync::Once;
+
+use sentry_core::protocol::SessionStatus;
+use sentry_core::{sentry_debug, Hub};
+
+/// Ends the session of the `hub` and closes its client, flushing all queued events.
+fn shutdown(hub: &Hub, status: SessionStatus) {
+    hub.end_session_with_status(status);
+    if let Some(client) = hub.client() {
+        sentry_debug!("process is terminating; closing client");
+        client.close(None);
+    }
+}
+
+/// Terminates the process with the given exit code, after flushing Sentry.
+///
+/// [`std::process::exit`] does not run any destructors, so the
+/// [`ClientInitGuard`](crate::ClientInitGuard) never gets a chance to send out
+/// queued events. This ends the current session as exited and closes the
+/// client first, waiting up to `shutdown_timeout` for the queue to drain.
+///
+/// # Examples
+///
+/// ```no_run
+/// let _guard = sentry::init("https://key@sentry.io/42");
+///
+/// sentry::capture_message("Invalid configuration", sentry::Level::Error);
+/// sentry::exit(1);
+/// ```
+pub fn exit(code: i32) -> ! {
+    shutdown(&Hub::current(), SessionStatus::Exited);
+    std::process::exit(code)
+}
+
+/// Aborts the process, after flushing Sentry.
+///
+/// This works like [`exit`], but ends the current session as crashed and
+/// terminates the process using [`std::process::abort`].
+pub fn abort() -> ! {
+    shutdown(&Hub::current(), SessionStatus::Crashed);
+    std::process::abort()
+}
+
+/// Installs a hook that flushes Sentry when the process exits.
+///
+/// The hook is registered using the C runtime `atexit` function, so it also
+/// runs when the process terminates through [`std::process::exit`] or by
+/// returning from `main` while the [`ClientInitGuard`](crate::ClientInitGuard)
+/// is leaked. It ends the session of the main hub as exited and closes its
+/// client. It does not run when the process is aborted or killed by a signal.
+///
+/// Installing the hook more than once has no effect.
+///
+/// # Examples
+///
+/// ```no_run
+/// std::mem::forget(sentry::init("https://key@sentry.io/42"));
+/// sentry::install_exit_hook();
+///
+/// sentry::capture_message("Shutting down", sentry::Level::Info);
+/// std::process::exit(0);
+/// ```
+pub fn install_exit_hook() {
+    extern "C" {
+        fn atexit(callback: extern "C" fn()) -> c_int;
+    }
+
+    extern "C" fn on_exit() {
+        // unwinding out of an `extern "C"` function aborts the process
+        let _ = std::panic::catch_unwind(|| shutdown(&Hub::main(), SessionStatus::Exited));
+    }
+
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        // SAFETY: `on_exit` is a valid function for the whole process lifetime.
+        if unsafe { atexit(on_exit) } != 0 {
+            sentry_debug!("failed to install exit hook");
+        }
+    });
+}
@@ -1,34 +1,45 @@
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::thread;
 
-use sentry_core::{sentry_debug, SessionMode};
+use once_cell::sync::Lazy;
+use sentry_core::{sentry_debug, SessionMode, Spool};
 
 use crate::defaults::apply_defaults;
 use crate::{Client, ClientOptions, Hub};
 
+/// The guard of the client that was last initialized with a DSN.
+static ACTIVE_GUARD: Lazy<Mutex<Weak<GuardInner>>> = Lazy::new(Default::default);
+
 /// Helper struct that is returned from `init`.
 ///
-/// When this is dropped events are drained with the configured `shutdown_timeout`.
+/// When the last guard of a client is dropped, events are drained with the
+/// configured `shutdown_timeout`.  Guards can be cloned, and calls to `init`
+/// without a DSN return another guard for the client that is already bound.
 #[must_use = "when the init guard is dropped the send queue is flushed and the \
               transport will be shut down and no further events can be sent."]
-pub struct ClientInitGuard(Arc<Client>);
+#[derive(Clone)]
+pub struct ClientInitGuard(Arc<GuardInner>);
+
+struct GuardInner(Arc<Client>);
 
 impl std::ops::Deref for ClientInitGuard {
     type Target = Client;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.0 .0
     }
 }
 
 impl ClientInitGuard {
     /// Quick check if the client is enabled.
     pub fn is_enabled(&self) -> bool {
-        self.0.is_enabled()
+        self.0 .0.is_enabled()
     }
 }
 
-impl Drop for ClientInitGuard {
+impl Drop for GuardInner {
     fn drop(&mut self) {
-        if self.is_enabled() {
+        if self.0.is_enabled() {
             sentry_debug!("dropping client guard -> disposing client");
         } else {
             sentry_debug!("dropping client guard (no client to dispose)");
@@ -46,6 +57,12 @@ impl Drop for ClientInitGuard {
 /// dropped, then the transport that was initialized shuts down and no
 /// further events can be sent on it.
 ///
+/// `init` can be called by several components of an application, such as the
+/// application itself and a plugin.  If the options have no DSN while a client
+/// with a DSN is already initialized, that client stays bound, and the returned
+/// guard keeps it alive.  The client is only shut down once all of its guards
+/// have been dropped.
+///
 /// If you don't want (or can not) keep the guard around, it's permissible to
 /// call `mem::forget` on it.
 ///
@@ -93,6 +110,19 @@ where
     C: Into<ClientOptions>,
 {
     let opts = apply_defaults(opts.into());
+    let mut active_guard = ACTIVE_GUARD.lock().unwrap_or_else(PoisonError::into_inner);
+    if opts.dsn.is_none() {
+        if let Some(guard) = active_guard.upgrade() {
+            sentry_debug!("keeping the already initialized sentry client");
+            Hub::with(|hub| {
+                if !hub.client().map_or(false, |client| client.is_enabled()) {
+                    hub.bind_client(Some(guard.0.clone()));
+                }
+            });
+            return ClientInitGuard(guard);
+        }
+    }
+
     let auto_session_tracking = opts.auto_session_tracking;
     let session_mode = opts.session_mode;
     let client = Arc::new(Client::from(opts));
@@ -100,11 +130,56 @@ where
     Hub::with(|hub| hub.bind_client(Some(client.clone())));
     if let Some(dsn) = client.dsn() {
         sentry_debug!("enabled sentry client for DSN {}", dsn);
+    } else if client.is_enabled() {
+        sentry_debug!("enabled sentry client for Spotlight only");
     } else {
         sentry_debug!("initialized disabled sentry client due to disabled or invalid DSN");
     }
     if auto_session_tracking && session_mode == SessionMode::Application {
         crate::start_session()
     }
-    ClientInitGuard(client)
+    if client.is_enabled() {
+        if let Some(dir) = client.options().panic_spool_dir.clone() {
+            send_spooled_envelopes(client.clone(), dir);
+        }
+    }
+    let guard = Arc::new(GuardInner(client));
+    if guard.0.dsn().is_some() {
+        *active_guard = Arc::downgrade(&guard);
+    }
+    ClientInitGuard(guard)
+}
+
+/// Sends the envelopes that were spooled when a previous run aborted on a
+/// panic, on a background thread.
+fn send_spooled_envelopes(client: Arc<Client>, dir: PathBuf) {
+    let spawned = thread::Builder::new()
+        .name("sentry-spool-uploader".into())
+        .spawn(move || {
+            let uploader = match Spool::open(&dir).and_then(|spool| spool.lock_uploader()) {
+                Ok(Some(uploader)) => uploader,
+                Ok(None) => return,
+                Err(err) => {
+                    sentry_debug!("failed to open panic spool: {}", err);
+                    return;
+                }
+            };
+            // envelopes are only removed from the spool once the transport
+            // flushed them, and are retried on the next start otherwise
+            let sent = uploader.drain(|envelope| {
+                if !client.is_enabled() {
+                    return false;
+                }
+                client.send_envelope(envelope);
+                client.flush(None)
+            });
+            if let Ok(sent) = sent {
+                if sent > 0 {
+                    sentry_debug!("sent {} envelopes from the panic spool", sent);
+                }
+            }
+        });
+    if let Err(err) = spawned {
+        sentry_debug!("failed to spawn the panic spool uploader: {}", err);
+    }
 }
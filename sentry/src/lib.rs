@@ -63,12 +63,15 @@
 //! | `contexts`        | ✅      | 🔌             |            |                                                                                          |
 //! | `panic`           | ✅      | 🔌             |            |                                                                                          |
 //! | `transport`       | ✅      |                |            |                                                                                          |
+//! | `connectivity`    |         |                |            |                                                                                          |
 //! | `anyhow`          |         | 🔌             |            |                                                                                          |
 //! | `test`            |         |                |            |                                                                                          |
 //! | `debug-images`    |         | 🔌             |            |                                                                                          |
+//! | `io`              |         | 🔌             |            |                                                                                          |
 //! | `log`             |         | 🔌             |            | Requires extra setup; See [`sentry-log`]'s documentation.                                |
 //! | `debug-logs`      |         |                | ❗         | Requires extra setup; See [`sentry-log`]'s documentation.                                |
 //! | `slog`            |         | 🔌             |            | Requires extra setup; See [`sentry-slog`]'s documentation.                               |
+//! | `snapshot`        |         | 🔌             |            |                                                                                          |
 //! | `reqwest`         | ✅      |                |            |                                                                                          |
 //! | `native-tls`      | ✅      |                |            | `reqwest` must be enabled.                                                               |
 //! | `rustls`          |         |                |            | `reqwest` must be enabled. `native-tls` must be disabled via `default-features = false`. |
@@ -77,6 +80,7 @@
 //! | `tower`           |         | 🔌             |            | Requires extra setup; See [`sentry-tower`]'s documentation.                              |
 //! | `ureq`            |         |                |            | `ureq` transport support using `rustls` by default                                       |
 //! | `ureq-native-tls` |         |                |            |                                                                                          |
+//! | `compression`     |         |                |            | Requires one of the transports.                                                          |
 //!
 //! [`sentry-log`]: https://crates.io/crates/sentry-log
 //! [`sentry-slog`]: https://crates.io/crates/sentry-slog
@@ -108,8 +112,15 @@
 //! - `surf`: Enables the `surf` transport.
 //! - `ureq`: Enables the `ureq` transport using `rustls`.
 //! - `ureq-native-tls`: Enables the `ureq` transport using `native-tls`.
+//! - `compression`: Makes the transports gzip-compress request bodies, which can be disabled with
+//!   `ClientOptions::compress_payloads`.
+//! - `connectivity`: Enables a transport wrapper that spools envelopes to disk while the network
+//!   is down, and records network transitions as breadcrumbs.
 //!
 //! ## Integrations
+//! - `io`: Enables instrumented wrappers for `std` file and network IO, and for subprocesses
+//!   spawned with [`sentry::process::Command`](crate::process::Command).
+//! - `snapshot`: Persists scope snapshots to enrich crashes reported on the next run.
 //! - `tower`: Enables support for the `tower` crate and those using it.
 
 #![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
@@ -121,7 +132,11 @@
 #![cfg_attr(doc_cfg, feature(doc_cfg))]
 
 mod defaults;
+mod exit;
 mod init;
+mod properties;
+#[cfg(feature = "backtrace")]
+mod stacktrace;
 pub mod transports;
 
 // re-export from core
@@ -130,7 +145,14 @@ pub use sentry_core::*;
 
 // added public API
 pub use crate::defaults::apply_defaults;
+pub use crate::exit::{abort, exit, install_exit_hook};
 pub use crate::init::{init, ClientInitGuard};
+#[cfg(feature = "backtrace")]
+pub use crate::stacktrace::current_stacktrace;
+#[cfg(feature = "io")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "io")))]
+#[doc(inline)]
+pub use sentry_io::process;
 
 /// Available Sentry Integrations.
 ///
@@ -202,6 +224,10 @@ pub mod integrations {
     #[cfg_attr(doc_cfg, doc(cfg(feature = "debug_images")))]
     #[doc(inline)]
     pub use sentry_debug_images as debug_images;
+    #[cfg(feature = "io")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "io")))]
+    #[doc(inline)]
+    pub use sentry_io as io;
     #[cfg(feature = "log")]
     #[cfg_attr(doc_cfg, doc(cfg(feature = "log")))]
     #[doc(inline)]
@@ -214,6 +240,10 @@ pub mod integrations {
     #[cfg_attr(doc_cfg, doc(cfg(feature = "slog")))]
     #[doc(inline)]
     pub use sentry_slog as slog;
+    #[cfg(feature = "snapshot")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "snapshot")))]
+    #[doc(inline)]
+    pub use sentry_snapshot as snapshot;
     #[cfg(feature = "tower")]
     #[cfg_attr(doc_cfg, doc(cfg(feature = "tower")))]
     #[doc(inline)]
@@ -0,0 +1,134 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// The names of the files that are discovered, in order of precedence.
+const FILE_NAMES: &[&str] = &["sentry.properties", ".sentryclirc"];
+
+/// Options read from a `sentry.properties` or `.sentryclirc` file.
+///
+/// Both the flat `defaults.dsn=...` style of `sentry.properties` and the
+/// `[defaults]` sections of `.sentryclirc` are understood, as well as plain
+/// `dsn=...` keys.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct FileOptions {
+    pub dsn: Option<String>,
+    pub release: Option<String>,
+    pub environment: Option<String>,
+}
+
+impl FileOptions {
+    /// Reads the options from the first file that exists.
+    ///
+    /// The file named by the `SENTRY_PROPERTIES` environment variable is used
+    /// if it is set.  Otherwise, the files are looked up in the directory of
+    /// the executable, and then in the current working directory.
+    pub fn discover() -> Self {
+        candidates()
+            .into_iter()
+            .find_map(|path| fs::read_to_string(path).ok())
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default()
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut options = Self::default();
+        let mut section = "";
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';', '!']) {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim();
+                continue;
+            }
+            let (key, value) = match line.find(['=', ':']) {
+                Some(idx) => (line[..idx].trim(), line[idx + 1..].trim()),
+                None => continue,
+            };
+            if value.is_empty() {
+                continue;
+            }
+            let key = match section {
+                "" => key.strip_prefix("defaults.").unwrap_or(key),
+                "defaults" => key,
+                _ => continue,
+            };
+            let field = match key {
+                "dsn" => &mut options.dsn,
+                "release" => &mut options.release,
+                "environment" => &mut options.environment,
+                _ => continue,
+            };
+            *field = Some(value.to_owned());
+        }
+        options
+    }
+}
+
+fn candidates() -> Vec<PathBuf> {
+    if let Some(path) = env::var_os("SENTRY_PROPERTIES") {
+        return vec![path.into()];
+    }
+    let mut dirs = Vec::new();
+    if let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from))
+    {
+        dirs.push(dir);
+    }
+    if let Ok(dir) = env::current_dir() {
+        dirs.push(dir);
+    }
+    dirs.iter()
+        .flat_map(|dir| FILE_NAMES.iter().map(move |name| dir.join(name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_properties() {
+        let options = FileOptions::parse(
+            "# written by CI\n\
+             defaults.org=my-org\n\
+             defaults.dsn = https://public@sentry.example.com/1\n\
+             release: my-app@1.2.3\n\
+             auth.token=secret\n\
+             environment=\n",
+        );
+        assert_eq!(
+            options,
+            FileOptions {
+                dsn: Some("https://public@sentry.example.com/1".into()),
+                release: Some("my-app@1.2.3".into()),
+                environment: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_sentryclirc() {
+        let options = FileOptions::parse(
+            "[auth]\n\
+             token=secret\n\
+             dsn=https://wrong@sentry.example.com/2\n\
+             \n\
+             [defaults]\n\
+             ; the DSN of the desktop app\n\
+             dsn=https://public@sentry.example.com/1\n\
+             environment=staging\n",
+        );
+        assert_eq!(
+            options,
+            FileOptions {
+                dsn: Some("https://public@sentry.example.com/1".into()),
+                release: None,
+                environment: Some("staging".into()),
+            }
+        );
+    }
+}
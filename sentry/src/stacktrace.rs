@@ -0,0 +1,33 @@
+use sentry_backtrace::{current_unresolved_stacktrace, Stacktrace};
+
+/// Captures the stacktrace of the current thread.
+///
+/// This allows custom integrations to attach stacktraces to the events they
+/// create.  With `resolve` set to `false`, symbol resolution is deferred to
+/// the processing of the event, which happens on a background thread with
+/// [`ClientOptions::background_processing`], and keeps capturing cheap.  Such
+/// stacktraces are only resolved when attached to events while the default
+/// `backtrace` integrations are enabled.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::protocol::{Event, Level};
+///
+/// let event = Event {
+///     message: Some("slow request".into()),
+///     level: Level::Warning,
+///     stacktrace: sentry::current_stacktrace(false),
+///     ..Default::default()
+/// };
+/// sentry::capture_event(event);
+/// ```
+///
+/// [`ClientOptions::background_processing`]: crate::ClientOptions::background_processing
+pub fn current_stacktrace(resolve: bool) -> Option<Stacktrace> {
+    if resolve {
+        sentry_backtrace::current_stacktrace()
+    } else {
+        current_unresolved_stacktrace()
+    }
+}
@@ -3,9 +3,12 @@ use std::time::Duration;
 
 use curl::easy::Easy as CurlClient;
 
+use super::ratelimit::RateLimiter;
 use super::thread::TransportThread;
+use super::workers::SendStatus;
+use super::{encode_body, report_rejection};
 
-use crate::{sentry_debug, types::Scheme, ClientOptions, Envelope, Transport};
+use crate::{sentry_debug, types::Scheme, Backpressure, ClientOptions, Envelope, Transport};
 
 /// A [`Transport`] that sends events via the [`curl`] library.
 ///
@@ -22,6 +25,9 @@ impl CurlHttpTransport {
     }
 
     /// Creates a new Transport that uses the specified [`CurlClient`].
+    ///
+    /// When more than one [`transport_threads`](ClientOptions::transport_threads)
+    /// is configured, the additional threads use default clients.
     pub fn with_client(options: &ClientOptions, client: CurlClient) -> Self {
         Self::new_internal(options, Some(client))
     }
@@ -36,95 +42,133 @@ impl CurlHttpTransport {
         let url = dsn.envelope_api_url().to_string();
         let scheme = dsn.scheme();
         let accept_invalid_certs = options.accept_invalid_certs;
+        let on_event_rejected = options.on_event_rejected.clone();
+        let compress = options.compress_payloads;
+        let timeout = options.transport_timeout;
+
+        // the first worker uses the given client, any further workers need
+        // their own handle.
+        let mut client = Some(client);
+        let thread = TransportThread::new(options, move || {
+            let mut handle = client.take().unwrap_or_else(CurlClient::new);
+            let http_proxy = http_proxy.clone();
+            let https_proxy = https_proxy.clone();
+            let auth = auth.clone();
+            let url = url.clone();
+            let on_event_rejected = on_event_rejected.clone();
+            move |envelope: Envelope, rl: &mut RateLimiter| {
+                let event_id = envelope.uuid().copied();
+                handle.reset();
+                handle.url(&url).unwrap();
+                handle.custom_request("POST").unwrap();
+                if let Some(timeout) = timeout {
+                    handle.timeout(timeout).unwrap();
+                }
 
-        let mut handle = client;
-        let thread = TransportThread::new(move |envelope, rl| {
-            handle.reset();
-            handle.url(&url).unwrap();
-            handle.custom_request("POST").unwrap();
-
-            if accept_invalid_certs {
-                handle.ssl_verify_host(false).unwrap();
-                handle.ssl_verify_peer(false).unwrap();
-            }
+                if accept_invalid_certs {
+                    handle.ssl_verify_host(false).unwrap();
+                    handle.ssl_verify_peer(false).unwrap();
+                }
 
-            match (scheme, &http_proxy, &https_proxy) {
-                (Scheme::Https, _, Some(proxy)) => {
-                    if let Err(err) = handle.proxy(proxy) {
-                        sentry_debug!("invalid proxy: {:?}", err);
+                match (scheme, &http_proxy, &https_proxy) {
+                    (Scheme::Https, _, Some(proxy)) => {
+                        if let Err(err) = handle.proxy(proxy) {
+                            sentry_debug!("invalid proxy: {:?}", err);
+                        }
                     }
-                }
-                (_, Some(proxy), _) => {
-                    if let Err(err) = handle.proxy(proxy) {
-                        sentry_debug!("invalid proxy: {:?}", err);
+                    (_, Some(proxy), _) => {
+                        if let Err(err) = handle.proxy(proxy) {
+                            sentry_debug!("invalid proxy: {:?}", err);
+                        }
                     }
+                    _ => {}
                 }
-                _ => {}
-            }
 
-            let mut body = Vec::new();
-            envelope.to_writer(&mut body).unwrap();
-            let mut body = Cursor::new(body);
-
-            let mut retry_after = None;
-            let mut sentry_header = None;
-            let mut headers = curl::easy::List::new();
-            headers.append(&format!("X-Sentry-Auth: {}", auth)).unwrap();
-            headers.append("Expect:").unwrap();
-            handle.http_headers(headers).unwrap();
-            handle.upload(true).unwrap();
-            handle.in_filesize(body.get_ref().len() as u64).unwrap();
-            handle
-                .read_function(move |buf| Ok(body.read(buf).unwrap_or(0)))
-                .unwrap();
-            handle.verbose(true).unwrap();
-            handle
-                .debug_function(move |info, data| {
-                    let prefix = match info {
-                        curl::easy::InfoType::HeaderIn => "< ",
-                        curl::easy::InfoType::HeaderOut => "> ",
-                        curl::easy::InfoType::DataOut => "",
-                        _ => return,
-                    };
-                    sentry_debug!("curl: {}{}", prefix, String::from_utf8_lossy(data).trim());
-                })
-                .unwrap();
-
-            {
-                let mut handle = handle.transfer();
-                let retry_after_setter = &mut retry_after;
-                let sentry_header_setter = &mut sentry_header;
+                let (body, encoding) = encode_body(&envelope, compress);
+                let mut body = Cursor::new(body);
+
+                let mut retry_after = None;
+                let mut sentry_header = None;
+                let mut response_body = Vec::new();
+                let mut headers = curl::easy::List::new();
+                headers.append(&format!("X-Sentry-Auth: {}", auth)).unwrap();
+                headers.append("Expect:").unwrap();
+                if let Some(encoding) = encoding {
+                    headers
+                        .append(&format!("Content-Encoding: {}", encoding))
+                        .unwrap();
+                }
+                handle.http_headers(headers).unwrap();
+                handle.upload(true).unwrap();
+                handle.in_filesize(body.get_ref().len() as u64).unwrap();
                 handle
-                    .header_function(move |data| {
-                        if let Ok(data) = std::str::from_utf8(data) {
-                            let mut iter = data.split(':');
-                            if let Some(key) = iter.next().map(str::to_lowercase) {
-                                if key == "retry-after" {
-                                    *retry_after_setter = iter.next().map(|x| x.trim().to_string());
-                                } else if key == "x-sentry-rate-limits" {
-                                    *sentry_header_setter =
-                                        iter.next().map(|x| x.trim().to_string());
-                                }
-                            }
-                        }
-                        true
+                    .read_function(move |buf| Ok(body.read(buf).unwrap_or(0)))
+                    .unwrap();
+                handle.verbose(true).unwrap();
+                handle
+                    .debug_function(move |info, data| {
+                        let prefix = match info {
+                            curl::easy::InfoType::HeaderIn => "< ",
+                            curl::easy::InfoType::HeaderOut => "> ",
+                            curl::easy::InfoType::DataOut => "",
+                            _ => return,
+                        };
+                        sentry_debug!("curl: {}{}", prefix, String::from_utf8_lossy(data).trim());
                     })
                     .unwrap();
-                handle.perform().ok();
-            }
 
-            match handle.response_code() {
-                Ok(response_code) => {
-                    if let Some(sentry_header) = sentry_header {
-                        rl.update_from_sentry_header(&sentry_header);
-                    } else if let Some(retry_after) = retry_after {
-                        rl.update_from_retry_after(&retry_after);
-                    } else if response_code == 429 {
-                        rl.update_from_429();
-                    }
+                {
+                    let mut handle = handle.transfer();
+                    let retry_after_setter = &mut retry_after;
+                    let sentry_header_setter = &mut sentry_header;
+                    let response_body = &mut response_body;
+                    handle
+                        .header_function(move |data| {
+                            if let Ok(data) = std::str::from_utf8(data) {
+                                let mut iter = data.split(':');
+                                if let Some(key) = iter.next().map(str::to_lowercase) {
+                                    if key == "retry-after" {
+                                        *retry_after_setter =
+                                            iter.next().map(|x| x.trim().to_string());
+                                    } else if key == "x-sentry-rate-limits" {
+                                        *sentry_header_setter =
+                                            iter.next().map(|x| x.trim().to_string());
+                                    }
+                                }
+                            }
+                            true
+                        })
+                        .unwrap();
+                    handle
+                        .write_function(move |data| {
+                            response_body.extend_from_slice(data);
+                            Ok(data.len())
+                        })
+                        .unwrap();
+                    handle.perform().ok();
                 }
-                Err(err) => {
-                    sentry_debug!("Failed to send envelope: {}", err);
+
+                match handle.response_code() {
+                    Ok(response_code) => {
+                        if let Some(sentry_header) = sentry_header {
+                            rl.update_from_sentry_header(&sentry_header);
+                        } else if let Some(retry_after) = retry_after {
+                            rl.update_from_retry_after(&retry_after);
+                        } else if response_code == 429 {
+                            rl.update_from_429();
+                        }
+                        report_rejection(
+                            on_event_rejected.as_ref(),
+                            event_id,
+                            response_code as u16,
+                            &String::from_utf8_lossy(&response_body),
+                        );
+                        SendStatus::from_response(response_code as u16)
+                    }
+                    Err(err) => {
+                        sentry_debug!("Failed to send envelope: {}", err);
+                        SendStatus::Failed
+                    }
                 }
             }
         });
@@ -143,4 +187,8 @@ impl Transport for CurlHttpTransport {
     fn shutdown(&self, timeout: Duration) -> bool {
         self.flush(timeout)
     }
+
+    fn backpressure(&self) -> Backpressure {
+        self.thread.backpressure()
+    }
 }
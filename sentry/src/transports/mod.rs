@@ -6,12 +6,24 @@
 use crate::{ClientOptions, Transport, TransportFactory};
 use std::sync::Arc;
 
+#[cfg(feature = "connectivity")]
+mod connectivity;
 #[cfg(feature = "httpdate")]
 mod ratelimit;
 #[cfg(any(feature = "curl", feature = "ureq"))]
 mod thread;
 #[cfg(any(feature = "reqwest", feature = "surf",))]
 mod tokio_thread;
+#[cfg(any(
+    feature = "curl",
+    feature = "ureq",
+    feature = "reqwest",
+    feature = "surf"
+))]
+mod workers;
+
+#[cfg(feature = "connectivity")]
+pub use self::connectivity::ConnectivityTransportFactory;
 
 #[cfg(feature = "reqwest")]
 mod reqwest;
@@ -69,6 +81,55 @@ type DefaultTransport = UreqHttpTransport;
 ))]
 pub type HttpTransport = DefaultTransport;
 
+/// Reports a response of the server that rejected an envelope to the
+/// `on_event_rejected` callback.
+#[cfg(any(
+    feature = "reqwest",
+    feature = "curl",
+    feature = "surf",
+    feature = "ureq"
+))]
+fn report_rejection(
+    callback: Option<&crate::EventRejectedCallback>,
+    event_id: Option<crate::types::Uuid>,
+    status: u16,
+    body: &str,
+) {
+    if let Some(rejection) = crate::EventRejection::from_response(event_id, status, body) {
+        crate::sentry_debug!("Envelope was rejected: {:?}", rejection);
+        if let Some(callback) = callback {
+            callback(&rejection);
+        }
+    }
+}
+
+/// Serializes an envelope into the body of a request.
+///
+/// With the `compression` feature, the body is gzip-compressed if `compress`
+/// is set.  The `Content-Encoding` of the body is returned along with it.
+#[cfg(any(
+    feature = "reqwest",
+    feature = "curl",
+    feature = "surf",
+    feature = "ureq"
+))]
+fn encode_body(envelope: &crate::Envelope, compress: bool) -> (Vec<u8>, Option<&'static str>) {
+    #[cfg(feature = "compression")]
+    if compress {
+        use flate2::{write::GzEncoder, Compression};
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        envelope.to_writer(&mut encoder).unwrap();
+        return (encoder.finish().unwrap(), Some("gzip"));
+    }
+    #[cfg(not(feature = "compression"))]
+    let _ = compress;
+
+    let mut body = Vec::new();
+    envelope.to_writer(&mut body).unwrap();
+    (body, None)
+}
+
 /// Creates the default HTTP transport.
 ///
 /// This is the default value for `transport` on the client options.  It
@@ -100,3 +161,45 @@ impl TransportFactory for DefaultTransportFactory {
         }
     }
 }
+
+#[cfg(all(
+    test,
+    feature = "compression",
+    any(
+        feature = "reqwest",
+        feature = "curl",
+        feature = "surf",
+        feature = "ureq"
+    )
+))]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::encode_body;
+    use crate::protocol::Event;
+    use crate::Envelope;
+
+    #[test]
+    fn test_encode_body() {
+        let envelope: Envelope = Event {
+            message: Some("x".repeat(1000)),
+            ..Default::default()
+        }
+        .into();
+
+        let (plain, encoding) = encode_body(&envelope, false);
+        assert_eq!(encoding, None);
+
+        let (compressed, encoding) = encode_body(&envelope, true);
+        assert_eq!(encoding, Some("gzip"));
+        assert!(compressed.len() < plain.len());
+
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, plain);
+    }
+}
@@ -2,10 +2,10 @@ use httpdate::parse_http_date;
 use std::time::{Duration, SystemTime};
 
 use crate::protocol::EnvelopeItem;
-use crate::Envelope;
+use crate::{Envelope, RateLimits};
 
 /// A Utility that helps with rate limiting sentry requests.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct RateLimiter {
     global: Option<SystemTime>,
     error: Option<SystemTime>,
@@ -21,6 +21,18 @@ impl RateLimiter {
         Self::default()
     }
 
+    /// Merges the limits of another RateLimiter into this one.
+    ///
+    /// For each category, the limit that lasts longer wins.
+    pub fn merge(&mut self, other: RateLimiter) {
+        self.global = self.global.max(other.global);
+        self.error = self.error.max(other.error);
+        self.session = self.session.max(other.session);
+        self.transaction = self.transaction.max(other.transaction);
+        self.attachment = self.attachment.max(other.attachment);
+        self.profile = self.profile.max(other.profile);
+    }
+
     /// Updates the RateLimiter with information from a `Retry-After` header.
     pub fn update_from_retry_after(&mut self, header: &str) {
         let new_time = if let Ok(value) = header.parse::<f64>() {
@@ -103,6 +115,17 @@ impl RateLimiter {
         self.is_disabled(category).is_none()
     }
 
+    /// Returns the rate limits of all categories, including global limits.
+    pub fn rate_limits(&self) -> RateLimits {
+        RateLimits {
+            errors: self.global.max(self.error),
+            sessions: self.global.max(self.session),
+            transactions: self.global.max(self.transaction),
+            attachments: self.global.max(self.attachment),
+            profiles: self.global.max(self.profile),
+        }
+    }
+
     /// Filters the [`Envelope`] according to the current rate limits.
     ///
     /// Returns [`None`] if all the envelope items were filtered out.
@@ -167,6 +190,21 @@ mod tests {
         assert!(rl.is_disabled(RateLimitingCategory::Any).unwrap() <= Duration::from_secs(30));
     }
 
+    #[test]
+    fn test_rate_limits() {
+        let mut rl = RateLimiter::new();
+        rl.update_from_sentry_header("120:transaction:project");
+        let limits = rl.rate_limits();
+        assert!(limits.transactions.is_some());
+        assert_eq!(limits.errors, None);
+        assert!(limits.is_any_active());
+
+        rl.update_from_retry_after("600");
+        let limits = rl.rate_limits();
+        assert_eq!(limits.errors, limits.sessions);
+        assert_eq!(limits.errors, limits.transactions);
+    }
+
     #[test]
     fn test_retry_after() {
         let mut rl = RateLimiter::new();
@@ -179,4 +217,18 @@ mod tests {
         );
         assert!(rl.is_disabled(RateLimitingCategory::Any).unwrap() <= Duration::from_secs(60));
     }
+
+    #[test]
+    fn test_merge() {
+        let mut rl = RateLimiter::new();
+        rl.update_from_sentry_header("120:error:project");
+
+        let mut other = RateLimiter::new();
+        other.update_from_sentry_header("60:error;session:project");
+        rl.merge(other);
+
+        assert!(rl.is_disabled(RateLimitingCategory::Error).unwrap() > Duration::from_secs(60));
+        assert!(rl.is_disabled(RateLimitingCategory::Session).unwrap() <= Duration::from_secs(60));
+        assert!(rl.is_disabled(RateLimitingCategory::Transaction).is_none());
+    }
 }
@@ -2,9 +2,12 @@ use std::time::Duration;
 
 use reqwest::{header as ReqwestHeaders, Client as ReqwestClient, Proxy, StatusCode};
 
+use super::ratelimit::RateLimiter;
 use super::tokio_thread::TransportThread;
+use super::workers::SendStatus;
+use super::{encode_body, report_rejection};
 
-use crate::{sentry_debug, ClientOptions, Envelope, Transport};
+use crate::{sentry_debug, Backpressure, ClientOptions, Envelope, Transport};
 
 /// A [`Transport`] that sends events via the [`reqwest`] library.
 ///
@@ -23,89 +26,124 @@ impl ReqwestHttpTransport {
     }
 
     /// Creates a new Transport that uses the specified [`ReqwestClient`].
+    ///
+    /// With more than one [`transport_threads`](ClientOptions::transport_threads),
+    /// only the first worker uses the given client, as connections can not be
+    /// shared between the runtimes of the workers.
     pub fn with_client(options: &ClientOptions, client: ReqwestClient) -> Self {
         Self::new_internal(options, Some(client))
     }
 
     fn new_internal(options: &ClientOptions, client: Option<ReqwestClient>) -> Self {
-        let client = client.unwrap_or_else(|| {
-            let mut builder = reqwest::Client::builder();
-            if options.accept_invalid_certs {
-                builder = builder.danger_accept_invalid_certs(true);
-            }
-            if let Some(url) = options.http_proxy.as_ref() {
-                match Proxy::http(url.as_ref()) {
-                    Ok(proxy) => {
-                        builder = builder.proxy(proxy);
-                    }
-                    Err(err) => {
-                        sentry_debug!("invalid proxy: {:?}", err);
-                    }
-                }
-            };
-            if let Some(url) = options.https_proxy.as_ref() {
-                match Proxy::https(url.as_ref()) {
-                    Ok(proxy) => {
-                        builder = builder.proxy(proxy);
-                    }
-                    Err(err) => {
-                        sentry_debug!("invalid proxy: {:?}", err);
-                    }
-                }
-            };
-            builder.build().unwrap()
-        });
         let dsn = options.dsn.as_ref().unwrap();
         let user_agent = options.user_agent.clone();
         let auth = dsn.to_auth(Some(&user_agent)).to_string();
         let url = dsn.envelope_api_url().to_string();
+        let on_event_rejected = options.on_event_rejected.clone();
+        let compress = options.compress_payloads;
+        let timeout = options.transport_timeout;
 
-        let thread = TransportThread::new(move |envelope, mut rl| {
-            let mut body = Vec::new();
-            envelope.to_writer(&mut body).unwrap();
-            let request = client.post(&url).header("X-Sentry-Auth", &auth).body(body);
-
-            // NOTE: because of lifetime issues, building the request using the
-            // `client` has to happen outside of this async block.
-            async move {
-                match request.send().await {
-                    Ok(response) => {
-                        let headers = response.headers();
+        // every worker drives its own runtime, and connections must not be
+        // shared across runtimes.  The first worker uses the given client, any
+        // further workers need their own.
+        let mut client = client;
+        let thread = TransportThread::new(options, || {
+            let client = client.take().unwrap_or_else(|| build_client(options));
+            let auth = auth.clone();
+            let url = url.clone();
+            let on_event_rejected = on_event_rejected.clone();
+            move |envelope: Envelope, mut rl: RateLimiter| {
+                let event_id = envelope.uuid().copied();
+                let (body, encoding) = encode_body(&envelope, compress);
+                let mut request = client.post(&url).header("X-Sentry-Auth", &auth);
+                if let Some(encoding) = encoding {
+                    request = request.header("Content-Encoding", encoding);
+                }
+                if let Some(timeout) = timeout {
+                    request = request.timeout(timeout);
+                }
+                let request = request.body(body);
+                let on_event_rejected = on_event_rejected.clone();
 
-                        if let Some(sentry_header) = headers
-                            .get("x-sentry-rate-limits")
-                            .and_then(|x| x.to_str().ok())
-                        {
-                            rl.update_from_sentry_header(sentry_header);
-                        } else if let Some(retry_after) = headers
-                            .get(ReqwestHeaders::RETRY_AFTER)
-                            .and_then(|x| x.to_str().ok())
-                        {
-                            rl.update_from_retry_after(retry_after);
-                        } else if response.status() == StatusCode::TOO_MANY_REQUESTS {
-                            rl.update_from_429();
-                        }
+                // NOTE: because of lifetime issues, building the request using the
+                // `client` has to happen outside of this async block.
+                async move {
+                    match request.send().await {
+                        Ok(response) => {
+                            let headers = response.headers();
 
-                        match response.text().await {
-                            Err(err) => {
-                                sentry_debug!("Failed to read sentry response: {}", err);
+                            if let Some(sentry_header) = headers
+                                .get("x-sentry-rate-limits")
+                                .and_then(|x| x.to_str().ok())
+                            {
+                                rl.update_from_sentry_header(sentry_header);
+                            } else if let Some(retry_after) = headers
+                                .get(ReqwestHeaders::RETRY_AFTER)
+                                .and_then(|x| x.to_str().ok())
+                            {
+                                rl.update_from_retry_after(retry_after);
+                            } else if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                                rl.update_from_429();
                             }
-                            Ok(text) => {
-                                sentry_debug!("Get response: `{}`", text);
+
+                            let status = response.status().as_u16();
+                            match response.text().await {
+                                Err(err) => {
+                                    sentry_debug!("Failed to read sentry response: {}", err);
+                                }
+                                Ok(text) => {
+                                    sentry_debug!("Get response: `{}`", text);
+                                    report_rejection(
+                                        on_event_rejected.as_ref(),
+                                        event_id,
+                                        status,
+                                        &text,
+                                    );
+                                }
                             }
+                            (rl, SendStatus::from_response(status))
+                        }
+                        Err(err) => {
+                            sentry_debug!("Failed to send envelope: {}", err);
+                            (rl, SendStatus::Failed)
                         }
-                    }
-                    Err(err) => {
-                        sentry_debug!("Failed to send envelope: {}", err);
                     }
                 }
-                rl
             }
         });
         Self { thread }
     }
 }
 
+/// Creates a client with the TLS and proxy settings of the `options`.
+fn build_client(options: &ClientOptions) -> ReqwestClient {
+    let mut builder = reqwest::Client::builder();
+    if options.accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if let Some(url) = options.http_proxy.as_ref() {
+        match Proxy::http(url.as_ref()) {
+            Ok(proxy) => {
+                builder = builder.proxy(proxy);
+            }
+            Err(err) => {
+                sentry_debug!("invalid proxy: {:?}", err);
+            }
+        }
+    };
+    if let Some(url) = options.https_proxy.as_ref() {
+        match Proxy::https(url.as_ref()) {
+            Ok(proxy) => {
+                builder = builder.proxy(proxy);
+            }
+            Err(err) => {
+                sentry_debug!("invalid proxy: {:?}", err);
+            }
+        }
+    };
+    builder.build().unwrap()
+}
+
 impl Transport for ReqwestHttpTransport {
     fn send_envelope(&self, envelope: Envelope) {
         self.thread.send(envelope)
@@ -117,4 +155,8 @@ impl Transport for ReqwestHttpTransport {
     fn shutdown(&self, timeout: Duration) -> bool {
         self.flush(timeout)
     }
+
+    fn backpressure(&self) -> Backpressure {
+        self.thread.backpressure()
+    }
 }
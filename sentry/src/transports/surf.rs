@@ -2,13 +2,17 @@ use std::time::Duration;
 
 use isahc::{
     config::{Configurable, SslOption},
+    http::Uri,
     HttpClient,
 };
 use surf::{http::headers as SurfHeaders, Client as SurfClient, StatusCode};
 
+use super::ratelimit::RateLimiter;
 use super::tokio_thread::TransportThread;
+use super::workers::SendStatus;
+use super::{encode_body, report_rejection};
 
-use crate::{sentry_debug, ClientOptions, Envelope, Transport};
+use crate::{sentry_debug, types::Scheme, Backpressure, ClientOptions, Envelope, Transport};
 
 /// A [`Transport`] that sends events via the [`surf`] library.
 ///
@@ -27,30 +31,33 @@ impl SurfHttpTransport {
     }
 
     /// Creates a new Transport that uses the specified [`SurfClient`].
+    ///
+    /// The client is used as it is, so the proxies,
+    /// [`accept_invalid_certs`](ClientOptions::accept_invalid_certs) and
+    /// [`transport_timeout`](ClientOptions::transport_timeout) of the options
+    /// have to be configured on it instead.
     pub fn with_client(options: &ClientOptions, client: SurfClient) -> Self {
         Self::new_internal(options, Some(client))
     }
 
     fn new_internal(options: &ClientOptions, client: Option<SurfClient>) -> Self {
-        let mut client = client.unwrap_or_else(SurfClient::new);
-        if options.accept_invalid_certs {
-            let hc = HttpClient::builder()
-                .ssl_options(SslOption::DANGER_ACCEPT_INVALID_CERTS)
-                .build()
-                .unwrap();
-            let http_client = http_client::isahc::IsahcClient::from_client(hc);
-            client = SurfClient::with_http_client(http_client)
-        }
-
         let dsn = options.dsn.as_ref().unwrap();
+        let client = client.unwrap_or_else(|| build_client(options));
         let user_agent = options.user_agent.clone();
         let auth = dsn.to_auth(Some(&user_agent)).to_string();
         let url = dsn.envelope_api_url().to_string();
+        let on_event_rejected = options.on_event_rejected.clone();
+        let compress = options.compress_payloads;
 
-        let thread = TransportThread::new(move |envelope, mut rl| {
-            let mut body = Vec::new();
-            envelope.to_writer(&mut body).unwrap();
-            let request = client.post(&url).header("X-Sentry-Auth", &auth).body(body);
+        let send = move |envelope: Envelope, mut rl: RateLimiter| {
+            let event_id = envelope.uuid().copied();
+            let (body, encoding) = encode_body(&envelope, compress);
+            let mut request = client.post(&url).header("X-Sentry-Auth", &auth);
+            if let Some(encoding) = encoding {
+                request = request.header("Content-Encoding", encoding);
+            }
+            let request = request.body(body);
+            let on_event_rejected = on_event_rejected.clone();
 
             async move {
                 match request.await {
@@ -68,26 +75,66 @@ impl SurfHttpTransport {
                             rl.update_from_429();
                         }
 
+                        let status = response.status() as u16;
                         match response.body_string().await {
                             Err(err) => {
                                 sentry_debug!("Failed to read sentry response: {}", err);
                             }
                             Ok(text) => {
                                 sentry_debug!("Get response: `{}`", text);
+                                report_rejection(
+                                    on_event_rejected.as_ref(),
+                                    event_id,
+                                    status,
+                                    &text,
+                                );
                             }
                         }
+                        (rl, SendStatus::from_response(status))
                     }
                     Err(err) => {
                         sentry_debug!("Failed to send envelope: {}", err);
+                        (rl, SendStatus::Failed)
                     }
                 }
-                rl
             }
-        });
+        };
+        let thread = TransportThread::new(options, move || send.clone());
         Self { thread }
     }
 }
 
+/// Builds the client of a transport that was not given one.
+fn build_client(options: &ClientOptions) -> SurfClient {
+    let dsn = options.dsn.as_ref().unwrap();
+    let proxy = match (dsn.scheme(), &options.http_proxy, &options.https_proxy) {
+        (Scheme::Https, _, Some(proxy)) | (_, Some(proxy), _) => match proxy.parse::<Uri>() {
+            Ok(proxy) => Some(proxy),
+            Err(err) => {
+                sentry_debug!("invalid proxy: {:?}", err);
+                None
+            }
+        },
+        _ => None,
+    };
+    if !options.accept_invalid_certs && proxy.is_none() && options.transport_timeout.is_none() {
+        return SurfClient::new();
+    }
+
+    let mut builder = HttpClient::builder();
+    if options.accept_invalid_certs {
+        builder = builder.ssl_options(SslOption::DANGER_ACCEPT_INVALID_CERTS);
+    }
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy);
+    }
+    if let Some(timeout) = options.transport_timeout {
+        builder = builder.timeout(timeout);
+    }
+    let http_client = http_client::isahc::IsahcClient::from_client(builder.build().unwrap());
+    SurfClient::with_http_client(http_client)
+}
+
 impl Transport for SurfHttpTransport {
     fn send_envelope(&self, envelope: Envelope) {
         self.thread.send(envelope)
@@ -99,4 +146,8 @@ impl Transport for SurfHttpTransport {
     fn shutdown(&self, timeout: Duration) -> bool {
         self.flush(timeout)
     }
+
+    fn backpressure(&self) -> Backpressure {
+        self.thread.backpressure()
+    }
 }
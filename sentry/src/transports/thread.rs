@@ -1,94 +1,92 @@
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{sync_channel, SyncSender};
-use std::sync::Arc;
-use std::thread::{self, JoinHandle};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::Duration;
 
-use super::ratelimit::{RateLimiter, RateLimitingCategory};
-use crate::{sentry_debug, Envelope};
-
-enum Task {
-    SendEnvelope(Envelope),
-    Flush(SyncSender<()>),
-    Shutdown,
-}
+use super::ratelimit::RateLimiter;
+use super::workers::{spawn_workers, SendStatus, Task, WorkerState, QUEUE_CAPACITY};
+use crate::{Backpressure, ClientOptions, Envelope};
 
 pub struct TransportThread {
     sender: SyncSender<Task>,
-    shutdown: Arc<AtomicBool>,
-    handle: Option<JoinHandle<()>>,
+    state: Arc<WorkerState>,
+    handles: Vec<JoinHandle<()>>,
 }
 
 impl TransportThread {
-    pub fn new<SendFn>(mut send: SendFn) -> Self
+    /// Spawns the worker threads configured in `options`.
+    ///
+    /// `make_send` is called once per worker to create its send function.
+    pub fn new<MakeSend, SendFn>(options: &ClientOptions, mut make_send: MakeSend) -> Self
     where
-        SendFn: FnMut(Envelope, &mut RateLimiter) + Send + 'static,
+        MakeSend: FnMut() -> SendFn,
+        SendFn: FnMut(Envelope, &mut RateLimiter) -> SendStatus + Send + 'static,
     {
-        let (sender, receiver) = sync_channel(30);
-        let shutdown = Arc::new(AtomicBool::new(false));
-        let shutdown_worker = shutdown.clone();
-        let handle = thread::Builder::new()
-            .name("sentry-transport".into())
-            .spawn(move || {
-                let mut rl = RateLimiter::new();
-
-                for task in receiver.into_iter() {
-                    if shutdown_worker.load(Ordering::SeqCst) {
-                        return;
-                    }
-                    let envelope = match task {
-                        Task::SendEnvelope(envelope) => envelope,
-                        Task::Flush(sender) => {
-                            sender.send(()).ok();
-                            continue;
-                        }
-                        Task::Shutdown => {
-                            return;
+        let (sender, receiver) = sync_channel(QUEUE_CAPACITY);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let state = Arc::new(WorkerState::new(options));
+        let handles = spawn_workers(options, || {
+            let mut send = make_send();
+            let receiver = receiver.clone();
+            let sender = sender.clone();
+            let state = state.clone();
+            move || {
+                while let Some((envelope, size, mut rl)) = state.next_envelope(&receiver) {
+                    let mut attempt = 1;
+                    let mut next = Some(envelope);
+                    while let Some(envelope) = next.take() {
+                        let copy = state.copy_for_retry(&envelope, attempt);
+                        if send(envelope, &mut rl) == SendStatus::Failed {
+                            next = copy.and_then(|copy| state.retry_or_spool(copy, attempt, &rl));
+                            attempt += 1;
+                        } else {
+                            state.replay_spooled(&sender);
                         }
-                    };
-
-                    if let Some(time_left) = rl.is_disabled(RateLimitingCategory::Any) {
-                        sentry_debug!(
-                            "Skipping event send because we're disabled due to rate limits for {}s",
-                            time_left.as_secs()
-                        );
-                        continue;
                     }
-                    match rl.filter_envelope(envelope) {
-                        Some(envelope) => {
-                            send(envelope, &mut rl);
-                        }
-                        None => {
-                            sentry_debug!("Envelope was discarded due to per-item rate limits");
-                        }
-                    };
+                    state.update_rate_limits(rl);
+                    state.finished(size);
                 }
-            })
-            .ok();
+            }
+        });
+        state.replay_spooled(&sender);
 
         Self {
             sender,
-            shutdown,
-            handle,
+            state,
+            handles,
         }
     }
 
     pub fn send(&self, envelope: Envelope) {
-        let _ = self.sender.send(Task::SendEnvelope(envelope));
+        let (envelope, size) = match self.state.enqueue(envelope) {
+            Some(envelope) => envelope,
+            None => return,
+        };
+        if self
+            .sender
+            .send(Task::SendEnvelope(envelope, size))
+            .is_err()
+        {
+            self.state.finished(size);
+        }
     }
 
     pub fn flush(&self, timeout: Duration) -> bool {
-        let (sender, receiver) = sync_channel(1);
-        let _ = self.sender.send(Task::Flush(sender));
-        receiver.recv_timeout(timeout).is_err()
+        self.state.wait_drained(timeout)
+    }
+
+    pub fn backpressure(&self) -> Backpressure {
+        self.state.backpressure()
     }
 }
 
 impl Drop for TransportThread {
     fn drop(&mut self) {
-        self.shutdown.store(true, Ordering::SeqCst);
-        let _ = self.sender.send(Task::Shutdown);
-        if let Some(handle) = self.handle.take() {
+        self.state.shut_down();
+        for _ in &self.handles {
+            let _ = self.sender.send(Task::Shutdown);
+        }
+        for handle in self.handles.drain(..) {
             handle.join().unwrap();
         }
     }
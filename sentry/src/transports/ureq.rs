@@ -14,9 +14,12 @@ use ureq::{Agent, AgentBuilder, Proxy};
 #[cfg(feature = "rustls")]
 use webpki_roots::TLS_SERVER_ROOTS;
 
+use super::ratelimit::RateLimiter;
 use super::thread::TransportThread;
+use super::workers::SendStatus;
+use super::{encode_body, report_rejection};
 
-use crate::{sentry_debug, types::Scheme, ClientOptions, Envelope, Transport};
+use crate::{sentry_debug, types::Scheme, Backpressure, ClientOptions, Envelope, Transport};
 
 /// A [`Transport`] that sends events via the [`ureq`] library.
 ///
@@ -115,14 +118,27 @@ impl UreqHttpTransport {
         let user_agent = options.user_agent.clone();
         let auth = dsn.to_auth(Some(&user_agent)).to_string();
         let url = dsn.envelope_api_url().to_string();
+        let on_event_rejected = options.on_event_rejected.clone();
+        let compress = options.compress_payloads;
+        let timeout = options.transport_timeout;
+
+        let send = move |envelope: Envelope, rl: &mut RateLimiter| {
+            let event_id = envelope.uuid().copied();
+            let (body, encoding) = encode_body(&envelope, compress);
+            let mut request = agent.post(&url).set("X-Sentry-Auth", &auth);
+            if let Some(encoding) = encoding {
+                request = request.set("Content-Encoding", encoding);
+            }
+            if let Some(timeout) = timeout {
+                request = request.timeout(timeout);
+            }
+            let request = request.send_bytes(&body);
 
-        let thread = TransportThread::new(move |envelope, rl| {
-            let mut body = Vec::new();
-            envelope.to_writer(&mut body).unwrap();
-            let request = agent
-                .post(&url)
-                .set("X-Sentry-Auth", &auth)
-                .send_bytes(&body);
+            // `ureq` reports responses with an error status as errors
+            let request = match request {
+                Err(ureq::Error::Status(_, response)) => Ok(response),
+                request => request,
+            };
 
             match request {
                 Ok(response) => {
@@ -134,20 +150,25 @@ impl UreqHttpTransport {
                         rl.update_from_429();
                     }
 
+                    let status = response.status();
                     match response.into_string() {
                         Err(err) => {
                             sentry_debug!("Failed to read sentry response: {}", err);
                         }
                         Ok(text) => {
                             sentry_debug!("Get response: `{}`", text);
+                            report_rejection(on_event_rejected.as_ref(), event_id, status, &text);
                         }
                     }
+                    SendStatus::from_response(status)
                 }
                 Err(err) => {
                     sentry_debug!("Failed to send envelope: {}", err);
+                    SendStatus::Failed
                 }
             }
-        });
+        };
+        let thread = TransportThread::new(options, move || send.clone());
         Self { thread }
     }
 }
@@ -163,4 +184,8 @@ impl Transport for UreqHttpTransport {
     fn shutdown(&self, timeout: Duration) -> bool {
         self.flush(timeout)
     }
+
+    fn backpressure(&self) -> Backpressure {
+        self.thread.backpressure()
+    }
 }
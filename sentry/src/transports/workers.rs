@@ -0,0 +1,481 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::ratelimit::{RateLimiter, RateLimitingCategory};
+use crate::{
+    sentry_debug, Backpressure, BackpressureCallback, ClientOptions, Envelope, FlushProgress,
+    FlushProgressCallback, RetryPolicy, Spool,
+};
+
+/// The number of tasks the transport queue can hold.
+pub const QUEUE_CAPACITY: usize = 30;
+
+/// The number of pending envelopes from which the queue counts as nearly full.
+const QUEUE_PRESSURE_THRESHOLD: usize = QUEUE_CAPACITY * 4 / 5;
+
+pub enum Task {
+    /// Sends an envelope, along with its size as it is accounted as pending.
+    SendEnvelope(Envelope, usize),
+    Shutdown,
+}
+
+/// The outcome of an attempt to send an envelope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SendStatus {
+    /// The server handled the envelope, even if it rejected it.
+    Handled,
+    /// The envelope did not reach the server, but may when it is retried.
+    Failed,
+}
+
+impl SendStatus {
+    /// Returns the outcome of a request that got a response with `status`.
+    pub fn from_response(status: u16) -> Self {
+        if status >= 500 {
+            SendStatus::Failed
+        } else {
+            SendStatus::Handled
+        }
+    }
+}
+
+/// State shared between a transport and its worker threads.
+pub struct WorkerState {
+    shutdown: AtomicBool,
+    /// The envelopes that are either queued or currently being sent.
+    pending: Mutex<FlushProgress>,
+    changed: Condvar,
+    rate_limiter: Mutex<RateLimiter>,
+    /// The number of envelopes that were dropped because of rate limits.
+    rate_limited: AtomicU64,
+    retry_policy: Option<RetryPolicy>,
+    /// The spool for envelopes that could not be sent.
+    spool: Option<Spool>,
+    /// Whether envelopes might be waiting in the spool.
+    spooled: AtomicBool,
+    on_flush_progress: Option<FlushProgressCallback>,
+    on_backpressure_change: Option<BackpressureCallback>,
+    /// The backpressure that was last reported to `on_backpressure_change`.
+    reported_backpressure: Mutex<Backpressure>,
+}
+
+impl WorkerState {
+    pub fn new(options: &ClientOptions) -> Self {
+        let spool = options
+            .offline_spool_dir
+            .as_ref()
+            .and_then(|dir| match Spool::open(dir) {
+                Ok(spool) => Some(spool),
+                Err(err) => {
+                    sentry_debug!("failed to open the offline spool: {}", err);
+                    None
+                }
+            });
+        let spooled = spool
+            .as_ref()
+            .map_or(false, |spool| !spool.is_empty().unwrap_or(true));
+        WorkerState {
+            shutdown: AtomicBool::new(false),
+            pending: Mutex::new(FlushProgress::default()),
+            changed: Condvar::new(),
+            rate_limiter: Mutex::new(RateLimiter::new()),
+            rate_limited: AtomicU64::new(0),
+            retry_policy: options.retry_policy,
+            spool,
+            spooled: AtomicBool::new(spooled),
+            on_flush_progress: options.on_flush_progress.clone(),
+            on_backpressure_change: options.on_backpressure_change.clone(),
+            reported_backpressure: Mutex::new(Backpressure::default()),
+        }
+    }
+
+    /// Records that an envelope is put into the queue.
+    ///
+    /// Envelopes that are rate limited are discarded right away instead, so
+    /// they neither take up space in the queue nor delay a flush.  Returns the
+    /// envelope along with its size as it is accounted as pending.
+    pub fn enqueue(&self, envelope: Envelope) -> Option<(Envelope, usize)> {
+        let envelope = {
+            let rl = self.rate_limiter.lock().unwrap();
+            if rl.is_disabled(RateLimitingCategory::Any).is_some() {
+                sentry_debug!("Discarding envelope because we're disabled due to rate limits");
+                self.rate_limited.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+            match rl.filter_envelope(envelope) {
+                Some(envelope) => envelope,
+                None => {
+                    self.rate_limited.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+            }
+        };
+        // the size is only needed to report the progress of flushing
+        let size = match self.on_flush_progress {
+            Some(_) => envelope_size(&envelope),
+            None => 0,
+        };
+        {
+            let mut pending = self.pending.lock().unwrap();
+            pending.envelopes_remaining += 1;
+            pending.bytes_remaining += size;
+        }
+        self.report_backpressure();
+        Some((envelope, size))
+    }
+
+    /// Records that an envelope of the given size was sent or discarded.
+    pub fn finished(&self, size: usize) {
+        {
+            let mut pending = self.pending.lock().unwrap();
+            pending.envelopes_remaining = pending.envelopes_remaining.saturating_sub(1);
+            pending.bytes_remaining = pending.bytes_remaining.saturating_sub(size);
+            self.changed.notify_all();
+        }
+        self.report_backpressure();
+    }
+
+    /// Waits until all pending envelopes were handled.
+    ///
+    /// The progress is reported to the `on_flush_progress` callback whenever
+    /// it changes.  Returns `false` if that did not happen within `timeout`.
+    pub fn wait_drained(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut pending = self.pending.lock().unwrap();
+        loop {
+            if let Some(ref on_flush_progress) = self.on_flush_progress {
+                let progress = *pending;
+                drop(pending);
+                on_flush_progress(progress);
+                pending = self.pending.lock().unwrap();
+            }
+            if pending.envelopes_remaining == 0 {
+                return true;
+            }
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                return false;
+            }
+            pending = self.changed.wait_timeout(pending, timeout).unwrap().0;
+        }
+    }
+
+    /// Tells the workers to stop once they pick up their next task.
+    pub fn shut_down(&self) {
+        // holding the lock, so that workers waiting to retry are woken up
+        let _pending = self.pending.lock().unwrap();
+        self.shutdown.store(true, Ordering::SeqCst);
+        self.changed.notify_all();
+    }
+
+    /// Receives the next envelope that should be sent.
+    ///
+    /// Envelopes that became rate limited while they were queued are
+    /// discarded right away, and envelopes that are still queued once the
+    /// transport shuts down are written to the offline spool, if there is
+    /// one.  Returns the envelope along with its size and a snapshot of the
+    /// current rate limits, or `None` once the worker should stop.
+    pub fn next_envelope(
+        &self,
+        receiver: &Mutex<Receiver<Task>>,
+    ) -> Option<(Envelope, usize, RateLimiter)> {
+        loop {
+            let task = receiver.lock().unwrap().recv().ok()?;
+            let (envelope, size) = match task {
+                Task::SendEnvelope(envelope, size) => (envelope, size),
+                Task::Shutdown => return None,
+            };
+            if self.shutdown.load(Ordering::SeqCst) {
+                // every worker stops at its own `Shutdown` task, which is
+                // queued after the envelopes
+                self.spool(&envelope);
+                self.finished(size);
+                continue;
+            }
+
+            let rl = self.rate_limiter.lock().unwrap().clone();
+            if let Some(time_left) = rl.is_disabled(RateLimitingCategory::Any) {
+                sentry_debug!(
+                    "Skipping event send because we're disabled due to rate limits for {}s",
+                    time_left.as_secs()
+                );
+                self.rate_limited.fetch_add(1, Ordering::Relaxed);
+                self.finished(size);
+                continue;
+            }
+            match rl.filter_envelope(envelope) {
+                Some(envelope) => return Some((envelope, size, rl)),
+                None => {
+                    sentry_debug!("Envelope was discarded due to per-item rate limits");
+                    self.rate_limited.fetch_add(1, Ordering::Relaxed);
+                    self.finished(size);
+                }
+            }
+        }
+    }
+
+    /// Returns a copy of the envelope, which is retried or spooled if the
+    /// given attempt fails.
+    ///
+    /// This has to be called before the attempt, as sending consumes the
+    /// envelope.
+    pub fn copy_for_retry(&self, envelope: &Envelope, attempt: u32) -> Option<Envelope> {
+        let retry = self
+            .retry_policy
+            .as_ref()
+            .map_or(false, |policy| attempt < policy.max_attempts);
+        (retry || self.spool.is_some()).then(|| envelope.clone())
+    }
+
+    /// Waits before retrying the envelope after the given attempt failed.
+    ///
+    /// Once there are no attempts left, or the transport is shutting down,
+    /// the envelope is written to the offline spool instead, if there is one,
+    /// and `None` is returned.  This is also returned if the envelope became
+    /// rate limited.
+    pub fn retry_or_spool(
+        &self,
+        envelope: Envelope,
+        attempt: u32,
+        rl: &RateLimiter,
+    ) -> Option<Envelope> {
+        let policy = match self.retry_policy {
+            Some(ref policy) if attempt < policy.max_attempts => policy,
+            _ => {
+                self.spool(&envelope);
+                return None;
+            }
+        };
+        let delay = policy.backoff(attempt);
+        sentry_debug!("Retrying to send envelope in {:?}", delay);
+        let pending = self.pending.lock().unwrap();
+        drop(
+            self.changed
+                .wait_timeout_while(pending, delay, |_| !self.shutdown.load(Ordering::SeqCst))
+                .unwrap(),
+        );
+        if self.shutdown.load(Ordering::SeqCst) {
+            self.spool(&envelope);
+            return None;
+        }
+        if rl.is_disabled(RateLimitingCategory::Any).is_some() {
+            self.rate_limited.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        let filtered = rl.filter_envelope(envelope);
+        if filtered.is_none() {
+            self.rate_limited.fetch_add(1, Ordering::Relaxed);
+        }
+        filtered
+    }
+
+    /// Writes an envelope that could not be sent to the offline spool.
+    fn spool(&self, envelope: &Envelope) {
+        let spool = match self.spool {
+            Some(ref spool) => spool,
+            None => return,
+        };
+        match spool.write(envelope) {
+            Ok(()) => {
+                sentry_debug!("Envelope could not be sent, writing it to the offline spool");
+                self.spooled.store(true, Ordering::SeqCst);
+            }
+            Err(err) => {
+                sentry_debug!("failed to spool envelope: {}", err);
+            }
+        }
+    }
+
+    /// Queues the envelopes of the offline spool to be sent again.
+    ///
+    /// This is called when the transport starts and whenever an envelope was
+    /// sent successfully, and does nothing if the spool is empty.  Envelopes
+    /// that do not fit into the queue stay in the spool.  The queued envelopes
+    /// are removed from the spool, and written to it again if they cannot be
+    /// sent before the transport shuts down.
+    pub fn replay_spooled(&self, sender: &SyncSender<Task>) {
+        let spool = match self.spool {
+            Some(ref spool) => spool,
+            None => return,
+        };
+        if !self.spooled.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        let uploader = match spool.lock_uploader() {
+            Ok(Some(uploader)) => uploader,
+            Ok(None) => {
+                // another process is replaying the spool right now
+                self.spooled.store(true, Ordering::SeqCst);
+                return;
+            }
+            Err(err) => {
+                sentry_debug!("failed to lock the offline spool: {}", err);
+                return;
+            }
+        };
+        let queued = uploader.drain(|envelope| {
+            let (envelope, size) = match self.enqueue(envelope) {
+                Some(envelope) => envelope,
+                // rate limited envelopes are discarded
+                None => return true,
+            };
+            let queued = sender.try_send(Task::SendEnvelope(envelope, size)).is_ok();
+            if !queued {
+                self.finished(size);
+                self.spooled.store(true, Ordering::SeqCst);
+            }
+            queued
+        });
+        match queued {
+            Ok(queued) => {
+                sentry_debug!("queued {} envelopes from the offline spool", queued);
+            }
+            Err(err) => {
+                sentry_debug!("failed to drain the offline spool: {}", err);
+            }
+        }
+    }
+
+    /// Merges the rate limits a worker received while sending an envelope.
+    pub fn update_rate_limits(&self, rl: RateLimiter) {
+        self.rate_limiter.lock().unwrap().merge(rl);
+        self.report_backpressure();
+    }
+
+    pub fn backpressure(&self) -> Backpressure {
+        Backpressure {
+            queue_nearly_full: self.pending.lock().unwrap().envelopes_remaining
+                >= QUEUE_PRESSURE_THRESHOLD,
+            rate_limits: self.rate_limiter.lock().unwrap().rate_limits(),
+            rate_limited_envelopes: self.rate_limited.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Invokes `on_backpressure_change` if the queue became nearly full or
+    /// has room again, or if the rate limits changed.
+    ///
+    /// The number of rate limited envelopes alone does not count as a change.
+    fn report_backpressure(&self) {
+        let callback = match self.on_backpressure_change {
+            Some(ref callback) => callback,
+            None => return,
+        };
+        let backpressure = self.backpressure();
+        {
+            let mut reported = self.reported_backpressure.lock().unwrap();
+            if reported.queue_nearly_full == backpressure.queue_nearly_full
+                && reported.rate_limits == backpressure.rate_limits
+            {
+                return;
+            }
+            *reported = backpressure;
+        }
+        callback(backpressure);
+    }
+}
+
+/// Returns the serialized size of the envelope.
+fn envelope_size(envelope: &Envelope) -> usize {
+    struct Counter(usize);
+
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    envelope.to_writer(&mut counter).ok();
+    counter.0
+}
+
+/// Spawns the transport worker threads as configured in the options.
+///
+/// `make_worker` is called once per thread to create the function it runs.
+pub fn spawn_workers<F, W>(options: &ClientOptions, mut make_worker: F) -> Vec<JoinHandle<()>>
+where
+    F: FnMut() -> W,
+    W: FnOnce() + Send + 'static,
+{
+    let count = options.transport_threads.max(1);
+    (0..count)
+        .filter_map(|index| {
+            let name = if count == 1 {
+                options.transport_thread_name.to_string()
+            } else {
+                format!("{}-{}", options.transport_thread_name, index)
+            };
+            let on_start = options.on_transport_thread_start.clone();
+            let worker = make_worker();
+            thread::Builder::new()
+                .name(name)
+                .spawn(move || {
+                    if let Some(on_start) = on_start {
+                        on_start();
+                    }
+                    worker()
+                })
+                .ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::protocol::{Event, Transaction};
+
+    #[test]
+    fn test_backpressure() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = changes.clone();
+        let state = WorkerState::new(&ClientOptions {
+            on_backpressure_change: Some(Arc::new(move |backpressure| {
+                recorded.lock().unwrap().push(backpressure);
+            })),
+            ..Default::default()
+        });
+
+        let mut sizes = Vec::new();
+        for _ in 0..QUEUE_PRESSURE_THRESHOLD {
+            assert!(!state.backpressure().queue_nearly_full);
+            let (_, size) = state.enqueue(Event::default().into()).unwrap();
+            sizes.push(size);
+        }
+        assert!(state.backpressure().queue_nearly_full);
+        for size in sizes {
+            state.finished(size);
+        }
+        assert!(!state.backpressure().is_under_pressure());
+
+        let mut rl = RateLimiter::new();
+        rl.update_from_sentry_header("60:transaction:project");
+        state.update_rate_limits(rl);
+        let backpressure = state.backpressure();
+        assert!(backpressure.rate_limits.transactions.is_some());
+        assert_eq!(backpressure.rate_limits.errors, None);
+        assert!(backpressure.is_rate_limited());
+
+        // dropping a rate limited envelope is counted, but is no change
+        assert!(state.enqueue(Transaction::default().into()).is_none());
+        assert!(state.enqueue(Event::default().into()).is_some());
+        assert_eq!(state.backpressure().rate_limited_envelopes, 1);
+
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes.len(), 3);
+        assert!(changes[0].queue_nearly_full);
+        assert!(!changes[1].queue_nearly_full);
+        assert_eq!(changes[2].rate_limits, backpressure.rate_limits);
+    }
+}
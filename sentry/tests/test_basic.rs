@@ -199,3 +199,636 @@ fn test_attachment_sent_from_scope() {
         && attachment.buffer == vec![1, 2, 3, 4, 5, 6, 7, 8, 9]
     ));
 }
+
+#[test]
+fn test_attachment_sent_from_capture_hook() {
+    let options = sentry::ClientOptions {
+        capture_hook: Some(Arc::new(|event: &sentry::protocol::Event<'static>| {
+            vec![Attachment {
+                buffer: event.message.clone().unwrap_or_default().into_bytes(),
+                filename: "state.txt".to_string(),
+                ..Default::default()
+            }]
+        })),
+        ..Default::default()
+    };
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            sentry::capture_message("app state", sentry::Level::Error);
+        },
+        options,
+    );
+
+    assert_eq!(envelopes.len(), 1);
+
+    let items = envelopes[0].items().collect::<Vec<_>>();
+
+    assert_eq!(items.len(), 2);
+    assert!(matches!(items[1],
+        EnvelopeItem::Attachment(attachment)
+        if attachment.filename == *"state.txt"
+        && attachment.buffer == b"app state".to_vec()
+    ));
+}
+
+#[test]
+fn test_scope_observer() {
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl sentry::ScopeObserver for Recorder {
+        fn set_tag(&self, key: &str, value: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("set_tag {}={}", key, value));
+        }
+        fn remove_tag(&self, key: &str) {
+            self.0.lock().unwrap().push(format!("remove_tag {}", key));
+        }
+        fn set_user(&self, user: Option<&sentry::User>) {
+            let id = user.and_then(|user| user.id.clone());
+            self.0.lock().unwrap().push(format!("set_user {:?}", id));
+        }
+        fn add_breadcrumb(&self, breadcrumb: &sentry::Breadcrumb) {
+            let message = breadcrumb.message.as_deref().unwrap_or_default();
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("add_breadcrumb {}", message));
+        }
+    }
+
+    let recorder = Recorder::default();
+    sentry::test::with_captured_events(|| {
+        sentry::configure_scope(|scope| scope.add_observer(recorder.clone()));
+        sentry::configure_scope(|scope| {
+            scope.set_tag("worker", "worker1");
+            scope.remove_tag("worker");
+            scope.remove_tag("not-set");
+            scope.set_user(Some(sentry::User {
+                id: Some("42".into()),
+                ..Default::default()
+            }));
+        });
+        sentry::add_breadcrumb(sentry::Breadcrumb {
+            message: Some("crumb".into()),
+            ..Default::default()
+        });
+    });
+
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        vec![
+            "set_tag worker=worker1",
+            "remove_tag worker",
+            "set_user Some(\"42\")",
+            "add_breadcrumb crumb",
+        ]
+    );
+}
+
+#[test]
+fn test_user_feedback() {
+    let mut event_id = Uuid::nil();
+    let envelopes = sentry::test::with_captured_envelopes(|| {
+        let feedback = sentry::UserFeedback::new("It broke");
+        assert_eq!(
+            sentry::capture_user_feedback(feedback.clone()),
+            Err(sentry::FeedbackError::MissingEventId)
+        );
+
+        event_id = sentry::capture_message("test", sentry::Level::Error);
+        sentry::capture_user_feedback(feedback.email("jane@example.com")).unwrap();
+    });
+
+    assert_eq!(envelopes.len(), 2);
+    let report = match envelopes[1].items().next() {
+        Some(EnvelopeItem::UserReport(report)) => report,
+        item => panic!("expected a user report, got {:?}", item),
+    };
+    assert_eq!(report.event_id, event_id);
+    assert_eq!(report.email, "jane@example.com");
+    assert_eq!(report.comments, "It broke");
+}
+
+#[test]
+fn test_deterministic_events() {
+    let capture = || {
+        sentry::test::with_captured_events_options(
+            || {
+                sentry::add_breadcrumb(sentry::Breadcrumb {
+                    message: Some("crumb".into()),
+                    ..Default::default()
+                });
+                sentry::capture_message("first", sentry::Level::Info);
+                sentry::capture_message("second", sentry::Level::Error);
+            },
+            sentry::test::deterministic(sentry::ClientOptions {
+                release: Some("app@1.0.0".into()),
+                ..Default::default()
+            }),
+        )
+    };
+
+    let first = capture();
+    let second = capture();
+    assert_eq!(first.len(), 2);
+    assert_eq!(first[0].event_id, Uuid::from_u128(1));
+    assert_eq!(first[1].event_id, Uuid::from_u128(2));
+    assert_eq!(
+        first[0].breadcrumbs[0].timestamp,
+        sentry::test::deterministic_timestamp()
+    );
+    assert_eq!(
+        first[0].sdk.as_ref().unwrap().version,
+        sentry::test::DETERMINISTIC_SDK_VERSION
+    );
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_assert_event() {
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::configure_scope(|scope| {
+                scope.set_tag("shard", 7);
+                scope.set_transaction(Some("/orders"));
+            });
+            let err = std::io::Error::new(std::io::ErrorKind::Other, "boom goes the disk");
+            sentry::capture_error(&err);
+        },
+        sentry::ClientOptions {
+            release: Some("app@1.0.0".into()),
+            ..Default::default()
+        },
+    );
+
+    sentry::assert_event!(
+        events[0],
+        level: Error,
+        message contains "boom",
+        tag "shard" == "7",
+        tag "shard",
+        transaction == "/orders",
+        exception == "Custom",
+        release == "app@1.0.0",
+    );
+
+    let result = std::panic::catch_unwind(|| {
+        sentry::assert_event!(events[0], tag "shard" == "8");
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_errors_link_to_active_span() {
+    // returns the trace and span id from the `sentry-trace` header
+    fn ids(headers: sentry::TraceHeadersIter) -> (String, String) {
+        let (_, value) = headers.into_iter().next().unwrap();
+        let mut parts = value.split('-');
+        let trace_id = parts.next().unwrap().to_owned();
+        let span_id = parts.next().unwrap().to_owned();
+        (trace_id, span_id)
+    }
+
+    let mut transaction_ids = None;
+    let mut span_ids = None;
+    let events = sentry::test::with_captured_events(|| {
+        let ctx = sentry::TransactionContext::new("transaction", "op");
+        let transaction = sentry::start_transaction(ctx);
+        sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+        sentry::capture_message("in transaction", sentry::Level::Error);
+
+        let parent = sentry::configure_scope(|scope| scope.get_span()).unwrap();
+        let span = parent.start_child("child.op", "child");
+        sentry::configure_scope(|scope| scope.set_span(Some(span.clone().into())));
+        sentry::capture_message("in span", sentry::Level::Error);
+
+        transaction_ids = Some(ids(transaction.iter_headers()));
+        span_ids = Some(ids(span.iter_headers()));
+        span.finish();
+        transaction.finish();
+    });
+    let (trace_id, transaction_span_id) = transaction_ids.unwrap();
+    let (_, span_id) = span_ids.unwrap();
+
+    assert_eq!(events.len(), 2);
+    let trace = |event: &sentry::protocol::Event| match event.contexts.get("trace") {
+        Some(sentry::protocol::Context::Trace(trace)) => (**trace).clone(),
+        context => panic!("expected trace context, got {:?}", context),
+    };
+
+    let trace_in_transaction = trace(&events[0]);
+    assert_eq!(trace_in_transaction.trace_id.to_string(), trace_id);
+    assert_eq!(
+        trace_in_transaction.span_id.to_string(),
+        transaction_span_id
+    );
+
+    let trace_in_span = trace(&events[1]);
+    assert_eq!(trace_in_span.trace_id.to_string(), trace_id);
+    assert_eq!(trace_in_span.span_id.to_string(), span_id);
+    assert_eq!(
+        trace_in_span.parent_span_id.map(|id| id.to_string()),
+        Some(transaction_span_id)
+    );
+    assert_eq!(trace_in_span.op.as_deref(), Some("child.op"));
+}
+
+#[test]
+fn test_current_trace_headers() {
+    assert!(sentry::current_trace_headers().is_empty());
+
+    let options = sentry::ClientOptions {
+        release: Some("app@1.0".into()),
+        ..Default::default()
+    };
+    sentry::test::with_captured_events_options(
+        || {
+            assert!(sentry::current_trace_headers().is_empty());
+
+            let headers = [(
+                "sentry-trace",
+                "09e04486820349518ac7b5d2adbf6ba5-9cf635fa5b870b3a-1",
+            )];
+            let ctx = sentry::TransactionContext::continue_from_headers("txn", "op", headers);
+            let transaction = sentry::start_transaction(ctx);
+            sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+
+            let headers = sentry::current_trace_headers();
+            assert_eq!(headers.len(), 2);
+            let trace = sentry::parse_sentry_trace(&headers[0].1).unwrap();
+            assert_eq!(
+                trace.trace_id().to_string(),
+                "09e04486820349518ac7b5d2adbf6ba5"
+            );
+            assert_eq!(trace.sampled(), Some(true));
+
+            assert_eq!(headers[1].0, "baggage");
+            let baggage = sentry::parse_baggage(&headers[1].1);
+            assert_eq!(
+                baggage.get("sentry-trace_id"),
+                Some("09e04486820349518ac7b5d2adbf6ba5")
+            );
+            assert_eq!(baggage.get("sentry-release"), Some("app@1.0"));
+            transaction.finish();
+        },
+        options,
+    );
+}
+
+#[test]
+fn test_explicit_sampling_decision() {
+    let run = |sampled: Option<bool>, rate| {
+        let is_sampled = std::cell::Cell::new(false);
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let mut ctx = sentry::TransactionContext::new("job", "task");
+                ctx.set_sampled(sampled);
+                let transaction = sentry::start_transaction(ctx);
+                let span = transaction.start_child("step", "");
+                assert_eq!(transaction.is_sampled(), span.is_sampled());
+                is_sampled.set(span.is_sampled());
+                span.finish();
+                transaction.finish();
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: rate,
+                ..Default::default()
+            },
+        );
+        assert_eq!(envelopes.len(), is_sampled.get() as usize);
+        is_sampled.get()
+    };
+
+    assert!(run(Some(true), 0.0));
+    assert!(!run(Some(false), 1.0));
+    assert!(run(None, 1.0));
+    assert!(!run(None, 0.0));
+}
+
+#[test]
+fn test_deferred_stacktrace_resolution() {
+    let stacktrace = sentry::current_stacktrace(false).unwrap();
+    assert!(stacktrace
+        .frames
+        .iter()
+        .all(|frame| frame.function.is_none() && frame.instruction_addr.is_some()));
+
+    let options = sentry::apply_defaults(sentry::ClientOptions {
+        background_processing: true,
+        ..Default::default()
+    });
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::capture_event(sentry::protocol::Event {
+                stacktrace: Some(stacktrace),
+                ..Default::default()
+            });
+        },
+        options,
+    );
+
+    let frames = &events[0].stacktrace.as_ref().unwrap().frames;
+    assert!(frames.iter().any(|frame| frame
+        .function
+        .as_deref()
+        .unwrap_or_default()
+        .contains("test_deferred_stacktrace_resolution")));
+}
+
+#[test]
+fn test_build_context() {
+    use sentry::protocol::{Context, Value};
+
+    let events = sentry::test::with_captured_events(|| {
+        sentry::configure_scope(|scope| {
+            scope.set_context("app.build", sentry::build_context!("test", "surf-h1"));
+        });
+        sentry::capture_message("hello", sentry::Level::Info);
+    });
+
+    let context = match events[0].contexts.get("app.build") {
+        Some(Context::Other(context)) => context,
+        _ => panic!("expected an app.build context"),
+    };
+    let expected: Vec<&str> = [
+        ("test", cfg!(feature = "test")),
+        ("surf-h1", cfg!(feature = "surf-h1")),
+    ]
+    .iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(feature, _)| *feature)
+    .collect();
+    assert_eq!(context["features"], Value::from(expected));
+    assert_eq!(context["target_os"], std::env::consts::OS);
+    assert_eq!(context["debug_assertions"], cfg!(debug_assertions));
+    let opt_level = if cfg!(debug_assertions) { "0" } else { "3" };
+    assert_eq!(context["opt_level"], opt_level);
+    assert_eq!(context["crate_name"], "sentry");
+}
+
+#[test]
+fn test_capture_error_with_locals() {
+    let events = sentry::test::with_captured_events(|| {
+        let user_id = 42;
+        let path = "config.toml";
+        let err = "NaN".parse::<u32>().unwrap_err();
+        sentry::capture_error_with_locals!(&err, user_id, path, retries = 1 + 2);
+    });
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].exception[0].ty, "ParseIntError");
+    let locals = &events[0].extra["locals"];
+    assert_eq!(locals["user_id"], "42");
+    assert_eq!(locals["path"], "\"config.toml\"");
+    assert_eq!(locals["retries"], "3");
+}
+
+#[test]
+fn test_unwrap_or_capture() {
+    use sentry::{OptionExt, ResultExt};
+
+    let events = sentry::test::with_captured_events(|| {
+        let parsed: Result<u32, _> = "NaN".parse::<u32>().capture_err("parsing the limit");
+        assert!(parsed.is_err());
+
+        let result = std::panic::catch_unwind(|| {
+            "NaN".parse::<u32>().unwrap_or_capture("invalid port");
+        });
+        assert!(result.is_err());
+
+        let result = std::panic::catch_unwind(|| None::<u32>.unwrap_or_capture("missing user"));
+        assert!(result.is_err());
+    });
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].level, sentry::Level::Error);
+    assert_eq!(events[0].message.as_deref(), Some("parsing the limit"));
+    assert_eq!(events[0].exception[0].ty, "ParseIntError");
+
+    assert_eq!(events[1].level, sentry::Level::Fatal);
+    assert_eq!(events[1].message.as_deref(), Some("invalid port"));
+    let location = events[1].extra["location"].as_str().unwrap();
+    assert!(location.starts_with(file!()), "{}", location);
+
+    assert_eq!(events[2].message.as_deref(), Some("missing user"));
+    assert!(events[2].exception.is_empty());
+}
+
+#[test]
+fn test_custom_envelope_items() {
+    use sentry::protocol::RawEnvelopeItem;
+
+    let envelopes = sentry::test::with_captured_envelopes(|| {
+        sentry::with_scope(
+            |scope| scope.add_envelope_item(RawEnvelopeItem::new("custom", "payload")),
+            || sentry::capture_message("with item", sentry::Level::Info),
+        );
+        sentry::capture_message("without item", sentry::Level::Info);
+    });
+
+    assert_eq!(envelopes.len(), 2);
+    let items: Vec<_> = envelopes[0].items().collect();
+    assert_eq!(items.len(), 2);
+    match items[1] {
+        EnvelopeItem::Raw(item) => {
+            assert_eq!(item.ty, "custom");
+            assert_eq!(item.payload, b"payload");
+        }
+        item => panic!("expected a raw item, got {:?}", item),
+    }
+    assert_eq!(envelopes[1].items().count(), 1);
+}
+
+#[test]
+fn test_transaction_scope() {
+    let events = sentry::test::with_captured_events(|| {
+        sentry::configure_scope(|scope| {
+            scope.set_transaction(Some("worker"));
+            assert_eq!(scope.transaction(), Some("worker"));
+        });
+        let name = sentry::transaction_scope("worker.process_batch", || {
+            sentry::capture_message("in batch", sentry::Level::Info);
+            sentry::configure_scope(|scope| scope.transaction().map(str::to_owned))
+        });
+        assert_eq!(name.as_deref(), Some("worker.process_batch"));
+        sentry::capture_message("after batch", sentry::Level::Info);
+    });
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[0].transaction.as_deref(),
+        Some("worker.process_batch")
+    );
+    assert_eq!(events[1].transaction.as_deref(), Some("worker"));
+}
+
+#[test]
+fn test_start_span_guard() {
+    fn lookup(key: &str) -> Option<u32> {
+        let _span = sentry::start_span("cache.get", key);
+        if key.is_empty() {
+            return None;
+        }
+        Some(1)
+    }
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let ctx = sentry::TransactionContext::new("request", "http.server");
+            let transaction = sentry::start_transaction(ctx);
+            sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+
+            lookup("");
+            lookup("user:1");
+            let panicked = std::panic::catch_unwind(|| {
+                let span = sentry::start_span("render", "page");
+                assert!(span.span().is_some());
+                panic!("render failed");
+            });
+            assert!(panicked.is_err());
+
+            // the transaction is the span of the scope again
+            let current = sentry::configure_scope(|scope| scope.get_span());
+            assert!(matches!(
+                current,
+                Some(sentry::TransactionOrSpan::Transaction(_))
+            ));
+            transaction.finish();
+        },
+        sentry::ClientOptions {
+            traces_sample_rate: 1.0,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(envelopes.len(), 1);
+    let transaction = match envelopes[0].items().next() {
+        Some(sentry::protocol::EnvelopeItem::Transaction(transaction)) => transaction,
+        item => panic!("expected a transaction, got {:?}", item),
+    };
+    let spans: Vec<_> = transaction
+        .spans
+        .iter()
+        .map(|span| (span.op.as_deref().unwrap(), span.status))
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            ("cache.get", None),
+            ("cache.get", None),
+            ("render", Some(sentry::protocol::SpanStatus::InternalError)),
+        ]
+    );
+    assert!(transaction
+        .spans
+        .iter()
+        .all(|span| span.timestamp.is_some()));
+}
+
+#[test]
+fn test_flag_evaluations() {
+    let events = sentry::test::with_captured_events(|| {
+        sentry::record_flag_evaluation("new-checkout", true);
+        sentry::record_flag_evaluation("dark-mode", false);
+        sentry::with_scope(
+            |scope| scope.record_flag_evaluation("new-checkout", false),
+            || sentry::capture_message("in scope", sentry::Level::Error),
+        );
+        sentry::capture_message("outside", sentry::Level::Error);
+    });
+
+    let flags: Vec<_> = events
+        .iter()
+        .map(|event| match &event.contexts["flags"] {
+            sentry::protocol::Context::Other(flags) => flags["values"].clone(),
+            context => panic!("unexpected flags context: {:?}", context),
+        })
+        .collect();
+    assert_eq!(
+        flags[0],
+        serde_json::json!([
+            {"flag": "dark-mode", "result": false},
+            {"flag": "new-checkout", "result": false},
+        ])
+    );
+    assert_eq!(
+        flags[1],
+        serde_json::json!([
+            {"flag": "new-checkout", "result": true},
+            {"flag": "dark-mode", "result": false},
+        ])
+    );
+}
+
+#[test]
+fn test_remote_config() {
+    let integration = Arc::new(sentry::RemoteConfigIntegration::new(|| {
+        Some(sentry::RemoteConfig {
+            traces_sample_rate: Some(1.0),
+            ignore_errors: vec!["noisy".into()],
+            ignore_transactions: vec!["GET /health".into()],
+            ..Default::default()
+        })
+    }));
+    let options = sentry::ClientOptions {
+        integrations: vec![integration.clone()],
+        traces_sample_rate: 0.0,
+        ..Default::default()
+    };
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            while integration.config().is_none() {
+                std::thread::yield_now();
+            }
+            sentry::capture_message("noisy", sentry::Level::Error);
+            sentry::capture_message("important", sentry::Level::Error);
+            sentry::start_transaction(sentry::TransactionContext::new("GET /health", "http"))
+                .finish();
+            sentry::start_transaction(sentry::TransactionContext::new("GET /users", "http"))
+                .finish();
+        },
+        options,
+    );
+
+    let mut messages = Vec::new();
+    let mut transactions = Vec::new();
+    for item in envelopes.iter().flat_map(|envelope| envelope.items()) {
+        match item {
+            EnvelopeItem::Event(event) => messages.push(event.message.clone()),
+            EnvelopeItem::Transaction(transaction) => transactions.push(transaction.name.clone()),
+            _ => {}
+        }
+    }
+    assert_eq!(messages, [Some("important".to_owned())]);
+    assert_eq!(transactions, [Some("GET /users".to_owned())]);
+}
+
+#[test]
+fn test_scope_guard_on_pop() {
+    use std::sync::{Arc, Mutex};
+
+    let popped = Arc::new(Mutex::new(Vec::new()));
+    let events = sentry::test::with_captured_events(|| {
+        let mut guard = sentry::Hub::current().push_scope();
+        sentry::configure_scope(|scope| scope.set_tag("request_id", "r-42"));
+        for name in ["span", "correlation id"] {
+            let popped = popped.clone();
+            guard.on_pop(move || {
+                // the scope is already popped when the callbacks run
+                sentry::capture_message(name, sentry::Level::Info);
+                popped.lock().unwrap().push(name);
+            });
+        }
+        drop(guard);
+    });
+
+    assert_eq!(*popped.lock().unwrap(), ["correlation id", "span"]);
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|event| event.tags.is_empty()));
+}
@@ -36,6 +36,36 @@ fn test_into_client() {
     assert!(sentry::Client::from_config(()).options().dsn.is_none());
 }
 
+#[test]
+fn test_dsn_query_options() {
+    let options: sentry::ClientOptions = (
+        "https://public@example.com/42?timeout=0.5&verify_ssl=false&proxy=http%3A%2F%2Fproxy%3A3128",
+        sentry::ClientOptions {
+            release: Some("foo@1.0".into()),
+            accept_invalid_certs_from_dsn: true,
+            ..Default::default()
+        },
+    )
+        .into();
+    assert_eq!(options.transport_timeout.unwrap().as_millis(), 500);
+    assert_eq!(
+        options.shutdown_timeout,
+        sentry::ClientOptions::default().shutdown_timeout
+    );
+    assert!(options.accept_invalid_certs);
+    assert_eq!(options.http_proxy.as_deref(), Some("http://proxy:3128"));
+    assert_eq!(options.https_proxy.as_deref(), Some("http://proxy:3128"));
+    assert_eq!(options.release.as_deref(), Some("foo@1.0"));
+    assert_eq!(
+        options.dsn.unwrap().to_string(),
+        "https://public:@example.com/42"
+    );
+
+    // the DSN alone can not disable SSL verification
+    let options: sentry::ClientOptions = "https://public@example.com/42?verify_ssl=0".into();
+    assert!(!options.accept_invalid_certs);
+}
+
 #[test]
 fn test_unwind_safe() {
     let transport = sentry::test::TestTransport::new();
@@ -82,3 +112,1425 @@ fn test_invalid_proxy() {
         ..Default::default()
     });
 }
+
+#[test]
+fn test_background_processing() {
+    let capturing_thread = std::thread::current().id();
+    let mut event_id = sentry::types::Uuid::nil();
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::configure_scope(|scope| scope.set_tag("scoped", "yes"));
+            event_id = sentry::capture_message("offloaded", sentry::Level::Info);
+        },
+        sentry::ClientOptions {
+            background_processing: true,
+            before_send: Some(Arc::new(move |event| {
+                assert_ne!(std::thread::current().id(), capturing_thread);
+                Some(event)
+            })),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_id, event_id);
+    assert_eq!(events[0].tags["scoped"], "yes");
+}
+
+#[test]
+fn test_background_processing_attaches_capturing_thread() {
+    let events = std::thread::Builder::new()
+        .name("capturing".into())
+        .spawn(|| {
+            sentry::test::with_captured_events_options(
+                || {
+                    sentry::capture_message("offloaded", sentry::Level::Info);
+                },
+                sentry::ClientOptions {
+                    background_processing: true,
+                    attach_stacktrace: true,
+                    ..Default::default()
+                }
+                .add_integration(sentry::integrations::backtrace::AttachStacktraceIntegration),
+            )
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    assert_eq!(events.len(), 1);
+    let thread = &events[0].threads.values[0];
+    assert_eq!(thread.name.as_deref(), Some("capturing"));
+    assert!(thread.stacktrace.is_some());
+}
+
+#[test]
+fn test_background_processing_queue_is_bounded() {
+    let blocked = Arc::new(std::sync::Mutex::new(()));
+    let guard = blocked.lock().unwrap();
+    let worker_blocked = blocked.clone();
+    let mut event_ids = Vec::new();
+    let events = sentry::test::with_captured_events_options(
+        || {
+            for _ in 0..50 {
+                event_ids.push(sentry::capture_message("offloaded", sentry::Level::Info));
+            }
+            drop(guard);
+        },
+        sentry::ClientOptions {
+            background_processing: true,
+            before_send: Some(Arc::new(move |event| {
+                drop(worker_blocked.lock().unwrap());
+                Some(event)
+            })),
+            ..Default::default()
+        },
+    );
+
+    let dropped = event_ids.iter().filter(|id| id.is_nil()).count();
+    assert!(dropped > 0);
+    assert_eq!(events.len() + dropped, 50);
+}
+
+#[test]
+fn test_background_processing_keeps_breadcrumbs_of_capture() {
+    let blocked = Arc::new(std::sync::Mutex::new(()));
+    let guard = blocked.lock().unwrap();
+    let worker_blocked = blocked.clone();
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::add_breadcrumb(sentry::Breadcrumb {
+                message: Some("before".into()),
+                ..Default::default()
+            });
+            sentry::capture_message("offloaded", sentry::Level::Info);
+            sentry::add_breadcrumb(sentry::Breadcrumb {
+                message: Some("after".into()),
+                ..Default::default()
+            });
+            drop(guard);
+        },
+        sentry::ClientOptions {
+            background_processing: true,
+            before_send: Some(Arc::new(move |event| {
+                drop(worker_blocked.lock().unwrap());
+                Some(event)
+            })),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+    let messages: Vec<_> = events[0]
+        .breadcrumbs
+        .iter()
+        .map(|breadcrumb| breadcrumb.message.as_deref().unwrap())
+        .collect();
+    assert_eq!(messages, ["before"]);
+}
+
+#[test]
+fn test_background_processing_survives_panics() {
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::capture_message("boom", sentry::Level::Info);
+            sentry::capture_message("processed", sentry::Level::Info);
+            let client = sentry::Hub::current().client().unwrap();
+            // clones share the worker of the client
+            let clone = sentry::Client::clone(&client);
+            assert!(clone.flush(None));
+        },
+        sentry::ClientOptions {
+            background_processing: true,
+            before_send: Some(Arc::new(|event| {
+                if event.message.as_deref() == Some("boom") {
+                    panic!("before_send failed");
+                }
+                Some(event)
+            })),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message.as_deref(), Some("processed"));
+}
+
+#[test]
+fn test_backpressure() {
+    struct CongestedTransport;
+
+    impl sentry::Transport for CongestedTransport {
+        fn send_envelope(&self, _envelope: sentry::Envelope) {}
+
+        fn backpressure(&self) -> sentry::Backpressure {
+            sentry::Backpressure {
+                queue_nearly_full: true,
+                ..Default::default()
+            }
+        }
+    }
+
+    assert!(!sentry::backpressure().is_under_pressure());
+
+    let options = sentry::ClientOptions {
+        dsn: "https://public@sentry.invalid/1".parse().ok(),
+        transport: Some(Arc::new(Arc::new(CongestedTransport))),
+        ..Default::default()
+    };
+    let hub = Arc::new(sentry::Hub::new(
+        Some(Arc::new(options.into())),
+        Arc::new(Default::default()),
+    ));
+    sentry::Hub::run(hub, || {
+        let backpressure = sentry::backpressure();
+        assert!(backpressure.queue_nearly_full);
+        assert!(!backpressure.is_rate_limited());
+        assert!(backpressure.is_under_pressure());
+    });
+}
+
+#[test]
+fn test_backpressure_of_transport() {
+    let server = sentry::test::TestServer::start_with(|_| {
+        sentry::test::TestResponse::new(200)
+            .with_header("X-Sentry-Rate-Limits", "60:transaction:project")
+    });
+    let changes = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = changes.clone();
+
+    let options = sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        on_backpressure_change: Some(Arc::new(move |backpressure| {
+            recorded.lock().unwrap().push(backpressure);
+        })),
+        ..Default::default()
+    });
+    let client = Arc::new(sentry::Client::from(options));
+    let hub = Arc::new(sentry::Hub::new(
+        Some(client.clone()),
+        Arc::new(Default::default()),
+    ));
+    sentry::Hub::run(hub, || {
+        assert!(!sentry::backpressure().is_under_pressure());
+        sentry::capture_message("limited", sentry::Level::Info);
+        assert!(client.flush(Some(std::time::Duration::from_secs(2))));
+
+        let backpressure = sentry::backpressure();
+        assert!(backpressure.rate_limits.transactions.is_some());
+        assert_eq!(backpressure.rate_limits.errors, None);
+        assert!(backpressure.is_rate_limited());
+        assert!(!backpressure.queue_nearly_full);
+    });
+
+    let changes = changes.lock().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].rate_limits.transactions.is_some());
+}
+
+#[test]
+fn test_transport_threads() {
+    let server = sentry::test::TestServer::start();
+    let started = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let started_hook = started.clone();
+
+    let options = sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        transport_threads: 2,
+        transport_thread_name: "custom-transport".into(),
+        on_transport_thread_start: Some(Arc::new(move || {
+            let name = std::thread::current().name().map(str::to_owned);
+            started_hook.lock().unwrap().push(name);
+        })),
+        ..Default::default()
+    });
+    let client = sentry::Client::from(options);
+    for i in 0..4 {
+        client.capture_event(
+            sentry::protocol::Event {
+                message: Some(format!("event {}", i)),
+                ..Default::default()
+            },
+            None,
+        );
+    }
+    assert!(client.flush(Some(std::time::Duration::from_secs(5))));
+    assert_eq!(server.events().len(), 4);
+
+    let mut started = started.lock().unwrap().clone();
+    started.sort();
+    assert_eq!(
+        started,
+        [
+            Some("custom-transport-0".to_owned()),
+            Some("custom-transport-1".to_owned())
+        ]
+    );
+}
+
+#[test]
+fn test_startup_crash_flush() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct FlushCounter(AtomicUsize);
+
+    impl sentry::Transport for FlushCounter {
+        fn send_envelope(&self, _envelope: sentry::Envelope) {}
+
+        fn flush(&self, _timeout: std::time::Duration) -> bool {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            true
+        }
+    }
+
+    let capture_with_window = |window| {
+        let transport = Arc::new(FlushCounter::default());
+        let client = sentry::Client::from(sentry::ClientOptions {
+            dsn: "https://public@sentry.invalid/1".parse().ok(),
+            transport: Some(Arc::new(transport.clone())),
+            startup_crash_window: window,
+            ..Default::default()
+        });
+        client.capture_event(Default::default(), None);
+        transport.0.load(Ordering::SeqCst)
+    };
+
+    assert_eq!(capture_with_window(None), 0);
+    assert_eq!(capture_with_window(Some(std::time::Duration::ZERO)), 0);
+    assert_eq!(
+        capture_with_window(Some(std::time::Duration::from_secs(60))),
+        1
+    );
+}
+
+#[test]
+fn test_custom_clock() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{Duration, SystemTime};
+
+    let ticks = Arc::new(AtomicU64::new(0));
+    let clock = {
+        let ticks = ticks.clone();
+        move || SystemTime::UNIX_EPOCH + Duration::from_secs(ticks.load(Ordering::SeqCst))
+    };
+    let options = sentry::ClientOptions {
+        traces_sample_rate: 1.0,
+        clock: Some(Arc::new(clock)),
+        ..Default::default()
+    };
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let transaction =
+                sentry::start_transaction(sentry::TransactionContext::new("tick", "simulation"));
+            ticks.store(10, Ordering::SeqCst);
+            let span = transaction.start_child("physics", "step");
+            ticks.store(15, Ordering::SeqCst);
+            span.finish();
+            ticks.store(20, Ordering::SeqCst);
+            transaction.finish();
+        },
+        options,
+    );
+
+    let transaction = match envelopes[0].items().next() {
+        Some(sentry::protocol::EnvelopeItem::Transaction(transaction)) => transaction.clone(),
+        _ => panic!("expected a transaction"),
+    };
+    let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+    assert_eq!(transaction.start_timestamp, at(0));
+    assert_eq!(transaction.timestamp, Some(at(20)));
+    assert_eq!(transaction.spans[0].start_timestamp, at(10));
+    assert_eq!(transaction.spans[0].timestamp, Some(at(15)));
+}
+
+#[test]
+fn test_transaction_context_preset() {
+    use sentry::protocol::{EnvelopeItem, TransactionSource};
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let ctx = sentry::TransactionContext::http_server("POST", "/users/{id}", []);
+            sentry::start_transaction(ctx).finish();
+        },
+        sentry::ClientOptions {
+            traces_sample_rate: 1.0,
+            ..Default::default()
+        },
+    );
+
+    let transaction = match envelopes[0].items().next() {
+        Some(EnvelopeItem::Transaction(transaction)) => transaction,
+        _ => panic!("expected a transaction"),
+    };
+    assert_eq!(transaction.name.as_deref(), Some("POST /users/{id}"));
+    assert_eq!(
+        transaction.transaction_info.unwrap().source,
+        TransactionSource::Route
+    );
+    assert_eq!(transaction.extra["http.request.method"], "POST");
+    assert_eq!(transaction.extra["http.route"], "/users/{id}");
+}
+
+#[test]
+fn test_unsampled_transaction_metrics() {
+    use sentry::protocol::{EnvelopeItem, MetricUnit, MetricValue};
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            for _ in 0..3 {
+                let ctx = sentry::TransactionContext::new("GET /users", "http.server");
+                let transaction = sentry::start_transaction(ctx);
+                assert!(!transaction.is_sampled());
+                transaction.finish();
+            }
+        },
+        sentry::ClientOptions {
+            traces_sample_rate: 0.0,
+            unsampled_transaction_metrics: true,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(envelopes.len(), 1);
+    let buckets = match envelopes[0].items().next() {
+        Some(EnvelopeItem::Statsd(buckets)) => buckets,
+        _ => panic!("expected metrics"),
+    };
+    let values = buckets
+        .iter()
+        .filter(|bucket| bucket.name == "transaction.duration")
+        .inspect(|bucket| {
+            assert_eq!(bucket.unit, MetricUnit::Millisecond);
+            assert_eq!(bucket.tags["transaction"], "GET /users");
+            assert_eq!(bucket.tags["transaction.op"], "http.server");
+        })
+        .map(|bucket| match &bucket.value {
+            MetricValue::Distribution(values) => values.len(),
+            _ => panic!("expected a distribution"),
+        })
+        .sum::<usize>();
+    assert_eq!(values, 3);
+}
+
+#[test]
+fn test_metrics_units_and_default_tags() {
+    use sentry::protocol::{EnvelopeItem, MetricUnit, MetricValue};
+    use sentry::Metric;
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            sentry::configure_scope(|scope| scope.set_transaction(Some("GET /users")));
+            Metric::count("requests").send();
+            Metric::count("requests").send();
+            Metric::distribution("response.size", 512.0)
+                .with_unit(MetricUnit::Byte)
+                .with_tag("environment", "canary")
+                .send();
+        },
+        sentry::ClientOptions {
+            release: Some("app@1.0.0".into()),
+            environment: Some("production".into()),
+            enable_metrics: true,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(envelopes.len(), 1);
+    let mut buckets = match envelopes[0].items().next() {
+        Some(EnvelopeItem::Statsd(buckets)) => buckets.clone(),
+        _ => panic!("expected metrics"),
+    };
+    buckets.sort_by(|a, b| a.name.cmp(&b.name));
+    assert_eq!(buckets.len(), 2);
+
+    let requests = &buckets[0];
+    assert_eq!(requests.name, "requests");
+    assert_eq!(requests.unit, MetricUnit::None);
+    assert_eq!(requests.value, MetricValue::Counter(2.0));
+    assert_eq!(requests.tags["release"], "app@1.0.0");
+    assert_eq!(requests.tags["environment"], "production");
+    assert_eq!(requests.tags["transaction"], "GET /users");
+
+    let size = &buckets[1];
+    assert_eq!(size.unit, MetricUnit::Byte);
+    assert_eq!(size.tags["environment"], "canary");
+    assert_eq!(
+        size.to_string().split('|').next(),
+        Some("response.size@byte:512")
+    );
+}
+
+#[test]
+fn test_scope_inheritance() {
+    use sentry::protocol::{Context, Value};
+    use sentry::{Breadcrumb, Hub, ScopeInheritance};
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::configure_scope(|scope| {
+                scope.set_tag("app", "server");
+                scope.set_user(Some(sentry::User {
+                    id: Some("42".into()),
+                    ..Default::default()
+                }));
+                scope.set_context("runtime", Context::Other(Default::default()));
+                scope.set_extra("worker", Value::from(1));
+            });
+            sentry::add_breadcrumb(Breadcrumb {
+                message: Some("startup".into()),
+                ..Default::default()
+            });
+
+            let hub = Arc::new(Hub::new_from_top(Hub::current()));
+            std::thread::spawn(move || {
+                Hub::run(hub, || {
+                    sentry::capture_message("request", sentry::Level::Info);
+                })
+            })
+            .join()
+            .unwrap();
+            sentry::capture_message("main", sentry::Level::Info);
+        },
+        sentry::ClientOptions {
+            scope_inheritance: ScopeInheritance {
+                breadcrumbs: false,
+                contexts: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 2);
+    let (request, main) = (&events[0], &events[1]);
+    assert_eq!(request.tags["app"], "server");
+    assert_eq!(request.user.as_ref().unwrap().id.as_deref(), Some("42"));
+    assert_eq!(request.extra["worker"], 1);
+    assert!(request.breadcrumbs.is_empty());
+    assert!(!request.contexts.contains_key("runtime"));
+
+    // the hub that the scope was derived from is left untouched
+    assert_eq!(main.breadcrumbs.len(), 1);
+    assert!(main.contexts.contains_key("runtime"));
+}
+
+#[test]
+fn test_max_breadcrumb_age() {
+    use std::time::{Duration, SystemTime};
+
+    use sentry::Breadcrumb;
+
+    let crumb = |message: &str, age| Breadcrumb {
+        message: Some(message.into()),
+        timestamp: SystemTime::now() - Duration::from_secs(age),
+        ..Default::default()
+    };
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::add_breadcrumb(crumb("startup", 2 * 60 * 60));
+            sentry::add_breadcrumb(crumb("request", 10));
+            sentry::capture_message("failed", sentry::Level::Error);
+        },
+        sentry::ClientOptions {
+            max_breadcrumb_age: Some(Duration::from_secs(60)),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+    let messages: Vec<_> = events[0]
+        .breadcrumbs
+        .iter()
+        .map(|crumb| crumb.message.as_deref().unwrap())
+        .collect();
+    assert_eq!(messages, ["request"]);
+}
+
+#[test]
+fn test_thread_local_breadcrumbs() {
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use sentry::{Breadcrumb, Hub};
+
+    let crumb = |message: &str, secs| Breadcrumb {
+        message: Some(message.into()),
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+        ..Default::default()
+    };
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            let hub = Hub::current();
+            let threads: Vec<_> = [["a", "c"], ["b", "d"]]
+                .iter()
+                .enumerate()
+                .map(|(offset, messages)| {
+                    let hub = Arc::clone(&hub);
+                    std::thread::spawn(move || {
+                        Hub::run(hub, || {
+                            for (i, message) in messages.iter().enumerate() {
+                                let secs = (2 * i + offset) as u64;
+                                sentry::add_breadcrumb(crumb(message, secs));
+                            }
+                        })
+                    })
+                })
+                .collect();
+            for thread in threads {
+                thread.join().unwrap();
+            }
+            sentry::add_breadcrumb(crumb("e", 4));
+            sentry::capture_message("first", sentry::Level::Info);
+            sentry::capture_message("second", sentry::Level::Info);
+        },
+        sentry::ClientOptions {
+            thread_local_breadcrumbs: true,
+            max_breadcrumbs: 4,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 2);
+    for event in &events {
+        let messages: Vec<_> = event
+            .breadcrumbs
+            .iter()
+            .map(|crumb| crumb.message.as_deref().unwrap())
+            .collect();
+        assert_eq!(messages, ["b", "c", "d", "e"]);
+    }
+}
+
+#[test]
+fn test_thread_local_breadcrumbs_with_scopes() {
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::add_breadcrumb(sentry::Breadcrumb {
+                message: Some("outer".into()),
+                ..Default::default()
+            });
+            sentry::with_scope(
+                |_| {},
+                || {
+                    sentry::add_breadcrumb(sentry::Breadcrumb {
+                        message: Some("inner".into()),
+                        ..Default::default()
+                    });
+                    sentry::capture_message("inside", sentry::Level::Info);
+                    sentry::add_breadcrumb(sentry::Breadcrumb {
+                        message: Some("discarded".into()),
+                        ..Default::default()
+                    });
+                },
+            );
+            sentry::capture_message("outside", sentry::Level::Info);
+        },
+        sentry::ClientOptions {
+            thread_local_breadcrumbs: true,
+            ..Default::default()
+        },
+    );
+
+    let messages: Vec<Vec<_>> = events
+        .iter()
+        .map(|event| {
+            event
+                .breadcrumbs
+                .iter()
+                .map(|crumb| crumb.message.as_deref().unwrap())
+                .collect()
+        })
+        .collect();
+    assert_eq!(messages, [vec!["outer", "inner"], vec!["outer"]]);
+}
+
+#[test]
+fn test_culprit_from_in_app_frame() {
+    use sentry::protocol::{Event, Frame, Stacktrace};
+
+    let frame = |function: &str, in_app| Frame {
+        function: Some(function.into()),
+        in_app: Some(in_app),
+        ..Default::default()
+    };
+    let stacktrace = Stacktrace {
+        frames: vec![
+            frame("app::main", true),
+            frame("app::db::connect", true),
+            frame("tokio::net::TcpStream::connect", false),
+        ],
+        ..Default::default()
+    };
+
+    let events = sentry::test::with_captured_events(|| {
+        sentry::capture_event(Event {
+            stacktrace: Some(stacktrace.clone()),
+            ..Default::default()
+        });
+        sentry::capture_event(Event {
+            stacktrace: Some(stacktrace.clone()),
+            transaction: Some("GET /users".into()),
+            ..Default::default()
+        });
+    });
+
+    assert_eq!(events[0].culprit.as_deref(), Some("app::db::connect"));
+    assert_eq!(events[1].culprit, None);
+}
+
+#[test]
+fn test_event_hints() {
+    use sentry::protocol::Event;
+    use sentry::Hint;
+
+    #[derive(Debug)]
+    struct RequestId(u32);
+
+    let options = sentry::ClientOptions {
+        before_send_with_hint: Some(Arc::new(|event, hint| {
+            let is_not_found = hint
+                .error()
+                .and_then(|err| err.downcast_ref::<std::io::Error>())
+                .map_or(false, |err| err.kind() == std::io::ErrorKind::NotFound);
+            (!is_not_found).then(|| event)
+        })),
+        ..Default::default()
+    };
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::configure_scope(|scope| {
+                scope.add_event_processor_with_hint(|mut event, hint| {
+                    if let Some(RequestId(id)) = hint.data::<RequestId>() {
+                        event.tags.insert("request_id".into(), id.to_string());
+                    }
+                    if let Some(integration) = hint.integration() {
+                        event.tags.insert("integration".into(), integration.into());
+                    }
+                    Some(event)
+                });
+            });
+
+            let not_found = std::io::Error::from(std::io::ErrorKind::NotFound);
+            sentry::capture_event_with_hint(
+                sentry::event_from_error(&not_found),
+                Hint::new().with_error(not_found),
+            );
+            let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+            sentry::capture_event_with_hint(
+                sentry::event_from_error(&denied),
+                Hint::new()
+                    .with_error(denied)
+                    .with_integration("custom")
+                    .with_data(RequestId(42)),
+            );
+            sentry::capture_event(Event::default());
+        },
+        options,
+    );
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].tags["request_id"], "42");
+    assert_eq!(events[0].tags["integration"], "custom");
+    assert!(events[1].tags.is_empty());
+}
+
+#[test]
+fn test_ignore_errors_and_transactions() {
+    let options = sentry::ClientOptions {
+        ignore_errors: vec!["*connection reset by peer*".into()],
+        ignore_transactions: vec!["GET /health*".into()],
+        traces_sample_rate: 1.0,
+        ..Default::default()
+    };
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let reset = std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "connection reset by peer",
+            );
+            sentry::capture_error(&reset);
+            sentry::capture_message("connection refused", sentry::Level::Error);
+
+            for name in ["GET /healthz", "GET /users"] {
+                let ctx = sentry::TransactionContext::new(name, "http.server");
+                sentry::start_transaction(ctx).finish();
+            }
+        },
+        options,
+    );
+
+    assert_eq!(envelopes.len(), 2);
+    let event = envelopes[0].event().unwrap();
+    assert_eq!(event.message.as_deref(), Some("connection refused"));
+    match envelopes[1].items().next().unwrap() {
+        sentry::protocol::EnvelopeItem::Transaction(transaction) => {
+            assert_eq!(transaction.name.as_deref(), Some("GET /users"));
+        }
+        _ => panic!("expected a transaction"),
+    }
+}
+
+#[test]
+fn test_panic_mechanism_data() {
+    use sentry::integrations::panic::PanicIntegration;
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            let hub = sentry::Hub::current();
+            std::thread::Builder::new()
+                .name("worker".into())
+                .spawn(|| sentry::Hub::run(hub, || panic!("boom")))
+                .unwrap()
+                .join()
+                .unwrap_err();
+        },
+        sentry::ClientOptions::new().add_integration(PanicIntegration::new()),
+    );
+
+    assert_eq!(events.len(), 1);
+    let mechanism = events[0].exception[0].mechanism.as_ref().unwrap();
+    assert_eq!(mechanism.ty, "panic");
+    assert_eq!(mechanism.data["file"], file!());
+    assert!(mechanism.data["line"].as_u64().unwrap() > 0);
+    assert!(mechanism.data.contains_key("column"));
+    assert!(mechanism.data.contains_key("thread_id"));
+    assert_eq!(mechanism.data["thread_name"], "worker");
+    assert_eq!(mechanism.data["nested"], false);
+}
+
+#[test]
+fn test_capture_raw_event() {
+    use sentry::protocol::EnvelopeItem;
+
+    let transport = sentry::test::TestTransport::new();
+    let client = sentry::Client::from(sentry::ClientOptions {
+        dsn: Some("https://public@example.com/1".parse().unwrap()),
+        transport: Some(Arc::new(transport.clone())),
+        before_send: Some(Arc::new(|mut event| {
+            if event.message.as_deref() == Some("drop me") {
+                return None;
+            }
+            event.tags.insert("bridged".into(), "yes".into());
+            event.server_name = None;
+            Some(event)
+        })),
+        ..Default::default()
+    });
+
+    let event_id = client.capture_raw_event(serde_json::json!({
+        "message": "from python",
+        "platform": "python",
+        "server_name": "worker-1",
+        "sdk": {"name": "sentry.python", "version": "1.0.0"},
+        "custom_field": {"nested": [1, 2, 3]},
+    }));
+    assert!(!event_id.is_nil());
+    assert!(client
+        .capture_raw_event(serde_json::json!({"message": "drop me"}))
+        .is_nil());
+    assert!(client.capture_raw_event(serde_json::json!([1, 2])).is_nil());
+
+    let envelopes = transport.fetch_and_clear_envelopes();
+    assert_eq!(envelopes.len(), 1);
+    let payload = match envelopes[0].items().next() {
+        Some(EnvelopeItem::Raw(item)) if item.ty == "event" => &item.payload,
+        item => panic!("expected a raw event, got {:?}", item),
+    };
+    let event: serde_json::Value = serde_json::from_slice(payload).unwrap();
+    assert_eq!(event["event_id"], event_id.simple().to_string());
+    assert_eq!(event["platform"], "python");
+    assert_eq!(event["sdk"]["name"], "sentry.python");
+    assert_eq!(event["custom_field"]["nested"][2], 3);
+    assert_eq!(event["tags"]["bridged"], "yes");
+    assert!(event.get("server_name").is_none());
+}
+
+#[cfg(feature = "reqwest")]
+#[test]
+fn test_on_event_rejected() {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use sentry::test::{TestResponse, TestServer};
+    use sentry::{EventRejection, RejectionReason};
+
+    // a server that rejects every envelope as too large
+    let server = TestServer::start_with(|_| {
+        TestResponse::new(413).with_body(r#"{"detail":"envelope exceeded size limits"}"#)
+    });
+
+    let rejections = Arc::new(Mutex::new(Vec::<EventRejection>::new()));
+    let callback_rejections = rejections.clone();
+    let client = sentry::Client::from_config(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        transport: Some(Arc::new(sentry::transports::DefaultTransportFactory)),
+        on_event_rejected: Some(Arc::new(move |rejection| {
+            callback_rejections.lock().unwrap().push(rejection.clone());
+        })),
+        ..Default::default()
+    });
+    let hub = sentry::Hub::new(Some(Arc::new(client)), Default::default());
+    let event_id = hub.capture_message("too large", sentry::Level::Info);
+    hub.client().unwrap().flush(Some(Duration::from_secs(5)));
+
+    let rejections = rejections.lock().unwrap();
+    assert_eq!(
+        *rejections,
+        [EventRejection {
+            event_id: Some(event_id),
+            status: 413,
+            reason: RejectionReason::TooLarge,
+            detail: Some("envelope exceeded size limits".into()),
+        }]
+    );
+}
+
+#[test]
+fn test_capture_event_deferred() {
+    use std::time::Duration;
+
+    use sentry::protocol::Event;
+    use sentry::Hub;
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::configure_scope(|scope| scope.set_tag("scope", "applied"));
+            let event = |message: &str| Event {
+                message: Some(message.into()),
+                ..Default::default()
+            };
+
+            let pending = Hub::current()
+                .capture_event_deferred(event("enriched"))
+                .unwrap();
+            assert_eq!(sentry::last_event_id(), Some(pending.event_id()));
+            // the scope was applied at capture time
+            sentry::configure_scope(|scope| scope.set_tag("scope", "changed"));
+            let handle = pending.clone();
+            std::thread::spawn(move || {
+                assert!(handle.enrich(|event| {
+                    event.tags.insert("deployment".into(), "d-42".into());
+                }))
+            })
+            .join()
+            .unwrap();
+            assert!(!pending.is_sent());
+            assert!(pending.send());
+            assert!(!pending.send());
+            assert!(!pending.enrich(|_| unreachable!()));
+
+            let pending = Hub::current()
+                .capture_event_deferred(event("timed out"))
+                .unwrap();
+            let deadline = std::time::Instant::now() + Duration::from_secs(5);
+            while !pending.is_sent() && std::time::Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            assert!(pending.is_sent());
+        },
+        sentry::ClientOptions {
+            deferred_event_timeout: Duration::from_millis(50),
+            before_send: Some(Arc::new(|mut event| {
+                let enriched = event.tags.contains_key("deployment");
+                event.extra.insert("enriched".into(), enriched.into());
+                Some(event)
+            })),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].message.as_deref(), Some("enriched"));
+    assert_eq!(events[0].tags["scope"], "applied");
+    assert_eq!(events[0].tags["deployment"], "d-42");
+    // `before_send` runs after the event was enriched
+    assert_eq!(events[0].extra["enriched"], true);
+    assert_eq!(events[1].message.as_deref(), Some("timed out"));
+    assert_eq!(events[1].extra["enriched"], false);
+}
+
+#[test]
+fn test_enrich_event_by_id() {
+    use std::time::Duration;
+
+    use sentry::Hub;
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            let event_id = sentry::capture_message("request failed", sentry::Level::Error);
+            assert!(Hub::current().enrich_event(event_id, |event| {
+                event.tags.insert("status_code".into(), "503".into());
+            }));
+            assert!(
+                !Hub::current().enrich_event(sentry::types::Uuid::new_v4(), |_| { unreachable!() })
+            );
+
+            // flushing sends the event right away
+            Hub::current().client().unwrap().flush(None);
+            assert!(!Hub::current().enrich_event(event_id, |_| unreachable!()));
+        },
+        sentry::ClientOptions {
+            event_enrichment_window: Some(Duration::from_secs(60)),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].tags["status_code"], "503");
+}
+
+#[test]
+fn test_last_event_id_of_dropped_events() {
+    use std::time::Duration;
+
+    use sentry::Hub;
+
+    let options = sentry::ClientOptions {
+        before_send: Some(Arc::new(|event| {
+            (event.message.as_deref() != Some("dropped")).then(|| event)
+        })),
+        ..Default::default()
+    };
+    let background = sentry::ClientOptions {
+        background_processing: true,
+        ..options.clone()
+    };
+    let deferred = sentry::ClientOptions {
+        event_enrichment_window: Some(Duration::from_secs(60)),
+        ..options
+    };
+
+    for options in [background, deferred] {
+        let events = sentry::test::with_captured_events_options(
+            || {
+                let kept = sentry::capture_message("kept", sentry::Level::Info);
+                assert_eq!(sentry::last_event_id(), Some(kept));
+                Hub::current().client().unwrap().flush(None);
+                assert_eq!(sentry::last_event_id(), Some(kept));
+
+                // the id is forgotten once the event is dropped by `before_send`
+                sentry::capture_message("dropped", sentry::Level::Info);
+                Hub::current().client().unwrap().flush(None);
+                assert_eq!(sentry::last_event_id(), None);
+
+                // events dropped by the scope are never recorded
+                let kept = sentry::capture_message("kept", sentry::Level::Info);
+                sentry::with_scope(
+                    |scope| scope.add_event_processor(|_| None),
+                    || {
+                        let pending = Hub::current()
+                            .capture_event_deferred(Default::default())
+                            .unwrap();
+                        assert!(pending.event_id().is_nil());
+                    },
+                );
+                assert_eq!(sentry::last_event_id(), Some(kept));
+            },
+            options,
+        );
+        assert_eq!(events.len(), 2);
+    }
+}
+
+#[test]
+fn test_strip_frame_symbols() {
+    use sentry::protocol::{Event, Exception, Frame, Stacktrace};
+
+    let frame = Frame {
+        function: Some("app::handler".into()),
+        module: Some("app".into()),
+        filename: Some("src/handler.rs".into()),
+        abs_path: Some("/build/app/src/handler.rs".into()),
+        lineno: Some(42),
+        context_line: Some("panic!()".into()),
+        in_app: Some(true),
+        instruction_addr: Some(0x1000.into()),
+        image_addr: Some(0x400.into()),
+        ..Default::default()
+    };
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::capture_event(Event {
+                exception: vec![Exception {
+                    ty: "Error".into(),
+                    stacktrace: Some(Stacktrace {
+                        frames: vec![frame.clone()],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }]
+                .into(),
+                ..Default::default()
+            });
+        },
+        sentry::ClientOptions {
+            strip_frame_symbols: true,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+    assert!(events[0].culprit.is_none());
+    let frames = &events[0].exception[0].stacktrace.as_ref().unwrap().frames;
+    assert_eq!(
+        frames[0],
+        Frame {
+            in_app: Some(true),
+            instruction_addr: Some(0x1000.into()),
+            image_addr: Some(0x400.into()),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_sample_rates_by_level() {
+    use sentry::Level;
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            for _ in 0..10 {
+                sentry::capture_message("noise", Level::Info);
+            }
+            sentry::capture_message("warning", Level::Warning);
+            sentry::capture_message("failure", Level::Fatal);
+        },
+        sentry::ClientOptions {
+            sample_rates: [(Level::Info, 0.0), (Level::Fatal, 1.0)].into(),
+            ..Default::default()
+        },
+    );
+
+    let messages: Vec<_> = events
+        .iter()
+        .map(|event| event.message.as_deref().unwrap())
+        .collect();
+    assert_eq!(messages, ["warning", "failure"]);
+}
+
+#[test]
+fn test_fingerprint_fn() {
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::configure_scope(|scope| scope.set_fingerprint(Some(&["from-scope"])));
+            sentry::capture_message("error E42: disk full", sentry::Level::Error);
+            sentry::capture_message("unrelated", sentry::Level::Error);
+        },
+        sentry::ClientOptions {
+            fingerprint_fn: Some(Arc::new(|event| {
+                let message = event.message.as_deref()?;
+                let code = message.strip_prefix("error ")?.split(':').next()?;
+                Some(vec!["error-code".into(), code.into()])
+            })),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].fingerprint[..], ["error-code", "E42"]);
+    assert_eq!(events[1].fingerprint[..], ["from-scope"]);
+}
+
+#[test]
+fn test_spotlight_without_dsn() {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let sidecar = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        // the request ends with the serialized event
+        while !String::from_utf8_lossy(&request).contains("from spotlight") {
+            let read = stream.read(&mut buf).unwrap();
+            assert!(read > 0);
+            request.extend_from_slice(&buf[..read]);
+        }
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    });
+
+    let client = Arc::new(sentry::Client::from(sentry::ClientOptions {
+        spotlight: true,
+        spotlight_url: format!("http://127.0.0.1:{}/stream", port).into(),
+        ..Default::default()
+    }));
+    assert!(client.is_enabled());
+    let hub = sentry::Hub::new(Some(client.clone()), Default::default());
+    hub.capture_message("from spotlight", sentry::Level::Info);
+    assert!(client.flush(None));
+
+    let request = sidecar.join().unwrap();
+    assert!(request.starts_with("POST /stream HTTP/1.1\r\n"));
+    assert!(request.contains("Content-Type: application/x-sentry-envelope\r\n"));
+}
+
+#[test]
+fn test_flush_progress() {
+    let server = sentry::test::TestServer::start();
+    let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let reports_hook = reports.clone();
+
+    let client = sentry::Client::from(sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        on_flush_progress: Some(Arc::new(move |progress| {
+            reports_hook.lock().unwrap().push(progress);
+        })),
+        ..Default::default()
+    }));
+    for i in 0..3 {
+        client.capture_event(
+            sentry::protocol::Event {
+                message: Some(format!("event {}", i)),
+                ..Default::default()
+            },
+            None,
+        );
+    }
+    assert!(client.flush(Some(std::time::Duration::from_secs(5))));
+    assert_eq!(server.events().len(), 3);
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.last(), Some(&sentry::FlushProgress::default()));
+    for pair in reports.windows(2) {
+        assert!(pair[0].envelopes_remaining >= pair[1].envelopes_remaining);
+    }
+    for progress in reports.iter() {
+        assert_eq!(
+            progress.envelopes_remaining > 0,
+            progress.bytes_remaining > 0
+        );
+    }
+}
+
+#[cfg(feature = "surf")]
+#[test]
+fn test_surf_transport_with_client() {
+    use std::time::Duration;
+
+    use sentry::transports::SurfHttpTransport;
+
+    // the proxy of the options must not replace the given client
+    let unreachable = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let server = sentry::test::TestServer::start();
+    let client = sentry::Client::from_config(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        http_proxy: Some(format!("http://{}", unreachable).into()),
+        transport: Some(Arc::new(|options: &sentry::ClientOptions| {
+            Arc::new(SurfHttpTransport::with_client(options, surf::Client::new()))
+                as Arc<dyn sentry::Transport>
+        })),
+        ..Default::default()
+    });
+    client.capture_event(Default::default(), None);
+    assert!(client.flush(Some(Duration::from_secs(5))));
+    assert_eq!(
+        server.wait_for_envelopes(1, Duration::from_secs(5)).len(),
+        1
+    );
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_compressed_payloads() {
+    let server = sentry::test::TestServer::start();
+    let client = sentry::Client::from(sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        ..Default::default()
+    }));
+    assert!(client.options().compress_payloads);
+    client.capture_event(
+        sentry::protocol::Event {
+            message: Some("compressed".into()),
+            ..Default::default()
+        },
+        None,
+    );
+    assert!(client.flush(Some(std::time::Duration::from_secs(5))));
+    assert_eq!(server.events()[0].message.as_deref(), Some("compressed"));
+}
+
+#[test]
+fn test_rate_limited_envelopes_are_not_queued() {
+    use sentry::test::{TestResponse, TestServer};
+
+    // a server that rate limits everything for a minute
+    let server =
+        TestServer::start_with(|_| TestResponse::new(429).with_header("Retry-After", "60"));
+
+    let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let reports_hook = reports.clone();
+    let client = sentry::Client::from(sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        on_flush_progress: Some(Arc::new(move |progress| {
+            reports_hook.lock().unwrap().push(progress);
+        })),
+        ..Default::default()
+    }));
+    client.capture_event(Default::default(), None);
+    assert!(client.flush(Some(std::time::Duration::from_secs(5))));
+    assert_eq!(server.requests(), 1);
+    assert!(client.backpressure().is_rate_limited());
+    assert_eq!(client.backpressure().rate_limited_envelopes, 0);
+
+    reports.lock().unwrap().clear();
+    for _ in 0..5 {
+        client.capture_event(Default::default(), None);
+    }
+    assert!(client.flush(Some(std::time::Duration::from_secs(5))));
+    assert_eq!(*reports.lock().unwrap(), [sentry::FlushProgress::default()]);
+    assert_eq!(server.requests(), 1);
+    assert_eq!(client.backpressure().rate_limited_envelopes, 5);
+}
+
+#[test]
+fn test_retry_policy() {
+    use std::time::Duration;
+
+    use sentry::test::{TestResponse, TestServer};
+
+    // a server that fails the first two requests
+    let failing_server = || {
+        TestServer::start_with(|request| match request {
+            0 | 1 => TestResponse::new(503),
+            _ => TestResponse::new(200),
+        })
+    };
+
+    let retry_policy = sentry::RetryPolicy {
+        initial_backoff: Duration::from_millis(10),
+        ..Default::default()
+    };
+    assert!(retry_policy.backoff(2) <= Duration::from_millis(20));
+    assert!(retry_policy.backoff(2) >= Duration::from_millis(16));
+    assert_eq!(
+        sentry::RetryPolicy {
+            jitter: 0.0,
+            ..retry_policy
+        }
+        .backoff(100),
+        retry_policy.max_backoff
+    );
+
+    let server = failing_server();
+    let client = sentry::Client::from(sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        retry_policy: Some(retry_policy),
+        ..Default::default()
+    }));
+    client.capture_event(Default::default(), None);
+    assert!(client.flush(Some(Duration::from_secs(5))));
+    assert_eq!(server.requests(), 3);
+
+    // the envelope is given up after the last attempt
+    let server = failing_server();
+    let client = sentry::Client::from(sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        retry_policy: Some(sentry::RetryPolicy {
+            max_attempts: 2,
+            ..retry_policy
+        }),
+        ..Default::default()
+    }));
+    client.capture_event(Default::default(), None);
+    assert!(client.flush(Some(Duration::from_secs(5))));
+    assert_eq!(server.requests(), 2);
+}
+
+#[test]
+fn test_offline_spool() {
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join(format!(
+        "sentry-offline-spool-{}",
+        sentry::types::Uuid::new_v4().simple()
+    ));
+
+    // nothing listens on this port anymore
+    let unreachable = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let client = sentry::Client::from(sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(format!("http://public@{}/1", unreachable).parse().unwrap()),
+        offline_spool_dir: Some(dir.clone()),
+        ..Default::default()
+    }));
+    client.capture_event(
+        sentry::protocol::Event {
+            message: Some("spooled".into()),
+            ..Default::default()
+        },
+        None,
+    );
+    assert!(client.flush(Some(Duration::from_secs(5))));
+    assert_eq!(sentry::Spool::open(&dir).unwrap().len().unwrap(), 1);
+    drop(client);
+
+    // the next client sends the spooled envelope
+    let server = sentry::test::TestServer::start();
+    let client = sentry::Client::from(sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        offline_spool_dir: Some(dir.clone()),
+        ..Default::default()
+    }));
+    assert!(client.flush(Some(Duration::from_secs(5))));
+    let events = server.events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message.as_deref(), Some("spooled"));
+    assert!(sentry::Spool::open(&dir).unwrap().is_empty().unwrap());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_offline_spool_keeps_unsent_envelopes() {
+    use std::time::Duration;
+
+    use sentry::test::{TestResponse, TestServer};
+
+    let dir = std::env::temp_dir().join(format!(
+        "sentry-offline-spool-{}",
+        sentry::types::Uuid::new_v4().simple()
+    ));
+    let spool = sentry::Spool::open(&dir).unwrap();
+    for _ in 0..2 {
+        spool
+            .write(&sentry::Envelope::from(sentry::protocol::Event::default()))
+            .unwrap();
+    }
+
+    // a server that is slow to respond
+    let server = TestServer::start_with(|_| {
+        std::thread::sleep(Duration::from_millis(200));
+        TestResponse::new(200)
+    });
+
+    // the client shuts down while sending the first spooled envelope
+    let client = sentry::Client::from(sentry::apply_defaults(sentry::ClientOptions {
+        dsn: Some(server.dsn()),
+        offline_spool_dir: Some(dir.clone()),
+        ..Default::default()
+    }));
+    assert_eq!(
+        server.wait_for_envelopes(1, Duration::from_secs(5)).len(),
+        1
+    );
+    drop(client);
+    assert_eq!(spool.len().unwrap(), 1);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
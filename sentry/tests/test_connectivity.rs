@@ -0,0 +1,74 @@
+#![cfg(all(feature = "test", feature = "connectivity"))]
+
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sentry::test::TestTransport;
+use sentry::transports::ConnectivityTransportFactory;
+use sentry::{Level, Spool};
+
+fn wait_until<F: FnMut() -> bool>(mut condition: F) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn test_spools_while_offline() {
+    let dir = std::env::temp_dir().join(format!("sentry-connectivity-{}", std::process::id()));
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let transport = TestTransport::new();
+    let factory = ConnectivityTransportFactory::new(transport.clone(), &dir)
+        .check_interval(Duration::from_millis(20))
+        .probe_timeout(Duration::from_millis(200));
+    let _sentry = sentry::init(sentry::ClientOptions {
+        dsn: Some(
+            format!("http://public@127.0.0.1:{}/1", port)
+                .parse()
+                .unwrap(),
+        ),
+        transport: Some(Arc::new(factory)),
+        ..Default::default()
+    });
+
+    sentry::capture_message("online", Level::Info);
+    assert_eq!(transport.fetch_and_clear_events().len(), 1);
+
+    // refused connections take the network down
+    drop(listener);
+    let spool = Spool::open(&dir).unwrap();
+    assert!(wait_until(|| {
+        sentry::capture_message("probe", Level::Info);
+        spool.len().unwrap() > 0
+    }));
+    transport.fetch_and_clear_events();
+    sentry::capture_message("offline", Level::Info);
+    assert!(transport.fetch_and_clear_events().is_empty());
+    let spooled = spool.len().unwrap();
+
+    let _listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+    let mut events = Vec::new();
+    assert!(wait_until(|| {
+        events.extend(transport.fetch_and_clear_events());
+        spool.is_empty().unwrap() && events.len() >= spooled
+    }));
+
+    let event = events
+        .iter()
+        .find(|event| event.message.as_deref() == Some("offline"))
+        .unwrap();
+    let breadcrumb = event.breadcrumbs.last().unwrap();
+    assert_eq!(breadcrumb.category.as_deref(), Some("network.event"));
+    assert_eq!(breadcrumb.message.as_deref(), Some("network lost"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "test")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::Arc;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+fn hot_path() {
+    sentry::capture_message("hot path", sentry::Level::Info);
+    sentry::add_breadcrumb(|| sentry::Breadcrumb {
+        message: Some("hot path".into()),
+        ..Default::default()
+    });
+    sentry::configure_scope(|scope| scope.set_tag("hot", "path"));
+    sentry::with_scope(|scope| scope.set_tag("hot", "path"), || {});
+    sentry::capture_error(&std::fmt::Error);
+}
+
+// This is the only test in this binary, as binding an enabled client anywhere
+// in the process turns off the fast path.
+#[test]
+fn test_disabled_client_does_not_allocate() {
+    assert_eq!(count_allocations(hot_path), 0);
+
+    // `sentry::init` binds a client even without a DSN.
+    let client = Arc::new(sentry::Client::from(sentry::ClientOptions::default()));
+    sentry::Hub::current().bind_client(Some(client));
+    assert!(!sentry::is_enabled());
+    assert_eq!(count_allocations(hot_path), 0);
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "test")]
+
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sentry::{Envelope, Transport};
+
+const CHILD_ENV: &str = "SENTRY_TEST_EXIT_CHILD";
+
+/// A transport which only sends the envelope when being flushed, like a
+/// buffering transport would.
+struct FlushingTransport(std::sync::Mutex<Vec<Envelope>>);
+
+impl Transport for FlushingTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        self.0.lock().unwrap().push(envelope);
+    }
+
+    fn flush(&self, _timeout: Duration) -> bool {
+        for envelope in self.0.lock().unwrap().drain(..) {
+            if let Some(event) = envelope.event() {
+                println!(
+                    "sent event: {}",
+                    event.message.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        true
+    }
+
+    fn shutdown(&self, timeout: Duration) -> bool {
+        self.flush(timeout)
+    }
+}
+
+fn run_child(mode: &str) -> std::process::Output {
+    Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "run_exit_child",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env(CHILD_ENV, mode)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn run_exit_child() {
+    let mode = match std::env::var(CHILD_ENV) {
+        Ok(mode) => mode,
+        Err(_) => return,
+    };
+    let guard = sentry::init(sentry::ClientOptions {
+        dsn: Some("https://public@example.com/1".parse().unwrap()),
+        transport: Some(Arc::new(Arc::new(FlushingTransport(Default::default())))),
+        ..Default::default()
+    });
+    // tests run on a separate thread, but the exit hook flushes the main hub
+    sentry::Hub::main().bind_client(sentry::Hub::current().client());
+    sentry::capture_message(&mode, sentry::Level::Error);
+    match mode.as_str() {
+        "exit" => sentry::exit(3),
+        _ => {
+            sentry::install_exit_hook();
+            std::mem::forget(guard);
+            std::process::exit(4)
+        }
+    }
+}
+
+#[test]
+fn test_exit_flushes() {
+    let output = run_child("exit");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("sent event: exit"));
+}
+
+#[test]
+fn test_exit_hook_flushes() {
+    let output = run_child("hook");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("sent event: hook"));
+}
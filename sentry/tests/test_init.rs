@@ -0,0 +1,34 @@
+#![cfg(feature = "test")]
+
+use std::sync::Arc;
+
+use sentry::test::TestTransport;
+use sentry::Hub;
+
+#[test]
+fn test_init_is_reference_counted() {
+    let transport = TestTransport::new();
+    let app_guard = sentry::init(sentry::ClientOptions {
+        dsn: Some("https://public@sentry.invalid/1".parse().unwrap()),
+        transport: Some(Arc::new(transport.clone())),
+        ..Default::default()
+    });
+    let client = Hub::current().client().unwrap();
+
+    // a plugin initializing without a DSN keeps the client of the application
+    let plugin_guard = sentry::init(sentry::ClientOptions::default());
+    assert!(plugin_guard.is_enabled());
+    assert!(Arc::ptr_eq(&Hub::current().client().unwrap(), &client));
+
+    drop(app_guard);
+    assert!(client.is_enabled());
+    sentry::capture_message("still enabled", sentry::Level::Info);
+    assert_eq!(transport.fetch_and_clear_envelopes().len(), 1);
+
+    drop(plugin_guard);
+    assert!(!client.is_enabled());
+
+    // once all guards are dropped, init without a DSN binds a disabled client
+    let guard = sentry::init(sentry::ClientOptions::default());
+    assert!(!guard.is_enabled());
+}
@@ -0,0 +1,202 @@
+#![cfg(feature = "test")]
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+
+use sentry::protocol::EnvelopeItem;
+use sentry_io::{InstrumentedFile, InstrumentedTcpStream};
+
+#[test]
+fn test_io_spans_and_breadcrumbs() {
+    let path = std::env::temp_dir().join("sentry-io-test.txt");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let options = sentry::ClientOptions {
+        traces_sample_rate: 1.0,
+        ..Default::default()
+    };
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let ctx = sentry::TransactionContext::new("io", "test");
+            let transaction = sentry::start_transaction(ctx);
+            sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+
+            let mut file = InstrumentedFile::create(&path).unwrap();
+            for _ in 0..100 {
+                file.write_all(b"hello").unwrap();
+            }
+            drop(file);
+
+            let mut chunk = [0; 5];
+            let mut file = InstrumentedFile::open(&path).unwrap();
+            while file.read(&mut chunk).unwrap() > 0 {
+                assert_eq!(&chunk, b"hello");
+            }
+            drop(file);
+
+            let mut stream = InstrumentedTcpStream::connect(addr).unwrap();
+            stream.write_all(b"ping").unwrap();
+            drop(stream);
+
+            sentry::capture_message("done", sentry::Level::Info);
+            transaction.finish();
+        },
+        options,
+    );
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(envelopes.len(), 2);
+
+    let event = envelopes[0].event().unwrap();
+    let messages: Vec<_> = event
+        .breadcrumbs
+        .iter()
+        .map(|crumb| {
+            let message = crumb.message.as_deref().unwrap();
+            (
+                crumb.category.as_deref().unwrap(),
+                message.split(' ').next(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            ("file", Some("opened")),
+            ("file", Some("closed")),
+            ("file", Some("opened")),
+            ("file", Some("closed")),
+            ("net", Some("connected")),
+            ("net", Some("closed")),
+        ]
+    );
+    assert_eq!(event.breadcrumbs[1].data["bytes_written"], 500);
+    assert_eq!(event.breadcrumbs[3].data["bytes_read"], 500);
+
+    let transaction = match envelopes[1].items().next() {
+        Some(EnvelopeItem::Transaction(transaction)) => transaction,
+        _ => panic!("expected a transaction"),
+    };
+    let ops: Vec<_> = transaction
+        .spans
+        .iter()
+        .map(|span| span.op.as_deref().unwrap())
+        .collect();
+    assert_eq!(
+        ops,
+        vec![
+            "file.open",
+            "file.write",
+            "file.open",
+            "file.read",
+            "net.connect",
+            "net.write"
+        ]
+    );
+    assert_eq!(transaction.spans[1].data["bytes"], 500);
+    assert_eq!(transaction.spans[3].data["bytes"], 500);
+    assert_eq!(transaction.spans[5].data["bytes"], 4);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_process_spans_and_breadcrumbs() {
+    use sentry_io::process::Command;
+
+    let options = sentry::ClientOptions {
+        traces_sample_rate: 1.0,
+        ..Default::default()
+    };
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let ctx = sentry::TransactionContext::new("process", "test");
+            let transaction = sentry::start_transaction(ctx);
+            sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+
+            let status = Command::new("sh")
+                .args(["-c", "exit 3", "--token", "secret", "--PASSWORD=hunter2"])
+                .args(["--session", "abc", "--verbose"])
+                .redact_args("Session")
+                .status()
+                .unwrap();
+            assert_eq!(status.code(), Some(3));
+
+            let output = Command::new("echo")
+                .args([
+                    "hello",
+                    "postgres://user:pw@db/orders",
+                    "https://host/@path",
+                ])
+                .output()
+                .unwrap();
+            assert_eq!(
+                output.stdout,
+                b"hello postgres://user:pw@db/orders https://host/@path\n"
+            );
+
+            let mut child = Command::new("true").spawn().unwrap();
+            assert!(child.wait().unwrap().success());
+            child.wait().unwrap();
+            drop(Command::new("true").spawn().unwrap());
+
+            assert!(Command::new("sentry-does-not-exist").status().is_err());
+
+            sentry::capture_message("done", sentry::Level::Info);
+            transaction.finish();
+        },
+        options,
+    );
+
+    assert_eq!(envelopes.len(), 2);
+
+    let event = envelopes[0].event().unwrap();
+    let crumbs: Vec<_> = event
+        .breadcrumbs
+        .iter()
+        .map(|crumb| {
+            assert_eq!(crumb.category.as_deref(), Some("subprocess"));
+            assert!(crumb.data.contains_key("duration_ms"));
+            (crumb.message.as_deref().unwrap(), crumb.level)
+        })
+        .collect();
+    assert_eq!(
+        crumbs,
+        vec![
+            (
+                "sh -c exit 3 --token [Filtered] --PASSWORD=[Filtered] --session [Filtered] --verbose",
+                sentry::Level::Warning
+            ),
+            (
+                "echo hello postgres://[Filtered]@db/orders https://host/@path",
+                sentry::Level::Info
+            ),
+            ("true", sentry::Level::Info),
+            ("sentry-does-not-exist", sentry::Level::Error),
+        ]
+    );
+    assert_eq!(event.breadcrumbs[0].data["exit_code"], 3);
+
+    let transaction = match envelopes[1].items().next() {
+        Some(EnvelopeItem::Transaction(transaction)) => transaction,
+        _ => panic!("expected a transaction"),
+    };
+    assert_eq!(transaction.spans.len(), 5);
+    assert!(transaction
+        .spans
+        .iter()
+        .all(|span| span.op.as_deref() == Some("subprocess")));
+    assert_eq!(transaction.spans[0].data["exit_code"], 3);
+    assert_eq!(
+        transaction.spans[1].status,
+        Some(sentry::protocol::SpanStatus::Ok)
+    );
+    assert_eq!(
+        transaction.spans[3].status,
+        Some(sentry::protocol::SpanStatus::UnknownError)
+    );
+    assert_eq!(
+        transaction.spans[4].status,
+        Some(sentry::protocol::SpanStatus::NotFound)
+    );
+}
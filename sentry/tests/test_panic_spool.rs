@@ -0,0 +1,42 @@
+#![cfg(feature = "test")]
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use sentry::test::TestTransport;
+use sentry::{Envelope, Spool};
+
+#[test]
+fn test_init_sends_panic_spool() {
+    let dir = std::env::temp_dir().join(format!("sentry-panic-spool-{}", std::process::id()));
+    let spool = Spool::open(&dir).unwrap();
+    spool
+        .write(&Envelope::from(sentry::protocol::Event {
+            message: Some("aborted".into()),
+            ..Default::default()
+        }))
+        .unwrap();
+
+    let transport = TestTransport::new();
+    let _guard = sentry::init(sentry::ClientOptions {
+        dsn: Some("https://public@sentry.invalid/1".parse().unwrap()),
+        transport: Some(Arc::new(transport.clone())),
+        panic_spool_dir: Some(dir.clone()),
+        ..Default::default()
+    });
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut events = Vec::new();
+    while events.is_empty() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+        events = transport.fetch_and_clear_events();
+    }
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message.as_deref(), Some("aborted"));
+
+    while !spool.is_empty().unwrap() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(spool.is_empty().unwrap());
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "test")]
+
+use std::sync::Arc;
+
+use sentry::protocol::{Breadcrumb, Event};
+use sentry_snapshot::{ScopeSnapshot, ScopeSnapshotIntegration};
+
+#[test]
+fn test_scope_snapshot() {
+    let path = std::env::temp_dir().join(format!("sentry-snapshot-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let options = sentry::ClientOptions {
+        dsn: "https://public@sentry.invalid/1".parse().ok(),
+        transport: Some(Arc::new(sentry::test::TestTransport::new())),
+        max_breadcrumbs: 2,
+        ..Default::default()
+    }
+    .add_integration(ScopeSnapshotIntegration::new(&path));
+    let guard = sentry::init(options);
+    sentry::with_integration(|integration: &ScopeSnapshotIntegration, _| {
+        assert!(integration.previous_snapshot().is_none());
+    });
+
+    sentry::configure_scope(|scope| {
+        scope.set_tag("crashy", "yes");
+        scope.set_tag("removed", "yes");
+        scope.remove_tag("removed");
+    });
+    for message in ["first", "second", "third"] {
+        sentry::add_breadcrumb(Breadcrumb {
+            message: Some(message.into()),
+            ..Default::default()
+        });
+    }
+    sentry::with_integration(|integration: &ScopeSnapshotIntegration, _| {
+        integration.persist().unwrap();
+    });
+    drop(guard);
+
+    let snapshot = ScopeSnapshot::load(&path).unwrap();
+    assert!(snapshot.timestamp.is_some());
+    assert_eq!(snapshot.tags.len(), 1);
+    assert_eq!(snapshot.tags["crashy"], "yes");
+    let messages: Vec<_> = snapshot
+        .breadcrumbs
+        .iter()
+        .map(|breadcrumb| breadcrumb.message.as_deref().unwrap())
+        .collect();
+    assert_eq!(messages, ["second", "third"]);
+
+    let mut event = Event::default();
+    event.tags.insert("crashy".into(), "no".into());
+    snapshot.apply_to_event(&mut event);
+    assert_eq!(event.tags["crashy"], "no");
+    assert_eq!(event.breadcrumbs.len(), 2);
+
+    let next_run = ScopeSnapshotIntegration::new(&path);
+    assert_eq!(next_run.previous_snapshot(), Some(&snapshot));
+    std::fs::remove_file(&path).unwrap();
+}